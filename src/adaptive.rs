@@ -0,0 +1,157 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Colors that depend on whether the terminal has a light or a dark
+// background. `detect_appearance` asks the terminal for its background color
+// (OSC 11) once; until then, or when it doesn't answer, `COLORFGBG` decides,
+// and without that the background is assumed dark.
+
+use crate::color::Color;
+use crate::command::Command;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Appearance {
+    Light,
+    #[default]
+    Dark,
+}
+
+impl Appearance {
+    // Light when the perceived brightness of `rgb` is above half.
+    pub fn from_background(rgb: (u8, u8, u8)) -> Self {
+        let luma = 299 * rgb.0 as u32 + 587 * rgb.1 as u32 + 114 * rgb.2 as u32;
+        if luma > 128_000 {
+            Appearance::Light
+        } else {
+            Appearance::Dark
+        }
+    }
+
+    // From `COLORFGBG` ("fg;bg", as set by rxvt and Konsole). Palette
+    // entries 7 and 9 to 15 are light backgrounds.
+    pub fn from_colorfgbg(value: &str) -> Option<Self> {
+        let bg: u8 = value.rsplit(';').next()?.parse().ok()?;
+        Some(if bg == 7 || (9..=15).contains(&bg) {
+            Appearance::Light
+        } else {
+            Appearance::Dark
+        })
+    }
+}
+
+// 0 until known, then 1 + the appearance
+static APPEARANCE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_appearance(appearance: Appearance) {
+    APPEARANCE.store(appearance as u8 + 1, Ordering::Relaxed);
+}
+
+// The background as far as known, without querying the terminal.
+pub fn appearance() -> Appearance {
+    match APPEARANCE.load(Ordering::Relaxed) {
+        1 => Appearance::Light,
+        2 => Appearance::Dark,
+        _ => std::env::var("COLORFGBG")
+            .ok()
+            .and_then(|v| Appearance::from_colorfgbg(&v))
+            .unwrap_or_default(),
+    }
+}
+
+// Asks the terminal for its background color (OSC 11).
+pub fn query_background_color(timeout: Duration) -> io::Result<(u8, u8, u8)> {
+    crate::query(b"\x1b]11;?\x1b\\", timeout, crate::input::background_report)
+}
+
+// Queries the background and remembers the result for `appearance()`. Falls
+// back to the heuristic when the terminal doesn't answer. Call it before
+// entering raw mode or starting an event reader.
+pub fn detect_appearance() -> Appearance {
+    let detected = match query_background_color(crate::QUERY_TIMEOUT) {
+        Ok(rgb) => Appearance::from_background(rgb),
+        Err(_) => appearance(),
+    };
+    set_appearance(detected);
+    detected
+}
+
+// A color with a variant for each background, picked when it is written.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Adaptive {
+    pub light: Color,
+    pub dark: Color,
+}
+
+impl Adaptive {
+    pub fn new(light: Color, dark: Color) -> Self {
+        Self { light, dark }
+    }
+
+    pub fn resolve(&self) -> Color {
+        self.for_appearance(appearance())
+    }
+
+    pub fn for_appearance(&self, appearance: Appearance) -> Color {
+        match appearance {
+            Appearance::Light => self.light,
+            Appearance::Dark => self.dark,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct AdaptiveForeground(pub Adaptive);
+
+impl Command for AdaptiveForeground {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        crate::color::Foreground::new(self.0.resolve()).write_ansi(out)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct AdaptiveBackground(pub Adaptive);
+
+impl Command for AdaptiveBackground {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        crate::color::Background::new(self.0.resolve()).write_ansi(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Adaptive, Appearance};
+    use crate::color::Color;
+
+    #[test]
+    fn picks_by_background() {
+        assert_eq!(
+            Appearance::from_background((253, 246, 227)),
+            Appearance::Light
+        );
+        assert_eq!(Appearance::from_background((40, 42, 54)), Appearance::Dark);
+        assert_eq!(Appearance::from_colorfgbg("0;15"), Some(Appearance::Light));
+        assert_eq!(
+            Appearance::from_colorfgbg("15;default;0"),
+            Some(Appearance::Dark)
+        );
+        let text = Adaptive::new(Color::Extended(235), Color::Extended(252));
+        assert_eq!(text.for_appearance(Appearance::Light), Color::Extended(235));
+    }
+}
@@ -0,0 +1,132 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Everything that produces terminal output writes through a `Backend`, so the
+// real terminal (`Terminal` on Unix, `Console` on Windows) can be swapped for
+// an in-memory one in tests.
+
+use crate::virtual_screen::VirtualScreen;
+use std::io::{self, Write};
+
+pub trait Backend: Write {
+    // (cols, rows) of the output device.
+    fn size(&self) -> io::Result<(u32, u32)>;
+}
+
+impl<B: Backend + ?Sized> Backend for &mut B {
+    fn size(&self) -> io::Result<(u32, u32)> {
+        (**self).size()
+    }
+}
+
+////////////////////
+//  Test Backend  //
+////////////////////
+
+// Backend that keeps everything written to it. Flushed output is also fed to
+// a `VirtualScreen` so tests can check what a terminal would display.
+#[derive(Debug, Clone)]
+pub struct TestBackend {
+    size: (u32, u32),
+    pending: Vec<u8>,
+    written: Vec<u8>,
+    flushes: usize,
+    screen: VirtualScreen,
+}
+
+impl TestBackend {
+    pub fn new(cols: u32, rows: u32) -> Self {
+        Self {
+            size: (cols, rows),
+            pending: Vec::new(),
+            written: Vec::new(),
+            flushes: 0,
+            screen: VirtualScreen::new(cols as usize, rows as usize),
+        }
+    }
+
+    // Every byte flushed so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    pub fn written_str(&self) -> String {
+        String::from_utf8_lossy(&self.written).into_owned()
+    }
+
+    // Bytes written but not yet flushed.
+    pub fn pending(&self) -> &[u8] {
+        &self.pending
+    }
+
+    pub fn flushes(&self) -> usize {
+        self.flushes
+    }
+
+    pub fn screen(&self) -> &VirtualScreen {
+        &self.screen
+    }
+
+    // Forgets the recorded output, the screen contents are kept.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.written.clear();
+        self.flushes = 0;
+    }
+
+    pub fn resize(&mut self, cols: u32, rows: u32) {
+        self.size = (cols, rows);
+        self.screen.resize(cols as usize, rows as usize);
+    }
+}
+
+impl Write for TestBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.screen.process(&self.pending);
+        self.written.append(&mut self.pending);
+        self.flushes += 1;
+        Ok(())
+    }
+}
+
+impl Backend for TestBackend {
+    fn size(&self) -> io::Result<(u32, u32)> {
+        Ok(self.size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Backend, TestBackend};
+    use std::io::Write;
+
+    #[test]
+    fn output_reaches_screen_on_flush() {
+        let mut b = TestBackend::new(10, 2);
+        write!(b, "\x1b[2;3Hhi").unwrap();
+        assert_eq!(b.screen().to_plain_string(), "\n");
+        b.flush().unwrap();
+        assert_eq!(b.screen().row_text(1), "  hi");
+        assert_eq!(b.written_str(), "\x1b[2;3Hhi");
+        assert_eq!(b.size().unwrap(), (10, 2));
+    }
+}
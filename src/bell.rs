@@ -0,0 +1,144 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Audible and visual bells.
+
+use crate::command::{Command, write_command};
+use crate::mode::{DecMode, Modes, ResetMode, SetMode};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// How long `visual_bell` keeps the screen inverted.
+pub const VISUAL_BELL_DURATION: Duration = Duration::from_millis(100);
+
+// BEL. Whether it beeps, flashes or does nothing is up to the terminal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Bell;
+
+impl Command for Bell {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x07")
+    }
+}
+
+// What `ring` does, so an application can let its users pick.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum BellStyle {
+    #[default]
+    Audible,
+    Visual,
+    Both,
+    None,
+}
+
+// A flash started by `visual_bell`. The screen stays flipped until `end` is
+// called, which should be once `time_left` reaches zero.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VisualBell {
+    until: Instant,
+    // DECSCNM as it was before the flash
+    reverse: bool,
+}
+
+impl VisualBell {
+    pub fn time_left(&self) -> Duration {
+        self.until.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.until
+    }
+
+    // Puts reverse video back the way it was before the flash.
+    pub fn end(self, out: &mut impl Write) -> io::Result<()> {
+        if self.reverse {
+            write_command(&SetMode(DecMode::ReverseVideo), out)?;
+        } else {
+            write_command(&ResetMode(DecMode::ReverseVideo), out)?;
+        }
+        out.flush()
+    }
+}
+
+// Flashes the whole screen by flipping reverse video (DECSCNM) from the state
+// `modes` has recorded. Returns right away; the flash lasts until the
+// returned bell is ended.
+pub fn visual_bell(
+    out: &mut impl Write,
+    modes: &Modes,
+    duration: Duration,
+) -> io::Result<VisualBell> {
+    let reverse = modes.is_set(DecMode::ReverseVideo);
+    if reverse {
+        write_command(&ResetMode(DecMode::ReverseVideo), out)?;
+    } else {
+        write_command(&SetMode(DecMode::ReverseVideo), out)?;
+    }
+    out.flush()?;
+    Ok(VisualBell {
+        until: Instant::now() + duration,
+        reverse,
+    })
+}
+
+pub fn ring(
+    out: &mut impl Write,
+    style: BellStyle,
+    modes: &Modes,
+) -> io::Result<Option<VisualBell>> {
+    if matches!(style, BellStyle::Audible | BellStyle::Both) {
+        write_command(&Bell, out)?;
+        out.flush()?;
+    }
+    match style {
+        BellStyle::Visual | BellStyle::Both => {
+            visual_bell(out, modes, VISUAL_BELL_DURATION).map(Some)
+        }
+        BellStyle::Audible | BellStyle::None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{BellStyle, ring, visual_bell};
+    use crate::mode::{DecMode, Modes};
+    use std::time::Duration;
+
+    #[test]
+    fn styles() {
+        let modes = Modes::new();
+        let mut out = Vec::new();
+        let bell = ring(&mut out, BellStyle::Both, &modes).unwrap().unwrap();
+        assert_eq!(out, b"\x07\x1b[?5h");
+        bell.end(&mut out).unwrap();
+        assert_eq!(out, b"\x07\x1b[?5h\x1b[?5l");
+        out.clear();
+        assert!(ring(&mut out, BellStyle::None, &modes).unwrap().is_none());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn keeps_reverse_video() {
+        let modes = Modes::new();
+        modes.record(DecMode::ReverseVideo, true);
+        let mut out = Vec::new();
+        let bell = visual_bell(&mut out, &modes, Duration::ZERO).unwrap();
+        assert!(bell.is_due());
+        bell.end(&mut out).unwrap();
+        assert_eq!(out, b"\x1b[?5l\x1b[?5h");
+    }
+}
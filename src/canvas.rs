@@ -0,0 +1,293 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Braille canvas for plots and sparklines. Each cell holds a 2x4 grid of
+// dots (U+2800..U+28FF), so a `cols` x `rows` canvas has `cols * 2` by
+// `rows * 4` points with (0, 0) in the top left corner. Colors are per
+// cell: the last color drawn into a cell wins.
+
+use crate::color::Color;
+use crate::draw::DrawTarget;
+use crate::style::Style;
+use std::io;
+
+const BRAILLE: u32 = 0x2800;
+
+// Bit of each dot, indexed by [y][x] inside the cell.
+const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    cols: u16,
+    rows: u16,
+    dots: Vec<u8>,
+    colors: Vec<Color>,
+    pen: Color,
+}
+
+impl Canvas {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        let cells = cols as usize * rows as usize;
+        Self {
+            cols,
+            rows,
+            dots: vec![0; cells],
+            colors: vec![Color::None; cells],
+            pen: Color::None,
+        }
+    }
+
+    // Size in cells.
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    // Size in points.
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.cols as u32 * 2, self.rows as u32 * 4)
+    }
+
+    // Color for the points drawn from now on.
+    pub fn set_color(&mut self, color: Color) {
+        self.pen = color;
+    }
+
+    pub fn clear(&mut self) {
+        self.dots.fill(0);
+        self.colors.fill(Color::None);
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Option<(usize, u8)> {
+        let (w, h) = self.resolution();
+        if x < 0 || y < 0 || x as u32 >= w || y as u32 >= h {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        Some(((y / 4) * self.cols as usize + x / 2, DOTS[y % 4][x % 2]))
+    }
+
+    // Points outside the canvas are ignored, so shapes are clipped.
+    pub fn set_point(&mut self, x: i32, y: i32) {
+        if let Some((i, bit)) = self.cell(x, y) {
+            self.dots[i] |= bit;
+            self.colors[i] = self.pen;
+        }
+    }
+
+    pub fn unset_point(&mut self, x: i32, y: i32) {
+        if let Some((i, bit)) = self.cell(x, y) {
+            self.dots[i] &= !bit;
+        }
+    }
+
+    pub fn point(&self, x: i32, y: i32) -> bool {
+        self.cell(x, y)
+            .is_some_and(|(i, bit)| self.dots[i] & bit != 0)
+    }
+
+    // Bresenham line including both ends. The ends are clipped to the
+    // canvas first, so far away ones cost nothing extra.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let (w, h) = self.resolution();
+        let Some((x0, y0, x1, y1)) = clip_line(x0, y0, x1, y1, w as f64 - 1.0, h as f64 - 1.0)
+        else {
+            return;
+        };
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.set_point(x, y);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // Connects `values` scaled between `min` and `max`, one point column
+    // per value from the left edge. Larger values are drawn higher up.
+    pub fn plot(&mut self, values: &[f64], min: f64, max: f64) {
+        let (_, h) = self.resolution();
+        let range = if max > min { max - min } else { 1.0 };
+        let y = |v: f64| {
+            let t = ((v - min) / range).clamp(0.0, 1.0);
+            ((1.0 - t) * (h.saturating_sub(1)) as f64).round() as i32
+        };
+        for (x, pair) in values.windows(2).enumerate() {
+            self.line(x as i32, y(pair[0]), x as i32 + 1, y(pair[1]));
+        }
+        if let [v] = values {
+            self.set_point(0, y(*v));
+        }
+    }
+
+    pub fn cell_char(&self, col: u16, row: u16) -> Option<char> {
+        if col >= self.cols || row >= self.rows {
+            return None;
+        }
+        let bits = self.dots[row as usize * self.cols as usize + col as usize];
+        char::from_u32(BRAILLE + bits as u32)
+    }
+
+    // Draws the whole canvas with its top left cell at (col, row), empty
+    // cells included so it replaces what was there.
+    pub fn draw<T: DrawTarget + ?Sized>(
+        &self,
+        target: &mut T,
+        col: u16,
+        row: u16,
+    ) -> io::Result<()> {
+        for r in 0..self.rows {
+            // rows and columns past u16::MAX can't be shown
+            let Some(y) = row.checked_add(r) else {
+                break;
+            };
+            let start = r as usize * self.cols as usize;
+            let mut run = String::new();
+            let mut run_col = 0;
+            let mut run_color = Color::None;
+            for c in 0..self.cols {
+                let color = self.colors[start + c as usize];
+                if color != run_color && !run.is_empty() {
+                    if let Some(x) = col.checked_add(run_col) {
+                        target.draw_str(x, y, &run, Style::new().fg(run_color))?;
+                    }
+                    run.clear();
+                }
+                if run.is_empty() {
+                    run_col = c;
+                    run_color = color;
+                }
+                run.extend(self.cell_char(c, r));
+            }
+            if let (false, Some(x)) = (run.is_empty(), col.checked_add(run_col)) {
+                target.draw_str(x, y, &run, Style::new().fg(run_color))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Liang-Barsky: the part of the line from (x0, y0) to (x1, y1) inside
+// 0..=max_x, 0..=max_y, None if it misses.
+fn clip_line(
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+    max_x: f64,
+    max_y: f64,
+) -> Option<(i32, i32, i32, i32)> {
+    if max_x < 0.0 || max_y < 0.0 {
+        return None;
+    }
+    let (x0, y0, x1, y1) = (x0 as f64, y0 as f64, x1 as f64, y1 as f64);
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, x0), (dx, max_x - x0), (-dy, y0), (dy, max_y - y0)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    let at = |t: f64| ((x0 + t * dx).round() as i32, (y0 + t * dy).round() as i32);
+    let ((ax, ay), (bx, by)) = (at(t0), at(t1));
+    Some((ax, ay, bx, by))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Canvas;
+    use crate::color::Color;
+    use crate::screen::Screen;
+
+    #[test]
+    fn points_map_to_dots() {
+        let mut canvas = Canvas::new(2, 1);
+        assert_eq!(canvas.resolution(), (4, 4));
+        canvas.set_point(0, 0);
+        canvas.set_point(1, 3);
+        canvas.set_point(9, 9);
+        assert_eq!(canvas.cell_char(0, 0), Some('⢁'));
+        assert_eq!(canvas.cell_char(1, 0), Some('⠀'));
+        canvas.unset_point(0, 0);
+        assert!(!canvas.point(0, 0));
+        assert!(canvas.point(1, 3));
+    }
+
+    #[test]
+    fn line_and_colors() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.line(0, 0, 3, 3);
+        assert_eq!(canvas.cell_char(0, 0), Some('⠑'));
+        assert_eq!(canvas.cell_char(1, 0), Some('⢄'));
+
+        let red = Color::Rgb { r: 255, g: 0, b: 0 };
+        canvas.set_color(red);
+        canvas.set_point(3, 0);
+        let mut screen = Screen::new(3, 1);
+        canvas.draw(&mut screen, 1, 0).unwrap();
+        assert_eq!(screen.get(1, 0).unwrap().symbol(), "⠑");
+        assert_eq!(screen.get(1, 0).unwrap().style.fg, Color::None);
+        assert_eq!(screen.get(2, 0).unwrap().symbol(), "⢌");
+        assert_eq!(screen.get(2, 0).unwrap().style.fg, red);
+    }
+
+    #[test]
+    fn far_away_lines_are_clipped() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.line(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+        assert!(canvas.point(0, 0));
+        assert!(canvas.point(3, 3));
+        canvas.clear();
+        canvas.line(-10, 1, i32::MAX, 1);
+        assert!((0..4).all(|x| canvas.point(x, 1)));
+        canvas.clear();
+        canvas.line(-10, -1, 10, -1);
+        assert_eq!(canvas, Canvas::new(2, 1));
+
+        let mut screen = Screen::new(3, 1);
+        canvas.draw(&mut screen, u16::MAX, u16::MAX).unwrap();
+    }
+
+    #[test]
+    fn plot_values() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.plot(&[0.0, 1.0, 0.0, 1.0], 0.0, 1.0);
+        assert!(canvas.point(0, 3));
+        assert!(canvas.point(1, 0));
+        assert!(canvas.point(2, 3));
+        assert!(canvas.point(3, 0));
+    }
+}
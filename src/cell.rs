@@ -0,0 +1,106 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// A single terminal cell. `symbol` holds a whole grapheme, which may be more
+// than one code point (a base character plus combining marks). A character
+// two cells wide occupies its own cell plus a continuation cell to the right
+// whose symbol is empty. `link` is an optional hyperlink id, resolved by
+// whoever owns the cell (`Screen::link_url`, `VirtualScreen::link`).
+
+use crate::style::Style;
+use crate::width::str_width;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: Style,
+    pub link: Option<u32>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::blank(Style::new())
+    }
+}
+
+impl Cell {
+    pub fn new(symbol: &str, style: Style) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            style,
+            link: None,
+        }
+    }
+
+    pub fn from_char(c: char, style: Style) -> Self {
+        Self {
+            symbol: c.to_string(),
+            style,
+            link: None,
+        }
+    }
+
+    pub fn blank(style: Style) -> Self {
+        Self::from_char(' ', style)
+    }
+
+    // Right half of a wide character.
+    pub fn continuation(style: Style) -> Self {
+        Self {
+            symbol: String::new(),
+            style,
+            link: None,
+        }
+    }
+
+    pub fn with_link(mut self, link: Option<u32>) -> Self {
+        self.link = link;
+        self
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn set_symbol(&mut self, symbol: &str) {
+        self.symbol.clear();
+        self.symbol.push_str(symbol);
+    }
+
+    pub fn set_char(&mut self, c: char) {
+        self.symbol.clear();
+        self.symbol.push(c);
+    }
+
+    // Adds a zero width code point (combining mark, variation selector, ...)
+    // to the grapheme in this cell.
+    pub fn push_char(&mut self, c: char) {
+        self.symbol.push(c);
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+
+    // Number of cells the symbol covers, 0 for continuation cells.
+    pub fn width(&self) -> usize {
+        if self.is_continuation() {
+            0
+        } else {
+            str_width(&self.symbol).max(1)
+        }
+    }
+}
@@ -18,33 +18,41 @@
 //  Foreground Color  //
 ////////////////////////
  
-#[derive(Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize, Clone)]
+#[derive(Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Foreground(Color);
 
 impl Foreground {
-    pub fn new(value: Color) -> Self {
+    pub const fn new(value: Color) -> Self {
         Self(value)
     }
 
-    pub fn black(b: bool) -> Self {Self(Color::Iso { color: Iso::Black, bright: b })}
-    pub fn red(b: bool) -> Self {Self(Color::Iso { color: Iso::Red, bright: b })}
-    pub fn green(b: bool) -> Self {Self(Color::Iso { color: Iso::Green, bright: b })}
-    pub fn yellow(b: bool) -> Self {Self(Color::Iso { color: Iso::Yellow, bright: b })}
-    pub fn blue(b: bool) -> Self {Self(Color::Iso { color: Iso::Blue, bright: b })}
-    pub fn magenta(b: bool) -> Self {Self(Color::Iso { color: Iso::Magenta, bright: b })}
-    pub fn cyan(b: bool) -> Self {Self(Color::Iso { color: Iso::Cyan, bright: b })}
-    pub fn white(b: bool) -> Self {Self(Color::Iso { color: Iso::White, bright: b })}
+    pub const fn black(b: bool) -> Self {Self(Color::Iso { color: Iso::Black, bright: b })}
+    pub const fn red(b: bool) -> Self {Self(Color::Iso { color: Iso::Red, bright: b })}
+    pub const fn green(b: bool) -> Self {Self(Color::Iso { color: Iso::Green, bright: b })}
+    pub const fn yellow(b: bool) -> Self {Self(Color::Iso { color: Iso::Yellow, bright: b })}
+    pub const fn blue(b: bool) -> Self {Self(Color::Iso { color: Iso::Blue, bright: b })}
+    pub const fn magenta(b: bool) -> Self {Self(Color::Iso { color: Iso::Magenta, bright: b })}
+    pub const fn cyan(b: bool) -> Self {Self(Color::Iso { color: Iso::Cyan, bright: b })}
+    pub const fn white(b: bool) -> Self {Self(Color::Iso { color: Iso::White, bright: b })}
+    // The terminal's default color (SGR 39 / 49), unlike `Color::None`
+    // which leaves the current color alone.
+    pub const fn default_color() -> Self {Self(Color::Default)}
 
-    pub fn iso(iso: Iso, bright: bool) -> Self {
-        Self(Color::Iso { color: iso, bright: bright })
+    pub const fn iso(iso: Iso, bright: bool) -> Self {
+        Self(Color::Iso { color: iso, bright })
     }
 
-    pub fn extended(ext: u8) -> Self {
+    pub const fn extended(ext: u8) -> Self {
         Self(Color::Extended(ext))
     }
 
-    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
-        Self(Color::Rgb { r: r, g: g, b: b })
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(Color::Rgb { r, g, b })
+    }
+
+    pub const fn color(&self) -> Color {
+        self.0
     }
 
     pub fn to_ansi(&self) -> String {
@@ -54,13 +62,17 @@ impl Foreground {
             }
             Color::Extended(val) => format!("\x1b[38;5;{}m", val),
             Color::Rgb { r, g, b } => format!("\x1b[38;2;{};{};{}m", r, g, b),
-            Color::None => String::new()
+            Color::None => String::new(),
+            Color::Default => "\x1b[39m".to_string(),
         }
     }
 }
 
 impl std::fmt::Display for Foreground {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
         write!(f, "{}", self.to_ansi())
     }
 }
@@ -70,39 +82,50 @@ impl std::fmt::Display for Foreground {
 //  Background Color  //
 ////////////////////////
 
-#[derive(Debug, Copy, Hash,PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize, Clone)]
+#[derive(Debug, Copy, Hash,PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Background(Color);
 
 impl std::fmt::Display for Background {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
         write!(f, "{}", self.to_ansi())
     }
 }
 
 impl Background {
-    pub fn new(value: Color) -> Self {
+    pub const fn new(value: Color) -> Self {
         Self(value)
     }
 
-    pub fn black(b: bool) -> Self {Self(Color::Iso { color: Iso::Black, bright: b })}
-    pub fn red(b: bool) -> Self {Self(Color::Iso { color: Iso::Red, bright: b })}
-    pub fn green(b: bool) -> Self {Self(Color::Iso { color: Iso::Green, bright: b })}
-    pub fn yellow(b: bool) -> Self {Self(Color::Iso { color: Iso::Yellow, bright: b })}
-    pub fn blue(b: bool) -> Self {Self(Color::Iso { color: Iso::Blue, bright: b })}
-    pub fn magenta(b: bool) -> Self {Self(Color::Iso { color: Iso::Magenta, bright: b })}
-    pub fn cyan(b: bool) -> Self {Self(Color::Iso { color: Iso::Cyan, bright: b })}
-    pub fn white(b: bool) -> Self {Self(Color::Iso { color: Iso::White, bright: b })}
+    pub const fn black(b: bool) -> Self {Self(Color::Iso { color: Iso::Black, bright: b })}
+    pub const fn red(b: bool) -> Self {Self(Color::Iso { color: Iso::Red, bright: b })}
+    pub const fn green(b: bool) -> Self {Self(Color::Iso { color: Iso::Green, bright: b })}
+    pub const fn yellow(b: bool) -> Self {Self(Color::Iso { color: Iso::Yellow, bright: b })}
+    pub const fn blue(b: bool) -> Self {Self(Color::Iso { color: Iso::Blue, bright: b })}
+    pub const fn magenta(b: bool) -> Self {Self(Color::Iso { color: Iso::Magenta, bright: b })}
+    pub const fn cyan(b: bool) -> Self {Self(Color::Iso { color: Iso::Cyan, bright: b })}
+    pub const fn white(b: bool) -> Self {Self(Color::Iso { color: Iso::White, bright: b })}
+    // The terminal's default color (SGR 39 / 49), unlike `Color::None`
+    // which leaves the current color alone.
+    pub const fn default_color() -> Self {Self(Color::Default)}
 
-    pub fn iso(iso: Iso, bright: bool) -> Self {
-        Self(Color::Iso { color: iso, bright: bright })
+    pub const fn iso(iso: Iso, bright: bool) -> Self {
+        Self(Color::Iso { color: iso, bright })
     }
 
-    pub fn extended(ext: u8) -> Self {
+    pub const fn extended(ext: u8) -> Self {
         Self(Color::Extended(ext))
     }
 
-    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
-        Self(Color::Rgb { r: r, g: g, b: b })
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(Color::Rgb { r, g, b })
+    }
+
+    pub const fn color(&self) -> Color {
+        self.0
     }
 
     pub fn to_ansi(&self) -> String {
@@ -112,8 +135,37 @@ impl Background {
             }
             Color::Extended(val) => format!("\x1b[48;5;{}m", val),
             Color::Rgb { r, g, b } => format!("\x1b[48;2;{};{};{}m", r, g, b),
-            Color::None => String::new()
+            Color::None => String::new(),
+            Color::Default => "\x1b[49m".to_string(),
+        }
+    }
+}
+
+
+/////////////
+//  Reset  //
+/////////////
+
+// SGR 0: default colors and no attributes.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Reset;
+
+pub const fn reset() -> Reset {
+    Reset
+}
+
+impl std::fmt::Display for Reset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
         }
+        f.write_str("\x1b[0m")
+    }
+}
+
+impl crate::command::Command for Reset {
+    fn write_ansi(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        out.write_all(b"\x1b[0m")
     }
 }
 
@@ -122,15 +174,20 @@ impl Background {
 //  Color Value  //
 ///////////////////
 
-#[derive(Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize, Clone)]
+// With the `serde` feature, serialized as a string, see `Names` below.
+#[derive(Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum Color {
     Iso { color: Iso, bright: bool },
     Extended(u8),
     Rgb { r: u8, g: u8, b: u8 },
-    None
+    // Emits nothing, leaving whatever color is active.
+    None,
+    // The terminal's own color, SGR 39 / 49.
+    Default,
 }
 
-#[derive(Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize, Clone)]
+#[derive(Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Iso {
     Black,
     Red,
@@ -142,8 +199,93 @@ pub enum Iso {
     White,
 }
 
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color::Rgb { r, g, b }
+    }
+
+    pub const fn extended(n: u8) -> Self {
+        Color::Extended(n)
+    }
+
+    pub const fn iso(color: Iso, bright: bool) -> Self {
+        Color::Iso { color, bright }
+    }
+}
+
+impl Color {
+    // 0-7 are the normal colors, 8-15 their bright variants.
+    pub const fn from_ansi16_index(index: u8) -> Option<Color> {
+        match Iso::from_index(index % 8) {
+            Some(color) if index < 16 => Some(Color::Iso { color, bright: index >= 8 }),
+            _ => None,
+        }
+    }
+
+    // The 16 color index of this color, the nearest one for 256 palette and
+    // rgb colors. None for `Color::None` and `Color::Default`.
+    pub fn to_ansi16_index(&self) -> Option<u8> {
+        match self.downgrade(ColorDepth::Ansi16) {
+            Color::Iso { color, bright } => Some(color.index() + if bright { 8 } else { 0 }),
+            _ => None,
+        }
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Color::Rgb { r, g, b }
+    }
+}
+
+// A 256 color palette index.
+impl From<u8> for Color {
+    fn from(n: u8) -> Self {
+        Color::Extended(n)
+    }
+}
+
+// The normal (not bright) variant.
+impl From<Iso> for Color {
+    fn from(color: Iso) -> Self {
+        Color::Iso { color, bright: false }
+    }
+}
+
+impl From<Color> for Foreground {
+    fn from(color: Color) -> Self {
+        Self(color)
+    }
+}
+
+impl From<Color> for Background {
+    fn from(color: Color) -> Self {
+        Self(color)
+    }
+}
+
 impl Iso {
-    pub fn to_char(&self) -> char {
+    // Position in the ANSI order, 0 (black) to 7 (white), as used by SGR
+    // 30-37, terminfo `setaf` and the console palette.
+    pub const fn index(&self) -> u8 {
+        *self as u8
+    }
+
+    pub const fn from_index(index: u8) -> Option<Iso> {
+        Some(match index {
+            0 => Iso::Black,
+            1 => Iso::Red,
+            2 => Iso::Green,
+            3 => Iso::Yellow,
+            4 => Iso::Blue,
+            5 => Iso::Magenta,
+            6 => Iso::Cyan,
+            7 => Iso::White,
+            _ => return None,
+        })
+    }
+
+    pub const fn to_char(&self) -> char {
         match self {
             Iso::Black => '0',
             Iso::Red => '1',
@@ -158,3 +300,570 @@ impl Iso {
 }
 
 
+
+impl crate::command::Command for Foreground {
+    fn write_ansi(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        out.write_all(self.to_ansi().as_bytes())
+    }
+}
+
+impl crate::command::Command for Background {
+    fn write_ansi(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        out.write_all(self.to_ansi().as_bytes())
+    }
+}
+
+impl Foreground {
+    // The color the active theme gives `role`, reduced to what the terminal
+    // can show.
+    pub fn role(role: crate::theme::Role) -> Self {
+        Self(role.color())
+    }
+}
+
+impl Background {
+    pub fn role(role: crate::theme::Role) -> Self {
+        Self(role.color())
+    }
+}
+
+
+///////////////////
+//  Color Depth  //
+///////////////////
+
+// How many colors the terminal can show.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    // From `COLORTERM` and `TERM`, the way most terminal programs guess.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        Self::from_env(&colorterm, &term)
+    }
+
+    pub fn from_env(colorterm: &str, term: &str) -> Self {
+        if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else if term == "dumb" {
+            ColorDepth::None
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+static COLOR_DEPTH: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
+
+// Depth used by `Foreground::role` and friends, detected on first use.
+pub fn color_depth() -> ColorDepth {
+    match COLOR_DEPTH.load(std::sync::atomic::Ordering::Relaxed) {
+        1 => ColorDepth::None,
+        2 => ColorDepth::Ansi16,
+        3 => ColorDepth::Ansi256,
+        4 => ColorDepth::TrueColor,
+        _ => {
+            let depth = ColorDepth::detect();
+            set_color_depth(depth);
+            depth
+        }
+    }
+}
+
+pub fn set_color_depth(depth: ColorDepth) {
+    COLOR_DEPTH.store(depth as u8 + 1, std::sync::atomic::Ordering::Relaxed);
+}
+
+////////////////////
+//  Color Choice  //
+////////////////////
+
+// Whether escape sequences are emitted at all. `Auto` turns them off when
+// the output isn't a terminal, so `prog > log.txt` gets plain text. Applies
+// to the `Display` impls here (which assume stdout) and to every `Output`
+// created afterwards, see `Output::set_color_choice`.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled_for(&self, stream: &impl std::io::IsTerminal) -> bool {
+        self.resolve(stream.is_terminal())
+    }
+
+    pub fn resolve(&self, is_tty: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_tty,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+static COLOR_CHOICE: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
+// 0 unknown, 1 off, 2 on
+static STDOUT_COLORS: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
+
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as u8, std::sync::atomic::Ordering::Relaxed);
+    STDOUT_COLORS.store(0, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn color_choice() -> ColorChoice {
+    match COLOR_CHOICE.load(std::sync::atomic::Ordering::Relaxed) {
+        1 => ColorChoice::Always,
+        2 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+// The color choice applied to stdout, checked once per `set_color_choice`.
+pub fn colors_enabled() -> bool {
+    match STDOUT_COLORS.load(std::sync::atomic::Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            let on = color_choice().enabled_for(&std::io::stdout());
+            STDOUT_COLORS.store(if on { 2 } else { 1 }, std::sync::atomic::Ordering::Relaxed);
+            on
+        }
+    }
+}
+
+// xterm's default palette, in ISO order, normal then bright.
+const XTERM_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+impl Color {
+    // Rgb value, using the xterm palette for indexed colors.
+    pub fn to_rgb(&self) -> Option<(u8, u8, u8)> {
+        match *self {
+            Color::Iso { color, bright } => {
+                Some(XTERM_PALETTE[color.index() as usize + if bright { 8 } else { 0 }])
+            }
+            Color::Extended(n) if n < 16 => Some(XTERM_PALETTE[n as usize]),
+            Color::Extended(n) if n >= 232 => {
+                let level = 8 + (n - 232) * 10;
+                Some((level, level, level))
+            }
+            Color::Extended(n) => {
+                let n = n - 16;
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                Some((level(n / 36), level((n / 6) % 6), level(n % 6)))
+            }
+            Color::Rgb { r, g, b } => Some((r, g, b)),
+            Color::None | Color::Default => None,
+        }
+    }
+
+    // The closest color the terminal can show at `depth`. Colors already
+    // within it are returned as they are.
+    pub fn downgrade(&self, depth: ColorDepth) -> Color {
+        match (depth, *self) {
+            (ColorDepth::None, _) => Color::None,
+            (ColorDepth::TrueColor, c) | (_, c @ (Color::None | Color::Default | Color::Iso { .. })) => c,
+            (ColorDepth::Ansi256, c @ Color::Extended(_)) => c,
+            (ColorDepth::Ansi256, Color::Rgb { r, g, b }) => Color::Extended(nearest_256((r, g, b))),
+            (ColorDepth::Ansi16, c) => {
+                let rgb = c.to_rgb().unwrap_or_default();
+                let index = (0..16).min_by_key(|&i| distance(XTERM_PALETTE[i], rgb)).unwrap_or(0);
+                Color::from_ansi16_index(index as u8).unwrap_or(Color::None)
+            }
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> i32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+// Closest entry of the 6x6x6 cube or the gray ramp.
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let step = |v: u8| if v < 48 { 0 } else if v < 115 { 1 } else { (v - 35) / 40 };
+    let cube = 16 + 36 * step(rgb.0) + 6 * step(rgb.1) + step(rgb.2);
+    let avg = (rgb.0 as u16 + rgb.1 as u16 + rgb.2 as u16) / 3;
+    let gray = 232 + ((avg.saturating_sub(3)) / 10).min(23) as u8;
+    let rgb_of = |n: u8| Color::Extended(n).to_rgb().unwrap_or_default();
+    if distance(rgb_of(gray), rgb) < distance(rgb_of(cube), rgb) { gray } else { cube }
+}
+
+/////////////////////
+//  Interpolation  //
+/////////////////////
+
+// How `Color::mix` and `gradient` travel between two colors. Rgb is a plain
+// per channel lerp, which turns muddy and dark halfway between saturated
+// colors; Oklab keeps perceived lightness even and Oklch also keeps the
+// chroma, going around the hue circle the short way.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    Rgb,
+    #[default]
+    Oklab,
+    Oklch,
+}
+
+// A color in the OKLab space: lightness `l` (0 to 1) and the `a`
+// (green-red) and `b` (blue-yellow) axes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Oklab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+// OKLab in polar form: lightness, chroma and hue in degrees.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Oklch {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+fn to_linear(v: u8) -> f64 {
+    let v = v as f64 / 255.0;
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+fn from_linear(v: f64) -> u8 {
+    let v = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+impl Oklab {
+    pub fn from_rgb((r, g, b): (u8, u8, u8)) -> Self {
+        let (r, g, b) = (to_linear(r), to_linear(g), to_linear(b));
+        let l = (0.41222147 * r + 0.53633254 * g + 0.05144599 * b).cbrt();
+        let m = (0.21190350 * r + 0.68069955 * g + 0.10739696 * b).cbrt();
+        let s = (0.08830246 * r + 0.28171884 * g + 0.62997870 * b).cbrt();
+        Self {
+            l: 0.21045426 * l + 0.79361779 * m - 0.00407205 * s,
+            a: 1.97799850 * l - 2.42859221 * m + 0.45059371 * s,
+            b: 0.02590404 * l + 0.78277177 * m - 0.80867577 * s,
+        }
+    }
+
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        let l = (self.l + 0.39633778 * self.a + 0.21580376 * self.b).powi(3);
+        let m = (self.l - 0.10556135 * self.a - 0.06385417 * self.b).powi(3);
+        let s = (self.l - 0.08948418 * self.a - 1.29148555 * self.b).powi(3);
+        (
+            from_linear(4.07674166 * l - 3.30771159 * m + 0.23096993 * s),
+            from_linear(-1.26843800 * l + 2.60975740 * m - 0.34131940 * s),
+            from_linear(-0.00419609 * l - 0.70341861 * m + 1.70761470 * s),
+        )
+    }
+
+    pub fn to_oklch(&self) -> Oklch {
+        Oklch {
+            l: self.l,
+            c: self.a.hypot(self.b),
+            h: self.b.atan2(self.a).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+impl Oklch {
+    pub fn to_oklab(&self) -> Oklab {
+        let h = self.h.to_radians();
+        Oklab { l: self.l, a: self.c * h.cos(), b: self.c * h.sin() }
+    }
+}
+
+impl Color {
+    // The color `t` (0 to 1) of the way from `self` to `other`. Indexed
+    // colors are mixed through their xterm palette rgb; `Color::None` has no
+    // value to mix, so the nearer end is returned.
+    pub fn mix(&self, other: &Color, t: f64, how: Interpolation) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let (Some(a), Some(b)) = (self.to_rgb(), other.to_rgb()) else {
+            return if t < 0.5 { *self } else { *other };
+        };
+        let lerp = |x: f64, y: f64| x + (y - x) * t;
+        let (r, g, b) = match how {
+            Interpolation::Rgb => (
+                lerp(a.0 as f64, b.0 as f64).round() as u8,
+                lerp(a.1 as f64, b.1 as f64).round() as u8,
+                lerp(a.2 as f64, b.2 as f64).round() as u8,
+            ),
+            Interpolation::Oklab => {
+                let (a, b) = (Oklab::from_rgb(a), Oklab::from_rgb(b));
+                Oklab { l: lerp(a.l, b.l), a: lerp(a.a, b.a), b: lerp(a.b, b.b) }.to_rgb()
+            }
+            Interpolation::Oklch => {
+                let (a, b) = (Oklab::from_rgb(a).to_oklch(), Oklab::from_rgb(b).to_oklch());
+                // shorter way around, and no hue to follow for grays
+                let mut dh = (b.h - a.h).rem_euclid(360.0);
+                if dh > 180.0 {
+                    dh -= 360.0;
+                }
+                let h = if a.c < 1e-4 { b.h } else if b.c < 1e-4 { a.h } else { a.h + dh * t };
+                Oklch { l: lerp(a.l, b.l), c: lerp(a.c, b.c), h }.to_oklab().to_rgb()
+            }
+        };
+        Color::Rgb { r, g, b }
+    }
+}
+
+impl Color {
+    // `over` drawn with opacity `alpha` (0 to 1) on top of `self`, the way a
+    // translucent layer looks: `self.blend(shadow, 0.6)` dims a pane. The
+    // terminal can't blend, so this is worked out here against a known
+    // background. Blends per channel in sRGB, like browsers do. When either
+    // side is `Color::None` the result is whichever dominates.
+    pub fn blend(&self, over: Color, alpha: f32) -> Color {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let (Some(base), Some(top)) = (self.to_rgb(), over.to_rgb()) else {
+            return if alpha < 0.5 { *self } else { over };
+        };
+        let mix = |b: u8, t: u8| (b as f32 + (t as f32 - b as f32) * alpha).round() as u8;
+        Color::Rgb { r: mix(base.0, top.0), g: mix(base.1, top.1), b: mix(base.2, top.2) }
+    }
+}
+
+// `steps` colors evenly spread over `stops`, first and last stop included,
+// e.g. for the cells of a progress bar.
+pub fn gradient(stops: &[Color], steps: usize, how: Interpolation) -> Vec<Color> {
+    match (stops, steps) {
+        ([], _) | (_, 0) => Vec::new(),
+        ([only], _) => vec![*only; steps],
+        (_, 1) => vec![stops[0]],
+        _ => (0..steps)
+            .map(|i| {
+                let pos = i as f64 / (steps - 1) as f64 * (stops.len() - 1) as f64;
+                let seg = (pos.floor() as usize).min(stops.len() - 2);
+                stops[seg].mix(&stops[seg + 1], pos - seg as f64, how)
+            })
+            .collect(),
+    }
+}
+
+
+/////////////
+//  Names  //
+/////////////
+
+// Colors as people write them in config files and read them in logs:
+// "red", "bright-blue", "#ff8800", "213" (256 color palette), "none",
+// "default". `Display` and `FromStr` use this form, as does serde.
+const ISO_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+impl std::fmt::Display for Iso {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(ISO_NAMES[self.index() as usize])
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Color::Iso { color, bright: false } => write!(f, "{}", color),
+            Color::Iso { color, bright: true } => write!(f, "bright-{}", color),
+            Color::Extended(n) => write!(f, "{}", n),
+            Color::Rgb { r, g, b } => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::None => f.write_str("none"),
+            Color::Default => f.write_str("default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl std::fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown color \"{}\", expected a name like \"bright-blue\", \"#rrggbb\" or 0-255", self.0)
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+impl std::str::FromStr for Iso {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        ISO_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .and_then(|i| Iso::from_index(i as u8))
+            .ok_or_else(|| ParseColorError(s.to_string()))
+    }
+}
+
+// Case and surrounding blanks don't matter.
+impl std::str::FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseColorError(s.to_string());
+        let lower = s.trim().to_ascii_lowercase();
+        if let Some(hex) = lower.strip_prefix('#') {
+            // from_str_radix and parse would take a leading '+'
+            if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(err());
+            }
+            let v = u32::from_str_radix(hex, 16).map_err(|_| err())?;
+            return Ok(Color::rgb((v >> 16) as u8, (v >> 8) as u8, v as u8));
+        }
+        if lower.bytes().all(|b| b.is_ascii_digit()) {
+            if let Ok(n) = lower.parse::<u8>() {
+                return Ok(Color::Extended(n));
+            }
+        }
+        match lower.as_str() {
+            "none" => Ok(Color::None),
+            "default" => Ok(Color::Default),
+            _ => match lower.strip_prefix("bright-") {
+                Some(name) => Ok(Color::iso(name.parse().map_err(|_| err())?, true)),
+                None => Ok(Color::iso(lower.parse().map_err(|_| err())?, false)),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Background, Color, ColorChoice, ColorDepth, Foreground, Interpolation, Iso, Oklab, gradient, reset};
+    use crate::command::Command;
+
+    #[test]
+    fn downgrades() {
+        let orange = Color::Rgb { r: 255, g: 135, b: 0 };
+        assert_eq!(orange.downgrade(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.downgrade(ColorDepth::Ansi256), Color::Extended(208));
+        assert_eq!(orange.downgrade(ColorDepth::Ansi16), Color::Iso { color: Iso::Yellow, bright: false });
+        assert_eq!(Color::Extended(244).downgrade(ColorDepth::Ansi16), Color::Iso { color: Iso::Black, bright: true });
+        assert_eq!(Color::Rgb { r: 128, g: 128, b: 128 }.downgrade(ColorDepth::Ansi256), Color::Extended(244));
+        assert_eq!(orange.downgrade(ColorDepth::None), Color::None);
+        assert_eq!(ColorDepth::from_env("", "xterm-256color"), ColorDepth::Ansi256);
+    }
+
+    #[test]
+    fn oklab_round_trips_and_mixes() {
+        for rgb in [(0, 0, 0), (255, 255, 255), (255, 0, 0), (12, 200, 99)] {
+            assert_eq!(Oklab::from_rgb(rgb).to_rgb(), rgb);
+        }
+        let red = Color::Rgb { r: 255, g: 0, b: 0 };
+        let green = Color::Rgb { r: 0, g: 255, b: 0 };
+        assert_eq!(red.mix(&green, 0.5, Interpolation::Rgb), Color::Rgb { r: 128, g: 128, b: 0 });
+        // perceptual midpoints are brighter than the muddy rgb one
+        let lightness = |c: Color| Oklab::from_rgb(c.to_rgb().unwrap()).l;
+        for how in [Interpolation::Oklab, Interpolation::Oklch] {
+            let mid = red.mix(&green, 0.5, how);
+            assert!(lightness(mid) > lightness(red.mix(&green, 0.5, Interpolation::Rgb)));
+        }
+        let steps = gradient(&[red, green, red], 5, Interpolation::Oklch);
+        assert_eq!((steps.len(), steps[0], steps[2], steps[4]), (5, red, green, red));
+    }
+
+    #[test]
+    fn blends_over_background() {
+        let bg = Color::Rgb { r: 200, g: 100, b: 0 };
+        let black = Color::Rgb { r: 0, g: 0, b: 0 };
+        assert_eq!(bg.blend(black, 0.5), Color::Rgb { r: 100, g: 50, b: 0 });
+        assert_eq!(bg.blend(black, 0.0), bg);
+        assert_eq!(bg.blend(Color::None, 0.7), Color::None);
+        assert_eq!(Color::Iso { color: Iso::White, bright: true }.blend(black, 1.0), black);
+    }
+
+    #[test]
+    fn const_tables_and_conversions() {
+        const PALETTE: [Color; 2] = [Color::rgb(1, 2, 3), Color::iso(Iso::Red, true)];
+        const TITLE: Foreground = Foreground::rgb(1, 2, 3);
+        assert_eq!(TITLE.color(), PALETTE[0]);
+        assert_eq!(Color::from((1, 2, 3)), PALETTE[0]);
+        assert_eq!(Color::from(208), Color::Extended(208));
+        assert_eq!(Color::from(Iso::Red), Color::iso(Iso::Red, false));
+        assert_eq!(Background::from(Color::from(Iso::Blue)), Background::blue(false));
+    }
+
+    #[test]
+    fn ansi16_indices() {
+        assert_eq!(Iso::from_index(4), Some(Iso::Blue));
+        assert_eq!(Iso::from_index(8), None);
+        assert_eq!(Color::from_ansi16_index(9), Some(Color::iso(Iso::Red, true)));
+        assert_eq!(Color::from_ansi16_index(16), None);
+        for i in 0..16 {
+            assert_eq!(Color::from_ansi16_index(i).unwrap().to_ansi16_index(), Some(i));
+        }
+        assert_eq!(Color::Extended(12).to_ansi16_index(), Some(12));
+        assert_eq!(Color::rgb(250, 250, 250).to_ansi16_index(), Some(15));
+        assert_eq!(Color::Default.to_ansi16_index(), None);
+    }
+
+    #[test]
+    fn color_choice() {
+        assert!(ColorChoice::Auto.resolve(true));
+        assert!(!ColorChoice::Auto.resolve(false));
+        assert!(ColorChoice::Always.resolve(false));
+        assert!(!ColorChoice::Never.resolve(true));
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for color in [Color::iso(Iso::Cyan, false), Color::iso(Iso::White, true), Color::rgb(1, 2, 255), Color::Extended(0), Color::None, Color::Default] {
+            assert_eq!(color.to_string().parse::<Color>(), Ok(color));
+        }
+        assert_eq!(" Bright-Red ".parse::<Color>(), Ok(Color::iso(Iso::Red, true)));
+        assert_eq!("#FF8800".parse::<Color>(), Ok(Color::rgb(255, 136, 0)));
+        assert_eq!(Iso::Magenta.to_string(), "magenta");
+        assert!("#ff88".parse::<Color>().is_err());
+        assert!("256".parse::<Color>().is_err());
+        assert!("#+12345".parse::<Color>().is_err());
+        assert!("+5".parse::<Color>().is_err());
+        assert!("bright-".parse::<Iso>().is_err());
+    }
+
+    #[test]
+    fn default_and_reset() {
+        assert_eq!(Foreground::default_color().to_ansi(), "\x1b[39m");
+        assert_eq!(Background::default_color().to_ansi(), "\x1b[49m");
+        assert_eq!(Foreground::new(Color::None).to_ansi(), "");
+        assert_eq!(reset().to_ansi_string(), "\x1b[0m");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn readable_serde() {
+        let colors = [Color::iso(Iso::Red, false), Color::iso(Iso::Blue, true), Color::rgb(255, 136, 0), Color::Extended(213), Color::None, Color::Default];
+        let json = serde_json::to_string(&colors).unwrap();
+        assert_eq!(json, r##"["red","bright-blue","#ff8800","213","none","default"]"##);
+        assert_eq!(serde_json::from_str::<Vec<Color>>(&json).unwrap(), colors);
+        assert!(serde_json::from_str::<Color>("\"purple\"").is_err());
+    }
+}
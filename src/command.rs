@@ -0,0 +1,184 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Every escape sequence the crate knows how to emit is a `Command`. Commands
+// are written to any `Write` and can be batched with `queue!` (write only) or
+// `execute!` (write then flush):
+//
+//     queue!(out, cursor::MoveTo(4, 2), Foreground::red(false), Print("hi"))?;
+//     execute!(out, cursor::Show)?;
+
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static EIGHT_BIT: AtomicBool = AtomicBool::new(false);
+
+pub trait Command {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()>;
+
+    // The bytes this command would write, mostly useful for tests and logs.
+    fn to_ansi_string(&self) -> String {
+        let mut buf = Vec::new();
+        let _ = self.write_ansi(&mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+impl<T: Command + ?Sized> Command for &T {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        (**self).write_ansi(out)
+    }
+}
+
+// Makes `queue!`, `execute!` and everything else in the crate writing
+// escape sequences (`Screen` rendering, prompts, ...) send 8-bit C1 controls
+// (CSI as 0x9b, ST as 0x9c, ...) in place of their two byte ESC forms, for
+// slow serial lines.
+// Crate wide and off by default. Only for terminals that accept them, which
+// rules out most terminals decoding UTF-8.
+pub fn set_eight_bit_controls(on: bool) {
+    EIGHT_BIT.store(on, Ordering::Relaxed);
+}
+
+pub fn eight_bit_controls() -> bool {
+    EIGHT_BIT.load(Ordering::Relaxed)
+}
+
+// Rewrites ESC followed by 0x40..=0x5f as the matching C1 byte.
+pub fn to_eight_bit(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i..] {
+            [0x1b, b @ 0x40..=0x5f, ..] => {
+                out.push(b + 0x40);
+                i += 2;
+            }
+            [b, ..] => {
+                out.push(b);
+                i += 1;
+            }
+            [] => break,
+        }
+    }
+    out
+}
+
+// What `queue!` calls for each command, honouring `set_eight_bit_controls`.
+// The command is rendered first and written with one call, so a
+// `SharedWriter` never lets another thread in halfway through a sequence.
+pub fn write_command(command: &impl Command, out: &mut impl Write) -> io::Result<()> {
+    let mut buf = Vec::new();
+    command.write_ansi(&mut buf)?;
+    write_controls(&buf, out)
+}
+
+// Writes already rendered output, e.g. a whole `Screen` frame, with the
+// same 8-bit rewrite as `write_command`.
+pub fn write_controls(bytes: &[u8], out: &mut impl Write) -> io::Result<()> {
+    if eight_bit_controls() {
+        out.write_all(&to_eight_bit(bytes))
+    } else {
+        out.write_all(bytes)
+    }
+}
+
+// Asks the terminal to send its replies and reports with 8-bit controls
+// (S8C1T). The input parser understands either.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EightBitReplies;
+
+impl Command for EightBitReplies {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b G")
+    }
+}
+
+// S7C1T, back to ESC prefixed replies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SevenBitReplies;
+
+impl Command for SevenBitReplies {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b F")
+    }
+}
+
+// Writes plain text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Print<T: Display>(pub T);
+
+impl<T: Display> Command for Print<T> {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{}", self.0)
+    }
+}
+
+// Writes a sequence of commands without flushing. Evaluates to
+// `io::Result<()>` and stops at the first error.
+#[macro_export]
+macro_rules! queue {
+    ($writer:expr $(, $command:expr)* $(,)?) => {{
+        let writer = &mut $writer;
+        let result: ::std::io::Result<()> = Ok(());
+        $(
+            let result = result.and_then(|_| $crate::command::write_command(&$command, writer));
+        )*
+        result
+    }};
+}
+
+// Like `queue!` but flushes the writer afterwards.
+#[macro_export]
+macro_rules! execute {
+    ($writer:expr $(, $command:expr)* $(,)?) => {{
+        let writer = &mut $writer;
+        $crate::queue!(*writer $(, $command)*)
+            .and_then(|_| ::std::io::Write::flush(writer))
+    }};
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Print, to_eight_bit};
+    use crate::color::Foreground;
+    use crate::cursor::MoveTo;
+    use crate::erase::{Clear, ClearType};
+
+    #[test]
+    fn queue_writes_in_order() {
+        let mut out: Vec<u8> = Vec::new();
+        queue!(
+            out,
+            Clear(ClearType::All),
+            MoveTo(4, 2),
+            Foreground::red(false),
+            Print("hi")
+        )
+        .unwrap();
+        assert_eq!(out, b"\x1b[2J\x1b[3;5H\x1b[31mhi");
+    }
+
+    #[test]
+    fn eight_bit_forms() {
+        assert_eq!(
+            to_eight_bit(b"\x1b[2J\x1b]0;t\x1b\\\x1b7h\xc3\xa9"),
+            b"\x9b2J\x9d0;t\x9c\x1b7h\xc3\xa9"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Adapters for projects moving over from crossterm one piece at a time.
+// `Crossterm` wraps a crossterm command so it can go through `queue!` and
+// `execute!` here, `Native` wraps one of ours for crossterm's macros:
+//
+//   my_term::queue!(out, Crossterm(crossterm::cursor::MoveTo(1, 1)), Print("hi"))?;
+//   crossterm::queue!(out, Native(Foreground::red(false)))?;
+//
+// Both sides write to any `io::Write`, so a `Terminal` can be handed to
+// crossterm's macros as is. Colors convert both ways with `From`.
+
+use crate::color::{Color, Iso};
+use crate::command::Command;
+use crossterm::style::Color as CtColor;
+use std::fmt;
+use std::io::{self, Write};
+
+// A crossterm command usable as a `Command`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Crossterm<C: crossterm::Command>(pub C);
+
+impl<C: crossterm::Command> Command for Crossterm<C> {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut s = String::new();
+        self.0.write_ansi(&mut s).map_err(io::Error::other)?;
+        out.write_all(s.as_bytes())
+    }
+}
+
+// One of this crate's commands usable as a crossterm command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Native<C: Command>(pub C);
+
+impl<C: Command> crossterm::Command for Native<C> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str(&self.0.to_ansi_string())
+    }
+
+    // Commands here only have an escape sequence form.
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "command needs virtual terminal processing",
+        ))
+    }
+
+    #[cfg(windows)]
+    fn is_ansi_code_supported(&self) -> bool {
+        true
+    }
+}
+
+// crossterm names the normal intensity colors "dark" and the bright ones
+// plainly, with grey standing in for (bright) black and white.
+impl From<CtColor> for Color {
+    fn from(color: CtColor) -> Self {
+        let iso = |color, bright| Color::Iso { color, bright };
+        match color {
+            CtColor::Reset => Color::None,
+            CtColor::Black => iso(Iso::Black, false),
+            CtColor::DarkGrey => iso(Iso::Black, true),
+            CtColor::DarkRed => iso(Iso::Red, false),
+            CtColor::Red => iso(Iso::Red, true),
+            CtColor::DarkGreen => iso(Iso::Green, false),
+            CtColor::Green => iso(Iso::Green, true),
+            CtColor::DarkYellow => iso(Iso::Yellow, false),
+            CtColor::Yellow => iso(Iso::Yellow, true),
+            CtColor::DarkBlue => iso(Iso::Blue, false),
+            CtColor::Blue => iso(Iso::Blue, true),
+            CtColor::DarkMagenta => iso(Iso::Magenta, false),
+            CtColor::Magenta => iso(Iso::Magenta, true),
+            CtColor::DarkCyan => iso(Iso::Cyan, false),
+            CtColor::Cyan => iso(Iso::Cyan, true),
+            CtColor::Grey => iso(Iso::White, false),
+            CtColor::White => iso(Iso::White, true),
+            CtColor::Rgb { r, g, b } => Color::Rgb { r, g, b },
+            CtColor::AnsiValue(n) => Color::Extended(n),
+        }
+    }
+}
+
+impl From<Color> for CtColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Iso { color, bright } => match (color, bright) {
+                (Iso::Black, false) => CtColor::Black,
+                (Iso::Black, true) => CtColor::DarkGrey,
+                (Iso::Red, false) => CtColor::DarkRed,
+                (Iso::Red, true) => CtColor::Red,
+                (Iso::Green, false) => CtColor::DarkGreen,
+                (Iso::Green, true) => CtColor::Green,
+                (Iso::Yellow, false) => CtColor::DarkYellow,
+                (Iso::Yellow, true) => CtColor::Yellow,
+                (Iso::Blue, false) => CtColor::DarkBlue,
+                (Iso::Blue, true) => CtColor::Blue,
+                (Iso::Magenta, false) => CtColor::DarkMagenta,
+                (Iso::Magenta, true) => CtColor::Magenta,
+                (Iso::Cyan, false) => CtColor::DarkCyan,
+                (Iso::Cyan, true) => CtColor::Cyan,
+                (Iso::White, false) => CtColor::Grey,
+                (Iso::White, true) => CtColor::White,
+            },
+            Color::Extended(n) => CtColor::AnsiValue(n),
+            Color::Rgb { r, g, b } => CtColor::Rgb { r, g, b },
+            Color::None | Color::Default => CtColor::Reset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Crossterm, Native};
+    use crate::color::{Color, Foreground, Iso};
+    use crate::command::{Command, Print};
+    use crossterm::style::Color as CtColor;
+
+    #[test]
+    fn commands_both_ways() {
+        let mut out = Vec::new();
+        crate::queue!(out, Crossterm(crossterm::cursor::MoveTo(1, 2)), Print("hi")).unwrap();
+        assert_eq!(out, b"\x1b[3;2Hhi");
+
+        let mut out = Vec::new();
+        crossterm::queue!(out, Native(Foreground::red(false)), crossterm::cursor::Hide).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}\x1b[?25l", Foreground::red(false).to_ansi_string())
+        );
+    }
+
+    #[test]
+    fn colors_match_sgr() {
+        for n in 0..16 {
+            let color = Color::from_ansi16_index(n).unwrap();
+            assert_eq!(Color::from(CtColor::from(color)), color);
+        }
+        assert_eq!(CtColor::from(Color::iso(Iso::Red, true)), CtColor::Red);
+        assert_eq!(Color::from(CtColor::DarkGrey), Color::iso(Iso::Black, true));
+    }
+}
@@ -0,0 +1,241 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Cursor movement and visibility commands. Positions are zero based
+// (col, row) and converted to the one based values the terminal expects.
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+// CUP, moves to an absolute position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveTo(pub u16, pub u16);
+
+impl Command for MoveTo {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{};{}H", self.1 + 1, self.0 + 1)
+    }
+}
+
+// CUU
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveUp(pub u16);
+
+impl Command for MoveUp {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}A", self.0)
+    }
+}
+
+// CUD
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveDown(pub u16);
+
+impl Command for MoveDown {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}B", self.0)
+    }
+}
+
+// CUF
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveRight(pub u16);
+
+impl Command for MoveRight {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}C", self.0)
+    }
+}
+
+// CUB
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveLeft(pub u16);
+
+impl Command for MoveLeft {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}D", self.0)
+    }
+}
+
+// CHA, moves to a column on the current row.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveToColumn(pub u16);
+
+impl Command for MoveToColumn {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}G", self.0 + 1)
+    }
+}
+
+// VPA, moves to a row keeping the column.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveToRow(pub u16);
+
+impl Command for MoveToRow {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}d", self.0 + 1)
+    }
+}
+
+// CNL, start of the line `n` rows down.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveToNextLine(pub u16);
+
+impl Command for MoveToNextLine {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}E", self.0)
+    }
+}
+
+// CPL, start of the line `n` rows up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveToPreviousLine(pub u16);
+
+impl Command for MoveToPreviousLine {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}F", self.0)
+    }
+}
+
+// DECSC, saves position and attributes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SavePosition;
+
+impl Command for SavePosition {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b7")
+    }
+}
+
+// DECRC
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RestorePosition;
+
+impl Command for RestorePosition {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b8")
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Show;
+
+impl Command for Show {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[?25h")
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Hide;
+
+impl Command for Hide {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[?25l")
+    }
+}
+
+// Shortest byte sequence moving the cursor from `from` to `to`, choosing
+// between absolute positioning and relative moves (CR, BS, CUU/CUD/CUF/CUB,
+// CHA, VPA) the way curses does. `from` is None when the position is unknown.
+//
+// Bare LF is never used since whether it also returns the carriage depends on
+// the ONLCR output flag.
+pub fn cheapest_move(from: Option<(u16, u16)>, to: (u16, u16)) -> String {
+    let absolute = if to == (0, 0) {
+        "\x1b[H".to_string()
+    } else {
+        MoveTo(to.0, to.1).to_ansi_string()
+    };
+    let Some((fc, fr)) = from else {
+        return absolute;
+    };
+    let (tc, tr) = to;
+
+    let mut best = absolute;
+    let mut consider = |candidate: String| {
+        if candidate.len() < best.len() {
+            best = candidate;
+        }
+    };
+
+    let vertical: Vec<String> = if fr == tr {
+        vec![String::new()]
+    } else {
+        let relative = if tr > fr {
+            count_seq(tr - fr, 'B')
+        } else {
+            count_seq(fr - tr, 'A')
+        };
+        vec![relative, MoveToRow(tr).to_ansi_string()]
+    };
+
+    let mut horizontal: Vec<String> = Vec::new();
+    if fc == tc {
+        horizontal.push(String::new());
+    } else {
+        if tc < fc {
+            let n = fc - tc;
+            horizontal.push("\x08".repeat(n as usize));
+            horizontal.push(count_seq(n, 'D'));
+        } else {
+            horizontal.push(count_seq(tc - fc, 'C'));
+        }
+        horizontal.push(MoveToColumn(tc).to_ansi_string());
+    }
+    if tc == 0 {
+        horizontal.push("\r".to_string());
+    } else {
+        horizontal.push(format!("\r{}", count_seq(tc, 'C')));
+    }
+
+    for v in &vertical {
+        for h in &horizontal {
+            consider(format!("{}{}", v, h));
+        }
+    }
+    if tc == 0 && tr == fr + 1 {
+        consider("\r\n".to_string());
+    }
+    best
+}
+
+// `CSI n x`, leaving out n when it is 1.
+fn count_seq(n: u16, c: char) -> String {
+    if n == 1 {
+        format!("\x1b[{}", c)
+    } else {
+        format!("\x1b[{}{}", n, c)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::cheapest_move;
+
+    #[test]
+    fn picks_shortest_move() {
+        assert_eq!(cheapest_move(None, (4, 2)), "\x1b[3;5H");
+        assert_eq!(cheapest_move(Some((9, 3)), (0, 0)), "\x1b[H");
+        assert_eq!(cheapest_move(Some((9, 3)), (0, 3)), "\r");
+        assert_eq!(cheapest_move(Some((9, 3)), (0, 4)), "\r\n");
+        assert_eq!(cheapest_move(Some((9, 3)), (8, 3)), "\x08");
+        assert_eq!(cheapest_move(Some((9, 3)), (20, 3)), "\x1b[11C");
+        assert_eq!(cheapest_move(Some((40, 3)), (40, 2)), "\x1b[A");
+        assert_eq!(cheapest_move(Some((40, 99)), (1, 100)), "\x1b[B\x1b[2G");
+    }
+}
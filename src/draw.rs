@@ -0,0 +1,473 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Line and box drawing with the Unicode box drawing characters. Everything is
+// drawn through `DrawTarget`, implemented for `Screen` (buffered) and for any
+// writer (immediate, using cursor moves).
+
+use crate::backend::Backend;
+use crate::command::write_command;
+use crate::cursor::MoveTo;
+use crate::layout::{Align, Rect};
+use crate::screen::{Screen, Viewport};
+use crate::style::Style;
+use crate::width::{str_width, truncate};
+use std::io::{self, Write};
+
+///////////////
+//  LineSet  //
+///////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LineSet {
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    pub cross: char,
+    // T junctions, named after the direction the stem points.
+    pub tee_down: char,
+    pub tee_up: char,
+    pub tee_right: char,
+    pub tee_left: char,
+}
+
+impl LineSet {
+    pub const SINGLE: Self = Self {
+        horizontal: '─',
+        vertical: '│',
+        top_left: '┌',
+        top_right: '┐',
+        bottom_left: '└',
+        bottom_right: '┘',
+        cross: '┼',
+        tee_down: '┬',
+        tee_up: '┴',
+        tee_right: '├',
+        tee_left: '┤',
+    };
+
+    pub const DOUBLE: Self = Self {
+        horizontal: '═',
+        vertical: '║',
+        top_left: '╔',
+        top_right: '╗',
+        bottom_left: '╚',
+        bottom_right: '╝',
+        cross: '╬',
+        tee_down: '╦',
+        tee_up: '╩',
+        tee_right: '╠',
+        tee_left: '╣',
+    };
+
+    // Single lines with rounded corners.
+    pub const ROUNDED: Self = Self {
+        top_left: '╭',
+        top_right: '╮',
+        bottom_left: '╰',
+        bottom_right: '╯',
+        ..Self::SINGLE
+    };
+
+    pub const HEAVY: Self = Self {
+        horizontal: '━',
+        vertical: '┃',
+        top_left: '┏',
+        top_right: '┓',
+        bottom_left: '┗',
+        bottom_right: '┛',
+        cross: '╋',
+        tee_down: '┳',
+        tee_up: '┻',
+        tee_right: '┣',
+        tee_left: '┫',
+    };
+}
+
+//////////////////
+//  DrawTarget  //
+//////////////////
+
+pub trait DrawTarget {
+    // Draws `text` on one row starting at (col, row).
+    fn draw_str(&mut self, col: u16, row: u16, text: &str, style: Style) -> io::Result<()>;
+
+    // What is already at (col, row), for targets that can tell. Lets
+    // borders join the lines they cross.
+    fn symbol_at(&self, _col: u16, _row: u16) -> Option<&str> {
+        None
+    }
+}
+
+impl DrawTarget for Screen {
+    fn draw_str(&mut self, col: u16, row: u16, text: &str, style: Style) -> io::Result<()> {
+        self.print(col, row, text, style);
+        Ok(())
+    }
+
+    fn symbol_at(&self, col: u16, row: u16) -> Option<&str> {
+        self.get(col, row).map(|c| c.symbol())
+    }
+}
+
+impl DrawTarget for Viewport<'_> {
+    fn draw_str(&mut self, col: u16, row: u16, text: &str, style: Style) -> io::Result<()> {
+        self.print(col, row, text, style);
+        Ok(())
+    }
+
+    fn symbol_at(&self, col: u16, row: u16) -> Option<&str> {
+        self.get(col, row).map(|c| c.symbol())
+    }
+}
+
+impl<W: Write> DrawTarget for W {
+    fn draw_str(&mut self, col: u16, row: u16, text: &str, style: Style) -> io::Result<()> {
+        write_command(&MoveTo(col, row), self)?;
+        if style.is_plain() {
+            return self.write_all(text.as_bytes());
+        }
+        write_command(&style, self)?;
+        self.write_all(text.as_bytes())?;
+        write_command(&Style::new(), self)
+    }
+}
+
+//////////////////
+//  Primitives  //
+//////////////////
+
+// Horizontal rule `len` cells long.
+pub fn hline<T: DrawTarget + ?Sized>(
+    target: &mut T,
+    col: u16,
+    row: u16,
+    len: u16,
+    set: &LineSet,
+    style: Style,
+) -> io::Result<()> {
+    let line: String = std::iter::repeat_n(set.horizontal, len as usize).collect();
+    target.draw_str(col, row, &line, style)
+}
+
+// Vertical rule `len` cells tall.
+pub fn vline<T: DrawTarget + ?Sized>(
+    target: &mut T,
+    col: u16,
+    row: u16,
+    len: u16,
+    set: &LineSet,
+    style: Style,
+) -> io::Result<()> {
+    let mut buf = [0; 4];
+    let s = set.vertical.encode_utf8(&mut buf);
+    for r in row..row.saturating_add(len) {
+        target.draw_str(col, r, s, style)?;
+    }
+    Ok(())
+}
+
+// Outline of a `width` x `height` box with its top left corner at
+// (col, row). The inside is left untouched. Boxes smaller than 2x2 are
+// not drawn, nor are edges that would lie past column or row `u16::MAX`.
+pub fn rect<T: DrawTarget + ?Sized>(
+    target: &mut T,
+    col: u16,
+    row: u16,
+    width: u16,
+    height: u16,
+    set: &LineSet,
+    style: Style,
+) -> io::Result<()> {
+    if width < 2 || height < 2 {
+        return Ok(());
+    }
+    let inner = std::iter::repeat_n(set.horizontal, width as usize - 2);
+    let top: String = std::iter::once(set.top_left)
+        .chain(inner.clone())
+        .chain(std::iter::once(set.top_right))
+        .collect();
+    let bottom_line: String = std::iter::once(set.bottom_left)
+        .chain(inner)
+        .chain(std::iter::once(set.bottom_right))
+        .collect();
+    target.draw_str(col, row, &top, style)?;
+    vline(target, col, row.saturating_add(1), height - 2, set, style)?;
+    if let Some(right) = col.checked_add(width - 1) {
+        vline(target, right, row.saturating_add(1), height - 2, set, style)?;
+    }
+    match row.checked_add(height - 1) {
+        Some(bottom) => target.draw_str(col, bottom, &bottom_line, style),
+        None => Ok(()),
+    }
+}
+
+// Draws `text` with its top left corner at (col, row), starting every line
+// back at `col`. A bare LF only moves down once output post-processing
+// (ONLCR) is off, so each line gets its own cursor move instead. Lines and
+// characters falling outside the terminal are left out.
+pub fn print_at<B: Backend>(
+    out: &mut B,
+    col: u16,
+    row: u16,
+    text: &str,
+    style: Style,
+) -> io::Result<()> {
+    let (cols, rows) = out.size()?;
+    if col as u32 >= cols {
+        return Ok(());
+    }
+    let lines = text.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l));
+    for (line, row) in lines.zip(row as u32..rows) {
+        let line = truncate(line, (cols - col as u32) as usize);
+        if !line.is_empty() {
+            out.draw_str(col, row as u16, line, style)?;
+        }
+    }
+    Ok(())
+}
+
+///////////////
+//  Borders  //
+///////////////
+
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum BorderStyle {
+    #[default]
+    Single,
+    Double,
+    Rounded,
+    Heavy,
+}
+
+impl BorderStyle {
+    pub fn line_set(&self) -> &'static LineSet {
+        match self {
+            BorderStyle::Single => &LineSet::SINGLE,
+            BorderStyle::Double => &LineSet::DOUBLE,
+            BorderStyle::Rounded => &LineSet::ROUNDED,
+            BorderStyle::Heavy => &LineSet::HEAVY,
+        }
+    }
+}
+
+// Directions a line character reaches out to.
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+impl LineSet {
+    fn arms(&self, symbol: &str) -> Option<u8> {
+        let mut chars = symbol.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return None;
+        };
+        [
+            (self.horizontal, LEFT | RIGHT),
+            (self.vertical, UP | DOWN),
+            (self.top_left, DOWN | RIGHT),
+            (self.top_right, DOWN | LEFT),
+            (self.bottom_left, UP | RIGHT),
+            (self.bottom_right, UP | LEFT),
+            (self.cross, UP | DOWN | LEFT | RIGHT),
+            (self.tee_down, LEFT | RIGHT | DOWN),
+            (self.tee_up, LEFT | RIGHT | UP),
+            (self.tee_right, UP | DOWN | RIGHT),
+            (self.tee_left, UP | DOWN | LEFT),
+        ]
+        .into_iter()
+        .find(|(ch, _)| *ch == c)
+        .map(|(_, arms)| arms)
+    }
+
+    fn with_arms(&self, arms: u8) -> char {
+        match arms {
+            a if a == DOWN | RIGHT => self.top_left,
+            a if a == DOWN | LEFT => self.top_right,
+            a if a == UP | RIGHT => self.bottom_left,
+            a if a == UP | LEFT => self.bottom_right,
+            a if a == LEFT | RIGHT | DOWN => self.tee_down,
+            a if a == LEFT | RIGHT | UP => self.tee_up,
+            a if a == UP | DOWN | RIGHT => self.tee_right,
+            a if a == UP | DOWN | LEFT => self.tee_left,
+            a if a == UP | DOWN | LEFT | RIGHT => self.cross,
+            a if a & (LEFT | RIGHT) == 0 => self.vertical,
+            _ => self.horizontal,
+        }
+    }
+}
+
+// Outline of `area` in `border` lines, optionally with a title on the top
+// edge. Where the outline meets lines of the same style already on the
+// target (targets that can tell, like `Screen`), corners become the right
+// junctions, so panels sharing an edge look like one frame. The title is
+// truncated to fit between the corners.
+pub fn draw_border<T: DrawTarget + ?Sized>(
+    target: &mut T,
+    area: Rect,
+    border: BorderStyle,
+    style: Style,
+    title: Option<(&str, Align)>,
+) -> io::Result<()> {
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = area;
+    if width < 2 || height < 2 {
+        return Ok(());
+    }
+    let set = border.line_set();
+    // nothing can show past u16::MAX, so the box is cut off there
+    let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+    let put = |target: &mut T, col: u16, row: u16, arms: u8| {
+        let arms = arms
+            | target
+                .symbol_at(col, row)
+                .and_then(|s| set.arms(s))
+                .unwrap_or(0);
+        let mut buf = [0; 4];
+        target.draw_str(col, row, set.with_arms(arms).encode_utf8(&mut buf), style)
+    };
+    put(target, x, y, DOWN | RIGHT)?;
+    put(target, right, y, DOWN | LEFT)?;
+    put(target, x, bottom, UP | RIGHT)?;
+    put(target, right, bottom, UP | LEFT)?;
+    for col in x.saturating_add(1)..right {
+        put(target, col, y, LEFT | RIGHT)?;
+        put(target, col, bottom, LEFT | RIGHT)?;
+    }
+    for row in y.saturating_add(1)..bottom {
+        put(target, x, row, UP | DOWN)?;
+        put(target, right, row, UP | DOWN)?;
+    }
+
+    // " title " with at least one line cell left on either side
+    let Some((title, align)) = title.filter(|(t, _)| !t.is_empty()) else {
+        return Ok(());
+    };
+    let space = width.saturating_sub(4) as usize;
+    if space < 3 {
+        return Ok(());
+    }
+    let text = format!(" {} ", truncate(title, space - 2));
+    let offset = align.offset(str_width(&text) as u16, space as u16);
+    target.draw_str(x.saturating_add(2 + offset), y, &text, style)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{BorderStyle, LineSet, draw_border, hline, print_at, rect};
+    use crate::backend::TestBackend;
+    use crate::layout::{Align, Rect};
+    use crate::screen::Screen;
+    use crate::style::Style;
+    use crate::virtual_screen::VirtualScreen;
+
+    #[test]
+    fn rect_on_screen_and_writer() {
+        let mut screen = Screen::new(6, 4);
+        rect(&mut screen, 1, 0, 4, 3, &LineSet::ROUNDED, Style::new()).unwrap();
+        assert_eq!(screen.get(1, 0).unwrap().symbol(), "╭");
+        assert_eq!(screen.get(4, 2).unwrap().symbol(), "╯");
+
+        let mut vs = VirtualScreen::new(6, 4);
+        rect(&mut vs, 1, 0, 4, 3, &LineSet::SINGLE, Style::new()).unwrap();
+        hline(&mut vs, 0, 3, 6, &LineSet::DOUBLE, Style::new()).unwrap();
+        assert_eq!(vs.to_plain_string(), " ┌──┐\n │  │\n └──┘\n══════");
+
+        // edges past u16::MAX are left out
+        let mut screen = Screen::new(6, 4);
+        rect(
+            &mut screen,
+            5,
+            3,
+            u16::MAX,
+            u16::MAX,
+            &LineSet::SINGLE,
+            Style::new(),
+        )
+        .unwrap();
+        rect(&mut screen, 0, 0, 0, 3, &LineSet::SINGLE, Style::new()).unwrap();
+        assert_eq!(screen.get(5, 3).unwrap().symbol(), "┌");
+        assert_eq!(screen.get(0, 0).unwrap().symbol(), " ");
+    }
+
+    #[test]
+    fn borders_join_and_take_titles() {
+        let mut screen = Screen::new(24, 3);
+        let style = Style::new();
+        let left = Rect::new(0, 0, 11, 3);
+        let right = Rect::new(10, 0, 14, 3);
+        draw_border(
+            &mut screen,
+            left,
+            BorderStyle::Single,
+            style,
+            Some(("Files", Align::Left)),
+        )
+        .unwrap();
+        draw_border(
+            &mut screen,
+            right,
+            BorderStyle::Single,
+            style,
+            Some(("Log", Align::Right)),
+        )
+        .unwrap();
+        let row = |r| -> String {
+            (0..24)
+                .map(|c| screen.get(c, r).unwrap().symbol().to_string())
+                .collect()
+        };
+        assert_eq!(row(0), "┌─ Files ─┬────── Log ─┐");
+        assert_eq!(row(1), format!("│{}│{}│", " ".repeat(9), " ".repeat(12)));
+        assert_eq!(row(2), "└─────────┴────────────┘");
+        let edge = Rect::new(u16::MAX - 1, u16::MAX - 1, 10, 10);
+        draw_border(
+            &mut screen,
+            edge,
+            BorderStyle::Single,
+            style,
+            Some(("x", Align::Center)),
+        )
+        .unwrap();
+
+        let mut vs = VirtualScreen::new(9, 3);
+        draw_border(
+            &mut vs,
+            Rect::sized(9, 3),
+            BorderStyle::Rounded,
+            style,
+            Some(("abcdef", Align::Center)),
+        )
+        .unwrap();
+        assert_eq!(vs.to_plain_string(), "╭─ abc ─╮\n│       │\n╰───────╯");
+    }
+
+    #[test]
+    fn print_at_starts_each_line_at_the_column() {
+        let mut backend = TestBackend::new(6, 3);
+        print_at(&mut backend, 2, 1, "ab\r\ncdefg\nh\ni", Style::new()).unwrap();
+        assert_eq!(backend.pending(), b"\x1b[2;3Hab\x1b[3;3Hcdef");
+    }
+}
@@ -0,0 +1,90 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Line and character insertion/deletion. These shift existing content
+// instead of redrawing it, e.g. to add a row to a list without repainting
+// everything below it.
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+// IL, inserts `n` blank lines at the cursor row, pushing the rows below down
+// (within the scroll region).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct InsertLines(pub u16);
+
+impl Command for InsertLines {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}L", self.0)
+    }
+}
+
+// DL, removes `n` lines at the cursor row, pulling the rows below up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteLines(pub u16);
+
+impl Command for DeleteLines {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}M", self.0)
+    }
+}
+
+// ICH, inserts `n` blanks at the cursor, shifting the rest of the line right.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct InsertChars(pub u16);
+
+impl Command for InsertChars {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}@", self.0)
+    }
+}
+
+// DCH, removes `n` characters at the cursor, shifting the rest of the line
+// left.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteChars(pub u16);
+
+impl Command for DeleteChars {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}P", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{DeleteChars, DeleteLines, InsertChars, InsertLines};
+    use crate::cursor::MoveTo;
+    use crate::queue;
+    use crate::virtual_screen::VirtualScreen;
+
+    #[test]
+    fn shifts_content() {
+        let mut s = VirtualScreen::new(6, 3);
+        s.process(b"abcdef\r\nline2\r\nline3");
+        queue!(
+            s,
+            MoveTo(1, 0),
+            InsertChars(2),
+            MoveTo(0, 1),
+            InsertLines(1)
+        )
+        .unwrap();
+        assert_eq!(s.to_plain_string(), "a  bcd\n\nline2");
+        queue!(s, MoveTo(0, 0), DeleteChars(3), DeleteLines(1)).unwrap();
+        assert_eq!(s.to_plain_string(), "\nline2\n");
+    }
+}
@@ -0,0 +1,67 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Erase commands (ED / EL).
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ClearType {
+    // Whole visible screen.
+    All,
+    // Cursor to end of screen.
+    FromCursorDown,
+    // Start of screen to cursor.
+    FromCursorUp,
+    // Whole current line.
+    CurrentLine,
+    // Cursor to end of line.
+    UntilNewLine,
+    // Start of line to cursor.
+    FromLineStart,
+    // Lines scrolled off the top (`CSI 3J`, an xterm extension). Some
+    // terminals also clear the visible screen, others leave it alone.
+    Scrollback,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Clear(pub ClearType);
+
+impl Command for Clear {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(match self.0 {
+            ClearType::All => b"\x1b[2J",
+            ClearType::FromCursorDown => b"\x1b[0J",
+            ClearType::FromCursorUp => b"\x1b[1J",
+            ClearType::CurrentLine => b"\x1b[2K",
+            ClearType::UntilNewLine => b"\x1b[0K",
+            ClearType::FromLineStart => b"\x1b[1K",
+            ClearType::Scrollback => b"\x1b[3J",
+        })
+    }
+}
+
+// Clears both the visible screen and the scrollback and homes the cursor,
+// leaving a blank terminal whichever way `2J`/`3J` are implemented.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Purge;
+
+impl Command for Purge {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[H\x1b[2J\x1b[3J")
+    }
+}
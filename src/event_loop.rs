@@ -0,0 +1,675 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// A single blocking wait over terminal input, window size changes and any
+// file descriptors the application adds, using epoll on Linux and kqueue on
+// the BSDs and macOS.
+
+use crate::cursor;
+use crate::event::Event;
+use crate::input::InputParser;
+use crate::mode::{DecMode, EnterAlternateScreen, LeaveAlternateScreen};
+use crate::queue;
+use crate::unix::{TermState, Terminal, VSUSP};
+use libc::{SIGCONT, SIGTSTP, SIGWINCH, c_int, termios};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+use std::{mem, ptr};
+
+// Identifies a user registered fd in `LoopEvent::Ready`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Token(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopEvent {
+    // Input or a resize from the terminal.
+    Terminal(Event),
+    // A registered fd is readable.
+    Ready(Token),
+    // `Waker::wake` was called. Several wakes before the loop gets to run
+    // are reported once.
+    Wake,
+}
+
+// Keys the poller reports; user tokens are used as they are.
+const TTY_KEY: u64 = u64::MAX;
+const SIGNAL_KEY: u64 = u64::MAX - 1;
+const WAKE_KEY: u64 = u64::MAX - 2;
+
+///////////////////
+//  Signal Pipe  //
+///////////////////
+
+static SIGNAL_WRITE: AtomicI32 = AtomicI32::new(-1);
+static SIGNAL_PIPE: Once = Once::new();
+
+// Writes the signal number to the pipe, which the loop picks up.
+extern "C" fn on_signal(signal: c_int) {
+    let fd = SIGNAL_WRITE.load(Ordering::Relaxed);
+    if fd != -1 {
+        unsafe { libc::write(fd, [signal as u8].as_ptr().cast(), 1) };
+    }
+}
+
+// SIGWINCH goes through the handler shared with `PtyChild`.
+fn on_resize() {
+    on_signal(SIGWINCH);
+}
+
+fn set_handler(signal: c_int, handler: usize) -> io::Result<libc::sigaction> {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        let mut previous: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(signal, &action, &mut previous) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(previous)
+    }
+}
+
+fn catch(signal: c_int) -> io::Result<libc::sigaction> {
+    set_handler(signal, on_signal as *const () as usize)
+}
+
+// The read end of a non-blocking pipe that gets the signal number of every
+// caught signal. Only one exists per process; it is created, and SIGWINCH
+// caught, on first use.
+fn signal_pipe() -> io::Result<RawFd> {
+    static READ: AtomicI32 = AtomicI32::new(-1);
+    let mut result = Ok(());
+    SIGNAL_PIPE.call_once(|| {
+        result = (|| {
+            let mut fds = [0 as c_int; 2];
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            for fd in fds {
+                set_flags(fd)?;
+            }
+            READ.store(fds[0], Ordering::Relaxed);
+            SIGNAL_WRITE.store(fds[1], Ordering::Relaxed);
+            crate::unix::on_resize_signal(on_resize)
+        })();
+    });
+    result?;
+    match READ.load(Ordering::Relaxed) {
+        -1 => Err(io::Error::other("signal pipe setup failed")),
+        fd => Ok(fd),
+    }
+}
+
+fn set_flags(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let fl = libc::fcntl(fd, libc::F_GETFL);
+        let fd_flags = libc::fcntl(fd, libc::F_GETFD);
+        if fl == -1
+            || fd_flags == -1
+            || libc::fcntl(fd, libc::F_SETFL, fl | libc::O_NONBLOCK) == -1
+            || libc::fcntl(fd, libc::F_SETFD, fd_flags | libc::FD_CLOEXEC) == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn drain(fd: RawFd) {
+    let mut buf = [0u8; 64];
+    while unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+}
+
+/////////////
+//  Waker  //
+/////////////
+
+#[derive(Debug)]
+struct WakePipe {
+    read: RawFd,
+    write: RawFd,
+}
+
+impl Drop for WakePipe {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read);
+            libc::close(self.write);
+        }
+    }
+}
+
+// Interrupts `EventLoop::wait` from another thread, e.g. when data arrived
+// from the network and the screen should be redrawn.
+#[derive(Debug, Clone)]
+pub struct Waker(Arc<WakePipe>);
+
+impl Waker {
+    pub fn wake(&self) -> io::Result<()> {
+        let n = unsafe { libc::write(self.0.write, [1u8].as_ptr().cast(), 1) };
+        if n == -1 {
+            let err = io::Error::last_os_error();
+            // a full pipe already has a wake up pending
+            if err.kind() != io::ErrorKind::WouldBlock {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+//////////////////
+//  Event Loop  //
+//////////////////
+
+// Terminal settings for `EventLoop::handle_suspend`.
+#[derive(Debug)]
+struct Suspend {
+    cooked: termios,
+    alt_screen: bool,
+    // writes where the application's terminal does and shares its mode
+    // record, for the cursor visibility
+    term: Terminal,
+    // settings in effect when the process was stopped
+    raw: Option<termios>,
+}
+
+// Waits on stdin, the signal pipe and registered fds at once. Terminal
+// input is decoded like `EventReader` does, resizes come out as
+// `Event::Resize`, and a readable user fd as `LoopEvent::Ready` with the
+// token it was registered under. The application reads the fd itself.
+#[derive(Debug)]
+pub struct EventLoop {
+    poller: Poller,
+    input: RawFd,
+    signals: RawFd,
+    wake: Arc<WakePipe>,
+    suspend: Option<Suspend>,
+    parser: InputParser,
+    events: VecDeque<LoopEvent>,
+}
+
+impl EventLoop {
+    pub fn new() -> io::Result<EventLoop> {
+        EventLoop::with_input(libc::STDIN_FILENO)
+    }
+
+    // Reads terminal input from `input` instead of stdin, e.g. an fd for
+    // /dev/tty when stdin is a pipe.
+    pub fn with_input(input: RawFd) -> io::Result<EventLoop> {
+        let signals = signal_pipe()?;
+        let mut poller = Poller::new()?;
+        poller.add(input, TTY_KEY)?;
+        poller.add(signals, SIGNAL_KEY)?;
+
+        let mut fds = [0 as c_int; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let wake = Arc::new(WakePipe {
+            read: fds[0],
+            write: fds[1],
+        });
+        set_flags(wake.read)?;
+        set_flags(wake.write)?;
+        poller.add(wake.read, WAKE_KEY)?;
+
+        Ok(EventLoop {
+            poller,
+            input,
+            signals,
+            wake,
+            suspend: None,
+            parser: InputParser::new(),
+            events: VecDeque::new(),
+        })
+    }
+
+    // Handle for waking this loop from other threads.
+    pub fn waker(&self) -> Waker {
+        Waker(self.wake.clone())
+    }
+
+    pub fn parser(&mut self) -> &mut InputParser {
+        &mut self.parser
+    }
+
+    // Adds an fd to wait on. It stays owned by the caller and must be
+    // deregistered before it is closed.
+    pub fn register(&mut self, fd: RawFd, token: Token) -> io::Result<()> {
+        self.poller.add(fd, token.0 as u64)
+    }
+
+    pub fn deregister(&mut self, fd: RawFd) -> io::Result<()> {
+        self.poller.delete(fd)
+    }
+
+    // Blocks until something happens, or `timeout` passes (None forever).
+    // Returns Ok(None) on timeout.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Option<LoopEvent>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            // input a cursor or mode query read past its reply
+            if self.input == libc::STDIN_FILENO {
+                let unread = crate::unix::take_unread();
+                if !unread.is_empty() {
+                    let events = self.parser.feed(&unread);
+                    self.push_input(events)?;
+                    continue;
+                }
+            }
+            let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            // a held ESC needs a wake up of its own
+            let esc = self.parser.timeout();
+            let wait = match (remaining, esc) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            let ready = self.poller.wait(wait)?;
+            if ready.is_empty() {
+                if self.parser.timeout().is_some_and(|t| t.is_zero()) {
+                    let events = self.parser.flush();
+                    self.push_input(events)?;
+                } else if deadline.is_some_and(|d| Instant::now() >= d) {
+                    return Ok(None);
+                }
+                continue;
+            }
+            for key in ready {
+                match key {
+                    TTY_KEY => self.read_tty()?,
+                    WAKE_KEY => {
+                        drain(self.wake.read);
+                        self.events.push_back(LoopEvent::Wake);
+                    }
+                    SIGNAL_KEY => self.read_signals()?,
+                    token => self
+                        .events
+                        .push_back(LoopEvent::Ready(Token(token as usize))),
+                }
+            }
+        }
+    }
+
+    fn read_signals(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 64];
+        loop {
+            let n = unsafe { libc::read(self.signals, buf.as_mut_ptr().cast(), buf.len()) };
+            if n <= 0 {
+                return Ok(());
+            }
+            let mut resized = false;
+            for &signal in &buf[..n as usize] {
+                match signal as c_int {
+                    SIGWINCH => resized = true,
+                    SIGTSTP => self.suspend()?,
+                    SIGCONT => self.resume()?,
+                    _ => {}
+                }
+            }
+            // a drag resize sends a burst of signals, one event is enough
+            if resized && let Some((cols, rows)) = crate::term_size() {
+                self.events
+                    .push_back(LoopEvent::Terminal(Event::Resize(cols as u16, rows as u16)));
+            }
+        }
+    }
+
+    // Makes Ctrl-Z usable in raw mode. With ISIG off the tty doesn't turn
+    // it into SIGTSTP, so the suspend character of `cooked` (VSUSP) read
+    // from the input is handled like the signal. On either, the terminal is
+    // handed back in `cooked` settings, with the cursor shown and (if
+    // `alt_screen`) the main screen restored, written to `term`'s output,
+    // and the process stops. On SIGCONT the settings active before are
+    // reapplied, the alternate screen re-entered and the cursor hidden
+    // again if `term` had hidden it. The application gets `Event::Suspend`
+    // followed by `Event::Resume`, and should redraw everything on the
+    // latter.
+    pub fn handle_suspend(
+        &mut self,
+        term: &Terminal,
+        cooked: &TermState,
+        alt_screen: bool,
+    ) -> io::Result<()> {
+        self.parser.set_suspend(match cooked.c_cc[VSUSP as usize] {
+            0 => None,
+            c => Some(c),
+        });
+        self.suspend = Some(Suspend {
+            cooked: cooked.to_termios(),
+            alt_screen,
+            term: term.clone(),
+            raw: None,
+        });
+        catch(SIGTSTP)?;
+        catch(SIGCONT)?;
+        Ok(())
+    }
+
+    fn suspend(&mut self) -> io::Result<()> {
+        let Some(suspend) = &mut self.suspend else {
+            return Ok(());
+        };
+        unsafe {
+            let mut raw: termios = mem::zeroed();
+            if libc::tcgetattr(self.input, &mut raw) == 0 {
+                suspend.raw = Some(raw);
+            }
+        }
+        if suspend.alt_screen {
+            queue!(suspend.term, LeaveAlternateScreen)?;
+        }
+        if !suspend.term.modes().is_set(DecMode::CursorVisible) {
+            queue!(suspend.term, cursor::Show)?;
+        }
+        suspend.term.flush()?;
+        unsafe { libc::tcsetattr(self.input, libc::TCSADRAIN, &suspend.cooked) };
+        self.events.push_back(LoopEvent::Terminal(Event::Suspend));
+
+        // stop for real with the default action, then catch it again
+        set_handler(SIGTSTP, libc::SIG_DFL)?;
+        unsafe { libc::raise(SIGTSTP) };
+        catch(SIGTSTP)?;
+        Ok(())
+    }
+
+    // Also reached when stopped by other means, e.g. SIGSTOP. The screen may
+    // have been drawn over meanwhile, so Resume is reported either way.
+    fn resume(&mut self) -> io::Result<()> {
+        if let Some(suspend) = &mut self.suspend
+            && let Some(raw) = suspend.raw.take()
+        {
+            unsafe { libc::tcsetattr(self.input, libc::TCSADRAIN, &raw) };
+            if suspend.alt_screen {
+                queue!(suspend.term, EnterAlternateScreen)?;
+            }
+            if !suspend.term.modes().is_set(DecMode::CursorVisible) {
+                queue!(suspend.term, cursor::Hide)?;
+            }
+            suspend.term.flush()?;
+        }
+        self.events.push_back(LoopEvent::Terminal(Event::Resume));
+        Ok(())
+    }
+
+    fn read_tty(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 1024];
+        let n = match crate::unix::read_fd(self.input, &mut buf) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            r => r?,
+        };
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let events = self.parser.feed(&buf[..n]);
+        self.push_input(events)
+    }
+
+    // The suspend character stops the process here, see `handle_suspend`.
+    fn push_input(&mut self, events: Vec<Event>) -> io::Result<()> {
+        for event in events {
+            if event == Event::Suspend && self.suspend.is_some() {
+                self.suspend()?;
+            } else {
+                self.events.push_back(LoopEvent::Terminal(event));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn timeout_millis(timeout: Option<Duration>) -> c_int {
+    match timeout {
+        // round up so a sub-millisecond wait doesn't spin
+        Some(t) => t.as_micros().div_ceil(1000).min(c_int::MAX as u128) as c_int,
+        None => -1,
+    }
+}
+
+/////////////
+//  epoll  //
+/////////////
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug)]
+struct Poller {
+    fd: RawFd,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Poller {
+    fn new() -> io::Result<Poller> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Poller { fd })
+    }
+
+    fn add(&mut self, fd: RawFd, key: u64) -> io::Result<()> {
+        let mut event = libc::epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: key,
+        };
+        if unsafe { libc::epoll_ctl(self.fd, libc::EPOLL_CTL_ADD, fd, &mut event) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, fd: RawFd) -> io::Result<()> {
+        if unsafe { libc::epoll_ctl(self.fd, libc::EPOLL_CTL_DEL, fd, ptr::null_mut()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<u64>> {
+        let mut events: [libc::epoll_event; 16] = unsafe { mem::zeroed() };
+        let n = unsafe {
+            libc::epoll_wait(
+                self.fd,
+                events.as_mut_ptr(),
+                events.len() as c_int,
+                timeout_millis(timeout),
+            )
+        };
+        if n == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                return Ok(Vec::new());
+            }
+            return Err(err);
+        }
+        Ok(events[..n as usize].iter().map(|e| e.u64).collect())
+    }
+}
+
+//////////////
+//  kqueue  //
+//////////////
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+#[derive(Debug)]
+struct Poller {
+    fd: RawFd,
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+impl Poller {
+    fn new() -> io::Result<Poller> {
+        let fd = unsafe { libc::kqueue() };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        set_flags(fd)?;
+        Ok(Poller { fd })
+    }
+
+    fn change(&mut self, fd: RawFd, add: bool, key: u64) -> io::Result<()> {
+        let mut change: libc::kevent = unsafe { mem::zeroed() };
+        change.ident = fd as _;
+        change.filter = libc::EVFILT_READ as _;
+        change.flags = if add {
+            libc::EV_ADD | libc::EV_ENABLE
+        } else {
+            libc::EV_DELETE
+        };
+        change.udata = key as usize as _;
+        let n = unsafe { libc::kevent(self.fd, &change, 1, ptr::null_mut(), 0, ptr::null()) };
+        if n == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, fd: RawFd, key: u64) -> io::Result<()> {
+        self.change(fd, true, key)
+    }
+
+    fn delete(&mut self, fd: RawFd) -> io::Result<()> {
+        self.change(fd, false, 0)
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<u64>> {
+        let mut events: [libc::kevent; 16] = unsafe { mem::zeroed() };
+        let spec = timeout.map(|t| libc::timespec {
+            tv_sec: t.as_secs() as _,
+            tv_nsec: t.subsec_nanos() as _,
+        });
+        let n = unsafe {
+            libc::kevent(
+                self.fd,
+                ptr::null(),
+                0,
+                events.as_mut_ptr(),
+                events.len() as c_int,
+                spec.as_ref().map_or(ptr::null(), |s| s as *const _),
+            )
+        };
+        if n == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                return Ok(Vec::new());
+            }
+            return Err(err);
+        }
+        Ok(events[..n as usize]
+            .iter()
+            .map(|e| e.udata as usize as u64)
+            .collect())
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{EventLoop, LoopEvent, Token};
+    use crate::event::{Event, KeyCode, KeyEvent, Modifiers};
+    use std::time::Duration;
+
+    fn pipe() -> [i32; 2] {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        fds
+    }
+
+    fn write(fd: i32, bytes: &[u8]) {
+        assert_eq!(
+            unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) },
+            bytes.len() as isize
+        );
+    }
+
+    #[test]
+    fn wake_from_another_thread() {
+        let input = pipe();
+        let mut event_loop = EventLoop::with_input(input[0]).unwrap();
+        let waker = event_loop.waker();
+        let other = std::thread::spawn(move || {
+            waker.wake().unwrap();
+            waker.wake().unwrap();
+        });
+        other.join().unwrap();
+        assert_eq!(event_loop.wait(None).unwrap(), Some(LoopEvent::Wake));
+        assert_eq!(
+            event_loop.wait(Some(Duration::from_millis(10))).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn input_user_fd_and_timeout() {
+        let input = pipe();
+        let user = pipe();
+        let mut event_loop = EventLoop::with_input(input[0]).unwrap();
+        event_loop.register(user[0], Token(7)).unwrap();
+
+        assert_eq!(
+            event_loop.wait(Some(Duration::from_millis(10))).unwrap(),
+            None
+        );
+        write(user[1], b"x");
+        assert_eq!(
+            event_loop.wait(Some(Duration::from_secs(1))).unwrap(),
+            Some(LoopEvent::Ready(Token(7)))
+        );
+        event_loop.deregister(user[0]).unwrap();
+
+        // a lone ESC comes out once the parser's timeout has passed
+        write(input[1], b"q\x1b");
+        let key = |code| {
+            Some(LoopEvent::Terminal(Event::Key(KeyEvent::new(
+                code,
+                Modifiers::NONE,
+            ))))
+        };
+        assert_eq!(event_loop.wait(None).unwrap(), key(KeyCode::Char('q')));
+        assert_eq!(event_loop.wait(None).unwrap(), key(KeyCode::Esc));
+
+        for fd in input.into_iter().chain(user) {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * Copyright 2025 Luke Maple
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Images drawn with `▀` half blocks: every cell shows two pixels, the top
+// one in the foreground color and the bottom one in the background. Works on
+// any terminal with true color and on a `Screen`, at a very low resolution.
+
+use super::Image;
+use crate::color::Color;
+use crate::draw::DrawTarget;
+use crate::style::Style;
+use std::io;
+
+fn rgb(p: [u8; 4]) -> Color {
+    Color::Rgb {
+        r: p[0],
+        g: p[1],
+        b: p[2],
+    }
+}
+
+// Draws `image` at (col, row), one cell per pixel column and two pixel
+// rows per cell. Scale it first, see `Image::fit`. Fully transparent pixels
+// leave the terminal background visible.
+pub fn half_blocks<T: DrawTarget + ?Sized>(
+    target: &mut T,
+    col: u16,
+    row: u16,
+    image: &Image,
+) -> io::Result<()> {
+    for y in (0..image.height()).step_by(2) {
+        for x in 0..image.width() {
+            let top = image.pixel(x, y).filter(|p| p[3] >= 128);
+            let bottom = image.pixel(x, y + 1).filter(|p| p[3] >= 128);
+            let (symbol, style) = match (top, bottom) {
+                (Some(t), Some(b)) => ("▀", Style::new().fg(rgb(t)).bg(rgb(b))),
+                (Some(t), None) => ("▀", Style::new().fg(rgb(t))),
+                (None, Some(b)) => ("▄", Style::new().fg(rgb(b))),
+                (None, None) => (" ", Style::new()),
+            };
+            target.draw_str(col + x as u16, row + (y / 2) as u16, symbol, style)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::half_blocks;
+    use crate::color::Color;
+    use crate::graphics::Image;
+    use crate::screen::Screen;
+
+    #[test]
+    fn two_pixels_per_cell() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let clear = [0, 0, 0, 0];
+        // 2x3: the last row only fills the top half of the second cell row
+        let pixels = [red, clear, blue, blue, red, clear].concat();
+        let image = Image::new(2, 3, pixels);
+        let mut screen = Screen::new(4, 3);
+        half_blocks(&mut screen, 1, 1, &image).unwrap();
+
+        let cell = screen.get(1, 1).unwrap();
+        assert_eq!(cell.symbol(), "▀");
+        assert_eq!(cell.style.fg, Color::Rgb { r: 255, g: 0, b: 0 });
+        assert_eq!(cell.style.bg, Color::Rgb { r: 0, g: 0, b: 255 });
+        let cell = screen.get(2, 1).unwrap();
+        assert_eq!(cell.symbol(), "▄");
+        assert_eq!(cell.style.fg, Color::Rgb { r: 0, g: 0, b: 255 });
+        assert_eq!(screen.get(1, 2).unwrap().symbol(), "▀");
+        assert_eq!(screen.get(2, 2).unwrap().symbol(), " ");
+    }
+}
@@ -0,0 +1,156 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// iTerm2 inline images (OSC 1337 `File=`), also understood by WezTerm,
+// mintty and VS Code. The terminal decodes the file itself, so any format it
+// supports (PNG, JPEG, GIF) can be sent as is.
+//
+//     let image = InlineImage::new(&png).width(Dimension::Cells(20));
+//     iterm::display(&mut out, &Capabilities::detect(), &image)?;
+
+use super::{Capabilities, base64};
+use crate::command::{Command, write_command};
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    #[default]
+    Auto,
+    Cells(u32),
+    Pixels(u32),
+    // of the terminal width or height
+    Percent(u32),
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dimension::Auto => write!(f, "auto"),
+            Dimension::Cells(n) => write!(f, "{}", n),
+            Dimension::Pixels(n) => write!(f, "{}px", n),
+            Dimension::Percent(n) => write!(f, "{}%", n),
+        }
+    }
+}
+
+// Shows `data` at the cursor; the cursor ends up below the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineImage<'a> {
+    data: &'a [u8],
+    name: Option<String>,
+    width: Dimension,
+    height: Dimension,
+    preserve_aspect_ratio: bool,
+}
+
+impl<'a> InlineImage<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            name: None,
+            width: Dimension::Auto,
+            height: Dimension::Auto,
+            preserve_aspect_ratio: true,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn width(mut self, width: Dimension) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: Dimension) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn preserve_aspect_ratio(mut self, preserve: bool) -> Self {
+        self.preserve_aspect_ratio = preserve;
+        self
+    }
+}
+
+impl Command for InlineImage<'_> {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b]1337;File=inline=1;size={}", self.data.len())?;
+        if let Some(name) = &self.name {
+            write!(out, ";name={}", base64(name.as_bytes()))?;
+        }
+        if self.width != Dimension::Auto {
+            write!(out, ";width={}", self.width)?;
+        }
+        if self.height != Dimension::Auto {
+            write!(out, ";height={}", self.height)?;
+        }
+        if !self.preserve_aspect_ratio {
+            write!(out, ";preserveAspectRatio=0")?;
+        }
+        write!(out, ":{}\x07", base64(self.data))
+    }
+}
+
+// Writes `image` if the terminal supports the protocol. Returns whether it
+// was written, so callers can fall back to another protocol or to text.
+pub fn display(out: &mut impl Write, caps: &Capabilities, image: &InlineImage) -> io::Result<bool> {
+    if !caps.iterm_images {
+        return Ok(false);
+    }
+    write_command(&image, out)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Dimension, InlineImage, display};
+    use crate::command::Command;
+    use crate::graphics::{Capabilities, Emulator};
+
+    #[test]
+    fn file_sequence() {
+        let image = InlineImage::new(b"png")
+            .name("a.png")
+            .width(Dimension::Cells(10))
+            .height(Dimension::Percent(50))
+            .preserve_aspect_ratio(false);
+        assert_eq!(
+            image.to_ansi_string(),
+            "\x1b]1337;File=inline=1;size=3;name=YS5wbmc=;width=10;height=50%;preserveAspectRatio=0:cG5n\x07"
+        );
+    }
+
+    #[test]
+    fn gated_on_support() {
+        let mut caps = Capabilities {
+            emulator: Emulator::Unknown,
+            iterm_images: false,
+            sixel: false,
+            kitty_graphics: false,
+        };
+        let mut out = Vec::new();
+        assert!(!display(&mut out, &caps, &InlineImage::new(b"x")).unwrap());
+        assert!(out.is_empty());
+        caps.iterm_images = true;
+        assert!(display(&mut out, &caps, &InlineImage::new(b"x")).unwrap());
+        assert!(!out.is_empty());
+    }
+}
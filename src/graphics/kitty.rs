@@ -0,0 +1,322 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Kitty graphics protocol (https://sw.kovidgoyal.net/kitty/graphics-protocol),
+// supported by kitty, Ghostty and WezTerm. Images are transmitted once under
+// an id, then placed any number of times and deleted when no longer needed:
+//
+//     queue!(out, Transmit::new(1, Format::Png, &png))?;
+//     queue!(out, Place::new(1).size(20, 10))?;
+//     ...
+//     queue!(out, Delete::Image { id: 1, free: true })?;
+//
+// Every command asks the terminal not to reply (`q=2`) so answers don't end
+// up in the input stream.
+
+use super::{Capabilities, base64};
+use crate::command::{Command, write_command};
+use std::io::{self, Write};
+
+// Maximum payload per escape sequence, as the protocol requires.
+const CHUNK: usize = 4096;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Format {
+    // raw pixels, 4 or 3 bytes each
+    Rgba { width: u32, height: u32 },
+    Rgb { width: u32, height: u32 },
+    // any PNG file, decoded by the terminal
+    Png,
+}
+
+impl Format {
+    fn keys(&self) -> String {
+        match self {
+            Format::Rgba { width, height } => format!("f=32,s={},v={}", width, height),
+            Format::Rgb { width, height } => format!("f=24,s={},v={}", width, height),
+            Format::Png => "f=100".into(),
+        }
+    }
+}
+
+// Where and how big a transmitted image is shown. Columns and rows default
+// to the image size in cells.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Place {
+    image: u32,
+    placement: Option<u32>,
+    columns: Option<u32>,
+    rows: Option<u32>,
+    // pixel offset inside the first cell
+    offset: (u32, u32),
+    z: i32,
+    move_cursor: bool,
+}
+
+impl Place {
+    pub fn new(image: u32) -> Self {
+        Self {
+            image,
+            move_cursor: true,
+            ..Self::default()
+        }
+    }
+
+    // Id of this placement, so it can be moved or deleted on its own.
+    // Placing again with the same id replaces it.
+    pub fn id(mut self, placement: u32) -> Self {
+        self.placement = Some(placement);
+        self
+    }
+
+    pub fn size(mut self, columns: u32, rows: u32) -> Self {
+        self.columns = Some(columns);
+        self.rows = Some(rows);
+        self
+    }
+
+    pub fn offset(mut self, x: u32, y: u32) -> Self {
+        self.offset = (x, y);
+        self
+    }
+
+    // Negative values draw below text.
+    pub fn z_index(mut self, z: i32) -> Self {
+        self.z = z;
+        self
+    }
+
+    // Leave the cursor where it is instead of after the image.
+    pub fn keep_cursor(mut self) -> Self {
+        self.move_cursor = false;
+        self
+    }
+
+    fn keys(&self) -> String {
+        let mut keys = String::new();
+        if let Some(p) = self.placement {
+            keys.push_str(&format!(",p={}", p));
+        }
+        if let Some(c) = self.columns {
+            keys.push_str(&format!(",c={}", c));
+        }
+        if let Some(r) = self.rows {
+            keys.push_str(&format!(",r={}", r));
+        }
+        if self.offset != (0, 0) {
+            keys.push_str(&format!(",X={},Y={}", self.offset.0, self.offset.1));
+        }
+        if self.z != 0 {
+            keys.push_str(&format!(",z={}", self.z));
+        }
+        if !self.move_cursor {
+            keys.push_str(",C=1");
+        }
+        keys
+    }
+}
+
+impl Command for Place {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b_Ga=p,i={}{},q=2\x1b\\", self.image, self.keys())
+    }
+}
+
+// Sends image data to the terminal under `id`, split into chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transmit<'a> {
+    id: u32,
+    format: Format,
+    data: &'a [u8],
+    place: Option<Place>,
+}
+
+impl<'a> Transmit<'a> {
+    pub fn new(id: u32, format: Format, data: &'a [u8]) -> Self {
+        Self {
+            id,
+            format,
+            data,
+            place: None,
+        }
+    }
+
+    // Shows the image right away (`a=T`) at the cursor. The placement's
+    // image id is ignored in favour of the transmitted one.
+    pub fn display(mut self, place: Place) -> Self {
+        self.place = Some(place);
+        self
+    }
+}
+
+impl Command for Transmit<'_> {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        let payload = base64(self.data);
+        let action = match &self.place {
+            Some(place) => format!("a=T{}", place.keys()),
+            None => "a=t".into(),
+        };
+        let chunks: Vec<&[u8]> = payload.as_bytes().chunks(CHUNK).collect();
+        let last = chunks.len().saturating_sub(1);
+        if chunks.is_empty() {
+            return write!(
+                out,
+                "\x1b_G{},i={},{},q=2\x1b\\",
+                action,
+                self.id,
+                self.format.keys()
+            );
+        }
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let more = (i < last) as u8;
+            if i == 0 {
+                write!(
+                    out,
+                    "\x1b_G{},i={},{},q=2,m={};",
+                    action,
+                    self.id,
+                    self.format.keys(),
+                    more
+                )?;
+            } else {
+                write!(out, "\x1b_Gm={};", more)?;
+            }
+            out.write_all(chunk)?;
+            write!(out, "\x1b\\")?;
+        }
+        Ok(())
+    }
+}
+
+// What to remove. With `free` the image data is dropped too, otherwise only
+// the placements go and the image can be placed again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Delete {
+    All {
+        free: bool,
+    },
+    Image {
+        id: u32,
+        free: bool,
+    },
+    Placement {
+        image: u32,
+        placement: u32,
+        free: bool,
+    },
+    AtCursor {
+        free: bool,
+    },
+}
+
+impl Command for Delete {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        let key = |c: char, free: bool| if free { c.to_ascii_uppercase() } else { c };
+        match *self {
+            Delete::All { free } => write!(out, "\x1b_Ga=d,d={},q=2\x1b\\", key('a', free)),
+            Delete::Image { id, free } => {
+                write!(out, "\x1b_Ga=d,d={},i={},q=2\x1b\\", key('i', free), id)
+            }
+            Delete::Placement {
+                image,
+                placement,
+                free,
+            } => write!(
+                out,
+                "\x1b_Ga=d,d={},i={},p={},q=2\x1b\\",
+                key('i', free),
+                image,
+                placement
+            ),
+            Delete::AtCursor { free } => write!(out, "\x1b_Ga=d,d={},q=2\x1b\\", key('c', free)),
+        }
+    }
+}
+
+// Transmits and shows an image at the cursor if the terminal supports the
+// protocol. Returns whether it was written.
+pub fn display(
+    out: &mut impl Write,
+    caps: &Capabilities,
+    id: u32,
+    format: Format,
+    data: &[u8],
+) -> io::Result<bool> {
+    if !caps.kitty_graphics {
+        return Ok(false);
+    }
+    write_command(
+        &Transmit::new(id, format, data).display(Place::new(id)),
+        out,
+    )?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Delete, Format, Place, Transmit};
+    use crate::command::Command;
+
+    #[test]
+    fn transmit_in_chunks() {
+        let small = Transmit::new(
+            7,
+            Format::Rgb {
+                width: 1,
+                height: 1,
+            },
+            b"abc",
+        );
+        assert_eq!(
+            small.to_ansi_string(),
+            "\x1b_Ga=t,i=7,f=24,s=1,v=1,q=2,m=0;YWJj\x1b\\"
+        );
+
+        // 3072 bytes encode to exactly one chunk, one more byte needs two
+        let data = vec![0u8; 3073];
+        let s = Transmit::new(1, Format::Png, &data)
+            .display(Place::new(1).keep_cursor())
+            .to_ansi_string();
+        let parts: Vec<&str> = s.split("\x1b\\").filter(|p| !p.is_empty()).collect();
+        assert_eq!(parts.len(), 2);
+        assert!(parts[0].starts_with("\x1b_Ga=T,C=1,i=1,f=100,q=2,m=1;AAAA"));
+        assert_eq!(parts[0].len() - parts[0].find(';').unwrap() - 1, 4096);
+        assert_eq!(parts[1], "\x1b_Gm=0;AA==");
+    }
+
+    #[test]
+    fn place_and_delete() {
+        let place = Place::new(3).id(2).size(10, 5).offset(1, 2).z_index(-1);
+        assert_eq!(
+            place.to_ansi_string(),
+            "\x1b_Ga=p,i=3,p=2,c=10,r=5,X=1,Y=2,z=-1,q=2\x1b\\"
+        );
+        assert_eq!(
+            Delete::Image { id: 3, free: true }.to_ansi_string(),
+            "\x1b_Ga=d,d=I,i=3,q=2\x1b\\"
+        );
+        assert_eq!(
+            Delete::Placement {
+                image: 3,
+                placement: 2,
+                free: false
+            }
+            .to_ansi_string(),
+            "\x1b_Ga=d,d=i,i=3,p=2,q=2\x1b\\"
+        );
+    }
+}
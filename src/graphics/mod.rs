@@ -0,0 +1,480 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Inline images. Terminals disagree on how to show them, so each protocol
+// lives in its own module and `Capabilities` tells which ones the running
+// terminal understands. `draw_image` picks the best one available and falls
+// back to half block characters.
+
+pub mod blocks;
+pub mod iterm;
+pub mod kitty;
+pub mod sixel;
+
+use crate::command::write_command;
+use crate::cursor::MoveTo;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Terminal emulator, as far as the environment tells.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Emulator {
+    ITerm2,
+    WezTerm,
+    Kitty,
+    Ghostty,
+    Konsole,
+    Foot,
+    Mintty,
+    VsCode,
+    WindowsTerminal,
+    Mlterm,
+    Unknown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    pub emulator: Emulator,
+    // iTerm2 OSC 1337 `File=` images, see `iterm`
+    pub iterm_images: bool,
+    // DEC sixel graphics, see `sixel`
+    pub sixel: bool,
+    // kitty graphics protocol, see `kitty`
+    pub kitty_graphics: bool,
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    // Detection from environment variables, `var` looks one up.
+    pub fn from_env<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let emulator = if program == "iTerm.app" || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+        {
+            Emulator::ITerm2
+        } else if program == "WezTerm" {
+            Emulator::WezTerm
+        } else if program == "ghostty" || term == "xterm-ghostty" {
+            Emulator::Ghostty
+        } else if term == "xterm-kitty" || var("KITTY_WINDOW_ID").is_some() {
+            Emulator::Kitty
+        } else if var("KONSOLE_VERSION").is_some() {
+            Emulator::Konsole
+        } else if term.starts_with("foot") {
+            Emulator::Foot
+        } else if program == "mintty" {
+            Emulator::Mintty
+        } else if program == "vscode" {
+            Emulator::VsCode
+        } else if var("WT_SESSION").is_some() {
+            Emulator::WindowsTerminal
+        } else if term.starts_with("mlterm") {
+            Emulator::Mlterm
+        } else {
+            Emulator::Unknown
+        };
+        Self {
+            emulator,
+            iterm_images: matches!(
+                emulator,
+                Emulator::ITerm2 | Emulator::WezTerm | Emulator::Mintty | Emulator::VsCode
+            ),
+            sixel: matches!(
+                emulator,
+                Emulator::ITerm2
+                    | Emulator::WezTerm
+                    | Emulator::Konsole
+                    | Emulator::Foot
+                    | Emulator::Mintty
+                    | Emulator::WindowsTerminal
+                    | Emulator::Mlterm
+            ),
+            kitty_graphics: matches!(
+                emulator,
+                Emulator::Kitty | Emulator::Ghostty | Emulator::WezTerm
+            ),
+        }
+    }
+
+    // Takes the terminal's reply to a primary device attributes query
+    // (`CSI c`, answered with `CSI ? 62;4;22 c`) into account. Attribute 4
+    // means sixel graphics; this catches xterm and others the environment
+    // doesn't identify.
+    pub fn apply_device_attributes(&mut self, reply: &[u8]) {
+        let Some(body) = reply
+            .strip_prefix(b"\x1b[?")
+            .and_then(|r| r.strip_suffix(b"c"))
+        else {
+            return;
+        };
+        if body.split(|b| *b == b';').any(|attr| attr == b"4") {
+            self.sixel = true;
+        }
+    }
+}
+
+//////////////
+//  Images  //
+//////////////
+
+// Pixel size assumed for a cell when a protocol draws in pixels. Terminals
+// vary, this is close for common fonts.
+pub const CELL_PIXELS: (u32, u32) = (10, 20);
+
+// Ids handed to kitty for images shown by `draw_image`.
+static NEXT_KITTY_ID: AtomicU32 = AtomicU32::new(1);
+
+// RGBA pixels, row major.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width * height * 4, "expected RGBA pixels");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y * self.width + x) * 4;
+        self.pixels[i..i + 4].try_into().ok()
+    }
+
+    // Box filter: every output pixel averages the source pixels it covers.
+    pub fn resize(&self, width: usize, height: usize) -> Image {
+        let (width, height) = (width.max(1), height.max(1));
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            let (y0, y1) = span(y, height, self.height);
+            for x in 0..width {
+                let (x0, x1) = span(x, width, self.width);
+                let mut sum = [0u32; 4];
+                for sy in y0..y1 {
+                    for sx in x0..x1 {
+                        let p = self.pixel(sx, sy).unwrap_or_default();
+                        // weight color by alpha so transparent pixels don't darken edges
+                        for c in 0..3 {
+                            sum[c] += p[c] as u32 * p[3] as u32;
+                        }
+                        sum[3] += p[3] as u32;
+                    }
+                }
+                let n = ((x1 - x0) * (y1 - y0)).max(1) as u32;
+                let alpha = sum[3].max(1);
+                pixels.extend((0..3).map(|c| (sum[c] / alpha) as u8));
+                pixels.push((sum[3] / n) as u8);
+            }
+        }
+        Image::new(width, height, pixels)
+    }
+
+    // Largest size keeping the aspect ratio that fits `width` x `height`.
+    pub fn fit(&self, width: usize, height: usize) -> (usize, usize) {
+        let scale = (width as f64 / self.width.max(1) as f64)
+            .min(height as f64 / self.height.max(1) as f64);
+        (
+            ((self.width as f64 * scale) as usize).max(1),
+            ((self.height as f64 * scale) as usize).max(1),
+        )
+    }
+}
+
+// Source range covered by output index `i` when scaling `from` to `to`.
+fn span(i: usize, to: usize, from: usize) -> (usize, usize) {
+    let start = i * from / to;
+    let end = ((i + 1) * from).div_ceil(to).max(start + 1);
+    (start, end.min(from.max(1)))
+}
+
+// Draws `image` into the `cols` x `rows` cell area at (col, row), keeping
+// its aspect ratio, with the best protocol in `caps`: kitty, then sixel,
+// then iTerm2 inline images, then half blocks, which work everywhere.
+pub fn draw_image<W: Write>(
+    out: &mut W,
+    caps: &Capabilities,
+    image: &Image,
+    col: u16,
+    row: u16,
+    cols: u16,
+    rows: u16,
+) -> io::Result<()> {
+    let (cols, rows) = (cols as usize, rows as usize);
+    if caps.kitty_graphics {
+        let (w, h) = image.fit(cols * CELL_PIXELS.0 as usize, rows * CELL_PIXELS.1 as usize);
+        let (c, r) = (
+            w.div_ceil(CELL_PIXELS.0 as usize),
+            h.div_ceil(CELL_PIXELS.1 as usize),
+        );
+        let id = NEXT_KITTY_ID.fetch_add(1, Ordering::Relaxed);
+        let format = kitty::Format::Rgba {
+            width: image.width as u32,
+            height: image.height as u32,
+        };
+        write_command(&MoveTo(col, row), out)?;
+        let transmit = kitty::Transmit::new(id, format, &image.pixels)
+            .display(kitty::Place::new(id).size(c as u32, r as u32));
+        return write_command(&transmit, out);
+    }
+    if caps.sixel {
+        let (w, h) = image.fit(cols * CELL_PIXELS.0 as usize, rows * CELL_PIXELS.1 as usize);
+        let scaled = image.resize(w, h);
+        write_command(&MoveTo(col, row), out)?;
+        return write_command(&sixel::Sixel::new(w, h, &scaled.pixels), out);
+    }
+    if caps.iterm_images {
+        let (w, h) = image.fit(cols * CELL_PIXELS.0 as usize, rows * CELL_PIXELS.1 as usize);
+        let (c, r) = (
+            w.div_ceil(CELL_PIXELS.0 as usize),
+            h.div_ceil(CELL_PIXELS.1 as usize),
+        );
+        let png = png(image);
+        write_command(&MoveTo(col, row), out)?;
+        let inline = iterm::InlineImage::new(&png)
+            .width(iterm::Dimension::Cells(c as u32))
+            .height(iterm::Dimension::Cells(r as u32));
+        return write_command(&inline, out);
+    }
+    let (w, h) = image.fit(cols, rows * 2);
+    blocks::half_blocks(out, col, row, &image.resize(w, h))
+}
+
+// `image` as an uncompressed PNG, for protocols that take an image file.
+pub(crate) fn png(image: &Image) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    // 8 bit RGBA, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // every row starts with filter type 0
+    let mut raw = Vec::with_capacity((image.width * 4 + 1) * image.height);
+    for row in image.pixels.chunks(image.width * 4).take(image.height) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    // zlib stream of stored deflate blocks
+    let mut data = vec![0x78, 0x01];
+    let blocks = raw.chunks(0xffff).collect::<Vec<_>>();
+    for (i, block) in blocks.iter().enumerate() {
+        data.push((i + 1 == blocks.len()) as u8);
+        data.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+    if blocks.is_empty() {
+        data.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    data.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &data);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for x in chunk {
+            a += *x as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Standard base64 with padding.
+pub(crate) fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Capabilities, Emulator, Image, base64, draw_image, png};
+    use crate::virtual_screen::VirtualScreen;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn detects_emulator() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            Capabilities::from_env(move |name| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            })
+        };
+        let iterm = env(&[("TERM_PROGRAM", "iTerm.app")]);
+        assert_eq!(iterm.emulator, Emulator::ITerm2);
+        assert!(iterm.iterm_images);
+        let kitty = env(&[("TERM", "xterm-kitty")]);
+        assert_eq!(kitty.emulator, Emulator::Kitty);
+        assert!(kitty.kitty_graphics);
+        let plain = env(&[("TERM", "xterm-256color")]);
+        assert_eq!(plain.emulator, Emulator::Unknown);
+        assert!(!plain.iterm_images);
+        assert!(env(&[("TERM", "foot")]).sixel);
+    }
+
+    #[test]
+    fn sixel_from_device_attributes() {
+        let mut caps = Capabilities::from_env(|_| None);
+        assert!(!caps.sixel);
+        caps.apply_device_attributes(b"\x1b[?62;22c");
+        assert!(!caps.sixel);
+        caps.apply_device_attributes(b"\x1b[?63;1;4;22c");
+        assert!(caps.sixel);
+    }
+
+    #[test]
+    fn resize_averages() {
+        let pixels = [
+            [0, 0, 0, 255],
+            [200, 100, 0, 255],
+            [0, 0, 0, 0],
+            [0, 0, 0, 0],
+        ]
+        .concat();
+        let image = Image::new(2, 2, pixels);
+        assert_eq!(image.resize(1, 1).pixel(0, 0), Some([100, 50, 0, 127]));
+        assert_eq!(image.fit(10, 4), (4, 4));
+        assert_eq!(image.resize(4, 4).pixel(3, 0), Some([200, 100, 0, 255]));
+    }
+
+    #[test]
+    fn falls_back_to_half_blocks() {
+        let caps = Capabilities::from_env(|_| None);
+        let image = Image::new(1, 1, vec![0, 255, 0, 255]);
+        let mut vs = VirtualScreen::new(4, 4);
+        draw_image(&mut vs, &caps, &image, 1, 1, 2, 2).unwrap();
+        // a square image in a 2x2 cell area is 2 pixels wide and 2 tall
+        assert_eq!(vs.row_text(1), " ▀▀");
+        assert_eq!(vs.row_text(2), "");
+
+        let mut out = Vec::new();
+        let caps = Capabilities {
+            kitty_graphics: true,
+            ..caps
+        };
+        draw_image(&mut out, &caps, &image, 0, 0, 2, 2).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("a=T,c=2,r=1"));
+
+        let mut out = Vec::new();
+        let caps = Capabilities {
+            iterm_images: true,
+            ..Capabilities::from_env(|_| None)
+        };
+        draw_image(&mut out, &caps, &image, 0, 0, 2, 2).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1b[1;1H\x1b]1337;File=inline=1;"));
+        assert!(out.contains(";width=2;height=1:"));
+    }
+
+    #[test]
+    fn png_encoding() {
+        let png = png(&Image::new(1, 1, vec![1, 2, 3, 4]));
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        // raw row: filter 0, then the pixel, in one stored block
+        let idat = &png[33 + 8..png.len() - 12 - 4];
+        assert_eq!(
+            idat,
+            [
+                0x78, 0x01, 1, 5, 0, 0xfa, 0xff, 0, 1, 2, 3, 4, 0, 0x19, 0, 0x0b
+            ]
+        );
+        // the IEND chunk's well known CRC
+        assert_eq!(&png[png.len() - 4..], [0xae, 0x42, 0x60, 0x82]);
+    }
+}
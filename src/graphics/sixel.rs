@@ -0,0 +1,313 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Sixel images, understood by xterm (`-ti vt340`), foot, mlterm, WezTerm,
+// Konsole, mintty and Windows Terminal among others. The pixels are
+// quantized to a palette of at most `max_colors` entries (median cut) and
+// optionally dithered, then sent in bands of six pixel rows.
+//
+//     let image = Sixel::new(width, height, &rgba).dither(true);
+//     sixel::display(&mut out, &Capabilities::detect(), &image)?;
+
+use super::Capabilities;
+use crate::command::{Command, write_command};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+
+// Most terminals have 256 color registers, the VT340 had 16.
+pub const DEFAULT_MAX_COLORS: usize = 256;
+
+// Pixels with a lower alpha are left transparent.
+const ALPHA_THRESHOLD: u8 = 128;
+
+type Rgb = [u8; 3];
+
+// An RGBA buffer of `width * height * 4` bytes, row major.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sixel<'a> {
+    width: usize,
+    height: usize,
+    pixels: &'a [u8],
+    max_colors: usize,
+    dither: bool,
+}
+
+impl<'a> Sixel<'a> {
+    pub fn new(width: usize, height: usize, pixels: &'a [u8]) -> Self {
+        assert_eq!(pixels.len(), width * height * 4, "expected RGBA pixels");
+        Self {
+            width,
+            height,
+            pixels,
+            max_colors: DEFAULT_MAX_COLORS,
+            dither: false,
+        }
+    }
+
+    pub fn max_colors(mut self, colors: usize) -> Self {
+        self.max_colors = colors.clamp(1, 256);
+        self
+    }
+
+    // Floyd-Steinberg dithering, smoother gradients for more bytes.
+    pub fn dither(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Option<Rgb> {
+        let i = (y * self.width + x) * 4;
+        let p = &self.pixels[i..i + 4];
+        (p[3] >= ALPHA_THRESHOLD).then_some([p[0], p[1], p[2]])
+    }
+
+    // Palette index of every pixel, `None` where transparent.
+    fn quantize(&self) -> (Vec<Rgb>, Vec<Option<usize>>) {
+        let mut counts: HashMap<Rgb, u32> = HashMap::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(rgb) = self.pixel(x, y) {
+                    *counts.entry(rgb).or_default() += 1;
+                }
+            }
+        }
+        let mut colors: Vec<(Rgb, u32)> = counts.into_iter().collect();
+        colors.sort_unstable();
+        let palette = median_cut(colors, self.max_colors);
+
+        let mut cache: HashMap<Rgb, usize> = HashMap::new();
+        let mut nearest = |rgb: Rgb| *cache.entry(rgb).or_insert_with(|| nearest(&palette, rgb));
+        let mut indices = Vec::with_capacity(self.width * self.height);
+        if !self.dither {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    indices.push(self.pixel(x, y).map(&mut nearest));
+                }
+            }
+            return (palette, indices);
+        }
+
+        // quantization error carried to the right and to the next row
+        let mut error = vec![[0i32; 3]; self.width + 2];
+        let mut next = vec![[0i32; 3]; self.width + 2];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let Some(rgb) = self.pixel(x, y) else {
+                    indices.push(None);
+                    continue;
+                };
+                let wanted: [i32; 3] =
+                    std::array::from_fn(|c| (rgb[c] as i32 + error[x + 1][c] / 16).clamp(0, 255));
+                let index = nearest(wanted.map(|v| v as u8));
+                indices.push(Some(index));
+                for c in 0..3 {
+                    let e = wanted[c] - palette[index][c] as i32;
+                    error[x + 2][c] += e * 7;
+                    next[x][c] += e * 3;
+                    next[x + 1][c] += e * 5;
+                    next[x + 2][c] += e;
+                }
+            }
+            std::mem::swap(&mut error, &mut next);
+            next.iter_mut().for_each(|e| *e = [0; 3]);
+        }
+        (palette, indices)
+    }
+}
+
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    (0..3)
+        .map(|c| (a[c] as i32 - b[c] as i32).pow(2) as u32)
+        .sum()
+}
+
+fn nearest(palette: &[Rgb], rgb: Rgb) -> usize {
+    (0..palette.len())
+        .min_by_key(|&i| distance(palette[i], rgb))
+        .unwrap_or(0)
+}
+
+// Splits the color space along the widest channel of the box with the
+// largest spread until there are `max` boxes, then averages each box.
+fn median_cut(colors: Vec<(Rgb, u32)>, max: usize) -> Vec<Rgb> {
+    if colors.len() <= max {
+        return colors.into_iter().map(|(rgb, _)| rgb).collect();
+    }
+    let spread = |b: &[(Rgb, u32)]| {
+        (0..3)
+            .map(|c| {
+                let (lo, hi) = b.iter().fold((255, 0), |(lo, hi), (rgb, _)| {
+                    (rgb[c].min(lo), rgb[c].max(hi))
+                });
+                (hi - lo, c)
+            })
+            .max()
+            .unwrap_or((0, 0))
+    };
+    let mut boxes = vec![colors];
+    while boxes.len() < max {
+        let Some((i, (_, channel))) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| (i, spread(b)))
+            .max_by_key(|(_, s)| s.0)
+        else {
+            break;
+        };
+        let mut b = boxes.swap_remove(i);
+        b.sort_unstable_by_key(|(rgb, _)| rgb[channel]);
+        let total: u32 = b.iter().map(|(_, n)| n).sum();
+        let mut seen = 0;
+        let mut split = b.len() / 2;
+        for (j, (_, n)) in b.iter().enumerate() {
+            seen += n;
+            if seen * 2 >= total {
+                split = (j + 1).clamp(1, b.len() - 1);
+                break;
+            }
+        }
+        let rest = b.split_off(split);
+        boxes.push(b);
+        boxes.push(rest);
+    }
+    boxes
+        .iter()
+        .map(|b| {
+            let total: u64 = b.iter().map(|(_, n)| *n as u64).sum();
+            std::array::from_fn(|c| {
+                let sum: u64 = b.iter().map(|(rgb, n)| rgb[c] as u64 * *n as u64).sum();
+                (sum / total.max(1)) as u8
+            })
+        })
+        .collect()
+}
+
+fn write_run(out: &mut impl Write, sixel: u8, run: usize) -> io::Result<()> {
+    let c = (63 + sixel) as char;
+    match run {
+        0 => Ok(()),
+        1..=3 => write!(out, "{}", c.to_string().repeat(run)),
+        _ => write!(out, "!{}{}", run, c),
+    }
+}
+
+impl Command for Sixel<'_> {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        let (palette, indices) = self.quantize();
+        // P2 = 1: pixels that aren't drawn keep the background
+        write!(out, "\x1bP0;1;0q\"1;1;{};{}", self.width, self.height)?;
+        for (i, rgb) in palette.iter().enumerate() {
+            let pct = rgb.map(|v| (v as u32 * 100 + 127) / 255);
+            write!(out, "#{};2;{};{};{}", i, pct[0], pct[1], pct[2])?;
+        }
+        for top in (0..self.height).step_by(6) {
+            let rows = top..(top + 6).min(self.height);
+            let used: BTreeSet<usize> = rows
+                .clone()
+                .flat_map(|y| &indices[y * self.width..(y + 1) * self.width])
+                .flatten()
+                .copied()
+                .collect();
+            for (n, &color) in used.iter().enumerate() {
+                if n > 0 {
+                    write!(out, "$")?;
+                }
+                write!(out, "#{}", color)?;
+                let (mut last, mut run) = (0, 0);
+                for x in 0..self.width {
+                    let sixel = rows.clone().fold(0u8, |bits, y| {
+                        let set = indices[y * self.width + x] == Some(color);
+                        bits | (set as u8) << (y - top)
+                    });
+                    if sixel != last {
+                        write_run(out, last, run)?;
+                        (last, run) = (sixel, 0);
+                    }
+                    run += 1;
+                }
+                // trailing empty columns don't need to be sent
+                if last != 0 {
+                    write_run(out, last, run)?;
+                }
+            }
+            if rows.end < self.height {
+                write!(out, "-")?;
+            }
+        }
+        write!(out, "\x1b\\")
+    }
+}
+
+// Writes `image` at the cursor if the terminal supports sixel. Returns
+// whether it was written.
+pub fn display(out: &mut impl Write, caps: &Capabilities, image: &Sixel) -> io::Result<bool> {
+    if !caps.sixel {
+        return Ok(false);
+    }
+    write_command(&image, out)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Sixel, median_cut};
+    use crate::command::Command;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn encodes_bands() {
+        // 5x7: red with a blue left column below the first row and one
+        // transparent pixel in the corner
+        let mut pixels = Vec::new();
+        for y in 0..7 {
+            for x in 0..5 {
+                pixels.extend(match (x, y) {
+                    (4, 6) => CLEAR,
+                    (_, 0) => RED,
+                    (0, _) => BLUE,
+                    _ => RED,
+                });
+            }
+        }
+        let s = Sixel::new(5, 7, &pixels).to_ansi_string();
+        assert_eq!(
+            s,
+            "\x1bP0;1;0q\"1;1;5;7#0;2;0;0;100#1;2;100;0;0\
+             #0}$#1@!4~-#0@$#1?@@@\x1b\\"
+        );
+    }
+
+    #[test]
+    fn quantizes_to_max_colors() {
+        let pixels: Vec<u8> = (0..64u8)
+            .flat_map(|i| [i * 4, 255 - i * 4, i, 255])
+            .collect();
+        let image = Sixel::new(8, 8, &pixels).max_colors(4);
+        let (palette, indices) = image.quantize();
+        assert_eq!(palette.len(), 4);
+        assert!(indices.iter().all(|i| i.unwrap() < 4));
+        let dithered = image.dither(true).quantize();
+        assert_eq!(dithered.0, palette);
+
+        let colors = vec![([0, 0, 0], 1), ([10, 0, 0], 1), ([200, 0, 0], 1)];
+        assert_eq!(median_cut(colors, 2), [[5, 0, 0], [200, 0, 0]]);
+    }
+}
@@ -0,0 +1,89 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// OSC 8 hyperlinks. Text printed between `OpenLink` and `CloseLink` links to
+// the URL; terminals without support show it as plain text.
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+// Starts a link. Cells sharing an `id` are treated as one link even when
+// written in pieces, e.g. a URL wrapped over two rows or redrawn in parts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct OpenLink<'a> {
+    pub url: &'a str,
+    pub id: Option<u32>,
+}
+
+impl<'a> OpenLink<'a> {
+    pub fn new(url: &'a str) -> Self {
+        Self { url, id: None }
+    }
+
+    pub fn id(mut self, id: u32) -> Self {
+        self.id = Some(id);
+        self
+    }
+}
+
+impl Command for OpenLink<'_> {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.id {
+            Some(id) => write!(out, "\x1b]8;id={};{}\x1b\\", id, self.url),
+            None => write!(out, "\x1b]8;;{}\x1b\\", self.url),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CloseLink;
+
+impl Command for CloseLink {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b]8;;\x1b\\")
+    }
+}
+
+// Interns URLs so cells can refer to them by a small id.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Links {
+    urls: Vec<String>,
+}
+
+impl Links {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The id of `url`, adding it if it is new.
+    pub fn intern(&mut self, url: &str) -> u32 {
+        match self.urls.iter().position(|u| u == url) {
+            Some(i) => i as u32,
+            None => {
+                self.urls.push(url.to_string());
+                self.urls.len() as u32 - 1
+            }
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.urls.get(id as usize).map(String::as_str)
+    }
+
+    pub fn clear(&mut self) {
+        self.urls.clear();
+    }
+}
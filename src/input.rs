@@ -0,0 +1,1050 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crate::mode::ModeState;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+// Longest escape sequence we wait for before giving up on it.
+const MAX_SEQUENCE: usize = 64;
+
+// Bracketed paste delimiters.
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+// and their 8-bit forms, CSI as 0x9b
+const PASTE_END_C1: &[u8] = b"\x9b201~";
+
+// How long a lone ESC waits for the rest of a sequence by default.
+pub const DEFAULT_ESC_TIMEOUT: Duration = Duration::from_millis(50);
+
+////////////////////
+//  Input Parser  //
+////////////////////
+
+// Decodes the bytes a terminal sends on its input into `Event`s. A sequence
+// split across reads is held back until the rest of it arrives.
+//
+// ESC on its own is ambiguous: it is the Esc key, but also the first byte of
+// Alt+key and of every escape sequence. It is held like any other partial
+// sequence, and once `timeout()` runs out without more input the reader
+// calls `flush()`, which delivers it as Esc (or Alt+key).
+//
+// Everything between the bracketed paste delimiters is collected, however
+// many reads it takes, and delivered as one `Event::Paste`.
+//
+// 8-bit CSI (0x9b) and SS3 (0x8f) are accepted as well as the ESC forms. They
+// can't be mistaken for UTF-8, where neither byte starts a character.
+#[derive(Debug)]
+pub struct InputParser {
+    pending: Vec<u8>,
+    pending_since: Option<Instant>,
+    esc_timeout: Duration,
+    paste: Option<Vec<u8>>,
+    paste_end: &'static [u8],
+    normalize_paste: bool,
+    interrupt: Option<u8>,
+    suspend: Option<u8>,
+}
+
+impl Default for InputParser {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            pending_since: None,
+            esc_timeout: DEFAULT_ESC_TIMEOUT,
+            paste: None,
+            paste_end: PASTE_END,
+            normalize_paste: false,
+            interrupt: None,
+            suspend: None,
+        }
+    }
+}
+
+impl InputParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn esc_timeout(&self) -> Duration {
+        self.esc_timeout
+    }
+
+    // Longer timeouts help over slow links where sequences get split,
+    // shorter ones make Esc feel more responsive.
+    pub fn set_esc_timeout(&mut self, timeout: Duration) {
+        self.esc_timeout = timeout;
+    }
+
+    // Converts CRLF and lone CR in pasted text to LF. Terminals usually send
+    // CR for newlines in a paste, whatever the source text used.
+    pub fn normalize_paste_newlines(&mut self, normalize: bool) {
+        self.normalize_paste = normalize;
+    }
+
+    // Reports `byte` (the tty's VINTR character) as `Event::Interrupt`
+    // instead of a Ctrl key. Only useful with ISIG off, as in raw mode,
+    // since otherwise the tty turns it into SIGINT before we see it.
+    pub fn set_interrupt(&mut self, byte: Option<u8>) {
+        self.interrupt = byte;
+    }
+
+    // Reports `byte` (the tty's VSUSP character, usually Ctrl-Z) as
+    // `Event::Suspend`, the same way. `EventLoop::handle_suspend` sets it.
+    pub fn set_suspend(&mut self, byte: Option<u8>) {
+        self.suspend = byte;
+    }
+
+    // Time left before pending input should be flushed, None when nothing is
+    // pending. A paste in progress never times out.
+    pub fn timeout(&self) -> Option<Duration> {
+        if self.paste.is_some() {
+            return None;
+        }
+        let since = self.pending_since?;
+        Some(self.esc_timeout.saturating_sub(since.elapsed()))
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let events = self.decode_pending(bytes);
+        if self.pending.is_empty() {
+            self.pending_since = None;
+        } else if !bytes.is_empty() || self.pending_since.is_none() {
+            self.pending_since = Some(Instant::now());
+        }
+        events
+    }
+
+    // Gives up waiting: a held ESC becomes Esc, or Alt+ the byte after it,
+    // and any other partial sequence is dropped.
+    pub fn flush(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        while self.paste.is_none() && !self.pending.is_empty() {
+            match self.pending[..] {
+                [0x1b] => {
+                    events.push(key(KeyCode::Esc, Modifiers::NONE));
+                    self.pending.clear();
+                }
+                [0x1b, b, ..] => {
+                    let code = if b == 0x1b {
+                        KeyCode::Esc
+                    } else {
+                        KeyCode::Char(b as char)
+                    };
+                    events.push(key(code, Modifiers::ALT));
+                    self.pending.drain(..2);
+                    events.extend(self.decode_pending(&[]));
+                }
+                _ => self.pending.clear(),
+            }
+        }
+        self.pending_since = None;
+        events
+    }
+
+    fn decode_pending(&mut self, bytes: &[u8]) -> Vec<Event> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while start < self.pending.len() {
+            if let Some(paste) = &mut self.paste {
+                let rest = &self.pending[start..];
+                let marker = self.paste_end;
+                match rest.windows(marker.len()).position(|w| w == marker) {
+                    Some(end) => {
+                        paste.extend_from_slice(&rest[..end]);
+                        start += end + marker.len();
+                        events.push(self.finish_paste());
+                    }
+                    None => {
+                        // hold back what could be the start of the end marker
+                        let keep = (1..marker.len())
+                            .rev()
+                            .find(|&n| rest.ends_with(&marker[..n]))
+                            .unwrap_or(0);
+                        paste.extend_from_slice(&rest[..rest.len() - keep]);
+                        start += rest.len() - keep;
+                        break;
+                    }
+                }
+                continue;
+            }
+            if self.interrupt == Some(self.pending[start]) {
+                events.push(Event::Interrupt);
+                start += 1;
+                continue;
+            }
+            if self.suspend == Some(self.pending[start]) {
+                events.push(Event::Suspend);
+                start += 1;
+                continue;
+            }
+            match decode(&self.pending[start..]) {
+                Decoded::Event(event, len) => {
+                    events.push(event);
+                    start += len;
+                }
+                Decoded::PasteStart(len) => {
+                    self.paste = Some(Vec::new());
+                    self.paste_end = if self.pending[start] == 0x9b {
+                        PASTE_END_C1
+                    } else {
+                        PASTE_END
+                    };
+                    start += len;
+                }
+                Decoded::Skip(len) => start += len,
+                Decoded::Incomplete => break,
+            }
+        }
+        self.pending.drain(..start);
+        events
+    }
+
+    fn finish_paste(&mut self) -> Event {
+        let bytes = self.paste.take().unwrap_or_default();
+        let text = String::from_utf8_lossy(&bytes);
+        if self.normalize_paste {
+            Event::Paste(text.replace("\r\n", "\n").replace('\r', "\n"))
+        } else {
+            Event::Paste(text.into_owned())
+        }
+    }
+
+    // True while part of a sequence, or a paste, is waiting for more input.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty() || self.paste.is_some()
+    }
+}
+
+enum Decoded {
+    Event(Event, usize),
+    PasteStart(usize),
+    // bytes that don't mean anything to us, e.g. an unknown sequence
+    Skip(usize),
+    Incomplete,
+}
+
+fn key(code: KeyCode, modifiers: Modifiers) -> Event {
+    Event::Key(KeyEvent::new(code, modifiers))
+}
+
+fn decode(bytes: &[u8]) -> Decoded {
+    let event = match bytes[0] {
+        0x1b => return decode_escape(bytes),
+        0x9b | 0x8f => return decode_c1(bytes),
+        b'\r' | b'\n' => key(KeyCode::Enter, Modifiers::NONE),
+        b'\t' => key(KeyCode::Tab, Modifiers::NONE),
+        0x7f | 0x08 => key(KeyCode::Backspace, Modifiers::NONE),
+        0x00 => key(KeyCode::Char(' '), Modifiers::CONTROL),
+        c @ 0x01..=0x1a => key(KeyCode::Char((c - 1 + b'a') as char), Modifiers::CONTROL),
+        c @ 0x1c..=0x1f => key(KeyCode::Char((c + 0x40) as char), Modifiers::CONTROL),
+        0x80.. => return decode_utf8(bytes),
+        c => key(KeyCode::Char(c as char), Modifiers::NONE),
+    };
+    Decoded::Event(event, 1)
+}
+
+// A multi-byte character, which may be split across reads. Invalid bytes are
+// dropped one at a time so the input resynchronizes on the next good one.
+fn decode_utf8(bytes: &[u8]) -> Decoded {
+    let len = match bytes[0] {
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return Decoded::Skip(1),
+    };
+    match std::str::from_utf8(&bytes[..len.min(bytes.len())]) {
+        Ok(s) => match s.chars().next() {
+            Some(c) => Decoded::Event(key(KeyCode::Char(c), Modifiers::NONE), len),
+            None => Decoded::Skip(1),
+        },
+        // valid so far, the rest hasn't arrived yet
+        Err(e) if e.error_len().is_none() => Decoded::Incomplete,
+        Err(_) => Decoded::Skip(1),
+    }
+}
+
+fn decode_escape(bytes: &[u8]) -> Decoded {
+    match bytes.get(1) {
+        None => Decoded::Incomplete,
+        Some(b'[') => decode_csi(bytes),
+        Some(b'O') => decode_ss3(bytes),
+        // ESC followed by a key is how terminals send Alt+key
+        Some(_) => match decode(&bytes[1..]) {
+            Decoded::Event(Event::Key(mut k), len) => {
+                k.modifiers.insert(Modifiers::ALT);
+                Decoded::Event(Event::Key(k), len + 1)
+            }
+            Decoded::Event(_, len) | Decoded::PasteStart(len) | Decoded::Skip(len) => {
+                Decoded::Skip(len + 1)
+            }
+            Decoded::Incomplete => Decoded::Incomplete,
+        },
+    }
+}
+
+// 8-bit CSI or SS3, decoded as its ESC form. Only as much as a sequence can
+// span is copied.
+fn decode_c1(bytes: &[u8]) -> Decoded {
+    let end = bytes.len().min(MAX_SEQUENCE + 2);
+    let mut seven = vec![0x1b, bytes[0] - 0x40];
+    seven.extend_from_slice(&bytes[1..end]);
+    match decode_escape(&seven) {
+        Decoded::Event(event, len) => Decoded::Event(event, len - 1),
+        Decoded::PasteStart(len) => Decoded::PasteStart(len - 1),
+        Decoded::Skip(len) => Decoded::Skip(len.saturating_sub(1).clamp(1, bytes.len())),
+        Decoded::Incomplete => Decoded::Incomplete,
+    }
+}
+
+// SS3 carries the cursor keys in application cursor mode (DECCKM), F1-F4 and,
+// in application keypad mode, the keypad. Some terminals put a modifier
+// digit in front of the final byte (`ESC O 5 A`).
+fn decode_ss3(bytes: &[u8]) -> Decoded {
+    let digits = bytes[2..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let Some(&last) = bytes.get(2 + digits) else {
+        return Decoded::Incomplete;
+    };
+    let len = 3 + digits;
+    let mods = match std::str::from_utf8(&bytes[2..2 + digits]) {
+        Ok(d) if !d.is_empty() => modifiers(d.parse().unwrap_or(1)),
+        _ => Modifiers::NONE,
+    };
+    let code = match last {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P'..=b'S' => KeyCode::F(last - b'P' + 1),
+        // application keypad
+        b'M' => KeyCode::Enter,
+        b'I' => KeyCode::Tab,
+        b' ' => KeyCode::Char(' '),
+        b'X' => KeyCode::Char('='),
+        b'j' => KeyCode::Char('*'),
+        b'k' => KeyCode::Char('+'),
+        b'l' => KeyCode::Char(','),
+        b'm' => KeyCode::Char('-'),
+        b'n' => KeyCode::Char('.'),
+        b'o' => KeyCode::Char('/'),
+        b'p'..=b'y' => KeyCode::Char((b'0' + last - b'p') as char),
+        _ => return Decoded::Skip(len),
+    };
+    Decoded::Event(key(code, mods), len)
+}
+
+fn decode_csi(bytes: &[u8]) -> Decoded {
+    let Some(end) = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) else {
+        if bytes.len() > MAX_SEQUENCE {
+            return Decoded::Skip(bytes.len());
+        }
+        return Decoded::Incomplete;
+    };
+    let len = end + 3;
+    let params = &bytes[2..len - 1];
+    let last = bytes[len - 1];
+    if &bytes[..len] == PASTE_START {
+        return Decoded::PasteStart(len);
+    }
+
+    let event = if params.first() == Some(&b'<') {
+        sgr_mouse(&params[1..], last)
+    } else {
+        csi_key(params, last)
+    };
+    match event {
+        Some(event) => Decoded::Event(event, len),
+        None => Decoded::Skip(len),
+    }
+}
+
+// Keys in their CSI forms:
+//   CSI [1;mod] A-D H F P-S   cursor keys, Home/End and F1-F4 (xterm)
+//   CSI n [;mod] ~            editing keys and F1-F20 (vt220 numbering)
+//   CSI 27 ; mod ; code ~     xterm modifyOtherKeys
+//   CSI code [;mod] u         fixterms / kitty CSI-u
+fn csi_key(params: &[u8], last: u8) -> Option<Event> {
+    let groups = if params.is_empty() {
+        Vec::new()
+    } else {
+        groups(params)?
+    };
+    let number = |i: usize| groups.get(i).and_then(|g| g.first().copied());
+    // the kitty protocol adds the event type after the modifiers: mod:type
+    let mods = modifiers(number(1).unwrap_or(1));
+    let kind = match groups.get(1).and_then(|g| g.get(1)) {
+        Some(2) => KeyEventKind::Repeat,
+        Some(3) => KeyEventKind::Release,
+        _ => KeyEventKind::Press,
+    };
+    let code = match (last, number(0)) {
+        (b'~', Some(27)) => code_point_key(number(2)?)?,
+        (b'~', Some(n)) => tilde_key(n)?,
+        (b'u', Some(n)) => code_point_key(n)?,
+        (_, None | Some(1)) => match last {
+            b'A' => KeyCode::Up,
+            b'B' => KeyCode::Down,
+            b'C' => KeyCode::Right,
+            b'D' => KeyCode::Left,
+            b'H' => KeyCode::Home,
+            b'F' => KeyCode::End,
+            b'P'..=b'S' => KeyCode::F(last - b'P' + 1),
+            b'Z' => KeyCode::BackTab,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match shifted_tab(code, mods) {
+        Event::Key(k) => Some(Event::Key(k.with_kind(kind))),
+        event => Some(event),
+    }
+}
+
+fn tilde_key(n: u32) -> Option<KeyCode> {
+    Some(match n {
+        1 | 7 => KeyCode::Home,
+        2 => KeyCode::Insert,
+        3 => KeyCode::Delete,
+        4 | 8 => KeyCode::End,
+        5 => KeyCode::PageUp,
+        6 => KeyCode::PageDown,
+        11..=15 => KeyCode::F((n - 10) as u8),
+        17..=21 => KeyCode::F((n - 11) as u8),
+        23..=26 => KeyCode::F((n - 12) as u8),
+        28 | 29 => KeyCode::F((n - 13) as u8),
+        31..=34 => KeyCode::F((n - 14) as u8),
+        _ => return None,
+    })
+}
+
+// Unicode code point of a key as reported by modifyOtherKeys and CSI-u.
+// Kitty puts F13-F24 in the private use area.
+fn code_point_key(code: u32) -> Option<KeyCode> {
+    Some(match code {
+        13 => KeyCode::Enter,
+        9 => KeyCode::Tab,
+        27 => KeyCode::Esc,
+        8 | 127 => KeyCode::Backspace,
+        57376..=57387 => KeyCode::F((code - 57376 + 13) as u8),
+        _ => KeyCode::Char(char::from_u32(code)?),
+    })
+}
+
+// Shift+Tab is reported as BackTab everywhere, whatever the encoding.
+fn shifted_tab(code: KeyCode, mut mods: Modifiers) -> Event {
+    if code == KeyCode::Tab && mods.contains(Modifiers::SHIFT) {
+        mods.remove(Modifiers::SHIFT);
+        return key(KeyCode::BackTab, mods);
+    }
+    key(code, mods)
+}
+
+// Modifier parameters are 1 + a bit mask of shift, alt, ctrl and super.
+fn modifiers(param: u32) -> Modifiers {
+    let bits = param.saturating_sub(1);
+    let mut mods = Modifiers::NONE;
+    for (bit, m) in [
+        Modifiers::SHIFT,
+        Modifiers::ALT,
+        Modifiers::CONTROL,
+        Modifiers::SUPER,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if bits & (1 << bit) != 0 {
+            mods.insert(m);
+        }
+    }
+    mods
+}
+
+// Parameters split on `;`, each with its `:` separated sub-parameters. An
+// empty sub-parameter reads as 0.
+fn groups(params: &[u8]) -> Option<Vec<Vec<u32>>> {
+    std::str::from_utf8(params)
+        .ok()?
+        .split(';')
+        .map(|group| {
+            group
+                .split(':')
+                .map(|n| {
+                    if n.is_empty() {
+                        Some(0)
+                    } else {
+                        n.parse().ok()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn numbers(params: &[u8]) -> Option<Vec<u32>> {
+    std::str::from_utf8(params)
+        .ok()?
+        .split(';')
+        .map(|n| n.parse().ok())
+        .collect()
+}
+
+// SGR mouse reports (mode 1006): CSI < button ; col ; row M, with a final
+// `m` for releases. Positions are 1-based.
+fn sgr_mouse(params: &[u8], last: u8) -> Option<Event> {
+    let numbers = numbers(params)?;
+    let [cb, col, row] = numbers[..] else {
+        return None;
+    };
+    let button = match cb & 0b11 {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    };
+    // 64 marks the wheel, 32 motion (with a button held in the low bits when
+    // dragging)
+    let kind = if cb & 64 != 0 {
+        match cb & 0b11 {
+            0 => MouseEventKind::ScrollUp,
+            1 => MouseEventKind::ScrollDown,
+            2 => MouseEventKind::ScrollLeft,
+            _ => MouseEventKind::ScrollRight,
+        }
+    } else if cb & 32 != 0 {
+        match button {
+            Some(b) => MouseEventKind::Drag(b),
+            None => MouseEventKind::Moved,
+        }
+    } else if last == b'M' {
+        MouseEventKind::Down(button?)
+    } else {
+        MouseEventKind::Up(button?)
+    };
+
+    let mut modifiers = Modifiers::NONE;
+    if cb & 4 != 0 {
+        modifiers.insert(Modifiers::SHIFT);
+    }
+    if cb & 8 != 0 {
+        modifiers.insert(Modifiers::ALT);
+    }
+    if cb & 16 != 0 {
+        modifiers.insert(Modifiers::CONTROL);
+    }
+    Some(Event::Mouse(MouseEvent {
+        kind,
+        column: col.saturating_sub(1) as u16,
+        row: row.saturating_sub(1) as u16,
+        modifiers,
+    }))
+}
+
+///////////////
+//  Replies  //
+///////////////
+
+// Finds a reply to a terminal query in raw input, e.g. the `CSI 12;40 R`
+// answering a cursor position request. `parse` gets each complete CSI
+// sequence (without the leading `ESC [`) and each OSC string (starting at its
+// `]`, without the terminator) and says whether it is the reply.
+// Returns the byte range of the sequence and the parsed value, so the input
+// around it can still be decoded as keys.
+pub(crate) fn find_reply<T>(
+    bytes: &[u8],
+    parse: impl Fn(&[u8]) -> Option<T>,
+) -> Option<(Range<usize>, T)> {
+    let mut start = 0;
+    while let Some(i) = bytes[start..]
+        .iter()
+        .position(|&b| b == 0x1b || b == 0x9b || b == 0x9d)
+    {
+        let begin = start + i;
+        start = begin + 1;
+        if bytes[begin] != 0x1b && continues_char(bytes, begin) {
+            continue;
+        }
+        // ESC [ or the 8-bit CSI
+        let intro = match &bytes[begin..] {
+            [0x1b, b'[', ..] => 2,
+            [0x9b, ..] => 1,
+            [0x1b, b']', ..] | [0x9d, ..] => {
+                if let Some((len, value)) = osc_reply(&bytes[begin..], &parse) {
+                    return Some((begin..begin + len, value));
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let body = &bytes[begin + intro..];
+        // parameters and intermediates, then a final byte in 0x40..=0x7e
+        if let Some(len) = body.iter().position(|b| (0x40..=0x7e).contains(b))
+            && body[..len].iter().all(|b| (0x20..=0x3f).contains(b))
+            && let Some(value) = parse(&body[..=len])
+        {
+            return Some((begin..begin + intro + len + 1, value));
+        }
+    }
+    None
+}
+
+// Whether `bytes[i]` belongs to a UTF-8 character started before it: 0x9b
+// and 0x9d are continuation bytes too ('ě' is C4 9B), so there they are text
+// and not 8-bit controls.
+fn continues_char(bytes: &[u8], i: usize) -> bool {
+    for back in 1..=i.min(3) {
+        let len = match bytes[i - back] {
+            0x80..=0xbf => continue,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return false,
+        };
+        return back < len;
+    }
+    false
+}
+
+// An OSC string at the start of `bytes`, ended by BEL or ST. Returns its
+// whole length and what `parse` made of it.
+fn osc_reply<T>(bytes: &[u8], parse: impl Fn(&[u8]) -> Option<T>) -> Option<(usize, T)> {
+    let intro = if bytes[0] == 0x9d { 1 } else { 2 };
+    let body = &bytes[intro..];
+    let end = body
+        .iter()
+        .position(|&b| b == 0x07 || b == 0x1b || b == 0x9c)?;
+    let term = match body[end] {
+        0x1b if body.get(end + 1) == Some(&b'\\') => 2,
+        0x1b => return None,
+        _ => 1,
+    };
+    let mut seq = Vec::with_capacity(end + 1);
+    seq.push(b']');
+    seq.extend_from_slice(&body[..end]);
+    Some((intro + end + term, parse(&seq)?))
+}
+
+// `row;col R`, as zero based (col, row).
+pub(crate) fn cursor_report(seq: &[u8]) -> Option<(u16, u16)> {
+    let body = std::str::from_utf8(seq.strip_suffix(b"R")?).ok()?;
+    let (row, col) = body.split_once(';')?;
+    let (row, col): (u16, u16) = (row.parse().ok()?, col.parse().ok()?);
+    Some((col.saturating_sub(1), row.saturating_sub(1)))
+}
+
+// `?mode;state$y` for `mode`.
+pub(crate) fn mode_report(seq: &[u8], mode: u16) -> Option<ModeState> {
+    let body = std::str::from_utf8(seq.strip_prefix(b"?")?.strip_suffix(b"$y")?).ok()?;
+    let (code, state) = body.split_once(';')?;
+    if code.parse::<u16>().ok()? != mode {
+        return None;
+    }
+    Some(ModeState::from_code(state.parse().ok()?))
+}
+
+// `]11;rgb:RRRR/GGGG/BBBB`, the background color (OSC 11). Each component
+// has 1 to 4 hex digits and is scaled to 8 bits.
+pub(crate) fn background_report(seq: &[u8]) -> Option<(u8, u8, u8)> {
+    let body = std::str::from_utf8(seq.strip_prefix(b"]11;rgb:")?).ok()?;
+    let mut parts = body.split('/').map(|hex| {
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len().clamp(1, 4))) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    let rgb = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(rgb)
+}
+
+// `answer;a;b t`, the reply to an XTWINOPS report request.
+pub(crate) fn window_report(seq: &[u8], answer: u16) -> Option<(u16, u16)> {
+    let body = std::str::from_utf8(seq.strip_suffix(b"t")?).ok()?;
+    let mut parts = body.split(';');
+    if parts.next()?.parse::<u16>().ok()? != answer {
+        return None;
+    }
+    let a = parts.next()?.parse().ok()?;
+    let b = parts.next()?.parse().ok()?;
+    Some((a, b))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{
+        InputParser, background_report, cursor_report, find_reply, mode_report, window_report,
+    };
+    use crate::event::{
+        Event, KeyCode, KeyEvent, KeyEventKind, Modifiers, MouseButton, MouseEventKind,
+    };
+    use crate::mode::ModeState;
+    use std::time::Duration;
+
+    fn keys(bytes: &[u8]) -> Vec<(KeyCode, Modifiers)> {
+        let mut parser = InputParser::new();
+        let mut events = parser.feed(bytes);
+        events.extend(parser.flush());
+        events
+            .into_iter()
+            .map(|e| match e {
+                Event::Key(KeyEvent {
+                    code, modifiers, ..
+                }) => (code, modifiers),
+                e => panic!("not a key: {:?}", e),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plain_and_control_keys() {
+        assert_eq!(
+            keys(b"a\r\x7f\x03\x1bx\x1b[A\x1bOP"),
+            vec![
+                (KeyCode::Char('a'), Modifiers::NONE),
+                (KeyCode::Enter, Modifiers::NONE),
+                (KeyCode::Backspace, Modifiers::NONE),
+                (KeyCode::Char('c'), Modifiers::CONTROL),
+                (KeyCode::Char('x'), Modifiers::ALT),
+                (KeyCode::Up, Modifiers::NONE),
+                (KeyCode::F(1), Modifiers::NONE),
+            ]
+        );
+        assert_eq!(keys(b"\x1b"), vec![(KeyCode::Esc, Modifiers::NONE)]);
+    }
+
+    #[test]
+    fn xterm_and_vte_captures() {
+        let ctrl = Modifiers::CONTROL;
+        let shift = Modifiers::SHIFT;
+        assert_eq!(
+            keys(b"\x1b[1;5A\x1b[1;2P\x1b[15;2~\x1b[24~\x1b[1;3H\x1b[6;5~\x1b[2~"),
+            vec![
+                (KeyCode::Up, ctrl),
+                (KeyCode::F(1), shift),
+                (KeyCode::F(5), shift),
+                (KeyCode::F(12), Modifiers::NONE),
+                (KeyCode::Home, Modifiers::ALT),
+                (KeyCode::PageDown, ctrl),
+                (KeyCode::Insert, Modifiers::NONE),
+            ]
+        );
+        // VTE sends Home/End and F1-F4 without parameters
+        assert_eq!(
+            keys(b"\x1b[H\x1b[F\x1bOS\x1b[3~"),
+            vec![
+                (KeyCode::Home, Modifiers::NONE),
+                (KeyCode::End, Modifiers::NONE),
+                (KeyCode::F(4), Modifiers::NONE),
+                (KeyCode::Delete, Modifiers::NONE),
+            ]
+        );
+        // application cursor keys and keypad, with and without modifiers
+        assert_eq!(
+            keys(b"\x1bOD\x1bO5C\x1bOM\x1bOp\x1bOy\x1bOk\x1bO2Q"),
+            vec![
+                (KeyCode::Left, Modifiers::NONE),
+                (KeyCode::Right, ctrl),
+                (KeyCode::Enter, Modifiers::NONE),
+                (KeyCode::Char('0'), Modifiers::NONE),
+                (KeyCode::Char('9'), Modifiers::NONE),
+                (KeyCode::Char('+'), Modifiers::NONE),
+                (KeyCode::F(2), shift),
+            ]
+        );
+        // modifyOtherKeys=2: Ctrl+Enter, Ctrl+Shift+a
+        assert_eq!(
+            keys(b"\x1b[27;5;13~\x1b[27;6;65~"),
+            vec![(KeyCode::Enter, ctrl), (KeyCode::Char('A'), ctrl | shift)]
+        );
+    }
+
+    #[test]
+    fn kitty_csi_u() {
+        assert_eq!(
+            keys(b"\x1b[13;5u\x1b[97;3u\x1b[9;2u\x1b[57376u\x1b[99;9u"),
+            vec![
+                (KeyCode::Enter, Modifiers::CONTROL),
+                (KeyCode::Char('a'), Modifiers::ALT),
+                (KeyCode::BackTab, Modifiers::NONE),
+                (KeyCode::F(13), Modifiers::NONE),
+                (KeyCode::Char('c'), Modifiers::SUPER),
+            ]
+        );
+    }
+
+    #[test]
+    fn kitty_event_types() {
+        let events = InputParser::new().feed(b"\x1b[97;1:1u\x1b[97;1:2u\x1b[97;1:3u\x1b[1;5:3A");
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|e| match e {
+                Event::Key(k) => (k.code, k.kind),
+                e => panic!("not a key: {:?}", e),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (KeyCode::Char('a'), KeyEventKind::Press),
+                (KeyCode::Char('a'), KeyEventKind::Repeat),
+                (KeyCode::Char('a'), KeyEventKind::Release),
+                (KeyCode::Up, KeyEventKind::Release),
+            ]
+        );
+    }
+
+    #[test]
+    fn suspend_byte() {
+        let mut parser = InputParser::new();
+        parser.set_suspend(Some(0x1a));
+        assert_eq!(
+            parser.feed(b"\x1a\x03"),
+            vec![
+                Event::Suspend,
+                Event::Key(KeyEvent::new(KeyCode::Char('c'), Modifiers::CONTROL))
+            ]
+        );
+    }
+
+    #[test]
+    fn interrupt_byte() {
+        let mut parser = InputParser::new();
+        assert_eq!(
+            parser.feed(b"\x03"),
+            vec![Event::Key(KeyEvent::new(
+                KeyCode::Char('c'),
+                Modifiers::CONTROL
+            ))]
+        );
+        parser.set_interrupt(Some(0x03));
+        assert_eq!(
+            parser.feed(b"a\x03"),
+            vec![
+                Event::Key(KeyEvent::new(KeyCode::Char('a'), Modifiers::NONE)),
+                Event::Interrupt
+            ]
+        );
+    }
+
+    #[test]
+    fn esc_waits_for_timeout() {
+        let mut parser = InputParser::new();
+        parser.set_esc_timeout(Duration::from_millis(20));
+        assert!(parser.feed(b"\x1b").is_empty());
+        assert!(parser.timeout().unwrap() <= Duration::from_millis(20));
+        // more input in time: Alt+x
+        assert_eq!(
+            parser.feed(b"x"),
+            vec![Event::Key(KeyEvent::new(
+                KeyCode::Char('x'),
+                Modifiers::ALT
+            ))]
+        );
+        assert_eq!(parser.timeout(), None);
+
+        parser.feed(b"\x1b");
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(parser.timeout(), Some(Duration::ZERO));
+        assert_eq!(
+            parser.flush(),
+            vec![Event::Key(KeyEvent::new(KeyCode::Esc, Modifiers::NONE))]
+        );
+        parser.feed(b"\x1b[");
+        assert_eq!(
+            parser.flush(),
+            vec![Event::Key(KeyEvent::new(
+                KeyCode::Char('['),
+                Modifiers::ALT
+            ))]
+        );
+        assert!(!parser.has_pending());
+    }
+
+    #[test]
+    fn paste_across_reads() {
+        let mut parser = InputParser::new();
+        assert_eq!(
+            parser.feed(b"a\x1b[200~one\r\ntw"),
+            vec![Event::Key(KeyEvent::new(
+                KeyCode::Char('a'),
+                Modifiers::NONE
+            ))]
+        );
+        assert!(parser.has_pending());
+        assert_eq!(parser.timeout(), None);
+        assert!(parser.feed(b"o\x1b[20").is_empty());
+        assert_eq!(
+            parser.feed(b"1~b"),
+            vec![
+                Event::Paste("one\r\ntwo".to_string()),
+                Event::Key(KeyEvent::new(KeyCode::Char('b'), Modifiers::NONE)),
+            ]
+        );
+
+        parser.normalize_paste_newlines(true);
+        assert_eq!(
+            parser.feed(b"\x1b[200~x\r\ny\rz\x1b[201~"),
+            vec![Event::Paste("x\ny\nz".to_string())]
+        );
+    }
+
+    #[test]
+    fn utf8_characters() {
+        assert_eq!(
+            keys("é\u{df}漢".as_bytes()),
+            vec![
+                (KeyCode::Char('é'), Modifiers::NONE),
+                (KeyCode::Char('ß'), Modifiers::NONE),
+                (KeyCode::Char('漢'), Modifiers::NONE),
+            ]
+        );
+        // Alt+é, then a stray continuation byte and a truncated sequence
+        assert_eq!(
+            keys(b"\x1b\xc3\xa9\xa9x\xe6\xbcy"),
+            vec![
+                (KeyCode::Char('é'), Modifiers::ALT),
+                (KeyCode::Char('x'), Modifiers::NONE),
+                (KeyCode::Char('y'), Modifiers::NONE),
+            ]
+        );
+
+        let mut parser = InputParser::new();
+        assert!(parser.feed(&[0xe6, 0xbc]).is_empty());
+        assert_eq!(
+            parser.feed(&[0xa2]),
+            vec![Event::Key(KeyEvent::new(
+                KeyCode::Char('漢'),
+                Modifiers::NONE
+            ))]
+        );
+    }
+
+    #[test]
+    fn sequence_split_across_reads() {
+        let mut parser = InputParser::new();
+        assert!(parser.feed(b"\x1b[").is_empty());
+        assert!(parser.has_pending());
+        assert_eq!(
+            parser.feed(b"Dq"),
+            vec![
+                Event::Key(KeyEvent::new(KeyCode::Left, Modifiers::NONE)),
+                Event::Key(KeyEvent::new(KeyCode::Char('q'), Modifiers::NONE)),
+            ]
+        );
+        assert!(!parser.has_pending());
+    }
+
+    #[test]
+    fn sgr_mouse() {
+        let events = InputParser::new().feed(
+            b"\x1b[<0;5;3M\x1b[<32;6;3M\x1b[<0;6;3m\x1b[<65;1;1M\x1b[<34;2;2M\x1b[<35;3;2M\x1b[<66;1;1M\x1b[<67;1;1M",
+        );
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|e| match e {
+                Event::Mouse(m) => (m.kind, m.column, m.row),
+                e => panic!("not a mouse event: {:?}", e),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (MouseEventKind::Down(MouseButton::Left), 4, 2),
+                (MouseEventKind::Drag(MouseButton::Left), 5, 2),
+                (MouseEventKind::Up(MouseButton::Left), 5, 2),
+                (MouseEventKind::ScrollDown, 0, 0),
+                (MouseEventKind::Drag(MouseButton::Right), 1, 1),
+                (MouseEventKind::Moved, 2, 1),
+                (MouseEventKind::ScrollLeft, 0, 0),
+                (MouseEventKind::ScrollRight, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn cursor_report_among_keys() {
+        let input = b"a\x1b[A\x1b[12;40Rb";
+        let (range, pos) = find_reply(input, cursor_report).unwrap();
+        assert_eq!(range, 4..12);
+        assert_eq!(pos, (39, 11));
+        assert_eq!(find_reply(b"\x1b[1;5A\x1b[12;", cursor_report), None);
+    }
+
+    #[test]
+    fn mode_reports() {
+        let input = b"\x1b[?1049;2$y\x1b[?2026;1$y";
+        let (range, state) = find_reply(input, |s| mode_report(s, 2026)).unwrap();
+        assert_eq!(range, 11..22);
+        assert_eq!(state, ModeState::Set);
+        let (_, state) = find_reply(input, |s| mode_report(s, 1049)).unwrap();
+        assert!(state.is_supported() && !state.is_set());
+        assert_eq!(
+            find_reply(b"\x1b[?9;0$y", |s| mode_report(s, 9)).unwrap().1,
+            ModeState::NotRecognized
+        );
+    }
+
+    #[test]
+    fn eight_bit_controls() {
+        assert_eq!(
+            keys(b"\x9bA\x9b1;5C\x8fP\x9b3~"),
+            vec![
+                (KeyCode::Up, Modifiers::NONE),
+                (KeyCode::Right, Modifiers::CONTROL),
+                (KeyCode::F(1), Modifiers::NONE),
+                (KeyCode::Delete, Modifiers::NONE),
+            ]
+        );
+        let mut parser = InputParser::new();
+        assert_eq!(
+            parser.feed(b"\x9b200~a\x1b[201~b\x9b201~"),
+            vec![Event::Paste("a\x1b[201~b".into())]
+        );
+        let (range, pos) = find_reply(b"x\x9b3;4R", cursor_report).unwrap();
+        assert_eq!((range, pos), (1..6, (3, 2)));
+        // the same bytes inside typed text are left alone
+        assert_eq!(find_reply("ě3;4R".as_bytes(), cursor_report), None);
+        assert_eq!(
+            find_reply("ŝ11;rgb:f/0/8\x07".as_bytes(), background_report),
+            None
+        );
+        // but a complete character before one doesn't hide it
+        let (range, _) = find_reply(b"\xc3\xa9\x9b3;4R", cursor_report).unwrap();
+        assert_eq!(range, 2..7);
+    }
+
+    #[test]
+    fn window_reports() {
+        let input = b"\x1b[8;24;80t\x1b[4;480;640t";
+        let (range, size) = find_reply(input, |s| window_report(s, 4)).unwrap();
+        assert_eq!(range, 10..22);
+        assert_eq!(size, (480, 640));
+        assert_eq!(find_reply(input, |s| window_report(s, 6)), None);
+    }
+
+    #[test]
+    fn background_reports() {
+        let input = b"a\x1b]11;rgb:ffff/8080/0000\x1b\\b";
+        let (range, rgb) = find_reply(input, background_report).unwrap();
+        assert_eq!((range, rgb), (1..26, (255, 128, 0)));
+        let (_, rgb) = find_reply(b"\x1b]11;rgb:f/0/8\x07", background_report).unwrap();
+        assert_eq!(rgb, (255, 0, 136));
+    }
+}
@@ -0,0 +1,276 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Screen geometry. A `Rect` is a region in cells, split into smaller ones by
+// `Constraint`s:
+//
+//   let cols = Rect::sized(80, 24).split_horizontal(&[Length(20), Fill(1)]);
+//   let rows = cols[1].inset(1).split_vertical(&[Length(1), Percentage(50), Fill(1)]);
+
+////////////
+//  Rect  //
+////////////
+
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub const fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // The whole `cols` x `rows` screen.
+    pub const fn sized(cols: u16, rows: u16) -> Self {
+        Self::new(0, 0, cols, rows)
+    }
+
+    pub fn area(&self) -> u32 {
+        self.width as u32 * self.height as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    // First column past the right edge.
+    pub fn right(&self) -> u16 {
+        self.x.saturating_add(self.width)
+    }
+
+    // First row past the bottom edge.
+    pub fn bottom(&self) -> u16 {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn contains(&self, col: u16, row: u16) -> bool {
+        col >= self.x && col < self.right() && row >= self.y && row < self.bottom()
+    }
+
+    // The overlap of both, empty (at the position of `self`) if none.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if right <= x || bottom <= y {
+            return Rect::new(self.x, self.y, 0, 0);
+        }
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    // Shrunk by `n` cells on every side.
+    pub fn inset(&self, n: u16) -> Rect {
+        self.shrink(Margin::uniform(n))
+    }
+
+    pub fn shrink(&self, margin: Margin) -> Rect {
+        let width = self.width.saturating_sub(margin.left + margin.right);
+        let height = self.height.saturating_sub(margin.top + margin.bottom);
+        Rect::new(
+            self.x + margin.left.min(self.width),
+            self.y + margin.top.min(self.height),
+            width,
+            height,
+        )
+    }
+
+    // Side by side columns, left to right.
+    pub fn split_horizontal(&self, constraints: &[Constraint]) -> Vec<Rect> {
+        split(self.width, constraints)
+            .into_iter()
+            .map(|(offset, len)| Rect::new(self.x + offset, self.y, len, self.height))
+            .collect()
+    }
+
+    // Stacked rows, top to bottom.
+    pub fn split_vertical(&self, constraints: &[Constraint]) -> Vec<Rect> {
+        split(self.height, constraints)
+            .into_iter()
+            .map(|(offset, len)| Rect::new(self.x, self.y + offset, self.width, len))
+            .collect()
+    }
+}
+
+//////////////
+//  Margin  //
+//////////////
+
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Margin {
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+    pub left: u16,
+}
+
+impl Margin {
+    pub const fn new(top: u16, right: u16, bottom: u16, left: u16) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    pub const fn uniform(n: u16) -> Self {
+        Self::new(n, n, n, n)
+    }
+
+    // `horizontal` on the left and right, `vertical` above and below.
+    pub const fn symmetric(horizontal: u16, vertical: u16) -> Self {
+        Self::new(vertical, horizontal, vertical, horizontal)
+    }
+}
+
+//////////////////
+//  Constraint  //
+//////////////////
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Constraint {
+    // Exactly this many cells, or what is left.
+    Length(u16),
+    // This share of the whole, 0 to 100.
+    Percentage(u16),
+    // Shares what Length and Percentage leave over, in proportion to the
+    // weights of all Fill constraints.
+    Fill(u16),
+}
+
+// (offset, length) of each part of `total` cells. Lengths and percentages
+// are handed out in order until the space runs out, the rest goes to the
+// fills, with rounding leftovers given to the last one.
+fn split(total: u16, constraints: &[Constraint]) -> Vec<(u16, u16)> {
+    let mut lens = vec![0u16; constraints.len()];
+    let mut left = total;
+    for (len, c) in lens.iter_mut().zip(constraints) {
+        let want = match *c {
+            Constraint::Length(n) => n,
+            Constraint::Percentage(p) => (total as u32 * p.min(100) as u32 / 100) as u16,
+            Constraint::Fill(_) => continue,
+        };
+        *len = want.min(left);
+        left -= *len;
+    }
+
+    let weights: u32 = constraints
+        .iter()
+        .map(|c| match c {
+            Constraint::Fill(w) => *w as u32,
+            _ => 0,
+        })
+        .sum();
+    let fills: Vec<usize> = (0..constraints.len())
+        .filter(|i| matches!(constraints[*i], Constraint::Fill(_)))
+        .collect();
+    let space = left;
+    for (n, &i) in fills.iter().enumerate() {
+        let Constraint::Fill(w) = constraints[i] else {
+            continue;
+        };
+        lens[i] = if n + 1 == fills.len() {
+            left
+        } else {
+            (space as u32 * w as u32).checked_div(weights).unwrap_or(0) as u16
+        };
+        left -= lens[i];
+    }
+
+    let mut offset = 0;
+    lens.into_iter()
+        .map(|len| {
+            let part = (offset, len);
+            offset += len;
+            part
+        })
+        .collect()
+}
+
+/////////////
+//  Align  //
+/////////////
+
+// Where something shorter than the space for it goes.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    // Offset of something `len` long in `space`, 0 if it doesn't fit.
+    // Centering rounds to the left.
+    pub fn offset(&self, len: u16, space: u16) -> u16 {
+        let free = space.saturating_sub(len);
+        match self {
+            Align::Left => 0,
+            Align::Center => free / 2,
+            Align::Right => free,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Constraint::{Fill, Length, Percentage};
+    use super::{Margin, Rect};
+
+    #[test]
+    fn splits() {
+        let area = Rect::new(2, 1, 100, 10);
+        let cols = area.split_horizontal(&[Length(20), Fill(1), Percentage(25), Fill(2)]);
+        assert_eq!(
+            cols,
+            vec![
+                Rect::new(2, 1, 20, 10),
+                Rect::new(22, 1, 18, 10),
+                Rect::new(40, 1, 25, 10),
+                Rect::new(65, 1, 37, 10),
+            ]
+        );
+        let rows = Rect::sized(10, 5).split_vertical(&[Length(3), Length(3), Fill(1)]);
+        assert_eq!(rows[1], Rect::new(0, 3, 10, 2));
+        assert_eq!(rows[2], Rect::new(0, 5, 10, 0));
+    }
+
+    #[test]
+    fn insets_and_intersections() {
+        let r = Rect::new(1, 1, 10, 4);
+        assert_eq!(r.inset(1), Rect::new(2, 2, 8, 2));
+        assert_eq!(r.shrink(Margin::symmetric(2, 0)), Rect::new(3, 1, 6, 4));
+        assert!(r.inset(2).is_empty());
+        assert_eq!(
+            r.intersection(&Rect::new(8, 0, 10, 2)),
+            Rect::new(8, 1, 3, 1)
+        );
+        assert!(r.intersection(&Rect::new(20, 0, 1, 1)).is_empty());
+        assert!(r.contains(10, 4) && !r.contains(11, 4));
+    }
+}
@@ -17,7 +17,51 @@
 #![allow(dead_code)]
 #![allow(invalid_value)]
 
+pub mod adaptive;
+pub mod backend;
+pub mod bell;
+pub mod canvas;
+pub mod cell;
 pub mod color;
+pub mod command;
+#[cfg(feature = "crossterm")]
+pub mod crossterm_interop;
+pub mod cursor;
+pub mod draw;
+pub mod edit;
+pub mod erase;
+pub mod event;
+#[cfg(unix)]
+pub mod event_loop;
+pub mod graphics;
+pub mod hyperlink;
+pub mod input;
+pub mod layout;
+pub mod line_editor;
+pub mod line_size;
+pub mod mode;
+pub mod output;
+pub mod parser;
+pub mod pointer;
+pub mod prompt;
+pub mod pty;
+#[cfg(feature = "ratatui-backend")]
+pub mod ratatui_backend;
+#[cfg(feature = "serde")]
+pub mod recorder;
+pub mod reset;
+pub mod screen;
+pub mod scroll;
+pub mod shell;
+pub mod style;
+pub mod tab;
+pub mod table;
+pub mod testing;
+pub mod theme;
+pub mod trace;
+pub mod virtual_screen;
+pub mod width;
+pub mod window;
 
 #[cfg(unix)]
 mod unix;
@@ -25,6 +69,8 @@ mod unix;
 #[cfg(windows)]
 mod windows;
 
+pub use output::STDOUT_BUFFER_SIZE;
+
 #[cfg(unix)]
 pub use unix::*;
 
@@ -0,0 +1,527 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Readline style line editing on top of raw mode and `EventReader`. The
+// editor itself only turns `Event`s into edits and redraws, so it can be fed
+// from an `EventLoop` too; `read_line` is the blocking convenience:
+//
+//     let mut editor = LineEditor::new("> ");
+//     editor.set_completer(|line, cursor| complete(line, cursor));
+//     while let Readline::Line(line) = editor.read_line()? {
+//         editor.add_history(&line);
+//     }
+//
+// Bindings: arrows, Home/End, Ctrl-A/E/B/F, Alt-B/F (words), Backspace,
+// Delete, Ctrl-D (delete, or end of input on an empty line), Ctrl-W/U/K,
+// Up/Down and Ctrl-P/N (history), Tab (completion), Ctrl-L (clear screen),
+// Ctrl-C (interrupt).
+
+use crate::command::write_command;
+use crate::cursor::{MoveRight, MoveUp};
+use crate::erase::{Clear, ClearType};
+use crate::event::{Event, KeyCode, KeyEvent, KeyEventKind, Modifiers};
+use crate::width::{graphemes, str_width};
+use std::io::{self, Write};
+
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+// How a line ended.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Readline {
+    Line(String),
+    // Ctrl-C
+    Interrupted,
+    // Ctrl-D on an empty line, or the input was closed
+    Eof,
+}
+
+// Completion callback: given the line and the cursor (byte offset), returns
+// where the completed word starts and the candidates replacing
+// `line[start..cursor]`.
+pub type Completer = Box<dyn FnMut(&str, usize) -> (usize, Vec<String>) + Send>;
+
+pub struct LineEditor {
+    prompt: String,
+    line: String,
+    // byte offset into `line`
+    cursor: usize,
+    history: Vec<String>,
+    max_history: usize,
+    // position while browsing history, and the line being edited before
+    history_index: Option<usize>,
+    draft: String,
+    completer: Option<Completer>,
+    // a second Tab in a row lists the candidates
+    last_was_tab: bool,
+    width: u16,
+    // terminal row of the cursor relative to the prompt's first row
+    cursor_row: u16,
+}
+
+impl LineEditor {
+    pub fn new(prompt: &str) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            line: String::new(),
+            cursor: 0,
+            history: Vec::new(),
+            max_history: DEFAULT_HISTORY_SIZE,
+            history_index: None,
+            draft: String::new(),
+            completer: None,
+            last_was_tab: false,
+            width: crate::term_size().map_or(80, |(c, _)| c as u16),
+            cursor_row: 0,
+        }
+    }
+
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+    }
+
+    pub fn set_completer<F>(&mut self, completer: F)
+    where
+        F: FnMut(&str, usize) -> (usize, Vec<String>) + Send + 'static,
+    {
+        self.completer = Some(Box::new(completer));
+    }
+
+    // Terminal width used for wrapping, updated by `Event::Resize`.
+    pub fn set_width(&mut self, width: u16) {
+        self.width = width.max(1);
+    }
+
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // Blank lines and repeats of the previous entry are skipped.
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|l| l == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > self.max_history {
+            self.history.remove(0);
+        }
+    }
+
+    pub fn set_max_history(&mut self, max: usize) {
+        self.max_history = max;
+        let excess = self.history.len().saturating_sub(max);
+        self.history.drain(..excess);
+    }
+
+    // Clears the line and draws the prompt. `handle` assumes this was done.
+    pub fn start(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.line.clear();
+        self.cursor = 0;
+        self.history_index = None;
+        self.last_was_tab = false;
+        self.cursor_row = 0;
+        self.redraw(out)
+    }
+
+    // Applies one event and redraws. Returns how the line ended once it did,
+    // with the cursor moved to the start of the next row.
+    pub fn handle(&mut self, event: &Event, out: &mut impl Write) -> io::Result<Option<Readline>> {
+        let key = match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => *key,
+            Event::Paste(text) => {
+                let text: String = text.chars().filter(|c| !c.is_control()).collect();
+                self.insert(&text);
+                self.redraw(out)?;
+                return Ok(None);
+            }
+            Event::Resize(cols, _) => {
+                self.set_width(*cols);
+                self.redraw(out)?;
+                return Ok(None);
+            }
+            Event::Interrupt => return self.finish(out, Readline::Interrupted),
+            _ => return Ok(None),
+        };
+        let tab = key.code == KeyCode::Tab && key.modifiers.is_empty();
+        let result = self.key(key, out);
+        self.last_was_tab = tab;
+        match result? {
+            Some(done) => self.finish(out, done),
+            None => {
+                self.redraw(out)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent, out: &mut impl Write) -> io::Result<Option<Readline>> {
+        let ctrl = key.modifiers.contains(Modifiers::CONTROL);
+        let alt = key.modifiers.contains(Modifiers::ALT);
+        match key.code {
+            KeyCode::Enter => return Ok(Some(Readline::Line(self.line.clone()))),
+            KeyCode::Char('c') if ctrl => return Ok(Some(Readline::Interrupted)),
+            KeyCode::Char('d') if ctrl && self.line.is_empty() => return Ok(Some(Readline::Eof)),
+            KeyCode::Char('d') if ctrl => self.delete_forward(),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.line.len(),
+            KeyCode::Char('b') if ctrl => self.cursor = self.prev_char(),
+            KeyCode::Char('f') if ctrl => self.cursor = self.next_char(),
+            KeyCode::Char('b') if alt => self.cursor = self.word_start(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_end(),
+            KeyCode::Char('h') if ctrl => self.delete_back(),
+            KeyCode::Char('w') if ctrl => {
+                let start = self.word_start();
+                self.line.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.line.truncate(self.cursor),
+            KeyCode::Char('p') if ctrl => self.history_prev(),
+            KeyCode::Char('n') if ctrl => self.history_next(),
+            KeyCode::Char('l') if ctrl => {
+                out.write_all(b"\x1b[H")?;
+                write_command(&Clear(ClearType::All), out)?;
+                self.cursor_row = 0;
+            }
+            KeyCode::Char(c) if !ctrl && !alt => self.insert(c.encode_utf8(&mut [0; 4])),
+            KeyCode::Backspace => self.delete_back(),
+            KeyCode::Delete => self.delete_forward(),
+            KeyCode::Left if ctrl => self.cursor = self.word_start(),
+            KeyCode::Right if ctrl => self.cursor = self.word_end(),
+            KeyCode::Left => self.cursor = self.prev_char(),
+            KeyCode::Right => self.cursor = self.next_char(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.line.len(),
+            KeyCode::Up => self.history_prev(),
+            KeyCode::Down => self.history_next(),
+            KeyCode::Tab if key.modifiers.is_empty() => self.complete(out)?,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn finish(&mut self, out: &mut impl Write, done: Readline) -> io::Result<Option<Readline>> {
+        // leave the cursor below the whole line, not just the cursor row
+        self.cursor = self.line.len();
+        self.redraw(out)?;
+        out.write_all(b"\r\n")?;
+        out.flush()?;
+        self.cursor_row = 0;
+        Ok(Some(done))
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.line.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    fn delete_back(&mut self) {
+        let prev = self.prev_char();
+        self.line.drain(prev..self.cursor);
+        self.cursor = prev;
+    }
+
+    fn delete_forward(&mut self) {
+        let next = self.next_char();
+        self.line.drain(self.cursor..next);
+    }
+
+    // Cursor movement and deletion go by grapheme cluster, so an accented
+    // letter or an emoji sequence is never split.
+    fn prev_char(&self) -> usize {
+        graphemes(&self.line[..self.cursor])
+            .next_back()
+            .map_or(0, |g| self.cursor - g.len())
+    }
+
+    fn next_char(&self) -> usize {
+        graphemes(&self.line[self.cursor..])
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    // Start of the word before the cursor, skipping whitespace first.
+    fn word_start(&self) -> usize {
+        let before = self.line[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    fn word_end(&self) -> usize {
+        let after = &self.line[self.cursor..];
+        let skipped = after.len() - after.trim_start().len();
+        let word = &after[skipped..];
+        let len = word.find(char::is_whitespace).unwrap_or(word.len());
+        self.cursor + skipped + len
+    }
+
+    fn history_prev(&mut self) {
+        let index = match self.history_index {
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.line.clone();
+                self.history.len() - 1
+            }
+            Some(0) => return,
+            Some(i) => i - 1,
+        };
+        self.history_index = Some(index);
+        self.line = self.history[index].clone();
+        self.cursor = self.line.len();
+    }
+
+    fn history_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            self.line = self.history[index + 1].clone();
+        } else {
+            self.history_index = None;
+            self.line = std::mem::take(&mut self.draft);
+        }
+        self.cursor = self.line.len();
+    }
+
+    fn complete(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let Some(completer) = self.completer.as_mut() else {
+            return Ok(());
+        };
+        let (start, candidates) = completer(&self.line, self.cursor);
+        // a start inside a character is moved back to where it begins
+        let mut start = start.min(self.cursor);
+        while !self.line.is_char_boundary(start) {
+            start -= 1;
+        }
+        let Some(first) = candidates.first() else {
+            return Ok(());
+        };
+        let mut common = first.as_str();
+        for c in &candidates[1..] {
+            let len = common
+                .char_indices()
+                .zip(c.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((i, a), _)| i + a.len_utf8());
+            common = &common[..len];
+        }
+        let replacement = if candidates.len() == 1 {
+            format!("{} ", first)
+        } else {
+            common.to_string()
+        };
+        if self.line[start..self.cursor] != replacement {
+            self.line.replace_range(start..self.cursor, &replacement);
+            self.cursor = start + replacement.len();
+        } else if self.last_was_tab && candidates.len() > 1 {
+            // list the candidates below the line, then redraw it after them
+            let end = self.cursor;
+            self.cursor = self.line.len();
+            self.redraw(out)?;
+            self.cursor = end;
+            write!(out, "\r\n{}\r\n", candidates.join("  "))?;
+            self.cursor_row = 0;
+        }
+        Ok(())
+    }
+
+    // (rows, column) the text up to `len` bytes of the line ends at.
+    fn position(&self, len: usize) -> (u16, u16) {
+        let w = str_width(&self.prompt) + str_width(&self.line[..len]);
+        let width = self.width as usize;
+        ((w / width) as u16, (w % width) as u16)
+    }
+
+    // Rewrites prompt and line from the first row and puts the cursor back.
+    fn redraw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.cursor_row > 0 {
+            write_command(&MoveUp(self.cursor_row), out)?;
+        }
+        out.write_all(b"\r")?;
+        write_command(&Clear(ClearType::FromCursorDown), out)?;
+        out.write_all(self.prompt.as_bytes())?;
+        out.write_all(self.line.as_bytes())?;
+        let (end_row, end_col) = self.position(self.line.len());
+        if end_col == 0 && end_row > 0 {
+            // the terminal holds the cursor in the last column, force the wrap
+            out.write_all(b" \r")?;
+        }
+        let (row, col) = self.position(self.cursor);
+        if end_row > row {
+            write_command(&MoveUp(end_row - row), out)?;
+        }
+        out.write_all(b"\r")?;
+        if col > 0 {
+            write_command(&MoveRight(col), out)?;
+        }
+        self.cursor_row = row;
+        out.flush()
+    }
+
+    // Reads a line from the terminal in raw mode, writing to stdout.
+    pub fn read_line(&mut self) -> io::Result<Readline> {
+        #[cfg(unix)]
+        let _restore = crate::set_raw();
+        #[cfg(windows)]
+        let _vt = crate::enable_vt()?;
+        let mut reader = crate::EventReader::new()?;
+        let mut out = io::stdout();
+        self.start(&mut out)?;
+        loop {
+            let event = match reader.read() {
+                Ok(event) => event,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return self.finish(&mut out, Readline::Eof).map(|_| Readline::Eof);
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(done) = self.handle(&event, &mut out)? {
+                return Ok(done);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{LineEditor, Readline};
+    use crate::event::{Event, KeyCode, KeyEvent, Modifiers};
+    use crate::virtual_screen::VirtualScreen;
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, Modifiers::NONE))
+    }
+
+    fn ctrl(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), Modifiers::CONTROL))
+    }
+
+    fn type_str(editor: &mut LineEditor, screen: &mut VirtualScreen, text: &str) {
+        for c in text.chars() {
+            assert_eq!(editor.handle(&key(KeyCode::Char(c)), screen).unwrap(), None);
+        }
+    }
+
+    fn editor(screen: &mut VirtualScreen) -> LineEditor {
+        let mut editor = LineEditor::new("> ");
+        editor.set_width(screen.size().0 as u16);
+        editor.start(screen).unwrap();
+        editor
+    }
+
+    #[test]
+    fn editing_keys() {
+        let mut screen = VirtualScreen::new(20, 3);
+        let mut ed = editor(&mut screen);
+        type_str(&mut ed, &mut screen, "hello world");
+        ed.handle(&ctrl('w'), &mut screen).unwrap();
+        assert_eq!(ed.line(), "hello ");
+        ed.handle(&ctrl('a'), &mut screen).unwrap();
+        type_str(&mut ed, &mut screen, "say ");
+        ed.handle(&key(KeyCode::Right), &mut screen).unwrap();
+        ed.handle(&key(KeyCode::Delete), &mut screen).unwrap();
+        assert_eq!(ed.line(), "say hllo ");
+        assert_eq!(screen.row_text(0), "> say hllo");
+        assert_eq!(screen.cursor(), (7, 0));
+        ed.handle(&ctrl('k'), &mut screen).unwrap();
+        ed.handle(&ctrl('u'), &mut screen).unwrap();
+        assert_eq!(ed.line(), "");
+        assert_eq!(
+            ed.handle(&ctrl('d'), &mut screen).unwrap(),
+            Some(Readline::Eof)
+        );
+    }
+
+    #[test]
+    fn wraps_and_redraws() {
+        let mut screen = VirtualScreen::new(8, 4);
+        let mut ed = editor(&mut screen);
+        type_str(&mut ed, &mut screen, "abcdefghij");
+        assert_eq!(screen.to_plain_string(), "> abcdef\nghij\n\n");
+        assert_eq!(screen.cursor(), (4, 1));
+        ed.handle(&ctrl('a'), &mut screen).unwrap();
+        assert_eq!(screen.cursor(), (2, 0));
+        ed.handle(&key(KeyCode::Backspace), &mut screen).unwrap();
+        ed.handle(&key(KeyCode::Delete), &mut screen).unwrap();
+        assert_eq!(screen.to_plain_string(), "> bcdefg\nhij\n\n");
+        let done = ed.handle(&key(KeyCode::Enter), &mut screen).unwrap();
+        assert_eq!(done, Some(Readline::Line("bcdefghij".into())));
+        assert_eq!(screen.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn history_navigation() {
+        let mut screen = VirtualScreen::new(20, 3);
+        let mut ed = editor(&mut screen);
+        ed.add_history("first");
+        ed.add_history("second");
+        ed.add_history("second");
+        assert_eq!(ed.history().len(), 2);
+        type_str(&mut ed, &mut screen, "draft");
+        ed.handle(&key(KeyCode::Up), &mut screen).unwrap();
+        ed.handle(&key(KeyCode::Up), &mut screen).unwrap();
+        ed.handle(&key(KeyCode::Up), &mut screen).unwrap();
+        assert_eq!(ed.line(), "first");
+        ed.handle(&key(KeyCode::Down), &mut screen).unwrap();
+        ed.handle(&key(KeyCode::Down), &mut screen).unwrap();
+        assert_eq!(ed.line(), "draft");
+        assert_eq!(screen.row_text(0), "> draft");
+    }
+
+    #[test]
+    fn tab_completion() {
+        let mut screen = VirtualScreen::new(30, 4);
+        let mut ed = editor(&mut screen);
+        ed.set_completer(|line, cursor| {
+            let start = line[..cursor].rfind(' ').map_or(0, |i| i + 1);
+            let word = &line[start..cursor];
+            let words = ["commit", "config", "clone"];
+            let found = words.iter().filter(|w| w.starts_with(word));
+            (start, found.map(|w| w.to_string()).collect())
+        });
+        type_str(&mut ed, &mut screen, "git co");
+        ed.handle(&key(KeyCode::Tab), &mut screen).unwrap();
+        assert_eq!(ed.line(), "git co");
+        ed.handle(&key(KeyCode::Tab), &mut screen).unwrap();
+        assert_eq!(screen.row_text(1), "commit  config");
+        assert_eq!(screen.row_text(2), "> git co");
+        type_str(&mut ed, &mut screen, "m");
+        ed.handle(&key(KeyCode::Tab), &mut screen).unwrap();
+        assert_eq!(ed.line(), "git commit ");
+
+        let mut ed = editor(&mut screen);
+        ed.set_completer(|_, _| (1, vec!["café".to_string()]));
+        type_str(&mut ed, &mut screen, "é");
+        ed.handle(&key(KeyCode::Tab), &mut screen).unwrap();
+        assert_eq!(ed.line(), "café ");
+    }
+}
@@ -0,0 +1,95 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Double width / double height lines (DECDWL, DECDHL). The size applies to
+// the whole line the cursor is on and every character on it takes two
+// columns, so a double size line only fits half as many characters.
+// `Screen::set_line_size` lays rows out accordingly and `VirtualScreen`
+// tracks the sizes set by the stream.
+
+use crate::command::{Command, write_command};
+use crate::width::str_width;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum LineSize {
+    #[default]
+    Single,
+    DoubleWidth,
+    // Double height lines are drawn as two rows with the same text, the top
+    // half on the first and the bottom half on the second.
+    DoubleHeightTop,
+    DoubleHeightBottom,
+}
+
+impl LineSize {
+    // Columns per character.
+    pub fn scale(&self) -> usize {
+        match self {
+            LineSize::Single => 1,
+            _ => 2,
+        }
+    }
+
+    // How many single width columns fit on a line of this size on a terminal
+    // `cols` wide.
+    pub fn columns(&self, cols: usize) -> usize {
+        cols / self.scale()
+    }
+
+    // Terminal columns `text` covers on a line of this size.
+    pub fn width_of(&self, text: &str) -> usize {
+        str_width(text) * self.scale()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetLineSize(pub LineSize);
+
+impl Command for SetLineSize {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(match self.0 {
+            LineSize::DoubleHeightTop => b"\x1b#3",
+            LineSize::DoubleHeightBottom => b"\x1b#4",
+            LineSize::Single => b"\x1b#5",
+            LineSize::DoubleWidth => b"\x1b#6",
+        })
+    }
+}
+
+// Writes `text` as a double height banner on the cursor line and the one
+// below, leaving the cursor at the start of the line after the banner.
+pub fn banner(out: &mut impl Write, text: &str) -> io::Result<()> {
+    write_command(&SetLineSize(LineSize::DoubleHeightTop), out)?;
+    write!(out, "{}\r\n", text)?;
+    write_command(&SetLineSize(LineSize::DoubleHeightBottom), out)?;
+    write!(out, "{}\r\n", text)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{LineSize, banner};
+
+    #[test]
+    fn double_size_widths() {
+        assert_eq!(LineSize::DoubleWidth.columns(81), 40);
+        assert_eq!(LineSize::DoubleHeightTop.width_of("abc"), 6);
+        let mut out = Vec::new();
+        banner(&mut out, "Hi").unwrap();
+        assert_eq!(out, b"\x1b#3Hi\r\n\x1b#4Hi\r\n");
+    }
+}
@@ -0,0 +1,606 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// DEC private modes (`CSI ? n h` / `CSI ? n l`).
+
+use crate::command::{Command, write_command};
+use crate::cursor::{RestorePosition, SavePosition};
+use crate::erase::{Clear, ClearType};
+use crate::pointer::ResetPointerShape;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DecMode {
+    ApplicationCursorKeys,
+    Columns132,
+    ReverseVideo,
+    AutoWrap,
+    CursorVisible,
+    Allow132Columns,
+    MouseClicks,
+    MouseDrag,
+    MouseMotion,
+    FocusEvents,
+    MouseSgr,
+    AltScreen,
+    BracketedPaste,
+    SynchronizedOutput,
+    Other(u16),
+}
+
+impl DecMode {
+    pub fn code(&self) -> u16 {
+        match self {
+            DecMode::ApplicationCursorKeys => 1,
+            DecMode::Columns132 => 3,
+            DecMode::ReverseVideo => 5,
+            DecMode::AutoWrap => 7,
+            DecMode::CursorVisible => 25,
+            DecMode::Allow132Columns => 40,
+            DecMode::MouseClicks => 1000,
+            DecMode::MouseDrag => 1002,
+            DecMode::MouseMotion => 1003,
+            DecMode::FocusEvents => 1004,
+            DecMode::MouseSgr => 1006,
+            DecMode::AltScreen => 1049,
+            DecMode::BracketedPaste => 2004,
+            DecMode::SynchronizedOutput => 2026,
+            DecMode::Other(code) => *code,
+        }
+    }
+
+    pub fn from_code(code: u16) -> DecMode {
+        match code {
+            1 => DecMode::ApplicationCursorKeys,
+            3 => DecMode::Columns132,
+            5 => DecMode::ReverseVideo,
+            7 => DecMode::AutoWrap,
+            25 => DecMode::CursorVisible,
+            40 => DecMode::Allow132Columns,
+            1000 => DecMode::MouseClicks,
+            1002 => DecMode::MouseDrag,
+            1003 => DecMode::MouseMotion,
+            1004 => DecMode::FocusEvents,
+            1006 => DecMode::MouseSgr,
+            1049 => DecMode::AltScreen,
+            2004 => DecMode::BracketedPaste,
+            2026 => DecMode::SynchronizedOutput,
+            code => DecMode::Other(code),
+        }
+    }
+
+    // Whether a freshly reset terminal has the mode set.
+    pub fn default_state(&self) -> bool {
+        matches!(self, DecMode::AutoWrap | DecMode::CursorVisible)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetMode(pub DecMode);
+
+impl Command for SetMode {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[?{}h", self.0.code())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetMode(pub DecMode);
+
+impl Command for ResetMode {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[?{}l", self.0.code())
+    }
+}
+
+// DECRPM answer to `RequestMode`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ModeState {
+    NotRecognized,
+    Set,
+    Reset,
+    PermanentlySet,
+    PermanentlyReset,
+}
+
+impl ModeState {
+    pub fn from_code(code: u16) -> ModeState {
+        match code {
+            1 => ModeState::Set,
+            2 => ModeState::Reset,
+            3 => ModeState::PermanentlySet,
+            4 => ModeState::PermanentlyReset,
+            _ => ModeState::NotRecognized,
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        matches!(self, ModeState::Set | ModeState::PermanentlySet)
+    }
+
+    // The terminal knows the mode and lets it be changed.
+    pub fn is_supported(&self) -> bool {
+        matches!(self, ModeState::Set | ModeState::Reset)
+    }
+}
+
+// DECRQM, asks for the state of a mode. The terminal answers with
+// `CSI ? mode ; state $ y`; see `query_mode`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RequestMode(pub DecMode);
+
+impl Command for RequestMode {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[?{}$p", self.0.code())
+    }
+}
+
+// Asks the terminal whether `mode` is supported and set, instead of assuming.
+// Terminals without DECRQM don't answer, which shows up as `TimedOut` after
+// `QUERY_TIMEOUT`.
+pub fn query_mode(mode: DecMode) -> io::Result<ModeState> {
+    query_mode_timeout(mode, crate::QUERY_TIMEOUT)
+}
+
+pub fn query_mode_timeout(mode: DecMode, timeout: Duration) -> io::Result<ModeState> {
+    let request = RequestMode(mode).to_ansi_string();
+    crate::query(request.as_bytes(), timeout, |seq| {
+        crate::input::mode_report(seq, mode.code())
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnterAlternateScreen;
+
+impl Command for EnterAlternateScreen {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        SetMode(DecMode::AltScreen).write_ansi(out)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LeaveAlternateScreen;
+
+impl Command for LeaveAlternateScreen {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        ResetMode(DecMode::AltScreen).write_ansi(out)
+    }
+}
+
+// How to switch to the alternate screen. Xterm's 1049 saves the cursor and
+// clears in one go; older terminals only have 1047 plus 1048 to save the
+// cursor, or the original 47 which needs DECSC/DECRC.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum AltScreenMethod {
+    #[default]
+    Xterm,
+    Mode1047,
+    Mode47,
+}
+
+impl AltScreenMethod {
+    // The mode that is set while on the alternate screen.
+    pub fn mode(&self) -> DecMode {
+        match self {
+            AltScreenMethod::Xterm => DecMode::AltScreen,
+            AltScreenMethod::Mode1047 => DecMode::Other(1047),
+            AltScreenMethod::Mode47 => DecMode::Other(47),
+        }
+    }
+}
+
+// Saves the cursor, switches to the alternate screen and clears it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnterAlternateScreenWith(pub AltScreenMethod);
+
+impl Command for EnterAlternateScreenWith {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.0 {
+            AltScreenMethod::Xterm => SetMode(DecMode::AltScreen).write_ansi(out),
+            AltScreenMethod::Mode1047 => {
+                SetMode(DecMode::Other(1048)).write_ansi(out)?;
+                SetMode(DecMode::Other(1047)).write_ansi(out)?;
+                Clear(ClearType::All).write_ansi(out)
+            }
+            AltScreenMethod::Mode47 => {
+                SavePosition.write_ansi(out)?;
+                SetMode(DecMode::Other(47)).write_ansi(out)?;
+                Clear(ClearType::All).write_ansi(out)
+            }
+        }
+    }
+}
+
+// Goes back to the main screen and puts the cursor where it was.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LeaveAlternateScreenWith(pub AltScreenMethod);
+
+impl Command for LeaveAlternateScreenWith {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.0 {
+            AltScreenMethod::Xterm => ResetMode(DecMode::AltScreen).write_ansi(out),
+            AltScreenMethod::Mode1047 => {
+                ResetMode(DecMode::Other(1047)).write_ansi(out)?;
+                ResetMode(DecMode::Other(1048)).write_ansi(out)
+            }
+            AltScreenMethod::Mode47 => {
+                // 47 keeps the alternate content around for next time
+                Clear(ClearType::All).write_ansi(out)?;
+                ResetMode(DecMode::Other(47)).write_ansi(out)?;
+                RestorePosition.write_ansi(out)
+            }
+        }
+    }
+}
+
+// Turns off every mode an application typically enables: mouse reporting,
+// focus events, bracketed paste and the alternate screen. Also resets colors
+// and shows the cursor. Used to hand a sane terminal back to the shell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RestoreDefaults;
+
+impl Command for RestoreDefaults {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        for mode in [
+            DecMode::MouseClicks,
+            DecMode::MouseDrag,
+            DecMode::MouseMotion,
+            DecMode::MouseSgr,
+            DecMode::FocusEvents,
+            DecMode::BracketedPaste,
+            DecMode::AltScreen,
+        ] {
+            ResetMode(mode).write_ansi(out)?;
+        }
+        SetMode(DecMode::CursorVisible).write_ansi(out)?;
+        out.write_all(b"\x1b[0m")
+    }
+}
+
+// Progressive enhancement flags of the kitty keyboard protocol. Terminals
+// that don't know the protocol ignore them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct KeyboardFlags(u8);
+
+impl KeyboardFlags {
+    pub const NONE: KeyboardFlags = KeyboardFlags(0);
+    pub const DISAMBIGUATE_ESCAPE_CODES: KeyboardFlags = KeyboardFlags(1);
+    pub const REPORT_EVENT_TYPES: KeyboardFlags = KeyboardFlags(2);
+    pub const REPORT_ALTERNATE_KEYS: KeyboardFlags = KeyboardFlags(4);
+    pub const REPORT_ALL_KEYS_AS_ESCAPE_CODES: KeyboardFlags = KeyboardFlags(8);
+    pub const REPORT_ASSOCIATED_TEXT: KeyboardFlags = KeyboardFlags(16);
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, other: KeyboardFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for KeyboardFlags {
+    type Output = KeyboardFlags;
+    fn bitor(self, rhs: KeyboardFlags) -> KeyboardFlags {
+        KeyboardFlags(self.0 | rhs.0)
+    }
+}
+
+// Pushes flags onto the terminal's keyboard mode stack (`CSI > flags u`).
+// REPORT_EVENT_TYPES is what makes key repeats and releases visible.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PushKeyboardFlags(pub KeyboardFlags);
+
+impl Command for PushKeyboardFlags {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[>{}u", self.0.bits())
+    }
+}
+
+// Pops the flags pushed by `PushKeyboardFlags`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PopKeyboardFlags;
+
+impl Command for PopKeyboardFlags {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[<u")
+    }
+}
+
+// How much mouse activity the terminal reports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum MouseTracking {
+    // presses, releases and the wheel (1000)
+    #[default]
+    Clicks,
+    // plus motion while a button is held (1002)
+    Drag,
+    // plus all motion, even with no button down (1003)
+    AnyMotion,
+}
+
+impl MouseTracking {
+    pub fn mode(&self) -> DecMode {
+        match self {
+            MouseTracking::Clicks => DecMode::MouseClicks,
+            MouseTracking::Drag => DecMode::MouseDrag,
+            MouseTracking::AnyMotion => DecMode::MouseMotion,
+        }
+    }
+}
+
+// Turns on mouse reporting at the given level, using SGR encoding (1006) so
+// positions past column 223 and releases of specific buttons come through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnableMouseCapture(pub MouseTracking);
+
+impl Command for EnableMouseCapture {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        SetMode(self.0.mode()).write_ansi(out)?;
+        SetMode(DecMode::MouseSgr).write_ansi(out)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DisableMouseCapture;
+
+impl Command for DisableMouseCapture {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        for mode in [
+            DecMode::MouseMotion,
+            DecMode::MouseDrag,
+            DecMode::MouseClicks,
+            DecMode::MouseSgr,
+        ] {
+            ResetMode(mode).write_ansi(out)?;
+        }
+        Ok(())
+    }
+}
+
+// Screen width for DECCOLM.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum ColumnMode {
+    #[default]
+    Columns80,
+    Columns132,
+}
+
+impl ColumnMode {
+    pub fn columns(&self) -> u16 {
+        match self {
+            ColumnMode::Columns80 => 80,
+            ColumnMode::Columns132 => 132,
+        }
+    }
+}
+
+// DECCOLM. Switching clears the screen, homes the cursor and resets the
+// scroll region. xterm only honours it once 80/132 switching is allowed
+// (mode 40), so that is set first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetColumnMode(pub ColumnMode);
+
+impl Command for SetColumnMode {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        SetMode(DecMode::Allow132Columns).write_ansi(out)?;
+        match self.0 {
+            ColumnMode::Columns80 => ResetMode(DecMode::Columns132).write_ansi(out),
+            ColumnMode::Columns132 => SetMode(DecMode::Columns132).write_ansi(out),
+        }
+    }
+}
+
+// DECKPAM, makes the keypad send `ESC O` sequences instead of digits so its
+// keys can be told apart from the main keyboard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ApplicationKeypad;
+
+impl Command for ApplicationKeypad {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b=")
+    }
+}
+
+// DECKPNM, the keypad sends what is printed on its keys again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NumericKeypad;
+
+impl Command for NumericKeypad {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b>")
+    }
+}
+
+/////////////
+//  Modes  //
+/////////////
+
+#[derive(Debug, Default)]
+struct ModesInner {
+    // modes away from their default, in the order they were changed
+    changed: Vec<(DecMode, bool)>,
+    keyboard_pushes: usize,
+    application_keypad: bool,
+    pointer_shape: bool,
+}
+
+// Record of the modes changed from their defaults, so they can all be put
+// back in one go: `Terminal` keeps one, fills it from `set_mode` and friends
+// and restores it when dropped or on panic. Clones share the record.
+#[derive(Debug, Clone, Default)]
+pub struct Modes(Arc<Mutex<ModesInner>>);
+
+impl Modes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn inner(&self) -> MutexGuard<'_, ModesInner> {
+        self.0.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    pub fn record(&self, mode: DecMode, set: bool) {
+        let mut inner = self.inner();
+        inner.changed.retain(|(m, _)| m.code() != mode.code());
+        if set != mode.default_state() {
+            inner.changed.push((mode, set));
+        }
+    }
+
+    pub fn record_keyboard_push(&self) {
+        self.inner().keyboard_pushes += 1;
+    }
+
+    pub fn record_keyboard_pop(&self) {
+        let mut inner = self.inner();
+        inner.keyboard_pushes = inner.keyboard_pushes.saturating_sub(1);
+    }
+
+    // Keypad mode isn't a private mode, so it is recorded on its own.
+    pub fn record_keypad(&self, application: bool) {
+        self.inner().application_keypad = application;
+    }
+
+    pub fn is_application_keypad(&self) -> bool {
+        self.inner().application_keypad
+    }
+
+    // Whether the mouse pointer was given a shape (OSC 22) that needs resetting.
+    pub fn record_pointer_shape(&self, changed: bool) {
+        self.inner().pointer_shape = changed;
+    }
+
+    // Current state as far as recorded, the default otherwise.
+    pub fn is_set(&self, mode: DecMode) -> bool {
+        self.inner()
+            .changed
+            .iter()
+            .find(|(m, _)| m.code() == mode.code())
+            .map_or(mode.default_state(), |(_, set)| *set)
+    }
+
+    pub fn is_changed(&self, mode: DecMode) -> bool {
+        self.inner()
+            .changed
+            .iter()
+            .any(|(m, _)| m.code() == mode.code())
+    }
+
+    pub fn changed(&self) -> Vec<(DecMode, bool)> {
+        self.inner().changed.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let inner = self.inner();
+        inner.changed.is_empty()
+            && inner.keyboard_pushes == 0
+            && !inner.application_keypad
+            && !inner.pointer_shape
+    }
+
+    // Forgets everything, e.g. after a hard reset put the terminal back.
+    pub fn clear(&self) {
+        *self.inner() = ModesInner::default();
+    }
+
+    // Writes the sequences undoing every recorded change, newest first, and
+    // clears the record.
+    pub fn restore(&self, out: &mut impl Write) -> io::Result<()> {
+        let inner = std::mem::take(&mut *self.inner());
+        for _ in 0..inner.keyboard_pushes {
+            write_command(&PopKeyboardFlags, out)?;
+        }
+        if inner.application_keypad {
+            write_command(&NumericKeypad, out)?;
+        }
+        if inner.pointer_shape {
+            write_command(&ResetPointerShape, out)?;
+        }
+        for (mode, set) in inner.changed.into_iter().rev() {
+            if set {
+                write_command(&ResetMode(mode), out)?;
+            } else {
+                write_command(&SetMode(mode), out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{
+        AltScreenMethod, DecMode, EnterAlternateScreenWith, LeaveAlternateScreenWith, Modes,
+    };
+    use crate::command::Command;
+
+    #[test]
+    fn restores_in_reverse() {
+        let modes = Modes::new();
+        modes.record(DecMode::AltScreen, true);
+        modes.record(DecMode::CursorVisible, false);
+        modes.record(DecMode::BracketedPaste, true);
+        modes.record(DecMode::BracketedPaste, false);
+        modes.record(DecMode::MouseSgr, true);
+        modes.record_keyboard_push();
+        modes.record_keypad(true);
+        assert!(modes.is_set(DecMode::AltScreen));
+        assert!(!modes.is_set(DecMode::CursorVisible));
+        assert!(modes.is_set(DecMode::AutoWrap));
+        assert!(!modes.is_changed(DecMode::BracketedPaste));
+
+        let shared = modes.clone();
+        let mut out = Vec::new();
+        shared.restore(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[<u\x1b>\x1b[?1006l\x1b[?25h\x1b[?1049l"
+        );
+        assert!(modes.is_empty());
+    }
+
+    #[test]
+    fn alt_screen_methods() {
+        let seqs = |m| {
+            (
+                EnterAlternateScreenWith(m).to_ansi_string(),
+                LeaveAlternateScreenWith(m).to_ansi_string(),
+            )
+        };
+        assert_eq!(
+            seqs(AltScreenMethod::Xterm),
+            ("\x1b[?1049h".into(), "\x1b[?1049l".into())
+        );
+        assert_eq!(
+            seqs(AltScreenMethod::Mode1047),
+            (
+                "\x1b[?1048h\x1b[?1047h\x1b[2J".into(),
+                "\x1b[?1047l\x1b[?1048l".into()
+            )
+        );
+        assert_eq!(
+            seqs(AltScreenMethod::Mode47),
+            (
+                "\x1b7\x1b[?47h\x1b[2J".into(),
+                "\x1b[2J\x1b[?47l\x1b8".into()
+            )
+        );
+    }
+}
@@ -0,0 +1,568 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::color::{ColorChoice, color_choice};
+use crate::command::write_command;
+use crate::mode::{DecMode, ResetMode, SetMode};
+use crate::parser::{Action, Parser};
+use crate::trace::Tracer;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+pub const STDOUT_BUFFER_SIZE: usize = 2048; // bytes
+
+// Standard places for terminal output. A progress display that shouldn't mix
+// with piped data goes to `Stderr`, or to `Tty` to keep drawing while both
+// stdout and stderr are redirected.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum Target {
+    #[default]
+    Stdout,
+    Stderr,
+    // The controlling terminal, `/dev/tty` (`CONOUT$` on Windows).
+    Tty,
+}
+
+// Destination of everything a `Terminal` emits. Defaults to stdout but can be
+// any writer: stderr, a socket, a pty master or a capture buffer.
+//
+// Writes are collected in a buffer of `STDOUT_BUFFER_SIZE` bytes and only
+// reach the sink when it fills up, on `flush()`, or when the output is
+// dropped (unless `set_flush_on_drop(false)` was called).
+//
+// When the color choice turns escapes off, they are stripped on the way in
+// and only text and control characters reach the sink.
+pub struct Output {
+    sink: Box<dyn Write + Send>,
+    buffer: Vec<u8>,
+    capacity: usize,
+    flush_on_drop: bool,
+    // whether the sink is a terminal, None when it can't be told
+    tty: Option<bool>,
+    strip: Option<Parser>,
+    // standard stream behind the sink, for `lock`
+    stream: Option<Target>,
+    // Some while a frame is open, true if it is synchronized
+    frame: Option<bool>,
+    // why `MY_TERM_TRACE` couldn't be opened
+    trace_error: Option<io::Error>,
+}
+
+impl Output {
+    pub fn new<W: Write + Send + 'static>(sink: W) -> Self {
+        Self::with_capacity(STDOUT_BUFFER_SIZE, sink)
+    }
+
+    pub fn with_capacity<W: Write + Send + 'static>(capacity: usize, sink: W) -> Self {
+        let mut out = Self {
+            sink: Box::new(sink),
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+            flush_on_drop: true,
+            tty: None,
+            strip: None,
+            stream: None,
+            frame: None,
+            trace_error: None,
+        };
+        out.set_color_choice(color_choice());
+        out
+    }
+
+    // Traced when `MY_TERM_TRACE` names a log file, see `trace`.
+    pub fn stdout() -> Self {
+        let tty = io::stdout().is_terminal();
+        let mut out = Self::traced(io::stdout());
+        out.stream = Some(Target::Stdout);
+        out.set_tty(tty);
+        out
+    }
+
+    pub fn stderr() -> Self {
+        let tty = io::stderr().is_terminal();
+        let mut out = Self::traced(io::stderr());
+        out.stream = Some(Target::Stderr);
+        out.set_tty(tty);
+        out
+    }
+
+    // Fails when the process has no controlling terminal.
+    pub fn tty() -> io::Result<Self> {
+        let (_, tty) = crate::open_tty()?;
+        let mut out = Self::traced(tty);
+        out.stream = Some(Target::Tty);
+        out.set_tty(true);
+        Ok(out)
+    }
+
+    fn traced<W: Write + Send + 'static>(sink: W) -> Self {
+        match Tracer::from_env(sink) {
+            Ok(tracer) => Self::new(tracer),
+            Err((sink, error)) => {
+                let mut out = Self::new(sink);
+                out.trace_error = error;
+                out
+            }
+        }
+    }
+
+    // Set when `MY_TERM_TRACE` names a file that couldn't be opened; the
+    // output then goes untraced.
+    pub fn trace_error(&self) -> Option<&io::Error> {
+        self.trace_error.as_ref()
+    }
+
+    pub fn to(target: Target) -> io::Result<Self> {
+        match target {
+            Target::Stdout => Ok(Self::stdout()),
+            Target::Stderr => Ok(Self::stderr()),
+            Target::Tty => Self::tty(),
+        }
+    }
+
+    // Which standard target the sink is, None for other writers.
+    pub fn target(&self) -> Option<Target> {
+        self.stream
+    }
+
+    // Tells `ColorChoice::Auto` whether the sink is a terminal. Sinks other
+    // than the standard targets are assumed to be one until told otherwise.
+    pub fn set_tty(&mut self, tty: bool) {
+        self.tty = Some(tty);
+        self.set_color_choice(color_choice());
+    }
+
+    pub fn set_color_choice(&mut self, choice: ColorChoice) {
+        if choice.resolve(self.tty.unwrap_or(true)) {
+            self.strip = None;
+        } else if self.strip.is_none() {
+            self.strip = Some(Parser::new());
+        }
+    }
+
+    pub fn escapes_enabled(&self) -> bool {
+        self.strip.is_none()
+    }
+
+    // Logs every sequence reaching the sink to `log` from now on.
+    pub fn set_trace<L: Write + Send + 'static>(&mut self, log: L) -> io::Result<()> {
+        self.flush_buffer()?;
+        let sink = std::mem::replace(&mut self.sink, Box::new(io::sink()));
+        self.sink = Box::new(Tracer::new(sink, log));
+        Ok(())
+    }
+
+    // Holds the stdout (or stderr) lock until the returned handle is dropped,
+    // which also flushes. Everything written through it reaches the stream
+    // as one piece, without other threads' prints in between.
+    pub fn lock(&mut self) -> OutputLock<'_> {
+        let guard = self.stream_lock();
+        OutputLock {
+            out: Held::Borrowed(self),
+            _guard: guard,
+        }
+    }
+
+    fn stream_lock(&self) -> StreamLock {
+        match self.stream {
+            Some(Target::Stdout) => StreamLock::Stdout(io::stdout().lock()),
+            Some(Target::Stderr) => StreamLock::Stderr(io::stderr().lock()),
+            _ => StreamLock::None,
+        }
+    }
+
+    // Moves the output behind a `SharedWriter`.
+    pub fn into_shared(self) -> SharedWriter {
+        SharedWriter(Arc::new(Mutex::new(self)))
+    }
+
+    // Starts a frame: nothing reaches the sink until `end_frame`, however
+    // much is written. With `synchronized` the frame is also wrapped in
+    // synchronized output mode (2026) so the terminal paints it in one go.
+    pub fn begin_frame(&mut self, synchronized: bool) -> io::Result<()> {
+        self.frame = Some(synchronized);
+        if synchronized {
+            write_command(&SetMode(DecMode::SynchronizedOutput), self)?;
+        }
+        Ok(())
+    }
+
+    // Closes the frame and flushes it.
+    pub fn end_frame(&mut self) -> io::Result<()> {
+        if self.frame.take() == Some(true) {
+            write_command(&ResetMode(DecMode::SynchronizedOutput), self)?;
+        }
+        self.flush()
+    }
+
+    pub fn in_frame(&self) -> bool {
+        self.frame.is_some()
+    }
+
+    // Sends a query straight to the sink, past escape stripping and an open
+    // frame, since the terminal has to see it to answer. Output buffered
+    // outside a frame goes first.
+    pub fn write_request(&mut self, request: &[u8]) -> io::Result<()> {
+        if self.frame.is_none() {
+            self.flush_buffer()?;
+        }
+        self.sink.write_all(request)?;
+        self.sink.flush()
+    }
+
+    // Bytes waiting in the buffer.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn set_flush_on_drop(&mut self, flush: bool) {
+        self.flush_on_drop = flush;
+    }
+
+    // Throws away buffered output without writing it.
+    pub fn discard(&mut self) {
+        self.buffer.clear();
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let res = self.sink.write_all(&self.buffer);
+            self.buffer.clear();
+            res?;
+        }
+        Ok(())
+    }
+}
+
+//////////////////
+//  OutputLock  //
+//////////////////
+
+// The standard stream locks are reentrant, so the `Output` can still write
+// to the stream while its lock is held here.
+enum StreamLock {
+    Stdout(io::StdoutLock<'static>),
+    Stderr(io::StderrLock<'static>),
+    None,
+}
+
+enum Held<'a> {
+    Borrowed(&'a mut Output),
+    Shared(MutexGuard<'a, Output>),
+}
+
+impl Deref for Held<'_> {
+    type Target = Output;
+    fn deref(&self) -> &Output {
+        match self {
+            Held::Borrowed(out) => out,
+            Held::Shared(out) => out,
+        }
+    }
+}
+
+impl DerefMut for Held<'_> {
+    fn deref_mut(&mut self) -> &mut Output {
+        match self {
+            Held::Borrowed(out) => out,
+            Held::Shared(out) => out,
+        }
+    }
+}
+
+// Handle for writing a whole frame, see `Output::lock`.
+pub struct OutputLock<'a> {
+    out: Held<'a>,
+    _guard: StreamLock,
+}
+
+impl Write for OutputLock<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Drop for OutputLock<'_> {
+    fn drop(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+////////////////////
+//  SharedWriter  //
+////////////////////
+
+// An `Output` behind a mutex, cloned cheaply to write from several threads.
+// Each `write` call goes in whole, and `queue!` writes every command with a
+// single call, so one thread's sequences are never split by another's.
+#[derive(Debug, Clone)]
+pub struct SharedWriter(Arc<Mutex<Output>>);
+
+impl SharedWriter {
+    pub fn new(out: Output) -> Self {
+        out.into_shared()
+    }
+
+    pub fn target(&self) -> Option<Target> {
+        self.output().target()
+    }
+
+    // See `Output::write_request`.
+    pub fn write_request(&self, request: &[u8]) -> io::Result<()> {
+        self.output().write_request(request)
+    }
+
+    fn output(&self) -> MutexGuard<'_, Output> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Keeps other threads out until the handle is dropped, see
+    // `Output::lock`.
+    pub fn lock(&self) -> OutputLock<'_> {
+        let out = self.output();
+        let guard = out.stream_lock();
+        OutputLock {
+            out: Held::Shared(out),
+            _guard: guard,
+        }
+    }
+}
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.output().write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        let text = fmt::format(args);
+        self.output().write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output().flush()
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if self.flush_on_drop {
+            let _ = self.end_frame();
+        }
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output").finish_non_exhaustive()
+    }
+}
+
+impl Output {
+    fn write_raw(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.frame.is_none() {
+            if self.buffer.len() + buf.len() > self.capacity {
+                self.flush_buffer()?;
+            }
+            if buf.len() >= self.capacity {
+                return self.sink.write(buf);
+            }
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(parser) = &mut self.strip else {
+            return self.write_raw(buf);
+        };
+        let mut text = Vec::new();
+        for action in parser.feed(buf) {
+            match action {
+                Action::Print(c) => text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                Action::Execute(b) => text.push(b),
+                _ => {}
+            }
+        }
+        let mut rest = text.as_slice();
+        while !rest.is_empty() {
+            match self.write_raw(rest)? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                n => rest = &rest[n..],
+            }
+        }
+        Ok(buf.len())
+    }
+
+    // Does nothing while a frame is open.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.frame.is_some() {
+            return Ok(());
+        }
+        self.flush_buffer()?;
+        self.sink.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Output;
+    use crate::color::ColorChoice;
+    use crate::parser::{Action, Parser};
+    use crate::style::Style;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn buffers_until_flush_or_drop() {
+        let capture = Capture::default();
+        let mut out = Output::with_capacity(8, capture.clone());
+        out.write_all(b"abc").unwrap();
+        assert!(capture.0.lock().unwrap().is_empty());
+        out.write_all(b"defghi").unwrap();
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"abc");
+        out.flush().unwrap();
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"abcdefghi");
+        out.write_all(b"j").unwrap();
+        drop(out);
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"abcdefghij");
+    }
+
+    #[test]
+    fn frame_holds_output_until_the_end() {
+        let capture = Capture::default();
+        let mut out = Output::with_capacity(4, capture.clone());
+        out.begin_frame(true).unwrap();
+        out.write_all(b"a frame longer than the buffer").unwrap();
+        out.flush().unwrap();
+        assert!(capture.0.lock().unwrap().is_empty());
+        out.end_frame().unwrap();
+        assert_eq!(
+            capture.0.lock().unwrap().as_slice(),
+            b"\x1b[?2026ha frame longer than the buffer\x1b[?2026l"
+        );
+    }
+
+    #[test]
+    fn requests_skip_stripping_and_frames() {
+        let capture = Capture::default();
+        let mut out = Output::new(capture.clone());
+        out.set_color_choice(ColorChoice::Never);
+        out.write_all(b"\x1b[1mhi").unwrap();
+        out.write_request(b"\x1b[6n").unwrap();
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"hi\x1b[6n");
+        out.begin_frame(false).unwrap();
+        out.write_all(b"!").unwrap();
+        out.write_request(b"\x1b[c").unwrap();
+        out.end_frame().unwrap();
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"hi\x1b[6n\x1b[c!");
+    }
+
+    #[test]
+    fn lock_flushes_when_dropped() {
+        let capture = Capture::default();
+        let mut out = Output::new(capture.clone());
+        {
+            let mut frame = out.lock();
+            frame.write_all(b"\x1b[H").unwrap();
+            frame.write_all(b"frame").unwrap();
+            assert!(capture.0.lock().unwrap().is_empty());
+        }
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"\x1b[Hframe");
+    }
+
+    #[test]
+    fn shared_writer_keeps_sequences_whole() {
+        let capture = Capture::default();
+        let shared = Output::with_capacity(16, capture.clone()).into_shared();
+        let threads: Vec<_> = (0..4)
+            .map(|n| {
+                let mut w = shared.clone();
+                std::thread::spawn(move || {
+                    let style = Style::new().fg(crate::color::Color::rgb(n, 200, 100));
+                    for _ in 0..200 {
+                        crate::queue!(w, style, crate::command::Print("x")).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        shared.lock().flush().unwrap();
+        let bytes = capture.0.lock().unwrap().clone();
+        let actions = Parser::new().feed(&bytes);
+        assert!(
+            actions
+                .iter()
+                .all(|a| matches!(a, Action::Print('x') | Action::Csi { .. }))
+        );
+        assert_eq!(actions.len(), 4 * 200 * 2);
+    }
+
+    #[test]
+    fn strips_escapes_when_colors_are_off() {
+        let capture = Capture::default();
+        let mut out = Output::new(capture.clone());
+        out.set_tty(false);
+        out.set_color_choice(ColorChoice::Auto);
+        assert!(!out.escapes_enabled());
+        out.write_all(b"\x1b[31mred\x1b").unwrap();
+        out.write_all(b"[0m \xc3\xa9\r\n").unwrap();
+        out.set_color_choice(ColorChoice::Always);
+        out.write_all(b"\x1b[1m").unwrap();
+        out.flush().unwrap();
+        assert_eq!(
+            capture.0.lock().unwrap().as_slice(),
+            "red é\r\n\x1b[1m".as_bytes()
+        );
+    }
+}
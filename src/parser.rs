@@ -0,0 +1,563 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// VT500 style escape sequence parser, following the state machine described
+// at https://vt100.net/emu/dec_ansi_parser. Bytes go in, `Action`s come out;
+// the parser itself knows nothing about what any sequence means.
+
+const MAX_PARAMS: usize = 32;
+const MAX_INTERMEDIATES: usize = 2;
+// Longer OSC strings are dropped, so input can't grow the buffer forever.
+const MAX_OSC: usize = 64 * 1024;
+
+//////////////
+//  Params  //
+//////////////
+
+// CSI/DCS parameters. Each parameter is a group of colon separated
+// sub-parameters, so `38:2:1:2:3;1` is two parameters: `[38,2,1,2,3]` and `[1]`.
+// Omitted values are stored as 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Params(Vec<Vec<u16>>);
+
+impl Params {
+    pub fn new(params: Vec<Vec<u16>>) -> Self {
+        Self(params)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // First value of parameter `i`, if it was given.
+    pub fn get(&self, i: usize) -> Option<u16> {
+        self.0.get(i).and_then(|p| p.first().copied())
+    }
+
+    // First value of parameter `i`, with missing or zero values replaced by
+    // `default` the way most VT sequences interpret them.
+    pub fn get_or(&self, i: usize, default: u16) -> u16 {
+        match self.get(i) {
+            Some(0) | None => default,
+            Some(v) => v,
+        }
+    }
+
+    pub fn groups(&self) -> &[Vec<u16>] {
+        &self.0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u16]> {
+        self.0.iter().map(|p| p.as_slice())
+    }
+
+    fn push_byte(&mut self, byte: u8) -> bool {
+        if self.0.is_empty() {
+            self.0.push(vec![0]);
+        }
+        match byte {
+            b';' => {
+                if self.0.len() >= MAX_PARAMS {
+                    return false;
+                }
+                self.0.push(vec![0]);
+            }
+            b':' => {
+                let group = self.0.last_mut().unwrap();
+                if group.len() >= MAX_PARAMS {
+                    return false;
+                }
+                group.push(0);
+            }
+            _ => {
+                let value = self.0.last_mut().unwrap().last_mut().unwrap();
+                *value = value
+                    .saturating_mul(10)
+                    .saturating_add((byte - b'0') as u16);
+            }
+        }
+        true
+    }
+}
+
+//////////////
+//  Action  //
+//////////////
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    // A printable character (already UTF-8 decoded).
+    Print(char),
+    // A C0 control such as BEL, BS, HT, LF or CR.
+    Execute(u8),
+    // `ESC [ ... final`. Private markers (`?`, `>`, ...) are reported as
+    // intermediates. `ignored` is set when the sequence overflowed the
+    // parameter or intermediate limits.
+    Csi {
+        params: Params,
+        intermediates: Vec<u8>,
+        ignored: bool,
+        final_byte: u8,
+    },
+    // `ESC intermediates final`, e.g. `ESC 7` or `ESC ( B`.
+    Esc {
+        intermediates: Vec<u8>,
+        ignored: bool,
+        final_byte: u8,
+    },
+    // `ESC ] ... BEL/ST`, split on `;`.
+    Osc {
+        params: Vec<Vec<u8>>,
+        bell_terminated: bool,
+    },
+    // Start of a device control string, followed by `DcsPut` for every data
+    // byte and a final `DcsUnhook`.
+    DcsHook {
+        params: Params,
+        intermediates: Vec<u8>,
+        ignored: bool,
+        final_byte: u8,
+    },
+    DcsPut(u8),
+    DcsUnhook,
+}
+
+/////////////
+//  State  //
+/////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum State {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    CsiEntry,
+    CsiParam,
+    CsiIntermediate,
+    CsiIgnore,
+    DcsEntry,
+    DcsParam,
+    DcsIntermediate,
+    DcsPassthrough,
+    DcsIgnore,
+    OscString,
+    SosPmApcString,
+}
+
+//////////////
+//  Parser  //
+//////////////
+
+#[derive(Debug, Clone)]
+pub struct Parser {
+    state: State,
+    params: Params,
+    intermediates: Vec<u8>,
+    ignored: bool,
+    osc: Vec<u8>,
+    // Set when a string (OSC/DCS/SOS/PM/APC) was ended by ESC so the `\` of
+    // the ST that follows is swallowed instead of dispatched.
+    string_ended: bool,
+    utf8: [u8; 4],
+    utf8_len: usize,
+    utf8_need: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: Params::default(),
+            intermediates: Vec::new(),
+            ignored: false,
+            osc: Vec::new(),
+            string_ended: false,
+            utf8: [0; 4],
+            utf8_len: 0,
+            utf8_need: 0,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    // Parses a chunk of bytes. Sequences may be split across calls.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for &b in bytes {
+            self.advance(b, &mut actions);
+        }
+        actions
+    }
+
+    pub fn advance(&mut self, byte: u8, out: &mut Vec<Action>) {
+        if self.utf8_need > 0 {
+            if (0x80..=0xBF).contains(&byte) {
+                self.utf8[self.utf8_len] = byte;
+                self.utf8_len += 1;
+                self.utf8_need -= 1;
+                if self.utf8_need == 0 {
+                    let c = std::str::from_utf8(&self.utf8[..self.utf8_len])
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.utf8_len = 0;
+                    out.push(Action::Print(c));
+                }
+                return;
+            }
+            // Truncated sequence, report it and handle the byte normally.
+            self.utf8_need = 0;
+            self.utf8_len = 0;
+            out.push(Action::Print(char::REPLACEMENT_CHARACTER));
+        }
+
+        // Transitions valid from any state.
+        match byte {
+            0x18 | 0x1A => {
+                self.exit_state(out);
+                out.push(Action::Execute(byte));
+                self.state = State::Ground;
+                return;
+            }
+            0x1B => {
+                self.string_ended = matches!(
+                    self.state,
+                    State::OscString
+                        | State::DcsPassthrough
+                        | State::DcsIgnore
+                        | State::SosPmApcString
+                );
+                self.exit_state(out);
+                self.clear();
+                self.state = State::Escape;
+                return;
+            }
+            _ => {}
+        }
+
+        match self.state {
+            State::Ground => self.ground(byte, out),
+            State::Escape => self.escape(byte, out),
+            State::EscapeIntermediate => match byte {
+                0x00..=0x1F => out.push(Action::Execute(byte)),
+                0x20..=0x2F => self.collect(byte),
+                0x30..=0x7E => self.esc_dispatch(byte, out),
+                _ => {}
+            },
+            State::CsiEntry => match byte {
+                0x00..=0x1F => out.push(Action::Execute(byte)),
+                0x20..=0x2F => {
+                    self.collect(byte);
+                    self.state = State::CsiIntermediate;
+                }
+                0x30..=0x3B => {
+                    self.param(byte);
+                    self.state = State::CsiParam;
+                }
+                0x3C..=0x3F => {
+                    self.collect(byte);
+                    self.state = State::CsiParam;
+                }
+                0x40..=0x7E => self.csi_dispatch(byte, out),
+                _ => {}
+            },
+            State::CsiParam => match byte {
+                0x00..=0x1F => out.push(Action::Execute(byte)),
+                0x20..=0x2F => {
+                    self.collect(byte);
+                    self.state = State::CsiIntermediate;
+                }
+                0x30..=0x3B => self.param(byte),
+                0x3C..=0x3F => self.state = State::CsiIgnore,
+                0x40..=0x7E => self.csi_dispatch(byte, out),
+                _ => {}
+            },
+            State::CsiIntermediate => match byte {
+                0x00..=0x1F => out.push(Action::Execute(byte)),
+                0x20..=0x2F => self.collect(byte),
+                0x30..=0x3F => self.state = State::CsiIgnore,
+                0x40..=0x7E => self.csi_dispatch(byte, out),
+                _ => {}
+            },
+            State::CsiIgnore => match byte {
+                0x00..=0x1F => out.push(Action::Execute(byte)),
+                0x40..=0x7E => self.state = State::Ground,
+                _ => {}
+            },
+            State::DcsEntry => match byte {
+                0x20..=0x2F => {
+                    self.collect(byte);
+                    self.state = State::DcsIntermediate;
+                }
+                0x30..=0x3B => {
+                    self.param(byte);
+                    self.state = State::DcsParam;
+                }
+                0x3C..=0x3F => {
+                    self.collect(byte);
+                    self.state = State::DcsParam;
+                }
+                0x40..=0x7E => self.hook(byte, out),
+                _ => {}
+            },
+            State::DcsParam => match byte {
+                0x20..=0x2F => {
+                    self.collect(byte);
+                    self.state = State::DcsIntermediate;
+                }
+                0x30..=0x3B => self.param(byte),
+                0x3C..=0x3F => self.state = State::DcsIgnore,
+                0x40..=0x7E => self.hook(byte, out),
+                _ => {}
+            },
+            State::DcsIntermediate => match byte {
+                0x20..=0x2F => self.collect(byte),
+                0x30..=0x3F => self.state = State::DcsIgnore,
+                0x40..=0x7E => self.hook(byte, out),
+                _ => {}
+            },
+            State::DcsPassthrough => {
+                if byte != 0x7F {
+                    out.push(Action::DcsPut(byte));
+                }
+            }
+            State::OscString => match byte {
+                0x07 => {
+                    self.osc_dispatch(true, out);
+                    self.state = State::Ground;
+                }
+                0x20..=0x7E | 0x80..=0xFF => {
+                    if self.osc.len() < MAX_OSC {
+                        self.osc.push(byte);
+                    } else {
+                        self.ignored = true;
+                    }
+                }
+                _ => {}
+            },
+            State::DcsIgnore | State::SosPmApcString => {}
+        }
+    }
+
+    fn ground(&mut self, byte: u8, out: &mut Vec<Action>) {
+        match byte {
+            0x00..=0x1F => out.push(Action::Execute(byte)),
+            0x20..=0x7E => out.push(Action::Print(byte as char)),
+            0x7F => {}
+            0xC2..=0xDF => self.utf8_start(byte, 1),
+            0xE0..=0xEF => self.utf8_start(byte, 2),
+            0xF0..=0xF4 => self.utf8_start(byte, 3),
+            _ => out.push(Action::Print(char::REPLACEMENT_CHARACTER)),
+        }
+    }
+
+    fn escape(&mut self, byte: u8, out: &mut Vec<Action>) {
+        let string_ended = std::mem::take(&mut self.string_ended);
+        match byte {
+            0x00..=0x1F => out.push(Action::Execute(byte)),
+            b'\\' if string_ended => self.state = State::Ground,
+            0x20..=0x2F => {
+                self.collect(byte);
+                self.state = State::EscapeIntermediate;
+            }
+            b'[' => self.state = State::CsiEntry,
+            b']' => self.state = State::OscString,
+            b'P' => self.state = State::DcsEntry,
+            b'X' | b'^' | b'_' => self.state = State::SosPmApcString,
+            0x30..=0x7E => self.esc_dispatch(byte, out),
+            _ => {}
+        }
+    }
+
+    fn utf8_start(&mut self, byte: u8, need: usize) {
+        self.utf8[0] = byte;
+        self.utf8_len = 1;
+        self.utf8_need = need;
+    }
+
+    fn exit_state(&mut self, out: &mut Vec<Action>) {
+        match self.state {
+            State::OscString => self.osc_dispatch(false, out),
+            State::DcsPassthrough => out.push(Action::DcsUnhook),
+            _ => {}
+        }
+    }
+
+    fn clear(&mut self) {
+        self.params = Params::default();
+        self.intermediates.clear();
+        self.ignored = false;
+        self.osc.clear();
+    }
+
+    fn collect(&mut self, byte: u8) {
+        if self.intermediates.len() < MAX_INTERMEDIATES {
+            self.intermediates.push(byte);
+        } else {
+            self.ignored = true;
+        }
+    }
+
+    fn param(&mut self, byte: u8) {
+        if !self.params.push_byte(byte) {
+            self.ignored = true;
+        }
+    }
+
+    fn csi_dispatch(&mut self, byte: u8, out: &mut Vec<Action>) {
+        out.push(Action::Csi {
+            params: std::mem::take(&mut self.params),
+            intermediates: std::mem::take(&mut self.intermediates),
+            ignored: self.ignored,
+            final_byte: byte,
+        });
+        self.state = State::Ground;
+    }
+
+    fn esc_dispatch(&mut self, byte: u8, out: &mut Vec<Action>) {
+        out.push(Action::Esc {
+            intermediates: std::mem::take(&mut self.intermediates),
+            ignored: self.ignored,
+            final_byte: byte,
+        });
+        self.state = State::Ground;
+    }
+
+    fn hook(&mut self, byte: u8, out: &mut Vec<Action>) {
+        out.push(Action::DcsHook {
+            params: std::mem::take(&mut self.params),
+            intermediates: std::mem::take(&mut self.intermediates),
+            ignored: self.ignored,
+            final_byte: byte,
+        });
+        self.state = State::DcsPassthrough;
+    }
+
+    fn osc_dispatch(&mut self, bell_terminated: bool, out: &mut Vec<Action>) {
+        if std::mem::take(&mut self.ignored) {
+            self.osc.clear();
+            return;
+        }
+        let params = self.osc.split(|b| *b == b';').map(|p| p.to_vec()).collect();
+        self.osc.clear();
+        out.push(Action::Osc {
+            params,
+            bell_terminated,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Action, Params, Parser};
+
+    #[test]
+    fn csi_with_private_marker_and_subparams() {
+        let mut p = Parser::new();
+        let actions = p.feed(b"a\x1b[?25h\x1b[38:2:1:2:3;1m");
+        assert_eq!(
+            actions,
+            vec![
+                Action::Print('a'),
+                Action::Csi {
+                    params: Params::new(vec![vec![25]]),
+                    intermediates: vec![b'?'],
+                    ignored: false,
+                    final_byte: b'h',
+                },
+                Action::Csi {
+                    params: Params::new(vec![vec![38, 2, 1, 2, 3], vec![1]]),
+                    intermediates: vec![],
+                    ignored: false,
+                    final_byte: b'm',
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn osc_terminators_and_split_utf8() {
+        let mut p = Parser::new();
+        let mut actions = p.feed(b"\x1b]0;title\x07\x1b]8;;x\x1b\\\xc3");
+        actions.extend(p.feed(b"\xa9"));
+        assert_eq!(
+            actions,
+            vec![
+                Action::Osc {
+                    params: vec![b"0".to_vec(), b"title".to_vec()],
+                    bell_terminated: true,
+                },
+                Action::Osc {
+                    params: vec![b"8".to_vec(), vec![], b"x".to_vec()],
+                    bell_terminated: false,
+                },
+                Action::Print('é'),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_strings_are_dropped() {
+        let mut p = Parser::new();
+        let mut input = b"\x1b]0;".to_vec();
+        input.resize(super::MAX_OSC + 100, b'x');
+        input.extend_from_slice(b"\x07\x1b]0;t\x07");
+        assert_eq!(
+            p.feed(&input),
+            vec![Action::Osc {
+                params: vec![b"0".to_vec(), b"t".to_vec()],
+                bell_terminated: true,
+            }]
+        );
+
+        let mut input = b"\x1b[1".to_vec();
+        input.extend(std::iter::repeat_n(b':', 100));
+        input.push(b'm');
+        assert!(matches!(
+            p.feed(&input)[..],
+            [Action::Csi { ignored: true, .. }]
+        ));
+    }
+
+    #[test]
+    fn dcs_passthrough() {
+        let mut p = Parser::new();
+        let actions = p.feed(b"\x1bPq#0\x1b\\");
+        assert_eq!(actions.len(), 4);
+        assert!(matches!(
+            actions[0],
+            Action::DcsHook {
+                final_byte: b'q',
+                ..
+            }
+        ));
+        assert_eq!(actions[1], Action::DcsPut(b'#'));
+        assert_eq!(actions[3], Action::DcsUnhook);
+    }
+}
@@ -0,0 +1,103 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Mouse pointer shape (OSC 22), understood by xterm, kitty, foot and a few
+// others. Terminals that don't know it ignore the sequence.
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+// CSS cursor names, which is what kitty and foot expect; xterm also takes
+// X cursor font names through `Named`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum PointerShape {
+    #[default]
+    Default,
+    Text,
+    Pointer,
+    Help,
+    Wait,
+    Progress,
+    Crosshair,
+    Move,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    EwResize,
+    NsResize,
+    Named(&'static str),
+}
+
+impl PointerShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PointerShape::Default => "default",
+            PointerShape::Text => "text",
+            PointerShape::Pointer => "pointer",
+            PointerShape::Help => "help",
+            PointerShape::Wait => "wait",
+            PointerShape::Progress => "progress",
+            PointerShape::Crosshair => "crosshair",
+            PointerShape::Move => "move",
+            PointerShape::NotAllowed => "not-allowed",
+            PointerShape::Grab => "grab",
+            PointerShape::Grabbing => "grabbing",
+            PointerShape::EwResize => "ew-resize",
+            PointerShape::NsResize => "ns-resize",
+            PointerShape::Named(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetPointerShape(pub PointerShape);
+
+impl Command for SetPointerShape {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b]22;{}\x1b\\", self.0.name())
+    }
+}
+
+// Goes back to the terminal's own pointer. An empty name is the reset form,
+// "default" isn't a cursor xterm knows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetPointerShape;
+
+impl Command for ResetPointerShape {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b]22;\x1b\\")
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{PointerShape, ResetPointerShape, SetPointerShape};
+    use crate::command::Command;
+
+    #[test]
+    fn osc_22() {
+        assert_eq!(
+            SetPointerShape(PointerShape::NotAllowed).to_ansi_string(),
+            "\x1b]22;not-allowed\x1b\\"
+        );
+        assert_eq!(
+            SetPointerShape(PointerShape::Named("hand2")).to_ansi_string(),
+            "\x1b]22;hand2\x1b\\"
+        );
+        assert_eq!(ResetPointerShape.to_ansi_string(), "\x1b]22;\x1b\\");
+    }
+}
@@ -0,0 +1,413 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Interactive prompts. They talk to the terminal directly (`/dev/tty` or the
+// Windows console) rather than stdin/stdout, so they keep working when the
+// program's input or output is redirected.
+
+use crate::color::{Color, Iso};
+use crate::command::write_command;
+use crate::cursor::{Hide, MoveUp, Show};
+use crate::erase::{Clear, ClearType};
+use crate::event::{Event, KeyCode, KeyEventKind, Modifiers};
+use crate::input::InputParser;
+use crate::style::{Attributes, Style};
+use crate::width::str_width;
+use crate::{HiddenInput, open_tty, wait_readable};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+////////////////
+//  Password  //
+////////////////
+
+// Reads a line without echoing it. Backspace and Ctrl-U edit as usual, Ctrl-D
+// on an empty line fails with `UnexpectedEof`. On Ctrl-C the terminal is
+// restored and it fails with `Interrupted`; `reraise_interrupt` then stops
+// the program the way Ctrl-C normally would.
+pub fn read_password(prompt: &str) -> io::Result<String> {
+    read_password_masked(prompt, None)
+}
+
+// Like `read_password`, but shows `mask` (e.g. '*') for every character typed.
+pub fn read_password_masked(prompt: &str, mask: Option<char>) -> io::Result<String> {
+    let (input, mut output) = open_tty()?;
+    output.write_all(prompt.as_bytes())?;
+    output.flush()?;
+    let hidden = HiddenInput::new(&input)?;
+    let password = read_hidden(&input, &mut output, mask, hidden.interrupt());
+    drop(hidden);
+    output.write_all(b"\r\n")?;
+    password
+}
+
+fn read_hidden(
+    mut input: impl Read,
+    output: &mut impl Write,
+    mask: Option<char>,
+    interrupt: u8,
+) -> io::Result<String> {
+    let mut password: Vec<u8> = Vec::new();
+    let erase = |output: &mut dyn Write, n: usize| -> io::Result<()> {
+        if mask.is_some() {
+            output.write_all(&b"\x08 \x08".repeat(n))?;
+            output.flush()?;
+        }
+        Ok(())
+    };
+    let chars = |bytes: &[u8]| bytes.iter().filter(|b| *b & 0xc0 != 0x80).count();
+    loop {
+        let mut byte = [0u8];
+        if input.read(&mut byte)? == 0 {
+            if password.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            break;
+        }
+        match byte[0] {
+            b'\r' | b'\n' => break,
+            b if b == interrupt => return Err(io::ErrorKind::Interrupted.into()),
+            0x04 if password.is_empty() => return Err(io::ErrorKind::UnexpectedEof.into()),
+            0x7f | 0x08 => {
+                let had = !password.is_empty();
+                // drop a whole UTF-8 character
+                while let Some(b) = password.pop() {
+                    if b & 0xc0 != 0x80 {
+                        break;
+                    }
+                }
+                erase(output, had as usize)?;
+            }
+            0x15 => {
+                erase(output, chars(&password))?;
+                password.clear();
+            }
+            b if b < 0x20 => {}
+            b => {
+                password.push(b);
+                if let Some(mask) = mask.filter(|_| b & 0xc0 != 0x80) {
+                    write!(output, "{}", mask)?;
+                    output.flush()?;
+                }
+            }
+        }
+    }
+    String::from_utf8(password).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+///////////////
+//  Choices  //
+///////////////
+
+fn accent() -> Style {
+    Style::new().fg(Color::Iso {
+        color: Iso::Cyan,
+        bright: false,
+    })
+}
+
+// Keys from the terminal, decoded like `EventReader` does.
+struct TtyEvents {
+    input: File,
+    parser: InputParser,
+    events: VecDeque<Event>,
+}
+
+impl Iterator for TtyEvents {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        let mut buf = [0u8; 64];
+        while self.events.is_empty() {
+            // a partial sequence waits for the rest until the ESC timeout
+            if let Some(timeout) = self.parser.timeout() {
+                match wait_readable(&self.input, timeout) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        self.events.extend(self.parser.flush());
+                        continue;
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            let n = match self.input.read(&mut buf) {
+                Ok(0) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+                Ok(n) => n,
+                Err(e) => return Some(Err(e)),
+            };
+            self.events.extend(self.parser.feed(&buf[..n]));
+        }
+        self.events.pop_front().map(Ok)
+    }
+}
+
+// Runs `f` with the terminal's keys and output, the cursor hidden. Ctrl-C
+// restores the terminal and fails with `Interrupted`.
+fn interact<T>(f: impl FnOnce(&mut TtyEvents, &mut File) -> io::Result<T>) -> io::Result<T> {
+    let (input, mut output) = open_tty()?;
+    let hidden = HiddenInput::new(&input)?;
+    let mut parser = InputParser::new();
+    parser.set_interrupt(Some(hidden.interrupt()));
+    let mut events = TtyEvents {
+        input,
+        parser,
+        events: VecDeque::new(),
+    };
+    write_command(&Hide, &mut output)?;
+    let result = f(&mut events, &mut output);
+    write_command(&Show, &mut output)?;
+    output.flush()?;
+    drop(hidden);
+    result
+}
+
+// Asks a yes/no question. Enter takes `default` when there is one, Esc
+// answers no.
+//
+//     ? Overwrite the file? (y/N) › no
+pub fn confirm(prompt: &str, default: Option<bool>) -> io::Result<bool> {
+    interact(|events, out| run_confirm(events, out, prompt, default))
+}
+
+fn run_confirm(
+    events: &mut impl Iterator<Item = io::Result<Event>>,
+    out: &mut impl Write,
+    prompt: &str,
+    default: Option<bool>,
+) -> io::Result<bool> {
+    let hint = match default {
+        Some(true) => "(Y/n)",
+        Some(false) => "(y/N)",
+        None => "(y/n)",
+    };
+    write_command(&accent(), out)?;
+    write!(out, "? ")?;
+    write_command(&Style::new(), out)?;
+    write!(out, "{} {} › ", prompt, hint)?;
+    out.flush()?;
+    let answer = loop {
+        let key = match events.next().transpose()? {
+            Some(Event::Interrupt) => return Err(io::ErrorKind::Interrupted.into()),
+            Some(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Some(_) => continue,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(Modifiers::CONTROL) => {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            KeyCode::Char('y' | 'Y') => break true,
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => break false,
+            KeyCode::Enter => {
+                if let Some(default) = default {
+                    break default;
+                }
+            }
+            _ => {}
+        }
+    };
+    write_command(&accent(), out)?;
+    write!(out, "{}", if answer { "yes" } else { "no" })?;
+    write_command(&Style::new(), out)?;
+    out.write_all(b"\r\n")?;
+    Ok(answer)
+}
+
+// Lets the user pick one of `options` with the arrow keys (or j/k, Home/End)
+// and Enter. Esc cancels with `None`.
+//
+//     ? Pick a color ›
+//     ❯ Red
+//       Green
+pub fn select<'a, T: Display>(prompt: &str, options: &'a [T]) -> io::Result<Option<&'a T>> {
+    let labels: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+    let cols = crate::term_size().map_or(0, |(cols, _)| cols as usize);
+    let picked = interact(|events, out| run_select(events, out, prompt, &labels, cols))?;
+    Ok(picked.map(|i| &options[i]))
+}
+
+// `cols` is the terminal width, to know how many rows wrapped lines take; 0
+// if unknown.
+fn run_select(
+    events: &mut impl Iterator<Item = io::Result<Event>>,
+    out: &mut impl Write,
+    prompt: &str,
+    labels: &[String],
+    cols: usize,
+) -> io::Result<Option<usize>> {
+    if labels.is_empty() {
+        return Ok(None);
+    }
+    let rows = |width: usize| match cols {
+        0 => 1,
+        cols => width.div_ceil(cols).max(1),
+    };
+    // from the last row of the list back up to the prompt
+    let height = rows(str_width(prompt) + 4) - 1
+        + labels.iter().map(|l| rows(str_width(l) + 2)).sum::<usize>();
+    let up = MoveUp(height.min(u16::MAX as usize) as u16);
+    let mut selected = 0;
+    let draw = |out: &mut dyn Write, selected: usize, redraw: bool| -> io::Result<()> {
+        let mut out = out;
+        if redraw {
+            write_command(&up, &mut out)?;
+        }
+        out.write_all(b"\r")?;
+        write_command(&Clear(ClearType::FromCursorDown), &mut out)?;
+        write_command(&accent(), &mut out)?;
+        write!(out, "? ")?;
+        write_command(&Style::new(), &mut out)?;
+        write!(out, "{} ›", prompt)?;
+        for (i, label) in labels.iter().enumerate() {
+            out.write_all(b"\r\n")?;
+            if i == selected {
+                write_command(&accent().attr(Attributes::BOLD), &mut out)?;
+                write!(out, "❯ {}", label)?;
+                write_command(&Style::new(), &mut out)?;
+            } else {
+                write!(out, "  {}", label)?;
+            }
+        }
+        out.flush()
+    };
+    draw(out, selected, false)?;
+    let picked = loop {
+        let key = match events.next().transpose()? {
+            Some(Event::Interrupt) => return Err(io::ErrorKind::Interrupted.into()),
+            Some(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Some(_) => continue,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(Modifiers::CONTROL) => {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            KeyCode::Enter => break Some(selected),
+            KeyCode::Esc => break None,
+            KeyCode::Up | KeyCode::Char('k') => {
+                selected = selected.checked_sub(1).unwrap_or(labels.len() - 1)
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                selected = (selected + 1) % labels.len()
+            }
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = labels.len() - 1,
+            _ => continue,
+        }
+        draw(out, selected, true)?;
+    };
+    // collapse the list into the answer
+    write_command(&up, out)?;
+    out.write_all(b"\r")?;
+    write_command(&Clear(ClearType::FromCursorDown), out)?;
+    write_command(&accent(), out)?;
+    write!(out, "? ")?;
+    write_command(&Style::new(), out)?;
+    write!(out, "{} › ", prompt)?;
+    if let Some(i) = picked {
+        write_command(&accent(), out)?;
+        write!(out, "{}", labels[i])?;
+        write_command(&Style::new(), out)?;
+    }
+    out.write_all(b"\r\n")?;
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{read_hidden, run_confirm, run_select};
+    use crate::event::{Event, KeyCode, KeyEvent, Modifiers};
+    use crate::virtual_screen::VirtualScreen;
+    use std::io::ErrorKind;
+
+    fn keys(codes: &[KeyCode]) -> impl Iterator<Item = std::io::Result<Event>> {
+        let events: Vec<_> = codes
+            .iter()
+            .map(|c| Ok(Event::Key(KeyEvent::new(*c, Modifiers::NONE))))
+            .collect();
+        events.into_iter()
+    }
+
+    #[test]
+    fn hidden_line_editing() {
+        let mut out = Vec::new();
+        let pw = read_hidden(&b"sec\x7fcr\xc3\xa9t\r"[..], &mut out, None, 3).unwrap();
+        assert_eq!(pw, "secrét");
+        assert!(out.is_empty());
+
+        let mut out = Vec::new();
+        let pw = read_hidden(&b"ab\x15x\xc3\xa9\x7fy\n"[..], &mut out, Some('*'), 3).unwrap();
+        assert_eq!(pw, "xy");
+        assert_eq!(out, b"**\x08 \x08\x08 \x08**\x08 \x08*");
+
+        let err = read_hidden(&b"ab\x03"[..], &mut Vec::new(), None, 3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+        let err = read_hidden(&b"\x04"[..], &mut Vec::new(), None, 3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn confirm_answers() {
+        let mut screen = VirtualScreen::new(40, 3);
+        let mut events = keys(&[KeyCode::Char('x'), KeyCode::Enter]);
+        assert!(run_confirm(&mut events, &mut screen, "Continue?", Some(true)).unwrap());
+        assert_eq!(screen.row_text(0), "? Continue? (Y/n) › yes");
+        assert_eq!(screen.cursor(), (0, 1));
+
+        // Enter without a default does nothing
+        let mut events = keys(&[KeyCode::Enter, KeyCode::Char('N')]);
+        assert!(!run_confirm(&mut events, &mut screen, "Sure?", None).unwrap());
+        let mut events = keys(&[]);
+        let err = run_confirm(&mut events, &mut screen, "Sure?", None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn select_with_arrows() {
+        let labels: Vec<String> = ["Red", "Green", "Blue"].map(String::from).to_vec();
+        let mut screen = VirtualScreen::new(30, 6);
+        // down wraps around at the end, up at the top
+        let mut events = keys(&[KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Up]);
+        let mut partial = VirtualScreen::new(30, 6);
+        let err = run_select(&mut events, &mut partial, "Color", &labels, 30).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(
+            partial.to_plain_string(),
+            "? Color ›\n  Red\n  Green\n❯ Blue\n\n"
+        );
+
+        let mut events = keys(&[KeyCode::Up, KeyCode::Up, KeyCode::Enter]);
+        let picked = run_select(&mut events, &mut screen, "Color", &labels, 30).unwrap();
+        assert_eq!(picked, Some(1));
+        assert_eq!(screen.to_plain_string(), "? Color › Green\n\n\n\n\n");
+        let mut events = keys(&[KeyCode::Esc]);
+        assert_eq!(
+            run_select(&mut events, &mut screen, "Color", &labels, 30).unwrap(),
+            None
+        );
+
+        // labels wider than the terminal take more than one row each
+        let labels = ["a long red label", "green"].map(String::from).to_vec();
+        let mut screen = VirtualScreen::new(8, 8);
+        let mut events = keys(&[KeyCode::Down, KeyCode::Enter]);
+        let picked = run_select(&mut events, &mut screen, "Pick", &labels, 8).unwrap();
+        assert_eq!(picked, Some(1));
+        assert_eq!(screen.to_plain_string(), "? Pick ›\n green\n\n\n\n\n\n");
+    }
+}
@@ -0,0 +1,350 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use libc::{
+    F_GETFD, F_SETFD, FD_CLOEXEC, TIOCGWINSZ, TIOCSCTTY, TIOCSWINSZ, c_int, fcntl, ioctl, winsize,
+};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{mem, ptr};
+
+// Default size handed to a child when the parent has no terminal to copy.
+pub const DEFAULT_PTY_SIZE: (u16, u16) = (80, 24);
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+fn mark_resized() {
+    RESIZED.store(true, Ordering::SeqCst);
+}
+
+fn set_cloexec(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = fcntl(fd, F_GETFD);
+        if flags == -1 || fcntl(fd, F_SETFD, flags | FD_CLOEXEC) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn set_size(fd: RawFd, cols: u16, rows: u16) -> io::Result<()> {
+    let size = winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe { ioctl(fd, TIOCSWINSZ, &size) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+//////////////////
+//  Pty Handle  //
+//////////////////
+
+// A master/slave pseudo terminal pair.
+#[derive(Debug)]
+pub struct Pty {
+    master: File,
+    slave: File,
+}
+
+impl Pty {
+    pub fn open(cols: u16, rows: u16) -> io::Result<Self> {
+        let mut master: c_int = -1;
+        let mut slave: c_int = -1;
+        let mut size = winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // macOS takes the termios and winsize as *mut, Linux as *const
+        let termp: *mut libc::termios = ptr::null_mut();
+        let winp: *mut winsize = &mut size;
+        unsafe {
+            if libc::openpty(
+                &mut master,
+                &mut slave,
+                ptr::null_mut(),
+                termp as _,
+                winp as _,
+            ) == -1
+            {
+                return Err(io::Error::last_os_error());
+            }
+            let pty = Self {
+                master: File::from_raw_fd(master),
+                slave: File::from_raw_fd(slave),
+            };
+            set_cloexec(master)?;
+            set_cloexec(slave)?;
+            Ok(pty)
+        }
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        set_size(self.master.as_raw_fd(), cols, rows)
+    }
+
+    // Returns (cols, rows) as seen by the slave side.
+    pub fn size(&self) -> io::Result<(u32, u32)> {
+        unsafe {
+            let mut size: winsize = mem::zeroed();
+            if ioctl(self.master.as_raw_fd(), TIOCGWINSZ, &mut size) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok((size.ws_col as u32, size.ws_row as u32))
+        }
+    }
+
+    pub fn master(&self) -> &File {
+        &self.master
+    }
+
+    pub fn slave(&self) -> &File {
+        &self.slave
+    }
+}
+
+///////////////////
+//  Pty Command  //
+///////////////////
+
+// Builder that runs a `std::process::Command` attached to a new pty.
+#[derive(Debug)]
+pub struct PtyCommand {
+    command: Command,
+    size: Option<(u16, u16)>,
+    forward_resize: bool,
+}
+
+impl PtyCommand {
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            size: None,
+            forward_resize: false,
+        }
+    }
+
+    // Initial size of the pty. Defaults to the size of the current terminal,
+    // or `DEFAULT_PTY_SIZE` when there is none.
+    pub fn size(mut self, cols: u16, rows: u16) -> Self {
+        self.size = Some((cols, rows));
+        self
+    }
+
+    // When enabled a SIGWINCH handler is installed and `PtyChild::sync_size`
+    // copies the current terminal size onto the child's pty after a resize.
+    pub fn forward_resize(mut self, forward: bool) -> Self {
+        self.forward_resize = forward;
+        self
+    }
+
+    pub fn spawn(mut self) -> io::Result<PtyChild> {
+        let (cols, rows) = self.size.unwrap_or_else(|| match crate::term_size() {
+            Some((c, r)) => (c as u16, r as u16),
+            None => DEFAULT_PTY_SIZE,
+        });
+        let pty = Pty::open(cols, rows)?;
+
+        self.command
+            .stdin(Stdio::from(pty.slave.try_clone()?))
+            .stdout(Stdio::from(pty.slave.try_clone()?))
+            .stderr(Stdio::from(pty.slave.try_clone()?));
+        unsafe {
+            self.command.pre_exec(|| {
+                // New session so the slave can become the controlling terminal.
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if ioctl(0, TIOCSCTTY as _, 0 as c_int) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        if self.forward_resize {
+            crate::unix::on_resize_signal(mark_resized)?;
+        }
+        let child = self.command.spawn()?;
+        // Only the child should hold the slave open, otherwise reads on the
+        // master never see the hangup when the child exits.
+        let Pty { master, slave } = pty;
+        drop(slave);
+        Ok(PtyChild {
+            master,
+            child,
+            forward_resize: self.forward_resize,
+        })
+    }
+}
+
+/////////////////
+//  Pty Child  //
+/////////////////
+
+// A running child attached to a pty. Reading yields the child's output and
+// writing feeds its input.
+#[derive(Debug)]
+pub struct PtyChild {
+    master: File,
+    child: Child,
+    forward_resize: bool,
+}
+
+impl PtyChild {
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    // Independent handles to the master, e.g. to read on a separate thread.
+    pub fn reader(&self) -> io::Result<PtyReader> {
+        Ok(PtyReader(self.master.try_clone()?))
+    }
+
+    pub fn writer(&self) -> io::Result<File> {
+        self.master.try_clone()
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        set_size(self.master.as_raw_fd(), cols, rows)
+    }
+
+    // Applies a pending SIGWINCH to the child's pty. Returns true if the size
+    // was updated.
+    pub fn sync_size(&self) -> io::Result<bool> {
+        if !self.forward_resize || !RESIZED.swap(false, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        match crate::term_size() {
+            Some((cols, rows)) => {
+                self.resize(cols as u16, rows as u16)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.child.wait()
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()
+    }
+}
+
+impl Read for PtyChild {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_master(&mut self.master, buf)
+    }
+}
+
+impl Write for PtyChild {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.master.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.master.flush()
+    }
+}
+
+// Read half of the master returned by `PtyChild::reader`.
+#[derive(Debug)]
+pub struct PtyReader(File);
+
+impl Read for PtyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_master(&mut self.0, buf)
+    }
+}
+
+// Linux reports EIO on the master once every slave fd is closed; treat that
+// as end of file.
+fn read_master(master: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    match master.read(buf) {
+        Err(e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+        r => r,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Pty, PtyCommand};
+    use std::io::{Read, Write};
+    use std::process::Command;
+
+    #[test]
+    fn child_output_and_status() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "stty size; exit 3"]);
+        let mut child = PtyCommand::new(cmd).size(100, 30).spawn().unwrap();
+        let mut out = String::new();
+        child.read_to_string(&mut out).unwrap();
+        assert_eq!(out.trim(), "30 100");
+        assert_eq!(child.wait().unwrap().code(), Some(3));
+    }
+
+    #[test]
+    fn open_and_resize() {
+        let pty = Pty::open(40, 10).unwrap();
+        assert_eq!(pty.size().unwrap(), (40, 10));
+        pty.resize(120, 50).unwrap();
+        assert_eq!(pty.size().unwrap(), (120, 50));
+    }
+
+    #[test]
+    fn input_reaches_the_child() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "read line; echo \"got $line\""]);
+        let mut child = PtyCommand::new(cmd).size(80, 24).spawn().unwrap();
+        child.write_all(b"hello\n").unwrap();
+        let mut out = String::new();
+        child.reader().unwrap().read_to_string(&mut out).unwrap();
+        assert!(out.contains("got hello"), "{out:?}");
+        assert!(child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn resize_is_seen_by_the_child() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "read line; stty size"]);
+        let mut child = PtyCommand::new(cmd).size(80, 24).spawn().unwrap();
+        child.resize(132, 43).unwrap();
+        child.write_all(b"\n").unwrap();
+        let mut out = String::new();
+        child.read_to_string(&mut out).unwrap();
+        assert_eq!(out.trim(), "43 132");
+        // nothing to forward unless asked for
+        assert!(!child.sync_size().unwrap());
+        child.wait().unwrap();
+    }
+}
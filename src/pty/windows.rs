@@ -0,0 +1,527 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::Cell;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::os::windows::process::ExitStatusExt;
+use std::process::{Command, ExitStatus};
+use std::{mem, ptr};
+use windows_sys::Win32::Foundation::{HANDLE, INVALID_HANDLE_VALUE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows_sys::Win32::System::Console::{
+    COORD, ClosePseudoConsole, CreatePseudoConsole, HPCON, ResizePseudoConsole,
+};
+use windows_sys::Win32::System::Pipes::CreatePipe;
+use windows_sys::Win32::System::Threading::{
+    CREATE_UNICODE_ENVIRONMENT, CreateProcessW, DeleteProcThreadAttributeList,
+    EXTENDED_STARTUPINFO_PRESENT, GetExitCodeProcess, INFINITE, InitializeProcThreadAttributeList,
+    LPPROC_THREAD_ATTRIBUTE_LIST, PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE, PROCESS_INFORMATION,
+    STARTF_USESTDHANDLES, STARTUPINFOEXW, TerminateProcess, UpdateProcThreadAttribute,
+    WaitForSingleObject,
+};
+use windows_sys::core::HRESULT;
+
+// Default size handed to a child when the parent has no terminal to copy.
+pub const DEFAULT_PTY_SIZE: (u16, u16) = (80, 24);
+
+fn coord(cols: u16, rows: u16) -> COORD {
+    COORD {
+        X: cols as i16,
+        Y: rows as i16,
+    }
+}
+
+// ConPTY reports failure as an HRESULT. Those wrapping a Win32 error code
+// (FACILITY_WIN32, the usual case) become that error, anything else is kept
+// as it is in the message.
+fn hresult_error(hr: HRESULT) -> io::Error {
+    const FACILITY_WIN32: i32 = 7;
+    if (hr >> 16) & 0x7ff == FACILITY_WIN32 {
+        io::Error::from_raw_os_error(hr & 0xffff)
+    } else {
+        io::Error::other(format!("pseudo console error HRESULT {:#010x}", hr as u32))
+    }
+}
+
+fn pipe() -> io::Result<(File, File)> {
+    let mut read: HANDLE = ptr::null_mut();
+    let mut write: HANDLE = ptr::null_mut();
+    unsafe {
+        if CreatePipe(&mut read, &mut write, ptr::null(), 0) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((File::from_raw_handle(read), File::from_raw_handle(write)))
+    }
+}
+
+//////////////////
+//  Pty Handle  //
+//////////////////
+
+// A ConPTY pseudo console and the pipes connected to it. Windows has no
+// slave device; the console itself holds the child's ends of the pipes.
+#[derive(Debug)]
+pub struct Pty {
+    console: HPCON,
+    input: File,
+    output: File,
+    size: Cell<(u16, u16)>,
+}
+
+impl Pty {
+    pub fn open(cols: u16, rows: u16) -> io::Result<Self> {
+        let (console_in, input) = pipe()?;
+        let (output, console_out) = pipe()?;
+        let mut console: HPCON = 0;
+        let hr = unsafe {
+            CreatePseudoConsole(
+                coord(cols, rows),
+                console_in.as_raw_handle(),
+                console_out.as_raw_handle(),
+                0,
+                &mut console,
+            )
+        };
+        if hr < 0 {
+            return Err(hresult_error(hr));
+        }
+        // The console duplicated its ends, ours are closed here.
+        Ok(Self {
+            console,
+            input,
+            output,
+            size: Cell::new((cols, rows)),
+        })
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        let hr = unsafe { ResizePseudoConsole(self.console, coord(cols, rows)) };
+        if hr < 0 {
+            return Err(hresult_error(hr));
+        }
+        self.size.set((cols, rows));
+        Ok(())
+    }
+
+    // Returns (cols, rows). ConPTY can't be queried, so this is the size it
+    // was last given.
+    pub fn size(&self) -> io::Result<(u32, u32)> {
+        let (cols, rows) = self.size.get();
+        Ok((cols as u32, rows as u32))
+    }
+
+    // Write end of the child's input.
+    pub fn input(&self) -> &File {
+        &self.input
+    }
+
+    // Read end of the child's output.
+    pub fn output(&self) -> &File {
+        &self.output
+    }
+
+    // Closing the console is what ends the output stream; until then reads
+    // block even after the child has exited.
+    fn close(&mut self) {
+        if self.console != 0 {
+            unsafe { ClosePseudoConsole(self.console) };
+            self.console = 0;
+        }
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+///////////////////
+//  Pty Command  //
+///////////////////
+
+// Builder that runs a `std::process::Command` attached to a new pseudo
+// console. Program, arguments, environment and working directory are taken
+// from the command; its stdio settings are ignored.
+#[derive(Debug)]
+pub struct PtyCommand {
+    command: Command,
+    size: Option<(u16, u16)>,
+    forward_resize: bool,
+}
+
+impl PtyCommand {
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            size: None,
+            forward_resize: false,
+        }
+    }
+
+    // Initial size of the pty. Defaults to the size of the current console,
+    // or `DEFAULT_PTY_SIZE` when there is none.
+    pub fn size(mut self, cols: u16, rows: u16) -> Self {
+        self.size = Some((cols, rows));
+        self
+    }
+
+    // When enabled `PtyChild::sync_size` copies the current console size
+    // onto the child's pty whenever it has changed.
+    pub fn forward_resize(mut self, forward: bool) -> Self {
+        self.forward_resize = forward;
+        self
+    }
+
+    pub fn spawn(self) -> io::Result<PtyChild> {
+        let (cols, rows) = self.size.unwrap_or_else(|| match crate::term_size() {
+            Some((c, r)) => (c as u16, r as u16),
+            None => DEFAULT_PTY_SIZE,
+        });
+        let pty = Pty::open(cols, rows)?;
+
+        let mut command_line = command_line(&self.command);
+        let environment = environment(&self.command);
+        let current_dir: Option<Vec<u16>> = self
+            .command
+            .get_current_dir()
+            .map(|dir| dir.as_os_str().encode_wide().chain(Some(0)).collect());
+
+        unsafe {
+            let mut list_size = 0;
+            InitializeProcThreadAttributeList(ptr::null_mut(), 1, 0, &mut list_size);
+            let mut list = vec![0u8; list_size];
+            let attributes = list.as_mut_ptr() as LPPROC_THREAD_ATTRIBUTE_LIST;
+            if InitializeProcThreadAttributeList(attributes, 1, 0, &mut list_size) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let result = (|| {
+                if UpdateProcThreadAttribute(
+                    attributes,
+                    0,
+                    PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE as usize,
+                    pty.console as *const _,
+                    mem::size_of::<HPCON>(),
+                    ptr::null_mut(),
+                    ptr::null(),
+                ) == 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+
+                let mut startup: STARTUPINFOEXW = mem::zeroed();
+                startup.StartupInfo.cb = mem::size_of::<STARTUPINFOEXW>() as u32;
+                // Without explicit (invalid) handles the child can pick up the
+                // parent's redirected stdio instead of the pseudo console.
+                startup.StartupInfo.dwFlags = STARTF_USESTDHANDLES;
+                startup.StartupInfo.hStdInput = INVALID_HANDLE_VALUE;
+                startup.StartupInfo.hStdOutput = INVALID_HANDLE_VALUE;
+                startup.StartupInfo.hStdError = INVALID_HANDLE_VALUE;
+                startup.lpAttributeList = attributes;
+
+                let mut info: PROCESS_INFORMATION = mem::zeroed();
+                if CreateProcessW(
+                    ptr::null(),
+                    command_line.as_mut_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    EXTENDED_STARTUPINFO_PRESENT | CREATE_UNICODE_ENVIRONMENT,
+                    environment
+                        .as_ref()
+                        .map_or(ptr::null(), |env| env.as_ptr() as *const _),
+                    current_dir.as_ref().map_or(ptr::null(), |dir| dir.as_ptr()),
+                    &startup.StartupInfo,
+                    &mut info,
+                ) == 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(info)
+            })();
+            DeleteProcThreadAttributeList(attributes);
+            let info = result?;
+
+            Ok(PtyChild {
+                pty,
+                process: OwnedHandle::from_raw_handle(info.hProcess),
+                _thread: OwnedHandle::from_raw_handle(info.hThread),
+                id: info.dwProcessId,
+                forward_resize: self.forward_resize,
+            })
+        }
+    }
+}
+
+// Builds the command line using the quoting rules of the MSVC runtime's
+// argument parser.
+fn command_line(command: &Command) -> Vec<u16> {
+    let mut line = Vec::new();
+    quote_arg(command.get_program(), &mut line);
+    for arg in command.get_args() {
+        line.push(' ' as u16);
+        quote_arg(arg, &mut line);
+    }
+    line.push(0);
+    line
+}
+
+fn quote_arg(arg: &OsStr, out: &mut Vec<u16>) {
+    let arg: Vec<u16> = arg.encode_wide().collect();
+    let needs_quotes = arg.is_empty()
+        || arg
+            .iter()
+            .any(|&c| c == ' ' as u16 || c == '\t' as u16 || c == '"' as u16);
+    if !needs_quotes {
+        out.extend_from_slice(&arg);
+        return;
+    }
+    out.push('"' as u16);
+    let mut backslashes = 0;
+    for &c in &arg {
+        if c == '\\' as u16 {
+            backslashes += 1;
+        } else {
+            if c == '"' as u16 {
+                // escape the run of backslashes and the quote itself
+                out.extend(std::iter::repeat_n('\\' as u16, backslashes + 1));
+            }
+            backslashes = 0;
+        }
+        out.push(c);
+    }
+    // backslashes before the closing quote must be doubled
+    out.extend(std::iter::repeat_n('\\' as u16, backslashes));
+    out.push('"' as u16);
+}
+
+// A NUL separated environment block, or None to inherit the parent's when
+// the command doesn't change anything.
+fn environment(command: &Command) -> Option<Vec<u16>> {
+    let changes: Vec<_> = command.get_envs().collect();
+    if changes.is_empty() {
+        return None;
+    }
+    let mut vars: Vec<(std::ffi::OsString, std::ffi::OsString)> = std::env::vars_os()
+        .filter(|(key, _)| !changes.iter().any(|(k, _)| k.eq_ignore_ascii_case(key)))
+        .collect();
+    for (key, value) in changes {
+        if let Some(value) = value {
+            vars.push((key.to_owned(), value.to_owned()));
+        }
+    }
+    // Windows expects the block sorted, ignoring case.
+    vars.sort_by_key(|(key, _)| key.to_ascii_uppercase());
+
+    let mut block = Vec::new();
+    for (key, value) in vars {
+        block.extend(key.encode_wide());
+        block.push('=' as u16);
+        block.extend(value.encode_wide());
+        block.push(0);
+    }
+    block.push(0);
+    Some(block)
+}
+
+/////////////////
+//  Pty Child  //
+/////////////////
+
+// A running child attached to a pseudo console. Reading yields the child's
+// output and writing feeds its input.
+//
+// The output only reaches end of file once the console is closed, which
+// `wait` does after the child exits. To read everything while waiting, read
+// from `reader` on another thread.
+#[derive(Debug)]
+pub struct PtyChild {
+    pty: Pty,
+    process: OwnedHandle,
+    _thread: OwnedHandle,
+    id: u32,
+    forward_resize: bool,
+}
+
+impl PtyChild {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    // Independent handles to the pipes, e.g. to read on a separate thread.
+    pub fn reader(&self) -> io::Result<PtyReader> {
+        Ok(PtyReader(self.pty.output.try_clone()?))
+    }
+
+    pub fn writer(&self) -> io::Result<File> {
+        self.pty.input.try_clone()
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        self.pty.resize(cols, rows)
+    }
+
+    // Copies the console size onto the child's pty if it changed since the
+    // last call. Returns true if the size was updated.
+    pub fn sync_size(&self) -> io::Result<bool> {
+        if !self.forward_resize {
+            return Ok(false);
+        }
+        match crate::term_size() {
+            Some((cols, rows)) if (cols as u16, rows as u16) != self.pty.size.get() => {
+                self.resize(cols as u16, rows as u16)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.wait_for(INFINITE)?;
+        self.exit_status()
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if !self.wait_for(0)? {
+            return Ok(None);
+        }
+        self.exit_status().map(Some)
+    }
+
+    pub fn kill(&mut self) -> io::Result<()> {
+        if unsafe { TerminateProcess(self.process.as_raw_handle(), 1) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn wait_for(&self, millis: u32) -> io::Result<bool> {
+        match unsafe { WaitForSingleObject(self.process.as_raw_handle(), millis) } {
+            WAIT_OBJECT_0 => Ok(true),
+            WAIT_TIMEOUT => Ok(false),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    fn exit_status(&mut self) -> io::Result<ExitStatus> {
+        let mut code = 0;
+        if unsafe { GetExitCodeProcess(self.process.as_raw_handle(), &mut code) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.pty.close();
+        Ok(ExitStatus::from_raw(code))
+    }
+}
+
+impl Read for PtyChild {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_output(&mut self.pty.output, buf)
+    }
+}
+
+impl Write for PtyChild {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pty.input.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pty.input.flush()
+    }
+}
+
+// Read half of the output pipe returned by `PtyChild::reader`.
+#[derive(Debug)]
+pub struct PtyReader(File);
+
+impl Read for PtyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_output(&mut self.0, buf)
+    }
+}
+
+// A closed pseudo console shows up as a broken pipe; treat that as end of
+// file.
+fn read_output(output: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    match output.read(buf) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(0),
+        r => r,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{command_line, coord, environment, hresult_error};
+    use std::process::Command;
+
+    fn decode(wide: &[u16]) -> String {
+        String::from_utf16(wide).unwrap()
+    }
+
+    #[test]
+    fn hresults() {
+        // E_INVALIDARG wraps ERROR_INVALID_PARAMETER
+        let err = hresult_error(0x8007_0057u32 as i32);
+        assert_eq!(err.raw_os_error(), Some(87));
+        // E_UNEXPECTED has no Win32 code
+        let err = hresult_error(0x8000_ffffu32 as i32);
+        assert_eq!(err.raw_os_error(), None);
+        assert!(err.to_string().contains("0x8000ffff"));
+    }
+
+    #[test]
+    fn sizes() {
+        let c = coord(132, 43);
+        assert_eq!((c.X, c.Y), (132, 43));
+    }
+
+    #[test]
+    fn quoting() {
+        let mut cmd = Command::new("prog");
+        cmd.args(["plain", "a b", "", r#"say "hi""#, r"dir\", r"C:\my dir\"]);
+        let line = command_line(&cmd);
+        assert_eq!(line.last(), Some(&0));
+        assert_eq!(
+            decode(&line[..line.len() - 1]),
+            r#"prog plain "a b" "" "say \"hi\"" dir\ "C:\my dir\\""#
+        );
+    }
+
+    #[test]
+    fn environment_block() {
+        assert_eq!(environment(&Command::new("prog")), None);
+
+        let mut cmd = Command::new("prog");
+        cmd.env("MY_TERM_TEST", "1").env_remove("PATH");
+        let block = environment(&cmd).unwrap();
+        assert!(block.ends_with(&[0, 0]));
+        let vars: Vec<String> = block[..block.len() - 1]
+            .split(|&c| c == 0)
+            .filter(|v| !v.is_empty())
+            .map(decode)
+            .collect();
+        assert!(vars.contains(&"MY_TERM_TEST=1".to_string()));
+        assert!(!vars.iter().any(|v| v.to_uppercase().starts_with("PATH=")));
+        let keys: Vec<String> = vars
+            .iter()
+            .map(|v| v.split('=').next().unwrap().to_uppercase())
+            .collect();
+        assert!(keys.is_sorted());
+    }
+}
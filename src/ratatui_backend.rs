@@ -0,0 +1,272 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// ratatui's `Backend` trait implemented on top of this crate's `Backend`, so
+// a ratatui application can draw through a `Terminal` (or a `TestBackend`,
+// `Recorder`, ...) instead of crossterm:
+//
+//   let backend = RatatuiBackend::new(my_term::set_raw());
+//   let mut terminal = ratatui::Terminal::new(backend)?;
+//
+// The cursor position is tracked from what was written rather than queried,
+// so it is unknown until the first `set_cursor_position` or `clear`.
+
+use crate::backend::Backend;
+use crate::color::{Color, Iso, Reset};
+use crate::command::{write_command, write_controls};
+use crate::cursor::{Hide, Show, cheapest_move};
+use crate::erase::{Clear, ClearType};
+use crate::style::{Attributes, Style};
+use crate::width::str_width;
+use ratatui_core::backend::{self as rt, WindowSize};
+use ratatui_core::buffer::{Cell, CellDiffOption};
+use ratatui_core::layout::{Position, Size};
+use ratatui_core::style::{Color as RtColor, Modifier};
+use std::io;
+
+pub struct RatatuiBackend<B: Backend> {
+    inner: B,
+    cursor: Option<(u16, u16)>,
+}
+
+impl<B: Backend> RatatuiBackend<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            cursor: None,
+        }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn move_to(&mut self, x: u16, y: u16) -> io::Result<()> {
+        if self.cursor != Some((x, y)) {
+            write_controls(
+                cheapest_move(self.cursor, (x, y)).as_bytes(),
+                &mut self.inner,
+            )?;
+            self.cursor = Some((x, y));
+        }
+        Ok(())
+    }
+}
+
+impl<B: Backend> rt::Backend for RatatuiBackend<B> {
+    type Error = io::Error;
+
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        let (cols, _) = self.inner.size()?;
+        let mut style = Style::new();
+        write_command(&Reset, &mut self.inner)?;
+        for (x, y, cell) in content {
+            if cell.diff_option == CellDiffOption::Skip {
+                continue;
+            }
+            self.move_to(x, y)?;
+            let next = cell_style(cell);
+            write_controls(next.diff_ansi(&style).as_bytes(), &mut self.inner)?;
+            style = next;
+            self.inner.write_all(cell.symbol().as_bytes())?;
+            let end = x as u32 + str_width(cell.symbol()).max(1) as u32;
+            // at the right margin the terminal is waiting to wrap
+            self.cursor = (end < cols).then_some((end as u16, y));
+        }
+        write_command(&Reset, &mut self.inner)
+    }
+
+    fn append_lines(&mut self, n: u16) -> io::Result<()> {
+        for _ in 0..n {
+            self.inner.write_all(b"\r\n")?;
+        }
+        self.cursor = None;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        write_command(&Hide, &mut self.inner)
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        write_command(&Show, &mut self.inner)
+    }
+
+    fn get_cursor_position(&mut self) -> io::Result<Position> {
+        self.cursor
+            .map(|(x, y)| Position::new(x, y))
+            .ok_or_else(|| io::Error::other("cursor position unknown"))
+    }
+
+    fn set_cursor_position<P: Into<Position>>(&mut self, position: P) -> io::Result<()> {
+        let position = position.into();
+        self.move_to(position.x, position.y)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        write_controls(b"\x1b[H", &mut self.inner)?;
+        write_command(&Clear(ClearType::All), &mut self.inner)?;
+        self.cursor = Some((0, 0));
+        Ok(())
+    }
+
+    fn clear_region(&mut self, clear_type: rt::ClearType) -> io::Result<()> {
+        let kind = match clear_type {
+            rt::ClearType::All => ClearType::All,
+            rt::ClearType::AfterCursor => ClearType::FromCursorDown,
+            rt::ClearType::BeforeCursor => ClearType::FromCursorUp,
+            rt::ClearType::CurrentLine => ClearType::CurrentLine,
+            rt::ClearType::UntilNewLine => ClearType::UntilNewLine,
+        };
+        write_command(&Clear(kind), &mut self.inner)
+    }
+
+    fn size(&self) -> io::Result<Size> {
+        let (cols, rows) = self.inner.size()?;
+        Ok(Size::new(cols as u16, rows as u16))
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        Ok(WindowSize {
+            columns_rows: rt::Backend::size(self)?,
+            pixels: Size::new(0, 0),
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+///////////////////
+//  Conversions  //
+///////////////////
+
+impl From<RtColor> for Color {
+    fn from(color: RtColor) -> Self {
+        let iso = |color, bright| Color::Iso { color, bright };
+        match color {
+            RtColor::Reset => Color::None,
+            RtColor::Black => iso(Iso::Black, false),
+            RtColor::Red => iso(Iso::Red, false),
+            RtColor::Green => iso(Iso::Green, false),
+            RtColor::Yellow => iso(Iso::Yellow, false),
+            RtColor::Blue => iso(Iso::Blue, false),
+            RtColor::Magenta => iso(Iso::Magenta, false),
+            RtColor::Cyan => iso(Iso::Cyan, false),
+            RtColor::Gray => iso(Iso::White, false),
+            RtColor::DarkGray => iso(Iso::Black, true),
+            RtColor::LightRed => iso(Iso::Red, true),
+            RtColor::LightGreen => iso(Iso::Green, true),
+            RtColor::LightYellow => iso(Iso::Yellow, true),
+            RtColor::LightBlue => iso(Iso::Blue, true),
+            RtColor::LightMagenta => iso(Iso::Magenta, true),
+            RtColor::LightCyan => iso(Iso::Cyan, true),
+            RtColor::White => iso(Iso::White, true),
+            RtColor::Rgb(r, g, b) => Color::Rgb { r, g, b },
+            RtColor::Indexed(n) => Color::Extended(n),
+        }
+    }
+}
+
+impl From<Modifier> for Attributes {
+    fn from(modifier: Modifier) -> Self {
+        let mut attrs = Attributes::NONE;
+        for (m, attr) in [
+            (Modifier::BOLD, Attributes::BOLD),
+            (Modifier::DIM, Attributes::DIM),
+            (Modifier::ITALIC, Attributes::ITALIC),
+            (Modifier::UNDERLINED, Attributes::UNDERLINE),
+            (Modifier::SLOW_BLINK, Attributes::BLINK),
+            (Modifier::RAPID_BLINK, Attributes::BLINK),
+            (Modifier::REVERSED, Attributes::REVERSE),
+            (Modifier::HIDDEN, Attributes::HIDDEN),
+            (Modifier::CROSSED_OUT, Attributes::STRIKETHROUGH),
+        ] {
+            if modifier.contains(m) {
+                attrs.insert(attr);
+            }
+        }
+        attrs
+    }
+}
+
+fn cell_style(cell: &Cell) -> Style {
+    Style {
+        fg: cell.fg.into(),
+        bg: cell.bg.into(),
+        attrs: cell.modifier.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::RatatuiBackend;
+    use crate::backend::TestBackend;
+    use crate::color::{Color, Iso};
+    use ratatui_core::backend::Backend;
+    use ratatui_core::buffer::Buffer;
+    use ratatui_core::layout::{Position, Rect};
+    use ratatui_core::style::{Color as RtColor, Modifier, Style};
+
+    #[test]
+    fn draws_buffer_cells() {
+        let mut backend = RatatuiBackend::new(TestBackend::new(10, 3));
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 3));
+        buf.set_string(
+            2,
+            1,
+            "hi",
+            Style::new()
+                .fg(RtColor::LightRed)
+                .add_modifier(Modifier::BOLD),
+        );
+        buf.set_string(0, 2, "界x", Style::new());
+        backend.clear().unwrap();
+        let blank = Buffer::empty(buf.area);
+        backend.draw(blank.diff(&buf).into_iter()).unwrap();
+        backend.flush().unwrap();
+
+        let screen = backend.inner().screen();
+        assert_eq!(screen.row_text(1), "  hi");
+        assert_eq!(screen.row_text(2), "界x");
+        let cell = screen.cell(2, 1).unwrap();
+        assert_eq!(
+            cell.style.fg,
+            Color::Iso {
+                color: Iso::Red,
+                bright: true
+            }
+        );
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(3, 2));
+        backend.set_cursor_position((9, 2)).unwrap();
+        backend.draw([(9, 2, &buf[(0, 0)])].into_iter()).unwrap();
+        // pending wrap at the right margin
+        assert!(backend.get_cursor_position().is_err());
+    }
+}
@@ -0,0 +1,656 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Records and replays terminal output in asciicast v2 format
+// (https://docs.asciinema.org), the format `asciinema play` and the web
+// player understand. A `Recorder` wraps the real writer and copies everything
+// written through it to the cast file with a timestamp, so it can be handed
+// to `Terminal::set_writer` or used as a `Backend`. A `Player` replays a cast.
+
+use crate::backend::Backend;
+use crate::virtual_screen::VirtualScreen;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const CAST_VERSION: u32 = 2;
+
+// Range of `Player::set_speed`.
+pub const MIN_SPEED: f64 = 0.01;
+pub const MAX_SPEED: f64 = 100.0;
+
+pub struct Recorder<W: Write> {
+    inner: W,
+    cast: BufWriter<Box<dyn Write + Send>>,
+    start: Instant,
+    // tail of a UTF-8 character split across writes
+    partial: Vec<u8>,
+    // first failure writing the cast, see `take_error`
+    error: Option<io::Error>,
+    // recording stops after that failure, even once the error is taken
+    failed: bool,
+}
+
+impl<W: Write> Recorder<W> {
+    // Writes the header for a `cols` x `rows` terminal to `cast`.
+    pub fn new<C: Write + Send + 'static>(
+        inner: W,
+        cast: C,
+        cols: u32,
+        rows: u32,
+    ) -> io::Result<Self> {
+        let mut cast = BufWriter::new(Box::new(cast) as Box<dyn Write + Send>);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut env = serde_json::Map::new();
+        for name in ["TERM", "SHELL"] {
+            if let Ok(value) = std::env::var(name) {
+                env.insert(name.to_string(), value.into());
+            }
+        }
+        let header = json!({
+            "version": CAST_VERSION,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+            "env": env,
+        });
+        writeln!(cast, "{}", header)?;
+        Ok(Self {
+            inner,
+            cast,
+            start: Instant::now(),
+            partial: Vec::new(),
+            error: None,
+            failed: false,
+        })
+    }
+
+    pub fn create<P: AsRef<Path>>(inner: W, path: P, cols: u32, rows: u32) -> io::Result<Self> {
+        Self::new(inner, File::create(path)?, cols, rows)
+    }
+
+    // Records a terminal resize ("r" event).
+    pub fn resize(&mut self, cols: u32, rows: u32) -> io::Result<()> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    // Records a marker ("m" event) that players can jump to.
+    pub fn marker(&mut self, label: &str) -> io::Result<()> {
+        self.event("m", label)
+    }
+
+    // Records keyboard input ("i" event). Input isn't seen by the recorder
+    // on its own, pass it in when it should be part of the cast.
+    pub fn input(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.failed {
+            return Ok(());
+        }
+        let result = self.event("i", &String::from_utf8_lossy(bytes));
+        self.failed = result.is_err();
+        result
+    }
+
+    // The first error writing output to the cast. `write` and `flush` don't
+    // report it, so the terminal keeps getting its output, and stop
+    // recording after it.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    // Flushes the cast and returns the wrapped writer. Fails with the error
+    // `take_error` would have returned, if any, still handing the writer
+    // back.
+    pub fn finish(mut self) -> Result<W, (io::Error, W)> {
+        let result = match self.error.take() {
+            Some(e) => Err(e),
+            None if self.failed => Ok(()),
+            None => self.flush_cast(),
+        };
+        let Recorder { inner, .. } = self;
+        match result {
+            Ok(()) => Ok(inner),
+            Err(e) => Err((e, inner)),
+        }
+    }
+
+    fn flush_cast(&mut self) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            let rest = std::mem::take(&mut self.partial);
+            self.event("o", &String::from_utf8_lossy(&rest))?;
+        }
+        self.cast.flush()
+    }
+
+    fn fail(&mut self, e: io::Error) {
+        self.failed = true;
+        self.error = Some(e);
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.cast, "{}", json!([time, code, data]))
+    }
+
+    fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.partial.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            // keep an incomplete character at the end for the next write
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.partial.len(),
+        };
+        if valid == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.partial[..valid]).into_owned();
+        self.partial.drain(..valid);
+        self.event("o", &text)
+    }
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if !self.failed
+            && let Err(e) = self.output(&buf[..n])
+        {
+            self.fail(e);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        if !self.failed
+            && let Err(e) = self.cast.flush()
+        {
+            self.fail(e);
+        }
+        Ok(())
+    }
+}
+
+impl<W: Backend> Backend for Recorder<W> {
+    fn size(&self) -> io::Result<(u32, u32)> {
+        self.inner.size()
+    }
+}
+
+////////////////
+//  Playback  //
+////////////////
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastHeader {
+    pub width: u32,
+    pub height: u32,
+    pub timestamp: Option<u64>,
+    // longest pause kept when replaying, as `asciinema rec -i` stores it
+    pub idle_time_limit: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastEvent {
+    // seconds since the start of the recording
+    pub time: f64,
+    // "o" output, "i" input, "r" resize, "m" marker
+    pub code: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub header: CastHeader,
+    pub events: Vec<CastEvent>,
+}
+
+// Cast times are whatever the file says; negative ones count as the start
+// and ones too large for a `Duration` as the end of time.
+fn secs(time: f64) -> Duration {
+    Duration::try_from_secs_f64(time.max(0.0)).unwrap_or(Duration::MAX)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Cast {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(io::BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: io::BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        let first = lines
+            .next()
+            .ok_or_else(|| invalid("empty cast file".into()))??;
+        let header: serde_json::Value =
+            serde_json::from_str(&first).map_err(|e| invalid(e.to_string()))?;
+        if header["version"] != CAST_VERSION {
+            return Err(invalid(format!(
+                "unsupported cast version {}",
+                header["version"]
+            )));
+        }
+        let dimension = |key: &str| {
+            header[key]
+                .as_u64()
+                .map(|n| n as u32)
+                .ok_or_else(|| invalid(format!("cast header has no {}", key)))
+        };
+        let header = CastHeader {
+            width: dimension("width")?,
+            height: dimension("height")?,
+            timestamp: header["timestamp"].as_u64(),
+            idle_time_limit: header["idle_time_limit"].as_f64(),
+        };
+
+        let mut events = Vec::new();
+        for (n, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (time, code, data): (f64, String, String) = serde_json::from_str(&line)
+                .map_err(|e| invalid(format!("line {}: {}", n + 2, e)))?;
+            events.push(CastEvent { time, code, data });
+        }
+        Ok(Self { header, events })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |e| secs(e.time))
+    }
+}
+
+// Replays a `Cast` against the wall clock, which starts running with the
+// first `advance` or `play`. Drive it with `advance` (or `advance_screen`)
+// from an event loop, or let `play` block until the end. Seeking backwards
+// starts over from a reset terminal and fast-forwards.
+pub struct Player {
+    cast: Cast,
+    // event timestamps with idle_time_limit applied
+    times: Vec<Duration>,
+    next: usize,
+    position: Duration,
+    started: Option<Instant>,
+    // the clock starts with the first `advance` or `play`, not on `new`
+    waiting_to_start: bool,
+    speed: f64,
+    rewound: bool,
+}
+
+impl Player {
+    pub fn new(cast: Cast) -> Self {
+        let limit = cast.header.idle_time_limit.filter(|l| *l > 0.0);
+        let mut times = Vec::with_capacity(cast.events.len());
+        let (mut last, mut at) = (0.0, 0.0);
+        for e in &cast.events {
+            let gap = (e.time - last).max(0.0);
+            at += limit.map_or(gap, |l| gap.min(l));
+            last = e.time;
+            times.push(secs(at));
+        }
+        Self {
+            cast,
+            times,
+            next: 0,
+            position: Duration::ZERO,
+            started: None,
+            waiting_to_start: true,
+            speed: 1.0,
+            rewound: false,
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(Cast::open(path)?))
+    }
+
+    pub fn cast(&self) -> &Cast {
+        &self.cast
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    // Clamped to `MIN_SPEED..=MAX_SPEED`; NaN is taken as normal speed.
+    pub fn set_speed(&mut self, speed: f64) {
+        let speed = if speed.is_nan() {
+            1.0
+        } else {
+            speed.clamp(MIN_SPEED, MAX_SPEED)
+        };
+        self.position = self.position();
+        if self.started.is_some() {
+            self.started = Some(Instant::now());
+        }
+        self.speed = speed;
+    }
+
+    // Current position in recording time.
+    pub fn position(&self) -> Duration {
+        match self.started {
+            Some(at) => self
+                .position
+                .saturating_add(secs(at.elapsed().as_secs_f64() * self.speed)),
+            None => self.position,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.times.last().copied().unwrap_or(Duration::ZERO)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.started.is_none() && !self.waiting_to_start
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.times.len()
+    }
+
+    pub fn pause(&mut self) {
+        self.position = self.position();
+        self.started = None;
+        self.waiting_to_start = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.waiting_to_start = false;
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.is_paused() {
+            self.resume()
+        } else {
+            self.pause()
+        }
+    }
+
+    pub fn seek(&mut self, to: Duration) {
+        let to = to.min(self.duration());
+        if to < self.position() {
+            self.next = 0;
+            self.rewound = true;
+        }
+        self.position = to;
+        if self.started.is_some() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    // Writes the output that is due and returns how long until the next
+    // event, `None` once the recording is over. Only "o" events are written.
+    pub fn advance<W: Write>(&mut self, out: &mut W) -> io::Result<Option<Duration>> {
+        self.start_clock();
+        if std::mem::take(&mut self.rewound) {
+            out.write_all(b"\x1bc")?;
+        }
+        for e in self.due() {
+            if e.code == "o" {
+                out.write_all(e.data.as_bytes())?;
+            }
+        }
+        out.flush()?;
+        Ok(self.wait())
+    }
+
+    // Like `advance`, but also applies resize events to the screen.
+    pub fn advance_screen(&mut self, screen: &mut VirtualScreen) -> Option<Duration> {
+        self.start_clock();
+        if std::mem::take(&mut self.rewound) {
+            let header = &self.cast.header;
+            *screen = VirtualScreen::new(header.width as usize, header.height as usize);
+        }
+        for e in self.due() {
+            match e.code.as_str() {
+                "o" => screen.process(e.data.as_bytes()),
+                "r" => {
+                    if let Some((cols, rows)) = e.data.split_once('x')
+                        && let (Ok(cols), Ok(rows)) = (cols.parse(), rows.parse())
+                    {
+                        screen.resize(cols, rows);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.wait()
+    }
+
+    // Blocks until the recording has been written out. Pausing has to happen
+    // from `advance`, `play` just runs to the end.
+    pub fn play<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        self.resume();
+        while let Some(wait) = self.advance(out)? {
+            std::thread::sleep(wait);
+        }
+        Ok(())
+    }
+
+    fn start_clock(&mut self) {
+        if self.waiting_to_start {
+            self.resume();
+        }
+    }
+
+    fn due(&mut self) -> &[CastEvent] {
+        let now = self.position();
+        let start = self.next;
+        while self.next < self.times.len() && self.times[self.next] <= now {
+            self.next += 1;
+        }
+        &self.cast.events[start..self.next]
+    }
+
+    fn wait(&self) -> Option<Duration> {
+        let next = *self.times.get(self.next)?;
+        if self.is_paused() {
+            return Some(Duration::MAX);
+        }
+        Some(secs(
+            next.saturating_sub(self.position()).as_secs_f64() / self.speed,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Cast, MAX_SPEED, MIN_SPEED, Player, Recorder};
+    use crate::virtual_screen::VirtualScreen;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tees_output_into_cast() {
+        let cast = Capture::default();
+        let mut rec = Recorder::new(Vec::new(), cast.clone(), 80, 24).unwrap();
+        rec.write_all(b"\x1b[1mhi").unwrap();
+        // 'é' split across two writes
+        rec.write_all(&[0xc3]).unwrap();
+        rec.write_all(&[0xa9]).unwrap();
+        rec.resize(100, 30).unwrap();
+        let out = rec.finish().unwrap();
+        assert_eq!(out, "\x1b[1mhié".as_bytes());
+
+        let text = String::from_utf8(cast.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[0]["height"], 24);
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "\x1b[1mhi");
+        assert_eq!(lines[2][2], "é");
+        assert_eq!(lines[3][1], "r");
+        assert_eq!(lines[3][2], "100x30");
+        assert_eq!(lines.len(), 4);
+    }
+
+    const CAST: &str = r#"{"version": 2, "width": 10, "height": 2, "idle_time_limit": 1.0}
+[0.5, "o", "ab"]
+[5.0, "o", "cd"]
+[5.1, "r", "12x3"]
+"#;
+
+    #[test]
+    fn parse_and_idle_limit() {
+        let cast = Cast::read(CAST.as_bytes()).unwrap();
+        assert_eq!((cast.header.width, cast.header.height), (10, 2));
+        assert_eq!(cast.events.len(), 3);
+        assert_eq!(cast.duration(), Duration::from_secs_f64(5.1));
+
+        let player = Player::new(cast);
+        // the 4.5s gap is cut down to 1s
+        assert!(player.duration() < Duration::from_secs_f64(1.7));
+        assert!(Cast::read(&b"{\"version\": 1}\n"[..]).is_err());
+    }
+
+    #[test]
+    fn clock_starts_with_the_first_advance() {
+        let mut player = Player::new(Cast::read(CAST.as_bytes()).unwrap());
+        assert!(!player.is_paused());
+        std::thread::sleep(Duration::from_millis(600));
+        assert_eq!(player.position(), Duration::ZERO);
+        let mut out = Vec::new();
+        assert!(player.advance(&mut out).unwrap().is_some());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn seek_and_pause() {
+        let mut player = Player::new(Cast::read(CAST.as_bytes()).unwrap());
+        player.pause();
+        let mut screen = VirtualScreen::new(10, 2);
+        assert_eq!(player.advance_screen(&mut screen), Some(Duration::MAX));
+        assert_eq!(screen.row_text(0), "");
+
+        player.seek(Duration::from_secs(1));
+        player.advance_screen(&mut screen);
+        assert_eq!(screen.row_text(0), "ab");
+
+        player.seek(Duration::from_secs(10));
+        assert_eq!(player.advance_screen(&mut screen), None);
+        assert_eq!(screen.row_text(0), "abcd");
+        assert_eq!(screen.size(), (12, 3));
+        assert!(player.is_finished());
+
+        // rewinding resets the terminal before replaying
+        player.seek(Duration::ZERO);
+        let mut out = Vec::new();
+        player.advance(&mut out).unwrap();
+        assert_eq!(out, b"\x1bc");
+        player.set_speed(100.0);
+        player.play(&mut out).unwrap();
+        assert_eq!(out, b"\x1bcabcd");
+    }
+
+    struct Full;
+
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::StorageFull.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cast_errors_leave_the_output_alone() {
+        let mut rec = Recorder::new(Vec::new(), Full, 80, 24).unwrap();
+        assert_eq!(rec.write(b"hello\n").unwrap(), 6);
+        rec.flush().unwrap();
+        assert_eq!(rec.write(b"more").unwrap(), 4);
+        assert_eq!(rec.get_ref(), b"hello\nmore");
+        assert_eq!(
+            rec.take_error().map(|e| e.kind()),
+            Some(io::ErrorKind::StorageFull)
+        );
+        assert!(rec.take_error().is_none());
+        // still stopped once the error was taken
+        rec.write_all(b"!").unwrap();
+        assert!(rec.take_error().is_none());
+        assert_eq!(rec.finish().unwrap(), b"hello\nmore!");
+
+        let mut rec = Recorder::new(Vec::new(), Full, 80, 24).unwrap();
+        rec.write_all(b"hi").unwrap();
+        let (e, out) = rec.finish().unwrap_err();
+        assert_eq!(
+            (e.kind(), out.as_slice()),
+            (io::ErrorKind::StorageFull, &b"hi"[..])
+        );
+    }
+
+    #[test]
+    fn speed_and_times_out_of_range() {
+        let mut player = Player::new(
+            Cast::read(
+                &b"{\"version\": 2, \"width\": 2, \"height\": 1}\n[1e300, \"o\", \"x\"]\n"[..],
+            )
+            .unwrap(),
+        );
+        assert_eq!(player.duration(), Duration::MAX);
+        assert_eq!(player.cast().duration(), Duration::MAX);
+        player.set_speed(0.0);
+        assert_eq!(player.speed(), MIN_SPEED);
+        player.set_speed(f64::INFINITY);
+        assert_eq!(player.speed(), MAX_SPEED);
+        player.set_speed(f64::NAN);
+        assert_eq!(player.speed(), 1.0);
+        let mut out = Vec::new();
+        assert!(player.advance(&mut out).unwrap().is_some());
+        player.seek(Duration::MAX);
+        assert_eq!(player.advance(&mut out).unwrap(), None);
+        assert_eq!(out, b"x");
+    }
+}
@@ -0,0 +1,42 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Terminal resets.
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+// DECSTR. Resets modes, margins, character sets and attributes to their
+// defaults and shows the cursor, but keeps the screen contents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SoftReset;
+
+impl Command for SoftReset {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[!p")
+    }
+}
+
+// RIS. Puts the terminal back into its power-on state, clearing the screen
+// and scrollback on most emulators.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HardReset;
+
+impl Command for HardReset {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1bc")
+    }
+}
@@ -0,0 +1,1366 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Double buffered drawing surface. Draw into the back buffer, then `render`
+// sends only the cells that differ from what was last rendered (the front
+// buffer) to the backend.
+
+use crate::backend::Backend;
+use crate::cell::Cell;
+use crate::color::{Color, Iso};
+use crate::command::{Command, write_command, write_controls};
+use crate::cursor::cheapest_move;
+use crate::erase::{Clear, ClearType};
+use crate::event::Event;
+use crate::hyperlink::{CloseLink, Links, OpenLink};
+use crate::layout::Rect;
+use crate::line_size::{LineSize, SetLineSize};
+use crate::mode::{DecMode, ResetMode, SetMode};
+use crate::style::{Attributes, Style};
+use crate::tab::TabStops;
+use crate::width::{grapheme_width, graphemes};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+//////////////
+//  Buffer  //
+//////////////
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Buffer {
+    cols: u16,
+    rows: u16,
+    cells: Vec<Cell>,
+}
+
+impl Buffer {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![Cell::default(); cols as usize * rows as usize],
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    fn index(&self, col: u16, row: u16) -> Option<usize> {
+        if col < self.cols && row < self.rows {
+            Some(row as usize * self.cols as usize + col as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, col: u16, row: u16) -> Option<&Cell> {
+        self.index(col, row).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, col: u16, row: u16) -> Option<&mut Cell> {
+        self.index(col, row).map(|i| &mut self.cells[i])
+    }
+
+    pub fn reset(&mut self) {
+        self.cells.fill(Cell::default());
+    }
+
+    // Resizes keeping the overlapping top-left region. A wide character cut
+    // in half at the new right edge is blanked.
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        let mut resized = Self::new(cols, rows);
+        for row in 0..rows.min(self.rows) {
+            for col in 0..cols.min(self.cols) {
+                let cell = self.get(col, row).unwrap();
+                if col as usize + cell.width() <= cols as usize {
+                    *resized.get_mut(col, row).unwrap() = cell.clone();
+                }
+            }
+        }
+        *self = resized;
+    }
+
+    // Resizes rewrapping the content to the new width. A row filled up to
+    // its last column is taken to continue on the next one, the way a
+    // terminal wraps long lines. Rows that no longer fit are dropped from the
+    // bottom.
+    pub fn reflow(&mut self, cols: u16, rows: u16) {
+        let mut lines: Vec<Vec<Cell>> = Vec::new();
+        let mut continued = false;
+        for row in 0..self.rows {
+            let start = row as usize * self.cols as usize;
+            let cells = &self.cells[start..start + self.cols as usize];
+            if !continued {
+                lines.push(Vec::new());
+            }
+            let line = lines.last_mut().unwrap();
+            line.extend(cells.iter().filter(|c| !c.is_continuation()).cloned());
+            continued = cells.last().is_some_and(|c| *c != Cell::default());
+            if !continued {
+                while line.last() == Some(&Cell::default()) {
+                    line.pop();
+                }
+            }
+        }
+
+        let mut resized = Self::new(cols, rows);
+        // in usize, a line ending at the last column of a 65535 wide buffer
+        // would overflow
+        let (cols, rows) = (cols as usize, rows as usize);
+        let (mut col, mut row) = (0, 0);
+        for line in lines {
+            for cell in line {
+                let width = cell.width();
+                if col + width > cols {
+                    (col, row) = (0, row + 1);
+                }
+                if row >= rows || width > cols {
+                    break;
+                }
+                let style = cell.style;
+                let i = row * cols + col;
+                resized.cells[i] = cell;
+                for c in 1..width {
+                    resized.cells[i + c] = Cell::continuation(style);
+                }
+                col += width;
+            }
+            (col, row) = (0, row + 1);
+            if row >= rows {
+                break;
+            }
+        }
+        *self = resized;
+    }
+}
+
+// How `Screen::resize` treats what is already drawn.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum Reflow {
+    // Keep the top-left region that still fits.
+    #[default]
+    Clip,
+    // Rewrap rows to the new width, see `Buffer::reflow`.
+    Wrap,
+}
+
+// Called after every resize with the new (cols, rows), see
+// `Screen::on_resize`.
+type ResizeFn = dyn FnMut(&mut Screen, u16, u16) + Send;
+
+#[derive(Clone)]
+struct ResizeHook(Arc<Mutex<ResizeFn>>);
+
+impl std::fmt::Debug for ResizeHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ResizeHook")
+    }
+}
+
+//////////////
+//  Screen  //
+//////////////
+
+#[derive(Debug, Clone)]
+pub struct Screen {
+    front: Buffer,
+    back: Buffer,
+    // Clear the terminal and repaint everything on the next render.
+    full_redraw: bool,
+    // Columns `start..end` of each row touched since the last render. Rows
+    // without damage are skipped entirely by `render`.
+    damage: Vec<Option<(u16, u16)>>,
+    // Minimum time between two flushed frames, see `set_max_fps`.
+    frame_interval: Option<Duration>,
+    last_frame: Option<Instant>,
+    // where `print` moves to on `\t`
+    tabs: TabStops,
+    // URLs of the hyperlink ids in cells
+    links: Links,
+    // wrap frames in synchronized output mode
+    synchronized: bool,
+    // between `begin_frame` and `end_frame`
+    in_frame: bool,
+    reflow: Reflow,
+    on_resize: Option<ResizeHook>,
+    // drawn over the back buffer at render time, lowest z first
+    layers: Vec<Layer>,
+    // DECDWL / DECDHL of each row, and what the terminal was last sent
+    line_sizes: Vec<LineSize>,
+    drawn_sizes: Vec<LineSize>,
+}
+
+impl Screen {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            front: Buffer::new(cols, rows),
+            back: Buffer::new(cols, rows),
+            full_redraw: true,
+            damage: vec![None; rows as usize],
+            frame_interval: None,
+            last_frame: None,
+            tabs: TabStops::new(cols as usize),
+            links: Links::new(),
+            synchronized: false,
+            in_frame: false,
+            reflow: Reflow::Clip,
+            on_resize: None,
+            layers: Vec::new(),
+            line_sizes: vec![LineSize::Single; rows as usize],
+            drawn_sizes: vec![LineSize::Single; rows as usize],
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        self.back.size()
+    }
+
+    // Resizes the buffers, keeping or reflowing the content according to
+    // `set_reflow`, and repaints everything on the next render since the
+    // terminal has rearranged its screen in its own way. Then runs the
+    // `on_resize` hook.
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        match self.reflow {
+            Reflow::Clip => {
+                self.back.resize(cols, rows);
+                self.line_sizes.resize(rows as usize, LineSize::Single);
+            }
+            // rows move around, so their sizes no longer belong to them
+            Reflow::Wrap => {
+                self.back.reflow(cols, rows);
+                self.line_sizes = vec![LineSize::Single; rows as usize];
+            }
+        }
+        self.front = Buffer::new(cols, rows);
+        self.tabs.resize(cols as usize);
+        self.damage = vec![None; rows as usize];
+        self.drawn_sizes = vec![LineSize::Single; rows as usize];
+        for layer in &mut self.layers {
+            layer.screen.resize(cols, rows);
+        }
+        self.full_redraw = true;
+        // taken out while it runs, so a hook resizing again doesn't recurse
+        if let Some(hook) = self.on_resize.take() {
+            (hook.0.lock().unwrap_or_else(|p| p.into_inner()))(self, cols, rows);
+            self.on_resize.get_or_insert(hook);
+        }
+    }
+
+    pub fn set_reflow(&mut self, reflow: Reflow) {
+        self.reflow = reflow;
+    }
+
+    // Lets the application redo its layout for the new size and redraw:
+    //
+    //   screen.on_resize(|screen, cols, rows| {
+    //       screen.clear();
+    //       draw_layout(screen, cols, rows);
+    //   });
+    pub fn on_resize(&mut self, hook: impl FnMut(&mut Screen, u16, u16) + Send + 'static) {
+        self.on_resize = Some(ResizeHook(Arc::new(Mutex::new(hook))));
+    }
+
+    // Resizes on `Event::Resize` if the size actually changed. Returns
+    // whether it did.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::Resize(cols, rows) if (cols, rows) != self.size() => {
+                self.resize(cols, rows);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Forces a complete repaint on the next render, e.g. after something else
+    // wrote to the terminal.
+    pub fn invalidate(&mut self) {
+        self.full_redraw = true;
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.back
+    }
+
+    // Direct access to the back buffer. Changes made through it can't be
+    // tracked, so the whole screen is considered damaged.
+    pub fn buffer_mut(&mut self) -> &mut Buffer {
+        self.damage_all();
+        &mut self.back
+    }
+
+    // True if anything changed since the last render.
+    pub fn is_damaged(&self) -> bool {
+        self.full_redraw
+            || self.damage.iter().any(|d| d.is_some())
+            || self.layers.iter().any(|l| l.screen.is_damaged())
+    }
+
+    // Damaged column range of `row`, if any.
+    pub fn damage(&self, row: u16) -> Option<(u16, u16)> {
+        self.damage.get(row as usize).copied().flatten()
+    }
+
+    pub fn damage_cells(&mut self, col: u16, row: u16, width: u16) {
+        if let Some(d) = self.damage.get_mut(row as usize) {
+            let end = col.saturating_add(width.max(1)).min(self.back.cols);
+            *d = match *d {
+                Some((s, e)) => Some((s.min(col), e.max(end))),
+                None => Some((col, end)),
+            };
+        }
+    }
+
+    fn damage_all(&mut self) {
+        let cols = self.back.cols;
+        self.damage.fill(Some((0, cols)));
+    }
+
+    pub fn get(&self, col: u16, row: u16) -> Option<&Cell> {
+        self.back.get(col, row)
+    }
+
+    pub fn set(&mut self, col: u16, row: u16, ch: char, style: Style) {
+        self.set_cell(col, row, Cell::from_char(ch, style));
+    }
+
+    // Places `cell` at (col, row), adding the continuation cell for wide
+    // symbols and blanking any wide character it partly overwrites.
+    pub fn set_cell(&mut self, col: u16, row: u16, cell: Cell) {
+        let width = cell.width() as u16;
+        if row >= self.back.rows
+            || col as usize + width.max(1) as usize > self.row_cols(row) as usize
+        {
+            return;
+        }
+        for c in col..col + width.max(1) {
+            self.split_wide(c, row);
+        }
+        let (style, link) = (cell.style, cell.link);
+        self.damage_cells(col, row, width);
+        *self.back.get_mut(col, row).unwrap() = cell;
+        if width == 2 {
+            *self.back.get_mut(col + 1, row).unwrap() = Cell::continuation(style).with_link(link);
+        }
+    }
+
+    fn split_wide(&mut self, col: u16, row: u16) {
+        let cell = self.back.get(col, row).unwrap();
+        let partner = if cell.is_continuation() && col > 0 {
+            col - 1
+        } else if cell.width() == 2 {
+            col + 1
+        } else {
+            return;
+        };
+        if let Some(p) = self.back.get_mut(partner, row) {
+            *p = Cell::blank(p.style);
+            self.damage_cells(partner, row, 1);
+        }
+    }
+
+    pub fn line_size(&self, row: u16) -> LineSize {
+        self.line_sizes
+            .get(row as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Makes `row` a double width or double height line. Such a row only holds
+    // `size.columns(cols)` cells; the ones past that are blanked, and drawing
+    // there is clipped.
+    pub fn set_line_size(&mut self, row: u16, size: LineSize) {
+        if self.line_sizes.get(row as usize).is_none_or(|s| *s == size) {
+            return;
+        }
+        self.line_sizes[row as usize] = size;
+        for col in self.row_cols(row)..self.back.cols {
+            *self.back.get_mut(col, row).unwrap() = Cell::default();
+        }
+        self.damage_cells(0, row, self.back.cols);
+    }
+
+    // Cells that fit on `row` given its line size.
+    fn row_cols(&self, row: u16) -> u16 {
+        self.line_size(row).columns(self.back.cols as usize) as u16
+    }
+
+    // Draws `text` starting at (col, row), clipped at the right edge. Each
+    // grapheme cluster takes one cell (two if wide); zero width characters
+    // are appended to the preceding cell. Tabs skip to the next tab stop. Returns the column
+    // after the last character drawn.
+    pub fn print(&mut self, col: u16, row: u16, text: &str, style: Style) -> u16 {
+        self.print_cells(col, row, text, style, None, self.back.cols)
+    }
+
+    // Like `print`, with the text linking to `url` (OSC 8).
+    pub fn print_link(&mut self, col: u16, row: u16, text: &str, style: Style, url: &str) -> u16 {
+        let link = self.link_id(url);
+        self.print_cells(col, row, text, style, Some(link), self.back.cols)
+    }
+
+    pub fn tab_stops(&self) -> &TabStops {
+        &self.tabs
+    }
+
+    pub fn set_tab_stops(&mut self, tabs: TabStops) {
+        self.tabs = tabs;
+        self.tabs.resize(self.back.cols as usize);
+    }
+
+    // Id to put in `Cell::link` for `url`.
+    pub fn link_id(&mut self, url: &str) -> u32 {
+        self.links.intern(url)
+    }
+
+    pub fn link_url(&self, id: u32) -> Option<&str> {
+        self.links.get(id)
+    }
+
+    fn print_cells(
+        &mut self,
+        col: u16,
+        row: u16,
+        text: &str,
+        style: Style,
+        link: Option<u32>,
+        // column to clip at
+        right: u16,
+    ) -> u16 {
+        let mut c = col;
+        for g in graphemes(text) {
+            if g == "\t" {
+                // blanks up to the next tab stop, like the terminal would show
+                let next = (self.tabs.next(c as usize) as u16).min(right);
+                for col in c..next {
+                    self.set_cell(col, row, Cell::blank(style).with_link(link));
+                }
+                c = next.max(c);
+                continue;
+            }
+            let width = grapheme_width(g) as u16;
+            if width == 0 {
+                if c > col && row < self.back.rows {
+                    let mut p = c - 1;
+                    if self.back.get(p, row).unwrap().is_continuation() {
+                        p = p.saturating_sub(1);
+                    }
+                    let cell = self.back.get_mut(p, row).unwrap();
+                    g.chars().for_each(|ch| cell.push_char(ch));
+                    self.damage_cells(p, row, 1);
+                }
+                continue;
+            }
+            if c as usize + width as usize > right.min(self.row_cols(row)) as usize {
+                break;
+            }
+            self.set_cell(c, row, Cell::new(g, style).with_link(link));
+            c += width;
+        }
+        c
+    }
+
+    // A drawing handle for `area`, see `Viewport`. The area is clipped to
+    // the screen.
+    pub fn viewport(&mut self, area: Rect) -> Viewport<'_> {
+        let (cols, rows) = self.size();
+        let area = area.intersection(&Rect::sized(cols, rows));
+        Viewport { screen: self, area }
+    }
+
+    // Draws `content` over the screen with its top left corner at
+    // (col, row), clipped to the screen, for dialogs and menus. With
+    // `shadow` the cells one to the right and one below the popup are
+    // darkened. Returns what was underneath, for `dismiss`. Overlays on top
+    // of each other have to be dismissed in reverse order.
+    pub fn overlay(&mut self, content: &Buffer, col: u16, row: u16, shadow: bool) -> Overlay {
+        let (cols, rows) = content.size();
+        let area = Rect::new(col, row, cols, rows);
+        let reach = if shadow {
+            Rect::new(col, row, cols.saturating_add(1), rows.saturating_add(1))
+        } else {
+            area
+        };
+        // one extra column either side, for wide characters the popup cuts
+        let (sc, sr) = self.size();
+        let saved_area = Rect::new(
+            col.saturating_sub(1),
+            row,
+            reach.width.saturating_add(2),
+            reach.height,
+        )
+        .intersection(&Rect::sized(sc, sr));
+        let mut saved = Buffer::new(saved_area.width, saved_area.height);
+        for r in 0..saved_area.height {
+            for c in 0..saved_area.width {
+                let cell = self.back.get(saved_area.x + c, saved_area.y + r).unwrap();
+                *saved.get_mut(c, r).unwrap() = cell.clone();
+            }
+        }
+
+        if shadow {
+            // cells past u16::MAX are off screen anyway
+            let at = |c: u16, r: u16| Some((col.checked_add(c)?, row.checked_add(r)?));
+            let edge = (0..=rows)
+                .filter_map(|r| at(cols, r))
+                .chain((1..cols).filter_map(|c| at(c, rows)));
+            for (c, r) in edge {
+                if let Some(cell) = self.back.get_mut(c, r) {
+                    cell.style = SHADOW;
+                    self.damage_cells(c, r, 1);
+                }
+            }
+        }
+        let visible = area.intersection(&Rect::sized(sc, sr));
+        for r in visible.y..visible.bottom() {
+            for c in visible.x..visible.right() {
+                let cell = content.get(c - col, r - row).unwrap();
+                if !cell.is_continuation() {
+                    self.set_cell(c, r, cell.clone());
+                }
+            }
+        }
+        Overlay {
+            area,
+            saved_area,
+            saved,
+        }
+    }
+
+    // Puts back what `overlay` covered.
+    pub fn dismiss(&mut self, overlay: Overlay) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = overlay.saved_area;
+        for r in 0..height {
+            for c in 0..width {
+                if let Some(cell) = self.back.get_mut(x + c, y + r) {
+                    *cell = overlay.saved.get(c, r).unwrap().clone();
+                }
+            }
+            self.damage_cells(x, y + r, width);
+        }
+    }
+
+    // Blanks the back buffer.
+    pub fn clear(&mut self) {
+        self.back.reset();
+        self.line_sizes.fill(LineSize::Single);
+        self.damage_all();
+    }
+
+    // Limits `render` to at most `fps` frames per second. Renders requested
+    // sooner than that are skipped and their changes carried over to the next
+    // frame. `None` (the default) renders every time.
+    pub fn set_max_fps(&mut self, fps: Option<u32>) {
+        self.frame_interval = fps.filter(|f| *f > 0).map(|f| Duration::from_secs(1) / f);
+    }
+
+    // Time left before the frame limiter allows another frame.
+    pub fn time_until_next_frame(&self) -> Duration {
+        match (self.frame_interval, self.last_frame) {
+            (Some(interval), Some(last)) => interval.saturating_sub(last.elapsed()),
+            _ => Duration::ZERO,
+        }
+    }
+
+    // True when there are changes that haven't been rendered yet, e.g.
+    // because the last `render` call was throttled.
+    pub fn has_pending_frame(&self) -> bool {
+        self.is_damaged()
+    }
+
+    //////////////
+    //  Layers  //
+    //////////////
+
+    // Adds a layer drawn on top of the screen's own buffer, or returns the
+    // existing one with that name moved to `z`. Layers with a higher `z` are
+    // drawn over lower ones; equal ones in the order they were added. Cells
+    // of a layer left blank with `Color::None` as background are transparent,
+    // and any other cell with a `Color::None` background keeps the background
+    // of what is under it:
+    //
+    //   screen.add_layer("status", 1).print(0, rows - 1, "ready", style);
+    //   screen.add_layer("popup", 2);
+    //   draw_menu(screen.layer_mut("popup").unwrap());
+    pub fn add_layer(&mut self, name: &str, z: i32) -> &mut Screen {
+        let (cols, rows) = self.size();
+        let layer = match self.layers.iter().position(|l| l.name == name) {
+            Some(i) => Layer {
+                z,
+                ..self.layers.remove(i)
+            },
+            None => Layer {
+                name: name.to_string(),
+                z,
+                visible: true,
+                screen: Screen::new(cols, rows),
+            },
+        };
+        let i = self.layers.partition_point(|l| l.z <= z);
+        self.layers.insert(i, layer);
+        self.damage_all();
+        &mut self.layers[i].screen
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Screen> {
+        self.layers
+            .iter()
+            .find(|l| l.name == name)
+            .map(|l| &l.screen)
+    }
+
+    // The layer to draw into. Changes show up on the next render.
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Screen> {
+        self.layers
+            .iter_mut()
+            .find(|l| l.name == name)
+            .map(|l| &mut l.screen)
+    }
+
+    pub fn remove_layer(&mut self, name: &str) -> Option<Screen> {
+        let i = self.layers.iter().position(|l| l.name == name)?;
+        self.damage_all();
+        Some(self.layers.remove(i).screen)
+    }
+
+    // Hides or shows a layer without dropping its content.
+    pub fn set_layer_visible(&mut self, name: &str, visible: bool) {
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.name == name) {
+            layer.visible = visible;
+            self.damage_all();
+        }
+    }
+
+    // Names of the layers, bottom to top.
+    pub fn layers(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|l| l.name.as_str())
+    }
+
+    // The back buffer with the visible layers drawn over it, what `render`
+    // puts on the terminal. Layer damage is moved over to the screen.
+    fn composite(&mut self) -> Option<Buffer> {
+        if self.layers.is_empty() {
+            return None;
+        }
+        let mut out = self.back.clone();
+        let mut layers = std::mem::take(&mut self.layers);
+        for layer in &mut layers {
+            // a layer resized on its own is clipped to the screen and can
+            // leave cells it used to cover behind
+            if layer.screen.full_redraw {
+                self.damage_all();
+            }
+            for (row, damage) in layer.screen.damage.iter_mut().enumerate() {
+                if let Some((start, end)) = damage.take()
+                    && row < self.back.rows as usize
+                    && start < self.back.cols
+                {
+                    self.damage_cells(start, row as u16, end - start);
+                }
+            }
+            layer.screen.full_redraw = false;
+            if !layer.visible {
+                continue;
+            }
+            let (cols, rows) = layer.screen.size();
+            for row in 0..rows.min(out.rows) {
+                for col in 0..cols.min(out.cols) {
+                    let cell = layer.screen.back.get(col, row).unwrap();
+                    if cell.symbol == " " && cell.style.bg == Color::None {
+                        continue;
+                    }
+                    let under = out.get_mut(col, row).unwrap();
+                    let mut cell = cell.clone();
+                    if cell.style.bg == Color::None {
+                        cell.style.bg = under.style.bg;
+                    }
+                    // link ids are per screen
+                    cell.link = cell
+                        .link
+                        .and_then(|id| layer.screen.links.get(id))
+                        .map(|url| self.links.intern(url));
+                    *under = cell;
+                }
+            }
+        }
+        self.layers = layers;
+
+        // blank wide characters a layer covered one half of
+        for row in 0..out.rows {
+            for col in 0..out.cols {
+                let cell = out.get(col, row).unwrap();
+                let broken = if cell.is_continuation() {
+                    col == 0 || out.get(col - 1, row).unwrap().width() != 2
+                } else {
+                    cell.width() == 2 && !out.get(col + 1, row).is_some_and(|c| c.is_continuation())
+                };
+                if broken {
+                    let cell = out.get_mut(col, row).unwrap();
+                    *cell = Cell::blank(cell.style);
+                }
+            }
+        }
+        Some(out)
+    }
+
+    /////////////
+    //  Frame  //
+    /////////////
+
+    // Makes `begin_frame` open synchronized output mode (2026), so the
+    // terminal shows each frame whole instead of painting it as it arrives.
+    pub fn set_synchronized(&mut self, on: bool) {
+        self.synchronized = on;
+    }
+
+    // Starts a frame. Until `end_frame`, `render` writes nothing and the
+    // changes wait for the end of the frame:
+    //
+    //   screen.begin_frame(&mut term)?;
+    //   draw_sidebar(&mut screen);
+    //   draw_body(&mut screen);
+    //   screen.end_frame(&mut term)?;
+    pub fn begin_frame<B: Backend>(&mut self, out: &mut B) -> io::Result<()> {
+        self.in_frame = true;
+        if self.synchronized {
+            write_command(&SetMode(DecMode::SynchronizedOutput), out)?;
+        }
+        Ok(())
+    }
+
+    // Writes everything that changed during the frame and flushes once,
+    // regardless of the frame limiter.
+    pub fn end_frame<B: Backend>(&mut self, out: &mut B) -> io::Result<()> {
+        let mut buf = self.diff()?;
+        if std::mem::take(&mut self.in_frame) && self.synchronized {
+            ResetMode(DecMode::SynchronizedOutput).write_ansi(&mut buf)?;
+        }
+        write_controls(&buf, out)?;
+        out.flush()
+    }
+
+    pub fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    // Writes the difference between the back buffer and what is on the
+    // terminal, then flushes. The back buffer is kept, so the next frame can
+    // update it incrementally.
+    //
+    // Returns false without writing anything when the frame limiter holds the
+    // frame back, or inside `begin_frame`/`end_frame`; call again once
+    // `time_until_next_frame` has passed.
+    pub fn render<B: Backend>(&mut self, out: &mut B) -> io::Result<bool> {
+        if self.in_frame || !self.time_until_next_frame().is_zero() {
+            return Ok(false);
+        }
+        self.render_now(out)?;
+        Ok(true)
+    }
+
+    // Renders immediately, ignoring the frame limiter.
+    pub fn render_now<B: Backend>(&mut self, out: &mut B) -> io::Result<()> {
+        let buf = self.diff()?;
+        write_controls(&buf, out)?;
+        out.flush()
+    }
+
+    // The bytes bringing the terminal up to date with the back buffer.
+    fn diff(&mut self) -> io::Result<Vec<u8>> {
+        if self.frame_interval.is_some() {
+            self.last_frame = Some(Instant::now());
+        }
+        let composed = self.composite();
+        let mut buf: Vec<u8> = Vec::new();
+        if self.full_redraw {
+            Style::new().write_ansi(&mut buf)?;
+            Clear(ClearType::All).write_ansi(&mut buf)?;
+            self.front.reset();
+            self.damage_all();
+            // erasing the display makes every line single size again
+            self.drawn_sizes.fill(LineSize::Single);
+        }
+
+        // The previous render always ends with the default style.
+        let mut cursor: Option<(u16, u16)> = None;
+        let mut style = Style::new();
+        // the link being printed, closed again before returning
+        let mut link: Option<u32> = None;
+        let back = composed.as_ref().unwrap_or(&self.back);
+        for row in 0..back.rows {
+            let Some((mut start, mut end)) = self.damage[row as usize].take() else {
+                continue;
+            };
+            let size = self.line_sizes[row as usize];
+            if size != self.drawn_sizes[row as usize] {
+                // switch the line over blank, then draw all of it
+                if cursor != Some((0, row)) {
+                    buf.extend_from_slice(cheapest_move(cursor, (0, row)).as_bytes());
+                }
+                if !style.is_plain() {
+                    buf.extend_from_slice(Style::new().diff_ansi(&style).as_bytes());
+                    style = Style::new();
+                }
+                SetLineSize(size).write_ansi(&mut buf)?;
+                Clear(ClearType::CurrentLine).write_ansi(&mut buf)?;
+                cursor = Some((0, row));
+                for col in 0..back.cols {
+                    *self.front.get_mut(col, row).unwrap() = Cell::default();
+                }
+                self.drawn_sizes[row as usize] = size;
+                (start, end) = (0, back.cols);
+            }
+            let limit = size.columns(back.cols as usize) as u16;
+            for col in start..end.min(limit) {
+                let cell = back.get(col, row).unwrap();
+                if self.front.get(col, row) == Some(cell) || cell.is_continuation() {
+                    continue;
+                }
+                if cursor != Some((col, row)) {
+                    buf.extend_from_slice(cheapest_move(cursor, (col, row)).as_bytes());
+                }
+                if style != cell.style {
+                    buf.extend_from_slice(cell.style.diff_ansi(&style).as_bytes());
+                    style = cell.style;
+                }
+                let url = cell.link.and_then(|id| self.links.get(id));
+                if link != cell.link.filter(|_| url.is_some()) {
+                    if link.is_some() {
+                        CloseLink.write_ansi(&mut buf)?;
+                    }
+                    if let (Some(id), Some(url)) = (cell.link, url) {
+                        OpenLink::new(url).id(id).write_ansi(&mut buf)?;
+                    }
+                    link = cell.link.filter(|_| url.is_some());
+                }
+                buf.extend_from_slice(cell.symbol.as_bytes());
+                let next = col + cell.width() as u16;
+                cursor = if next < limit {
+                    Some((next, row))
+                } else {
+                    None
+                };
+            }
+            for col in start..end {
+                *self.front.get_mut(col, row).unwrap() = back.get(col, row).unwrap().clone();
+            }
+        }
+        if link.is_some() {
+            CloseLink.write_ansi(&mut buf)?;
+        }
+        if !style.is_plain() {
+            Style::new().write_ansi(&mut buf)?;
+        }
+
+        self.full_redraw = false;
+        Ok(buf)
+    }
+}
+
+//////////////
+//  Layers  //
+//////////////
+
+// See `Screen::add_layer`.
+#[derive(Debug, Clone)]
+struct Layer {
+    name: String,
+    z: i32,
+    visible: bool,
+    screen: Screen,
+}
+
+///////////////
+//  Overlay  //
+///////////////
+
+// Drop shadow cells keep their text, greyed out.
+const SHADOW: Style = Style {
+    fg: Color::Iso {
+        color: Iso::Black,
+        bright: true,
+    },
+    bg: Color::Iso {
+        color: Iso::Black,
+        bright: false,
+    },
+    attrs: Attributes::NONE,
+};
+
+// What a popup drawn by `Screen::overlay` covered.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    area: Rect,
+    saved_area: Rect,
+    saved: Buffer,
+}
+
+impl Overlay {
+    // Where the popup is, without its shadow.
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+}
+
+////////////////
+//  Viewport  //
+////////////////
+
+// Part of a `Screen` drawn in its own coordinates: (0, 0) is the top left
+// corner of the area and nothing lands outside it. Lets a pane draw itself
+// without knowing where it sits:
+//
+//   let panes = Rect::sized(cols, rows).split_horizontal(&[Length(30), Fill(1)]);
+//   draw_list(&mut screen.viewport(panes[0]));
+//   draw_log(&mut screen.viewport(panes[1]));
+#[derive(Debug)]
+pub struct Viewport<'a> {
+    screen: &'a mut Screen,
+    area: Rect,
+}
+
+impl Viewport<'_> {
+    // Where the viewport is on the screen.
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.area.width, self.area.height)
+    }
+
+    // A nested viewport, `area` being relative to this one.
+    pub fn viewport(&mut self, area: Rect) -> Viewport<'_> {
+        let area = Rect::new(
+            self.area.x + area.x.min(self.area.width),
+            self.area.y + area.y.min(self.area.height),
+            area.width,
+            area.height,
+        )
+        .intersection(&self.area);
+        Viewport {
+            screen: self.screen,
+            area,
+        }
+    }
+
+    pub fn get(&self, col: u16, row: u16) -> Option<&Cell> {
+        if col >= self.area.width || row >= self.area.height {
+            return None;
+        }
+        self.screen.get(self.area.x + col, self.area.y + row)
+    }
+
+    pub fn set(&mut self, col: u16, row: u16, ch: char, style: Style) {
+        self.set_cell(col, row, Cell::from_char(ch, style));
+    }
+
+    pub fn set_cell(&mut self, col: u16, row: u16, cell: Cell) {
+        if row >= self.area.height || col as usize + cell.width().max(1) > self.area.width as usize
+        {
+            return;
+        }
+        self.screen
+            .set_cell(self.area.x + col, self.area.y + row, cell);
+    }
+
+    // Like `Screen::print`, clipped at the right edge of the viewport.
+    pub fn print(&mut self, col: u16, row: u16, text: &str, style: Style) -> u16 {
+        self.print_cells(col, row, text, style, None)
+    }
+
+    pub fn print_link(&mut self, col: u16, row: u16, text: &str, style: Style, url: &str) -> u16 {
+        let link = self.screen.link_id(url);
+        self.print_cells(col, row, text, style, Some(link))
+    }
+
+    fn print_cells(
+        &mut self,
+        col: u16,
+        row: u16,
+        text: &str,
+        style: Style,
+        link: Option<u32>,
+    ) -> u16 {
+        if row >= self.area.height || col >= self.area.width {
+            return col;
+        }
+        let Rect { x, y, .. } = self.area;
+        self.screen
+            .print_cells(x + col, y + row, text, style, link, self.area.right())
+            - x
+    }
+
+    // Blanks the viewport.
+    pub fn clear(&mut self) {
+        for row in 0..self.area.height {
+            for col in 0..self.area.width {
+                self.set_cell(col, row, Cell::default());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Buffer, Reflow, Screen};
+    use crate::backend::TestBackend;
+    use crate::cell::Cell;
+    use crate::color::{Color, Iso};
+    use crate::event::Event;
+    use crate::layout::Rect;
+    use crate::line_size::LineSize;
+    use crate::style::Style;
+    use crate::tab::TabStops;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn renders_only_changes() {
+        let mut backend = TestBackend::new(10, 3);
+        let mut screen = Screen::new(10, 3);
+        let red = Style::new().fg(Color::Iso {
+            color: Iso::Red,
+            bright: false,
+        });
+        screen.print(2, 1, "hello", red);
+        screen.render(&mut backend).unwrap();
+        assert_eq!(backend.screen().row_text(1), "  hello");
+        assert_eq!(backend.screen().cell(2, 1).unwrap().style, red);
+
+        backend.clear();
+        screen.set(3, 1, 'a', red);
+        screen.render(&mut backend).unwrap();
+        assert_eq!(backend.written_str(), "\x1b[2;4H\x1b[31ma\x1b[0m");
+        assert_eq!(backend.screen().row_text(1), "  hallo");
+        assert!(!screen.is_damaged());
+    }
+
+    #[test]
+    fn double_width_lines() {
+        let mut backend = TestBackend::new(10, 2);
+        let mut screen = Screen::new(10, 2);
+        screen.set_line_size(0, LineSize::DoubleWidth);
+        assert_eq!(screen.print(0, 0, "abcdefghij", Style::new()), 5);
+        screen.render(&mut backend).unwrap();
+        assert_eq!(backend.screen().line_size(0), LineSize::DoubleWidth);
+        assert_eq!(backend.screen().row_text(0), "abcde");
+
+        backend.clear();
+        screen.set_line_size(0, LineSize::Single);
+        screen.print(5, 0, "fg", Style::new());
+        screen.render(&mut backend).unwrap();
+        assert!(backend.written_str().starts_with("\x1b[H\x1b#5\x1b[2K"));
+        assert_eq!(backend.screen().line_size(0), LineSize::Single);
+        assert_eq!(backend.screen().row_text(0), "abcdefg");
+    }
+
+    #[test]
+    fn frame_limiter_defers_changes() {
+        let mut backend = TestBackend::new(10, 3);
+        let mut screen = Screen::new(10, 3);
+        screen.set_max_fps(Some(1));
+        assert!(screen.render(&mut backend).unwrap());
+        screen.set(0, 0, 'a', Style::new());
+        assert!(!screen.render(&mut backend).unwrap());
+        assert!(screen.has_pending_frame());
+        assert_eq!(backend.screen().row_text(0), "");
+        screen.render_now(&mut backend).unwrap();
+        assert_eq!(backend.screen().row_text(0), "a");
+    }
+
+    #[test]
+    fn frame_renders_once_at_the_end() {
+        let mut backend = TestBackend::new(10, 2);
+        let mut screen = Screen::new(10, 2);
+        screen.render(&mut backend).unwrap();
+        backend.clear();
+        screen.set_synchronized(true);
+        screen.begin_frame(&mut backend).unwrap();
+        screen.print(0, 0, "ab", Style::new());
+        assert!(!screen.render(&mut backend).unwrap());
+        screen.print(0, 1, "c", Style::new());
+        assert_eq!(backend.flushes(), 0);
+        screen.end_frame(&mut backend).unwrap();
+        assert_eq!(backend.flushes(), 1);
+        assert_eq!(
+            backend.written_str(),
+            "\x1b[?2026h\x1b[Hab\r\nc\x1b[?2026l"
+        );
+        assert!(!screen.in_frame());
+    }
+
+    #[test]
+    fn draws_up_to_the_last_column() {
+        let mut screen = Screen::new(u16::MAX, 1);
+        screen.set(u16::MAX - 1, 0, 'a', Style::new());
+        screen.set(u16::MAX - 1, 0, '界', Style::new());
+        assert_eq!(screen.get(u16::MAX - 1, 0).unwrap().symbol(), "a");
+        assert_eq!(screen.damage(0), Some((u16::MAX - 1, u16::MAX)));
+        assert_eq!(screen.print(u16::MAX - 1, 0, "b界", Style::new()), u16::MAX);
+        assert_eq!(screen.get(u16::MAX - 1, 0).unwrap().symbol(), "b");
+    }
+
+    #[test]
+    fn reflow_at_the_widest_buffer() {
+        let mut buffer = Buffer::new(u16::MAX, 2);
+        *buffer.get_mut(u16::MAX - 1, 0).unwrap() = Cell::from_char('x', Style::new());
+        *buffer.get_mut(0, 1).unwrap() = Cell::from_char('y', Style::new());
+        buffer.reflow(u16::MAX, 2);
+        assert_eq!(buffer.get(u16::MAX - 1, 0).unwrap().symbol(), "x");
+        assert_eq!(buffer.get(0, 1).unwrap().symbol(), "y");
+    }
+
+    #[test]
+    fn resize_reflows_and_runs_hook() {
+        let mut screen = Screen::new(6, 3);
+        screen.set_reflow(Reflow::Wrap);
+        screen.print(0, 0, "abcdef", Style::new());
+        screen.print(0, 1, "gh", Style::new());
+        screen.print(0, 2, "界", Style::new());
+        let seen = Arc::new(Mutex::new(None));
+        let hook_seen = seen.clone();
+        screen.on_resize(move |screen, cols, rows| {
+            *hook_seen.lock().unwrap() = Some((cols, rows));
+            screen.set(0, 3, '!', Style::new());
+        });
+
+        assert!(!screen.handle_event(&Event::Resize(6, 3)));
+        assert!(screen.handle_event(&Event::Resize(4, 4)));
+        assert_eq!(*seen.lock().unwrap(), Some((4, 4)));
+        let row = |r| -> String {
+            (0..4)
+                .map(|c| screen.get(c, r).unwrap().symbol().to_string())
+                .collect()
+        };
+        assert_eq!(row(0), "abcd");
+        assert_eq!(row(1), "efgh");
+        assert_eq!(row(2), "界  ");
+        assert_eq!(row(3), "!   ");
+
+        let mut backend = TestBackend::new(4, 4);
+        screen.render(&mut backend).unwrap();
+        assert!(backend.written_str().starts_with("\x1b[0m\x1b[2J"));
+    }
+
+    #[test]
+    fn reflow_resets_line_sizes() {
+        let mut screen = Screen::new(6, 3);
+        screen.set_line_size(1, LineSize::DoubleWidth);
+        screen.resize(4, 3);
+        assert_eq!(screen.line_size(1), LineSize::DoubleWidth);
+        screen.set_reflow(Reflow::Wrap);
+        screen.resize(3, 3);
+        assert_eq!(screen.line_size(1), LineSize::Single);
+    }
+
+    #[test]
+    fn viewport_clips_and_translates() {
+        let mut screen = Screen::new(10, 4);
+        {
+            let mut pane = screen.viewport(Rect::new(2, 1, 4, 2));
+            assert_eq!(pane.size(), (4, 2));
+            assert_eq!(pane.print(1, 0, "hello", Style::new()), 4);
+            pane.print(0, 1, "a界b界", Style::new());
+            pane.set(0, 2, 'x', Style::new());
+            let mut inner = pane.viewport(Rect::new(3, 0, 5, 5));
+            assert_eq!(inner.area(), Rect::new(5, 1, 1, 2));
+            inner.set(0, 1, '!', Style::new());
+        }
+        let row = |r| -> String {
+            (0..10)
+                .map(|c| screen.get(c, r).unwrap().symbol().to_string())
+                .collect()
+        };
+        assert_eq!(row(0), " ".repeat(10));
+        assert_eq!(row(1), "   hel    ");
+        assert_eq!(row(2), "  a界!    ");
+        assert_eq!(row(3), " ".repeat(10));
+
+        let mut bad = screen.viewport(Rect::new(8, 3, 10, 10));
+        assert_eq!(bad.area(), Rect::new(8, 3, 2, 1));
+        bad.clear();
+    }
+
+    #[test]
+    fn layers_composite_by_z() {
+        let mut screen = Screen::new(6, 1);
+        screen.print(0, 0, "abcdef", Style::new().bg(Color::Extended(1)));
+        screen.add_layer("top", 2).print(1, 0, "XY", Style::new());
+        screen
+            .add_layer("middle", 1)
+            .print(2, 0, "1234", Style::new().bg(Color::Extended(2)));
+        assert_eq!(screen.layers().collect::<Vec<_>>(), ["middle", "top"]);
+
+        let mut out = TestBackend::new(6, 1);
+        screen.render_now(&mut out).unwrap();
+        let front = |s: &Screen| -> String {
+            (0..6)
+                .map(|c| s.front.get(c, 0).unwrap().symbol().to_string())
+                .collect()
+        };
+        assert_eq!(front(&screen), "aXY234");
+        assert_eq!(screen.front.get(1, 0).unwrap().style.bg, Color::Extended(1));
+        assert_eq!(screen.front.get(2, 0).unwrap().style.bg, Color::Extended(2));
+        assert_eq!(screen.get(1, 0).unwrap().symbol(), "b");
+
+        screen
+            .layer_mut("top")
+            .unwrap()
+            .print(1, 0, "Z", Style::new());
+        assert!(screen.is_damaged());
+        screen.set_layer_visible("middle", false);
+        screen.render_now(&mut out).unwrap();
+        assert_eq!(front(&screen), "aZYdef");
+
+        assert!(screen.remove_layer("top").is_some());
+        screen.render_now(&mut out).unwrap();
+        assert_eq!(front(&screen), "abcdef");
+    }
+
+    #[test]
+    fn layers_of_another_size_are_clipped() {
+        let mut screen = Screen::new(4, 2);
+        screen.print(0, 0, "abcd", Style::new());
+        screen.print(0, 1, "efgh", Style::new());
+        let layer = screen.add_layer("wide", 1);
+        layer.resize(8, 3);
+        layer.print(2, 0, "XYZW", Style::new());
+        layer.print(0, 2, "gone", Style::new());
+        let mut out = TestBackend::new(4, 2);
+        screen.render_now(&mut out).unwrap();
+        let row = |s: &Screen, r| -> String {
+            (0..4)
+                .map(|c| s.front.get(c, r).unwrap().symbol().to_string())
+                .collect()
+        };
+        assert_eq!(row(&screen, 0), "abXY");
+        assert_eq!(row(&screen, 1), "efgh");
+
+        screen.layer_mut("wide").unwrap().resize(1, 1);
+        screen.render_now(&mut out).unwrap();
+        assert_eq!(row(&screen, 0), "abcd");
+    }
+
+    #[test]
+    fn overlay_and_dismiss() {
+        let mut screen = Screen::new(8, 4);
+        for r in 0..4 {
+            screen.print(0, r, "界abcde", Style::new());
+        }
+        let before = screen.buffer().clone();
+        let mut popup = Screen::new(3, 2);
+        popup.print(0, 0, "OK", Style::new());
+
+        let overlay = screen.overlay(popup.buffer(), 1, 1, true);
+        assert_eq!(overlay.area(), Rect::new(1, 1, 3, 2));
+        let row = |s: &Screen, r| -> String {
+            (0..8)
+                .map(|c| s.get(c, r).unwrap().symbol().to_string())
+                .collect()
+        };
+        assert_eq!(row(&screen, 0), "界abcde ");
+        assert_eq!(row(&screen, 1), " OK cde ");
+        assert_eq!(row(&screen, 3), "界abcde ");
+        assert_eq!(screen.get(4, 1).unwrap().style, super::SHADOW);
+        assert_eq!(screen.get(3, 3).unwrap().style, super::SHADOW);
+        assert_eq!(screen.get(1, 3).unwrap().style, Style::new());
+
+        screen.dismiss(overlay);
+        assert_eq!(screen.buffer(), &before);
+
+        // clipped at the screen edges, even where u16 runs out
+        for (col, row) in [(6, 3), (u16::MAX - 1, u16::MAX - 1)] {
+            let overlay = screen.overlay(popup.buffer(), col, row, true);
+            screen.dismiss(overlay);
+            assert_eq!(screen.buffer(), &before);
+        }
+        let overlay = screen.overlay(popup.buffer(), 6, 3, false);
+        assert_eq!(row(&screen, 3), "界abcdOK");
+        screen.dismiss(overlay);
+    }
+
+    #[test]
+    fn links_open_and_close_across_cells() {
+        let mut backend = TestBackend::new(10, 2);
+        let mut screen = Screen::new(10, 2);
+        screen.render(&mut backend).unwrap();
+        backend.clear();
+        screen.print_link(0, 0, "ab", Style::new(), "https://a");
+        screen.print(2, 0, "-", Style::new());
+        screen.print_link(3, 0, "c", Style::new(), "https://b");
+        screen.render(&mut backend).unwrap();
+        assert_eq!(
+            backend.written_str(),
+            "\x1b[H\x1b]8;id=0;https://a\x1b\\ab\x1b]8;;\x1b\\-\x1b]8;id=1;https://b\x1b\\c\x1b]8;;\x1b\\"
+        );
+        assert_eq!(backend.screen().link(1, 0), Some("https://a"));
+        assert_eq!(backend.screen().link(2, 0), None);
+
+        // relinking a cell redraws it even though the text is the same
+        backend.clear();
+        screen.print_link(2, 0, "-", Style::new(), "https://b");
+        screen.render(&mut backend).unwrap();
+        assert_eq!(backend.screen().link(2, 0), Some("https://b"));
+    }
+
+    #[test]
+    fn prints_grapheme_clusters() {
+        let mut screen = Screen::new(5, 1);
+        let end = screen.print(
+            0,
+            0,
+            "\u{1f469}\u{200d}\u{1f4bb}e\u{301}\u{1f1ef}\u{1f1f5}!",
+            Style::new(),
+        );
+        assert_eq!(end, 5);
+        assert_eq!(
+            screen.get(0, 0).unwrap().symbol(),
+            "\u{1f469}\u{200d}\u{1f4bb}"
+        );
+        assert_eq!(screen.get(2, 0).unwrap().symbol(), "e\u{301}");
+        assert_eq!(screen.get(3, 0).unwrap().symbol(), "\u{1f1ef}\u{1f1f5}");
+        // the flag fills the last cells, "!" doesn't fit
+        assert!(screen.get(4, 0).unwrap().is_continuation());
+    }
+
+    #[test]
+    fn expands_tabs() {
+        let mut screen = Screen::new(12, 1);
+        screen.set_tab_stops(TabStops::every(4, 12));
+        assert_eq!(screen.print(1, 0, "a\tb\t\tc", Style::new()), 12);
+        let mut backend = TestBackend::new(12, 1);
+        screen.render(&mut backend).unwrap();
+        assert_eq!(backend.screen().row_text(0), " a  b      c");
+    }
+
+    #[test]
+    fn tracks_damaged_columns() {
+        let mut screen = Screen::new(10, 3);
+        screen.render(&mut TestBackend::new(10, 3)).unwrap();
+        screen.print(3, 2, "ab", Style::new());
+        screen.set(1, 2, 'x', Style::new());
+        assert_eq!(screen.damage(0), None);
+        assert_eq!(screen.damage(2), Some((1, 5)));
+    }
+}
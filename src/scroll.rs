@@ -0,0 +1,288 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Scroll regions (DECSTBM) and scrolling. `PinnedRows` uses them for the
+// classic status bar without the alternate screen: the top and bottom rows
+// stay put while normal output scrolls in between.
+
+use crate::backend::Backend;
+use crate::command::{Command, write_command};
+use crate::cursor::{MoveTo, MoveUp, RestorePosition, SavePosition};
+use crate::erase::{Clear, ClearType};
+use crate::event::Event;
+use crate::style::Style;
+use crate::width::truncate;
+use std::io::{self, Write};
+
+// DECSTBM, limits scrolling to rows `top..=bottom` (zero based). Terminals
+// move the cursor to the home position when the region changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetScrollRegion(pub u16, pub u16);
+
+impl Command for SetScrollRegion {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{};{}r", self.0 + 1, self.1 + 1)
+    }
+}
+
+// DECSTBM without parameters, scrolling covers the whole screen again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetScrollRegion;
+
+impl Command for ResetScrollRegion {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[r")
+    }
+}
+
+// SU, scrolls the region up `n` lines (content moves up).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScrollUp(pub u16);
+
+impl Command for ScrollUp {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}S", self.0)
+    }
+}
+
+// SD, scrolls the region down `n` lines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScrollDown(pub u16);
+
+impl Command for ScrollDown {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}T", self.0)
+    }
+}
+
+//////////////////
+//  PinnedRows  //
+//////////////////
+
+// Reserves `header` rows at the top and `footer` rows at the bottom:
+//
+//     let mut pinned = PinnedRows::new(0, 1);
+//     pinned.reserve(&mut out)?;
+//     pinned.draw_footer(&mut out, 0, "building...", Style::new())?;
+//     writeln!(out, "regular output scrolls above the footer")?;
+//     ...
+//     // for every event read, so a resize sets the region up again
+//     if pinned.handle_event(&mut out, &event)? {
+//         pinned.draw_footer(&mut out, 0, "building...", Style::new())?;
+//     }
+//     ...
+//     pinned.release(&mut out)?;
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PinnedRows {
+    header: u16,
+    footer: u16,
+    size: (u16, u16),
+}
+
+impl PinnedRows {
+    pub fn new(header: u16, footer: u16) -> Self {
+        Self {
+            header,
+            footer,
+            size: (0, 0),
+        }
+    }
+
+    // Terminal size the region was last set up for.
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    // First and last row (inclusive) that scroll.
+    pub fn scroll_area(&self) -> (u16, u16) {
+        (
+            self.header,
+            self.size.1.saturating_sub(self.footer.saturating_add(1)),
+        )
+    }
+
+    pub fn header_row(&self, i: u16) -> u16 {
+        i
+    }
+
+    pub fn footer_row(&self, i: u16) -> u16 {
+        self.size.1.saturating_sub(self.footer).saturating_add(i)
+    }
+
+    fn query<B: Backend>(&mut self, out: &mut B) -> io::Result<()> {
+        let (cols, rows) = out.size()?;
+        if self.header as u32 + self.footer as u32 >= rows {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "pinned rows leave no room to scroll",
+            ));
+        }
+        self.size = (cols as u16, rows as u16);
+        Ok(())
+    }
+
+    fn set_region<B: Backend>(&self, out: &mut B) -> io::Result<()> {
+        let (top, bottom) = self.scroll_area();
+        write_command(&SavePosition, out)?;
+        write_command(&SetScrollRegion(top, bottom), out)?;
+        write_command(&RestorePosition, out)
+    }
+
+    // Sets the scroll region. The content at the bottom is scrolled up to
+    // make room for the footer so the cursor is left above it; moving it out
+    // of the header is up to the caller.
+    pub fn reserve<B: Backend>(&mut self, out: &mut B) -> io::Result<()> {
+        self.query(out)?;
+        if self.footer > 0 {
+            out.write_all(&vec![b'\n'; self.footer as usize])?;
+            write_command(&MoveUp(self.footer), out)?;
+        }
+        self.set_region(out)?;
+        out.flush()
+    }
+
+    // Sets the region up again for the current size, terminals reset or clip
+    // it when resized. Returns whether the size changed; the pinned rows need
+    // redrawing then.
+    pub fn resize<B: Backend>(&mut self, out: &mut B) -> io::Result<bool> {
+        let before = self.size;
+        self.query(out)?;
+        self.set_region(out)?;
+        out.flush()?;
+        Ok(self.size != before)
+    }
+
+    // Calls `resize` on `Event::Resize`. Returns whether the pinned rows need
+    // redrawing.
+    pub fn handle_event<B: Backend>(&mut self, out: &mut B, event: &Event) -> io::Result<bool> {
+        match event {
+            Event::Resize(..) => self.resize(out),
+            _ => Ok(false),
+        }
+    }
+
+    // Replaces the contents of a pinned row, leaving the cursor where it
+    // was. Text that doesn't fit is cut off.
+    pub fn draw<B: Backend>(
+        &self,
+        out: &mut B,
+        row: u16,
+        text: &str,
+        style: Style,
+    ) -> io::Result<()> {
+        let text = truncate(text, self.size.0 as usize);
+        write_command(&SavePosition, out)?;
+        write_command(&MoveTo(0, row), out)?;
+        write_command(&Clear(ClearType::CurrentLine), out)?;
+        if style.is_plain() {
+            out.write_all(text.as_bytes())?;
+        } else {
+            write_command(&style, out)?;
+            out.write_all(text.as_bytes())?;
+            write_command(&Style::new(), out)?;
+        }
+        write_command(&RestorePosition, out)?;
+        out.flush()
+    }
+
+    pub fn draw_header<B: Backend>(
+        &self,
+        out: &mut B,
+        i: u16,
+        text: &str,
+        style: Style,
+    ) -> io::Result<()> {
+        self.draw(out, self.header_row(i), text, style)
+    }
+
+    pub fn draw_footer<B: Backend>(
+        &self,
+        out: &mut B,
+        i: u16,
+        text: &str,
+        style: Style,
+    ) -> io::Result<()> {
+        self.draw(out, self.footer_row(i), text, style)
+    }
+
+    // Gives the whole screen back to scrolling output. The pinned rows keep
+    // their last contents.
+    pub fn release<B: Backend>(&mut self, out: &mut B) -> io::Result<()> {
+        write_command(&SavePosition, out)?;
+        write_command(&ResetScrollRegion, out)?;
+        write_command(&RestorePosition, out)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::PinnedRows;
+    use crate::backend::TestBackend;
+    use crate::event::Event;
+    use crate::style::Style;
+    use std::io::Write;
+
+    #[test]
+    fn output_scrolls_between_pinned_rows() {
+        let mut out = TestBackend::new(10, 5);
+        let mut pinned = PinnedRows::new(1, 1);
+        pinned.reserve(&mut out).unwrap();
+        assert_eq!(pinned.scroll_area(), (1, 3));
+        pinned
+            .draw_header(&mut out, 0, "header", Style::new())
+            .unwrap();
+        pinned
+            .draw_footer(&mut out, 0, "a footer too long", Style::new())
+            .unwrap();
+        write!(out, "\x1b[2;1H").unwrap();
+        for i in 0..5 {
+            write!(out, "line {}\r\n", i).unwrap();
+        }
+        out.flush().unwrap();
+        assert_eq!(
+            out.screen().to_plain_string(),
+            "header\nline 3\nline 4\n\na footer t"
+        );
+
+        out.resize(12, 6);
+        assert!(
+            !pinned
+                .handle_event(&mut out, &Event::Paste("x".into()))
+                .unwrap()
+        );
+        assert!(
+            pinned
+                .handle_event(&mut out, &Event::Resize(12, 6))
+                .unwrap()
+        );
+        assert_eq!(pinned.scroll_area(), (1, 4));
+        assert_eq!(pinned.footer_row(0), 5);
+        pinned.release(&mut out).unwrap();
+        assert!(out.written_str().ends_with("\x1b7\x1b[r\x1b8"));
+    }
+
+    #[test]
+    fn rejects_too_many_rows() {
+        let mut out = TestBackend::new(10, 2);
+        let mut pinned = PinnedRows::new(1, u16::MAX);
+        assert_eq!(pinned.footer_row(0), 0);
+        assert_eq!(pinned.scroll_area(), (1, 0));
+        assert!(pinned.reserve(&mut out).is_err());
+        assert!(PinnedRows::new(1, 1).reserve(&mut out).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// OSC 133 shell integration marks. A REPL writes them around its prompt,
+// the input and the command output, which lets terminals like kitty, WezTerm
+// and foot jump between prompts, select a command's output, or mark failed
+// commands:
+//
+//   queue!(out, PromptStart)?;
+//   write!(out, "> ")?;
+//   queue!(out, CommandStart)?;
+//   let line = read_line()?;
+//   queue!(out, OutputStart)?;
+//   let status = run(&line);
+//   queue!(out, CommandFinished(Some(status)))?;
+//
+// Terminals without support ignore them.
+
+use crate::command::Command;
+use std::io::{self, Write};
+
+// A: the prompt is about to be printed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PromptStart;
+
+impl Command for PromptStart {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b]133;A\x1b\\")
+    }
+}
+
+// B: the prompt is done, what follows is the command being typed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CommandStart;
+
+impl Command for CommandStart {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b]133;B\x1b\\")
+    }
+}
+
+// C: the command was entered and its output follows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct OutputStart;
+
+impl Command for OutputStart {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b]133;C\x1b\\")
+    }
+}
+
+// D: the command finished, with its exit code if there was one. `None` also
+// closes a prompt that was abandoned without running anything.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CommandFinished(pub Option<i32>);
+
+impl Command for CommandFinished {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.0 {
+            Some(code) => write!(out, "\x1b]133;D;{}\x1b\\", code),
+            None => out.write_all(b"\x1b]133;D\x1b\\"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{CommandFinished, CommandStart, OutputStart, PromptStart};
+    use crate::command::Command;
+
+    #[test]
+    fn marks() {
+        let mut out = Vec::new();
+        PromptStart.write_ansi(&mut out).unwrap();
+        CommandStart.write_ansi(&mut out).unwrap();
+        OutputStart.write_ansi(&mut out).unwrap();
+        CommandFinished(Some(1)).write_ansi(&mut out).unwrap();
+        CommandFinished(None).write_ansi(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b]133;A\x1b\\\x1b]133;B\x1b\\\x1b]133;C\x1b\\\x1b]133;D;1\x1b\\\x1b]133;D\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,363 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::color::{Color, Iso};
+use crate::command::Command;
+use crate::parser::Params;
+use std::io::{self, Write};
+
+//////////////////
+//  Attributes  //
+//////////////////
+
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attributes(u16);
+
+impl Attributes {
+    pub const NONE: Self = Self(0);
+    pub const BOLD: Self = Self(1 << 0);
+    pub const DIM: Self = Self(1 << 1);
+    pub const ITALIC: Self = Self(1 << 2);
+    pub const UNDERLINE: Self = Self(1 << 3);
+    pub const BLINK: Self = Self(1 << 4);
+    pub const REVERSE: Self = Self(1 << 5);
+    pub const HIDDEN: Self = Self(1 << 6);
+    pub const STRIKETHROUGH: Self = Self(1 << 7);
+
+    // (attribute, SGR code that sets it)
+    pub const SGR_CODES: [(Attributes, u8); 8] = [
+        (Self::BOLD, 1),
+        (Self::DIM, 2),
+        (Self::ITALIC, 3),
+        (Self::UNDERLINE, 4),
+        (Self::BLINK, 5),
+        (Self::REVERSE, 7),
+        (Self::HIDDEN, 8),
+        (Self::STRIKETHROUGH, 9),
+    ];
+
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl std::ops::BitOr for Attributes {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/////////////
+//  Style  //
+/////////////
+
+// Colors plus attributes of a piece of text. `Color::None` means the
+// terminal's default color.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub attrs: Attributes,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self {
+            fg: Color::None,
+            bg: Color::None,
+            attrs: Attributes::NONE,
+        }
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = color;
+        self
+    }
+
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = color;
+        self
+    }
+
+    pub fn attr(mut self, attr: Attributes) -> Self {
+        self.attrs.insert(attr);
+        self
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == Self::new()
+    }
+
+    // Full SGR sequence selecting this style from any prior state.
+    pub fn to_ansi(&self) -> String {
+        let mut codes = vec!["0".to_string()];
+        for (attr, code) in Attributes::SGR_CODES {
+            if self.attrs.contains(attr) {
+                codes.push(code.to_string());
+            }
+        }
+        if let Some(c) = sgr_color(&self.fg, true) {
+            codes.push(c);
+        }
+        if let Some(c) = sgr_color(&self.bg, false) {
+            codes.push(c);
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+
+    // Shortest SGR sequence that changes the terminal from `prev` to this
+    // style: only the attributes and colors that differ, or a full reset when
+    // that is shorter. Empty when nothing changes.
+    pub fn diff_ansi(&self, prev: &Style) -> String {
+        if self == prev {
+            return String::new();
+        }
+        let mut codes: Vec<String> = Vec::new();
+        let removed = Attributes(prev.attrs.0 & !self.attrs.0);
+        let mut added = Attributes(self.attrs.0 & !prev.attrs.0);
+        if removed.contains(Attributes::BOLD) || removed.contains(Attributes::DIM) {
+            // 22 clears both bold and dim, put back whichever stays.
+            codes.push("22".to_string());
+            for attr in [Attributes::BOLD, Attributes::DIM] {
+                if self.attrs.contains(attr) {
+                    added.insert(attr);
+                }
+            }
+        }
+        for (attr, code) in [
+            (Attributes::ITALIC, "23"),
+            (Attributes::UNDERLINE, "24"),
+            (Attributes::BLINK, "25"),
+            (Attributes::REVERSE, "27"),
+            (Attributes::HIDDEN, "28"),
+            (Attributes::STRIKETHROUGH, "29"),
+        ] {
+            if removed.contains(attr) {
+                codes.push(code.to_string());
+            }
+        }
+        for (attr, code) in Attributes::SGR_CODES {
+            if added.contains(attr) {
+                codes.push(code.to_string());
+            }
+        }
+        if self.fg != prev.fg {
+            codes.push(sgr_color(&self.fg, true).unwrap_or_else(|| "39".to_string()));
+        }
+        if self.bg != prev.bg {
+            codes.push(sgr_color(&self.bg, false).unwrap_or_else(|| "49".to_string()));
+        }
+        let delta = format!("\x1b[{}m", codes.join(";"));
+        let full = self.to_ansi();
+        if full.len() < delta.len() {
+            full
+        } else {
+            delta
+        }
+    }
+
+    // Updates the style from the parameters of an SGR (`CSI ... m`) sequence.
+    pub fn apply_sgr(&mut self, params: &Params) {
+        if params.is_empty() {
+            *self = Self::new();
+            return;
+        }
+        let groups = params.groups();
+        let mut i = 0;
+        while i < groups.len() {
+            let group = &groups[i];
+            // an empty group counts as an omitted value, 0
+            match group.first().copied().unwrap_or(0) {
+                0 => *self = Self::new(),
+                1 => self.attrs.insert(Attributes::BOLD),
+                2 => self.attrs.insert(Attributes::DIM),
+                3 => self.attrs.insert(Attributes::ITALIC),
+                4 => match group.get(1) {
+                    Some(0) => self.attrs.remove(Attributes::UNDERLINE),
+                    _ => self.attrs.insert(Attributes::UNDERLINE),
+                },
+                5 | 6 => self.attrs.insert(Attributes::BLINK),
+                7 => self.attrs.insert(Attributes::REVERSE),
+                8 => self.attrs.insert(Attributes::HIDDEN),
+                9 => self.attrs.insert(Attributes::STRIKETHROUGH),
+                21 => self.attrs.insert(Attributes::UNDERLINE),
+                22 => {
+                    self.attrs.remove(Attributes::BOLD);
+                    self.attrs.remove(Attributes::DIM);
+                }
+                23 => self.attrs.remove(Attributes::ITALIC),
+                24 => self.attrs.remove(Attributes::UNDERLINE),
+                25 => self.attrs.remove(Attributes::BLINK),
+                27 => self.attrs.remove(Attributes::REVERSE),
+                28 => self.attrs.remove(Attributes::HIDDEN),
+                29 => self.attrs.remove(Attributes::STRIKETHROUGH),
+                n @ 30..=37 => self.fg = iso_color(n - 30, false),
+                n @ 40..=47 => self.bg = iso_color(n - 40, false),
+                n @ 90..=97 => self.fg = iso_color(n - 90, true),
+                n @ 100..=107 => self.bg = iso_color(n - 100, true),
+                39 => self.fg = Color::None,
+                49 => self.bg = Color::None,
+                n @ (38 | 48) => {
+                    let (color, used) = if group.len() > 1 {
+                        (extended_color(&group[1..]), 0)
+                    } else {
+                        let rest: Vec<u16> = groups[i + 1..].iter().map(|g| g[0]).take(4).collect();
+                        extended_color_used(&rest)
+                    };
+                    if let Some(color) = color {
+                        if n == 38 {
+                            self.fg = color;
+                        } else {
+                            self.bg = color;
+                        }
+                    }
+                    i += used;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+impl Command for Style {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(self.to_ansi().as_bytes())
+    }
+}
+
+fn iso_color(n: u16, bright: bool) -> Color {
+    let color = match n {
+        0 => Iso::Black,
+        1 => Iso::Red,
+        2 => Iso::Green,
+        3 => Iso::Yellow,
+        4 => Iso::Blue,
+        5 => Iso::Magenta,
+        6 => Iso::Cyan,
+        _ => Iso::White,
+    };
+    Color::Iso { color, bright }
+}
+
+// Colon form: `38:5:n`, `38:2:r:g:b` or `38:2:cs:r:g:b`.
+fn extended_color(values: &[u16]) -> Option<Color> {
+    match values {
+        [5, n, ..] => Some(Color::Extended(*n as u8)),
+        [2, _, r, g, b, ..] => Some(Color::Rgb {
+            r: *r as u8,
+            g: *g as u8,
+            b: *b as u8,
+        }),
+        [2, r, g, b] => Some(Color::Rgb {
+            r: *r as u8,
+            g: *g as u8,
+            b: *b as u8,
+        }),
+        _ => None,
+    }
+}
+
+// Semicolon form: `38;5;n` or `38;2;r;g;b`. Also returns how many of the
+// following parameters were consumed.
+fn extended_color_used(values: &[u16]) -> (Option<Color>, usize) {
+    match values {
+        [5, n, ..] => (Some(Color::Extended(*n as u8)), 2),
+        [2, r, g, b, ..] => (
+            Some(Color::Rgb {
+                r: *r as u8,
+                g: *g as u8,
+                b: *b as u8,
+            }),
+            4,
+        ),
+        _ => (None, values.len()),
+    }
+}
+
+pub(crate) fn sgr_color(color: &Color, fg: bool) -> Option<String> {
+    match color {
+        Color::Iso { color, bright } => {
+            let base = match (fg, bright) {
+                (true, false) => 3,
+                (true, true) => 9,
+                (false, false) => 4,
+                (false, true) => 10,
+            };
+            Some(format!("{}{}", base, color.to_char()))
+        }
+        Color::Extended(n) => Some(format!("{};5;{}", if fg { 38 } else { 48 }, n)),
+        Color::Rgb { r, g, b } => Some(format!("{};2;{};{};{}", if fg { 38 } else { 48 }, r, g, b)),
+        Color::Default => Some(if fg { "39" } else { "49" }.to_string()),
+        Color::None => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Attributes, Style};
+    use crate::color::{Color, Iso};
+    use crate::parser::Params;
+
+    #[test]
+    fn sgr_with_empty_groups() {
+        let mut style = Style::new().fg(Color::Extended(1));
+        style.apply_sgr(&Params::new(vec![vec![1], vec![]]));
+        assert_eq!(style, Style::new());
+    }
+
+    #[test]
+    fn diff_emits_only_changes() {
+        let red = Color::Iso {
+            color: Iso::Red,
+            bright: false,
+        };
+        let bold_red = Style::new()
+            .fg(red)
+            .attr(Attributes::BOLD | Attributes::DIM);
+        let dim_red = Style::new().fg(red).attr(Attributes::DIM);
+        assert_eq!(bold_red.diff_ansi(&bold_red), "");
+        assert_eq!(bold_red.diff_ansi(&Style::new().fg(red)), "\x1b[1;2m");
+        assert_eq!(dim_red.diff_ansi(&bold_red), "\x1b[22;2m");
+        assert_eq!(Style::new().diff_ansi(&dim_red), "\x1b[0m");
+    }
+}
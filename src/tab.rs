@@ -0,0 +1,173 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Tab stop control. Terminals start with a stop every 8 columns.
+
+use crate::command::Command;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+// Default distance between tab stops.
+pub const TAB_WIDTH: usize = 8;
+
+// HTS, sets a tab stop at the cursor column.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetTabStop;
+
+impl Command for SetTabStop {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1bH")
+    }
+}
+
+// TBC 0, clears the tab stop at the cursor column.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClearTabStop;
+
+impl Command for ClearTabStop {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[0g")
+    }
+}
+
+// TBC 3
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClearAllTabStops;
+
+impl Command for ClearAllTabStops {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[3g")
+    }
+}
+
+// CHT, moves forward `n` tab stops.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TabForward(pub u16);
+
+impl Command for TabForward {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}I", self.0)
+    }
+}
+
+// CBT, moves back `n` tab stops.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TabBackward(pub u16);
+
+impl Command for TabBackward {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}Z", self.0)
+    }
+}
+
+/////////////////
+//  Tab Stops  //
+/////////////////
+
+// The tab stops of a screen `cols` wide, for expanding `\t` the way the
+// terminal would. Columns added by `resize` get the default stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabStops {
+    stops: BTreeSet<usize>,
+    cols: usize,
+}
+
+impl TabStops {
+    pub fn new(cols: usize) -> Self {
+        Self::every(TAB_WIDTH, cols)
+    }
+
+    // A stop every `interval` columns.
+    pub fn every(interval: usize, cols: usize) -> Self {
+        let interval = interval.max(1);
+        Self {
+            stops: (interval..cols).step_by(interval).collect(),
+            cols,
+        }
+    }
+
+    pub fn set(&mut self, col: usize) {
+        if col < self.cols {
+            self.stops.insert(col);
+        }
+    }
+
+    pub fn clear(&mut self, col: usize) {
+        self.stops.remove(&col);
+    }
+
+    pub fn clear_all(&mut self) {
+        self.stops.clear();
+    }
+
+    pub fn is_set(&self, col: usize) -> bool {
+        self.stops.contains(&col)
+    }
+
+    // Where a tab at `col` moves to: the next stop, or the last column.
+    pub fn next(&self, col: usize) -> usize {
+        self.stops
+            .range(col + 1..)
+            .next()
+            .copied()
+            .unwrap_or(self.cols.saturating_sub(1))
+            .max(col.min(self.cols.saturating_sub(1)))
+    }
+
+    // The previous stop, or the first column.
+    pub fn prev(&self, col: usize) -> usize {
+        self.stops.range(..col).next_back().copied().unwrap_or(0)
+    }
+
+    pub fn resize(&mut self, cols: usize) {
+        self.stops.retain(|&c| c < cols);
+        let first = self.cols.div_ceil(TAB_WIDTH).max(1) * TAB_WIDTH;
+        self.stops.extend((first..cols).step_by(TAB_WIDTH));
+        self.cols = cols;
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{ClearAllTabStops, ClearTabStop, SetTabStop, TabBackward, TabForward, TabStops};
+    use crate::command::Command;
+
+    #[test]
+    fn sequences() {
+        assert_eq!(SetTabStop.to_ansi_string(), "\x1bH");
+        assert_eq!(ClearTabStop.to_ansi_string(), "\x1b[0g");
+        assert_eq!(ClearAllTabStops.to_ansi_string(), "\x1b[3g");
+        assert_eq!(TabForward(2).to_ansi_string(), "\x1b[2I");
+        assert_eq!(TabBackward(1).to_ansi_string(), "\x1b[1Z");
+    }
+
+    #[test]
+    fn stops() {
+        let mut tabs = TabStops::new(20);
+        assert_eq!(tabs.next(0), 8);
+        assert_eq!(tabs.next(8), 16);
+        assert_eq!(tabs.next(16), 19);
+        assert_eq!(tabs.prev(10), 8);
+        tabs.set(3);
+        tabs.clear(8);
+        assert_eq!(tabs.next(0), 3);
+        assert_eq!(tabs.next(3), 16);
+        tabs.resize(30);
+        assert_eq!(tabs.next(16), 24);
+        assert_eq!(TabStops::every(4, 10).next(5), 8);
+    }
+}
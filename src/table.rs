@@ -0,0 +1,295 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Aligned tables for CLI output or a screen region:
+//
+//   let table = Table::new()
+//       .column(Column::new("Name").max(20))
+//       .column(Column::new("Size").align(Align::Right))
+//       .header_style(Style::new().attr(Attributes::BOLD))
+//       .row(["Cargo.toml", "1.2K"])
+//       .row(["src", "-"]);
+//   table.write_to(&mut io::stdout())?;
+//
+// Columns are as wide as their widest cell, within their min and max.
+// Longer cells are cut with an ellipsis.
+
+use crate::command::write_command;
+use crate::draw::DrawTarget;
+use crate::layout::{Align, Rect};
+use crate::style::Style;
+use crate::width::{str_width, truncate};
+use std::io::{self, Write};
+
+//////////////
+//  Column  //
+//////////////
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    header: String,
+    min: u16,
+    max: Option<u16>,
+    align: Align,
+}
+
+impl Column {
+    pub fn new(header: impl Into<String>) -> Self {
+        Self {
+            header: header.into(),
+            min: 0,
+            max: None,
+            align: Align::Left,
+        }
+    }
+
+    pub fn min(mut self, width: u16) -> Self {
+        self.min = width;
+        self
+    }
+
+    pub fn max(mut self, width: u16) -> Self {
+        self.max = Some(width);
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+/////////////
+//  Table  //
+/////////////
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<(Vec<String>, Style)>,
+    header_style: Style,
+    show_header: bool,
+    separator: String,
+    ellipsis: String,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self {
+            columns: Vec::new(),
+            rows: Vec::new(),
+            header_style: Style::new(),
+            show_header: true,
+            separator: "  ".to_string(),
+            ellipsis: "…".to_string(),
+        }
+    }
+
+    pub fn column(mut self, column: Column) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    pub fn header_style(mut self, style: Style) -> Self {
+        self.header_style = style;
+        self
+    }
+
+    pub fn show_header(mut self, show: bool) -> Self {
+        self.show_header = show;
+        self
+    }
+
+    // Between columns, two spaces by default.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    // Marks cut cells, "…" by default.
+    pub fn ellipsis(mut self, ellipsis: &str) -> Self {
+        self.ellipsis = ellipsis.to_string();
+        self
+    }
+
+    pub fn row<I: IntoIterator<Item = S>, S: Into<String>>(self, cells: I) -> Self {
+        self.styled_row(cells, Style::new())
+    }
+
+    pub fn styled_row<I: IntoIterator<Item = S>, S: Into<String>>(
+        mut self,
+        cells: I,
+        style: Style,
+    ) -> Self {
+        self.push_row(cells, style);
+        self
+    }
+
+    // Cells past the last column are dropped, missing ones left blank.
+    pub fn push_row<I: IntoIterator<Item = S>, S: Into<String>>(&mut self, cells: I, style: Style) {
+        self.rows
+            .push((cells.into_iter().map(Into::into).collect(), style));
+    }
+
+    // Width of each column.
+    pub fn widths(&self) -> Vec<u16> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let cells = self.rows.iter().filter_map(|(cells, _)| cells.get(i));
+                let header = self.show_header.then_some(&column.header);
+                let widest = header
+                    .into_iter()
+                    .chain(cells)
+                    .map(|c| str_width(c) as u16)
+                    .max()
+                    .unwrap_or(0);
+                let width = widest.max(column.min);
+                column
+                    .max
+                    .map_or(width, |max| width.min(max.max(column.min)))
+            })
+            .collect()
+    }
+
+    // Width of the whole table.
+    pub fn width(&self) -> u16 {
+        let gaps = self.columns.len().saturating_sub(1) * str_width(&self.separator);
+        self.widths().iter().sum::<u16>() + gaps as u16
+    }
+
+    // Each line of the table as plain text with its style, header first.
+    pub fn lines(&self) -> Vec<(String, Style)> {
+        let widths = self.widths();
+        let header = self.show_header.then(|| {
+            let cells = self.columns.iter().map(|c| c.header.clone()).collect();
+            (cells, self.header_style)
+        });
+        header
+            .iter()
+            .chain(&self.rows)
+            .map(|(cells, style)| {
+                let line = self
+                    .columns
+                    .iter()
+                    .zip(&widths)
+                    .enumerate()
+                    .map(|(i, (column, width))| {
+                        let text = cells.get(i).map_or("", String::as_str);
+                        self.fit(text, *width, column.align)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(&self.separator);
+                (line, *style)
+            })
+            .collect()
+    }
+
+    // `text` cut or padded to exactly `width` cells.
+    fn fit(&self, text: &str, width: u16, align: Align) -> String {
+        let width = width as usize;
+        let text = if str_width(text) <= width {
+            text.to_string()
+        } else if str_width(&self.ellipsis) < width {
+            format!(
+                "{}{}",
+                truncate(text, width - str_width(&self.ellipsis)),
+                self.ellipsis
+            )
+        } else {
+            truncate(text, width).to_string()
+        };
+        crate::width::align(&text, width, align)
+    }
+
+    // Writes the table line by line, styled with SGR sequences. Lines end in
+    // "\n", so with output post-processing off (raw mode) write "\r\n"
+    // yourself through `render` instead.
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        for (line, style) in self.lines() {
+            if style.is_plain() {
+                writeln!(out, "{}", line.trim_end())?;
+            } else {
+                write_command(&style, out)?;
+                out.write_all(line.as_bytes())?;
+                write_command(&Style::new(), out)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Draws the table into `area`, clipped to it.
+    pub fn render<T: DrawTarget + ?Sized>(&self, target: &mut T, area: Rect) -> io::Result<()> {
+        for ((line, style), row) in self.lines().into_iter().zip(area.y..area.bottom()) {
+            target.draw_str(area.x, row, truncate(&line, area.width as usize), style)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{Column, Table};
+    use crate::layout::{Align, Rect};
+    use crate::screen::Screen;
+    use crate::style::{Attributes, Style};
+
+    fn table() -> Table {
+        Table::new()
+            .column(Column::new("Name").max(8))
+            .column(Column::new("Size").min(6).align(Align::Right))
+            .header_style(Style::new().attr(Attributes::BOLD))
+            .row(["Cargo.toml", "1.2K"])
+            .row(["src"])
+    }
+
+    #[test]
+    fn lays_out_columns() {
+        let t = table();
+        assert_eq!(t.widths(), vec![8, 6]);
+        let mut out = Vec::new();
+        t.write_to(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[0;1mName        Size\x1b[0m\nCargo.t…    1.2K\nsrc\n"
+        );
+    }
+
+    #[test]
+    fn renders_into_screen_region() {
+        let mut screen = Screen::new(20, 3);
+        table().render(&mut screen, Rect::new(1, 1, 10, 5)).unwrap();
+        let row = |r| -> String {
+            (0..20)
+                .map(|c| screen.get(c, r).unwrap().symbol().to_string())
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+        assert_eq!(row(1), " Name");
+        assert_eq!(row(2), " Cargo.t…");
+        assert_eq!(screen.get(1, 1).unwrap().style.attrs, Attributes::BOLD);
+    }
+}
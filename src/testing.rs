@@ -0,0 +1,270 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Helpers for testing colored output. Two ANSI strings are equal when they
+// show the same text in the same effective styles, however the escape
+// sequences got there: `\x1b[1m\x1b[31mhi` and `\x1b[31;1mhi\x1b[0m` match.
+// Only SGR matters, other sequences are dropped.
+//
+//   assert_styled_eq!(render(), "\x1b[1mok\x1b[0m done");
+
+use crate::parser::{Action, Parser};
+use crate::style::{Attributes, Style};
+
+////////////
+//  Span  //
+////////////
+
+// A run of text in a single style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]{:?}", describe(&self.style), self.text)
+    }
+}
+
+// Splits `ansi` into styled runs, merging neighbours that end up in the
+// same style. LF, CR and HT are kept as text.
+pub fn spans(ansi: &str) -> Vec<Span> {
+    let mut style = Style::new();
+    let mut out: Vec<Span> = Vec::new();
+    for action in Parser::new().feed(ansi.as_bytes()) {
+        let c = match action {
+            Action::Print(c) => c,
+            Action::Execute(b @ (b'\n' | b'\r' | b'\t')) => b as char,
+            Action::Csi {
+                params,
+                intermediates,
+                final_byte: b'm',
+                ..
+            } if intermediates.is_empty() => {
+                style.apply_sgr(&params);
+                continue;
+            }
+            _ => continue,
+        };
+        match out.last_mut() {
+            Some(span) if span.style == style => span.text.push(c),
+            _ => out.push(Span {
+                text: c.to_string(),
+                style,
+            }),
+        }
+    }
+    out
+}
+
+// The visible text of `ansi` without any escape sequences.
+pub fn strip_ansi(ansi: &str) -> String {
+    spans(ansi).into_iter().map(|s| s.text).collect()
+}
+
+// "bold underline fg=red bg=#202020", or "plain".
+pub fn describe(style: &Style) -> String {
+    const NAMES: [(Attributes, &str); 8] = [
+        (Attributes::BOLD, "bold"),
+        (Attributes::DIM, "dim"),
+        (Attributes::ITALIC, "italic"),
+        (Attributes::UNDERLINE, "underline"),
+        (Attributes::BLINK, "blink"),
+        (Attributes::REVERSE, "reverse"),
+        (Attributes::HIDDEN, "hidden"),
+        (Attributes::STRIKETHROUGH, "strikethrough"),
+    ];
+    let mut parts: Vec<String> = NAMES
+        .iter()
+        .filter(|(attr, _)| style.attrs.contains(*attr))
+        .map(|(_, name)| name.to_string())
+        .collect();
+    if style.fg != crate::color::Color::None {
+        parts.push(format!("fg={}", style.fg));
+    }
+    if style.bg != crate::color::Color::None {
+        parts.push(format!("bg={}", style.bg));
+    }
+    if parts.is_empty() {
+        return "plain".into();
+    }
+    parts.join(" ")
+}
+
+////////////
+//  Diff  //
+////////////
+
+// Why two ANSI strings differ. `Display` shows both sides as text with the
+// first difference marked, then both sides as spans:
+//
+//   styles differ at char 2 ('!'): bold -> plain
+//     - |hi!|
+//     + |hi!|
+//          ^
+//     - [bold]"hi!"
+//     + [bold]"hi" [plain]"!"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyledDiff {
+    // index of the first differing char
+    pub index: usize,
+    pub left: Vec<Span>,
+    pub right: Vec<Span>,
+}
+
+impl StyledDiff {
+    fn chars(spans: &[Span]) -> Vec<(char, Style)> {
+        spans
+            .iter()
+            .flat_map(|s| s.text.chars().map(move |c| (c, s.style)))
+            .collect()
+    }
+
+    pub fn text_differs(&self) -> bool {
+        let text = |spans: &[Span]| spans.iter().map(|s| s.text.as_str()).collect::<String>();
+        text(&self.left) != text(&self.right)
+    }
+}
+
+impl std::fmt::Display for StyledDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let left = Self::chars(&self.left);
+        let right = Self::chars(&self.right);
+        let side = |chars: &[(char, Style)]| match chars.get(self.index) {
+            Some((c, style)) => format!("{:?} {}", c, describe(style)),
+            None => "end of text".to_string(),
+        };
+        if self.text_differs() {
+            write!(
+                f,
+                "text differs at char {}: {} -> {}",
+                self.index,
+                side(&left),
+                side(&right)
+            )?;
+        } else {
+            let (c, before) = left[self.index];
+            let after = right[self.index].1;
+            write!(
+                f,
+                "styles differ at char {} ({:?}): {} -> {}",
+                self.index,
+                c,
+                describe(&before),
+                describe(&after)
+            )?;
+        }
+        let line = |chars: &[(char, Style)]| -> String {
+            chars
+                .iter()
+                .map(|(c, _)| c.escape_debug().to_string())
+                .collect()
+        };
+        write!(f, "\n  - |{}|", line(&left))?;
+        write!(f, "\n  + |{}|", line(&right))?;
+        let offset: usize = left[..self.index.min(left.len())]
+            .iter()
+            .map(|(c, _)| c.escape_debug().count())
+            .sum();
+        write!(f, "\n     {}^", " ".repeat(offset))?;
+        let list = |spans: &[Span]| {
+            spans
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        write!(f, "\n  - {}", list(&self.left))?;
+        write!(f, "\n  + {}", list(&self.right))
+    }
+}
+
+// `None` when both strings show the same text in the same styles.
+pub fn styled_diff(left: impl AsRef<str>, right: impl AsRef<str>) -> Option<StyledDiff> {
+    let left = spans(left.as_ref());
+    let right = spans(right.as_ref());
+    let (l, r) = (StyledDiff::chars(&left), StyledDiff::chars(&right));
+    let index = match l.iter().zip(&r).position(|(a, b)| a != b) {
+        Some(i) => i,
+        None if l.len() == r.len() => return None,
+        None => l.len().min(r.len()),
+    };
+    Some(StyledDiff { index, left, right })
+}
+
+// Asserts that two ANSI strings show the same text in the same styles,
+// panicking with a `StyledDiff` report when they don't.
+#[macro_export]
+macro_rules! assert_styled_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(diff) = $crate::testing::styled_diff(&$left, &$right) {
+            panic!("assertion `left == right` failed (styled)\n{}", diff);
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        if let Some(diff) = $crate::testing::styled_diff(&$left, &$right) {
+            panic!("assertion `left == right` failed (styled): {}\n{}", format_args!($($arg)+), diff);
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{describe, spans, strip_ansi, styled_diff};
+    use crate::color::{Color, Iso};
+    use crate::style::{Attributes, Style};
+
+    #[test]
+    fn equivalent_sequences_match() {
+        assert_styled_eq!("\x1b[1m\x1b[31mhi\x1b[0m!", "\x1b[31;1mh\x1b[1mi\x1b[m!");
+        assert_styled_eq!(String::from("\x1b[2Jplain"), "plain");
+        let s = spans("a\x1b[4mb\x1b[24mc");
+        assert_eq!(s.len(), 3);
+        assert_eq!(s[1].style, Style::new().attr(Attributes::UNDERLINE));
+        assert_eq!(strip_ansi("\x1b[31mred\x1b[0m\n"), "red\n");
+    }
+
+    #[test]
+    fn reports_first_difference() {
+        let diff = styled_diff("\x1b[1mhi!", "\x1b[1mhi\x1b[0m!").unwrap();
+        assert_eq!(diff.index, 2);
+        assert!(!diff.text_differs());
+        let report = diff.to_string();
+        assert!(
+            report.starts_with("styles differ at char 2 ('!'): bold -> plain"),
+            "{}",
+            report
+        );
+        assert!(report.contains("+ [bold]\"hi\" [plain]\"!\""), "{}", report);
+
+        let diff = styled_diff("abc", "ab").unwrap();
+        assert!(diff.text_differs());
+        assert_eq!(diff.index, 2);
+        assert_eq!(
+            describe(&Style::new().fg(Color::iso(Iso::Blue, true))),
+            "fg=bright-blue"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "text differs at char 0")]
+    fn macro_panics() {
+        assert_styled_eq!("x", "y", "case {}", 1);
+    }
+}
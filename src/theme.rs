@@ -0,0 +1,335 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Named color schemes. A `Theme` maps semantic names ("background", "error",
+// ...) to colors, so an application draws with names and users pick or write
+// the scheme. With the `serde` feature themes load from TOML or JSON files:
+//
+//     name = "mine"
+//     [colors]
+//     error = "#dc322f"
+//     muted = "244"
+//     info = "bright-blue"
+
+use crate::color::{Color, Iso, color_depth};
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::sync::RwLock;
+
+// Names every built-in theme defines.
+pub const THEME_COLORS: [&str; 12] = [
+    "background",
+    "foreground",
+    "muted",
+    "selection",
+    "border",
+    "primary",
+    "secondary",
+    "accent",
+    "error",
+    "warning",
+    "success",
+    "info",
+];
+
+pub const BUILTIN_THEMES: [&str; 3] = ["solarized", "gruvbox", "dracula"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Theme {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub colors: BTreeMap<String, Color>,
+}
+
+impl Theme {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            colors: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, name: &str, color: Color) -> Self {
+        self.set(name, color);
+        self
+    }
+
+    pub fn set(&mut self, name: &str, color: Color) {
+        self.colors.insert(name.to_string(), color);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.colors.get(name).copied()
+    }
+
+    // The color for `name`, or the terminal default (`Color::None`) when the
+    // theme doesn't define it.
+    pub fn color(&self, name: &str) -> Color {
+        self.get(name).unwrap_or(Color::None)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.colors.keys().map(String::as_str)
+    }
+
+    // Colors of `other` replace ours, e.g. a user file over a built-in theme.
+    pub fn merge(&mut self, other: &Theme) {
+        for (name, color) in &other.colors {
+            self.colors.insert(name.clone(), *color);
+        }
+    }
+
+    /////////////
+    //  Files  //
+    /////////////
+
+    #[cfg(feature = "serde")]
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    // Reads a theme file, as JSON when the extension is `.json` and TOML
+    // otherwise. Parse errors come back as `InvalidData`.
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        if path.extension().is_some_and(|e| e == "json") {
+            Self::from_json(&text).map_err(|e| invalid(e.to_string()))
+        } else {
+            Self::from_toml(&text).map_err(|e| invalid(e.to_string()))
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let text = if path.extension().is_some_and(|e| e == "json") {
+            self.to_json().map_err(|e| invalid(e.to_string()))?
+        } else {
+            self.to_toml().map_err(|e| invalid(e.to_string()))?
+        };
+        std::fs::write(path, text)
+    }
+
+    ////////////////
+    //  Built-in  //
+    ////////////////
+
+    // One of `BUILTIN_THEMES` by name.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "solarized" => Some(Self::solarized()),
+            "gruvbox" => Some(Self::gruvbox()),
+            "dracula" => Some(Self::dracula()),
+            _ => None,
+        }
+    }
+
+    // Solarized dark.
+    pub fn solarized() -> Self {
+        Self::from_hex(
+            "solarized",
+            [
+                0x002b36, 0x839496, 0x586e75, 0x073642, 0x586e75, 0x268bd2, 0x2aa198, 0xd33682,
+                0xdc322f, 0xb58900, 0x859900, 0x6c71c4,
+            ],
+        )
+    }
+
+    // Gruvbox dark.
+    pub fn gruvbox() -> Self {
+        Self::from_hex(
+            "gruvbox",
+            [
+                0x282828, 0xebdbb2, 0x928374, 0x504945, 0x665c54, 0xfabd2f, 0x8ec07c, 0xfe8019,
+                0xfb4934, 0xfabd2f, 0xb8bb26, 0x83a598,
+            ],
+        )
+    }
+
+    pub fn dracula() -> Self {
+        Self::from_hex(
+            "dracula",
+            [
+                0x282a36, 0xf8f8f2, 0x6272a4, 0x44475a, 0x6272a4, 0xbd93f9, 0x8be9fd, 0xff79c6,
+                0xff5555, 0xffb86c, 0x50fa7b, 0x8be9fd,
+            ],
+        )
+    }
+
+    // `hex` in the order of `THEME_COLORS`.
+    fn from_hex(name: &str, hex: [u32; 12]) -> Self {
+        let mut theme = Self::new(name);
+        for (color, rgb) in THEME_COLORS.iter().zip(hex) {
+            theme.set(
+                color,
+                Color::Rgb {
+                    r: (rgb >> 16) as u8,
+                    g: (rgb >> 8) as u8,
+                    b: rgb as u8,
+                },
+            );
+        }
+        theme
+    }
+}
+
+/////////////
+//  Roles  //
+/////////////
+
+static ACTIVE: RwLock<Option<Theme>> = RwLock::new(None);
+
+// Makes `theme` the one roles resolve through, for the whole process.
+pub fn set_theme(theme: Theme) {
+    *ACTIVE.write().unwrap_or_else(|p| p.into_inner()) = Some(theme);
+}
+
+// Goes back to the plain ANSI colors roles use without a theme.
+pub fn clear_theme() {
+    *ACTIVE.write().unwrap_or_else(|p| p.into_inner()) = None;
+}
+
+pub fn active_theme() -> Option<Theme> {
+    ACTIVE.read().unwrap_or_else(|p| p.into_inner()).clone()
+}
+
+// What a piece of text means rather than how it looks, e.g.
+// `Foreground::role(Role::Error)`. Resolved through the active theme, or to
+// the usual ANSI colors without one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Role {
+    Error,
+    Warning,
+    Info,
+    Success,
+    Hint,
+}
+
+impl Role {
+    // The theme color this role reads.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Error => "error",
+            Role::Warning => "warning",
+            Role::Info => "info",
+            Role::Success => "success",
+            Role::Hint => "hint",
+        }
+    }
+
+    pub fn default_color(&self) -> Color {
+        let (color, bright) = match self {
+            Role::Error => (Iso::Red, false),
+            Role::Warning => (Iso::Yellow, false),
+            Role::Info => (Iso::Blue, false),
+            Role::Success => (Iso::Green, false),
+            Role::Hint => (Iso::Black, true),
+        };
+        Color::Iso { color, bright }
+    }
+
+    // The color in `theme`. Hint falls back to "muted", which every
+    // built-in theme has.
+    pub fn resolve(&self, theme: &Theme) -> Color {
+        theme
+            .get(self.name())
+            .or_else(|| (*self == Role::Hint).then(|| theme.get("muted")).flatten())
+            .unwrap_or_else(|| self.default_color())
+    }
+
+    // The color from the active theme, downgraded to `color_depth()`.
+    pub fn color(&self) -> Color {
+        let color = match &*ACTIVE.read().unwrap_or_else(|p| p.into_inner()) {
+            Some(theme) => self.resolve(theme),
+            None => self.default_color(),
+        };
+        color.downgrade(color_depth())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{BUILTIN_THEMES, Role, THEME_COLORS, Theme};
+    use crate::color::Color;
+
+    #[test]
+    fn builtins_define_every_color() {
+        for name in BUILTIN_THEMES {
+            let theme = Theme::builtin(name).unwrap();
+            assert!(
+                THEME_COLORS.iter().all(|c| theme.get(c).is_some()),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            Theme::solarized().color("background"),
+            Color::Rgb {
+                r: 0,
+                g: 0x2b,
+                b: 0x36
+            }
+        );
+        assert_eq!(Theme::dracula().color("nonsense"), Color::None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn toml_and_json_round_trip() {
+        let theme = Theme::new("mine")
+            .with("error", Color::Extended(160))
+            .with("muted", Color::None);
+        assert_eq!(Theme::from_toml(&theme.to_toml().unwrap()).unwrap(), theme);
+        assert_eq!(Theme::from_json(&theme.to_json().unwrap()).unwrap(), theme);
+        let parsed = Theme::from_toml("name = \"x\"\n[colors]\nerror = \"#010203\"\n").unwrap();
+        assert_eq!(parsed.color("error"), Color::Rgb { r: 1, g: 2, b: 3 });
+    }
+
+    #[test]
+    fn roles_resolve_through_theme() {
+        let gruvbox = Theme::gruvbox();
+        assert_eq!(Role::Error.resolve(&gruvbox), gruvbox.color("error"));
+        assert_eq!(Role::Hint.resolve(&gruvbox), gruvbox.color("muted"));
+        assert_eq!(
+            Role::Success.resolve(&Theme::new("empty")),
+            Role::Success.default_color()
+        );
+    }
+}
@@ -0,0 +1,437 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Debug layer that logs everything sent to the terminal in readable form,
+// one line per sequence:
+//
+//   CUP(3,4)
+//   SGR(bold, fg=Red)
+//   "hello"
+//
+// Wrap any writer in a `Tracer`, call `Output::set_trace`, or set
+// `MY_TERM_TRACE=<file>` to trace every `Terminal` writing to stdout.
+
+use crate::parser::{Action, Params, Parser};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const TRACE_ENV: &str = "MY_TERM_TRACE";
+
+pub struct Tracer<W: Write> {
+    inner: W,
+    log: Box<dyn Write + Send>,
+    parser: Parser,
+    text: String,
+    // first failure writing the log, see `take_error`
+    error: Option<io::Error>,
+    // tracing stops after that failure, even once the error is taken
+    failed: bool,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new<L: Write + Send + 'static>(inner: W, log: L) -> Self {
+        Self {
+            inner,
+            log: Box::new(log),
+            parser: Parser::new(),
+            text: String::new(),
+            error: None,
+            failed: false,
+        }
+    }
+
+    // Appends to the log file at `path`.
+    pub fn to_file<P: AsRef<Path>>(inner: W, path: P) -> io::Result<Self> {
+        Ok(Self::new(inner, open_log(path)?))
+    }
+
+    // Traces to the file named by `MY_TERM_TRACE`, if it is set. Otherwise
+    // hands `inner` back, with the error if the file couldn't be opened.
+    pub fn from_env(inner: W) -> Result<Self, (W, Option<io::Error>)> {
+        match std::env::var_os(TRACE_ENV).map(open_log) {
+            Some(Ok(log)) => Ok(Self::new(inner, log)),
+            Some(Err(e)) => Err((inner, Some(e))),
+            None => Err((inner, None)),
+        }
+    }
+
+    // The first error writing the log. The output itself never fails
+    // because of the log; tracing just stops after it.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(mut self) -> W {
+        if !self.failed {
+            let _ = self.log_text();
+            let _ = self.log.flush();
+        }
+        let Tracer { inner, .. } = self;
+        inner
+    }
+
+    fn fail(&mut self, e: io::Error) {
+        self.failed = true;
+        self.error = Some(e);
+    }
+
+    fn log_text(&mut self) -> io::Result<()> {
+        if self.text.is_empty() {
+            return Ok(());
+        }
+        let text = std::mem::take(&mut self.text);
+        writeln!(self.log, "{:?}", text)
+    }
+
+    fn trace(&mut self, bytes: &[u8]) -> io::Result<()> {
+        for action in self.parser.feed(bytes) {
+            match action {
+                Action::Print(c) => self.text.push(c),
+                Action::DcsPut(_) => {}
+                action => {
+                    self.log_text()?;
+                    writeln!(self.log, "{}", describe(&action))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn open_log<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl<W: Write> Write for Tracer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if !self.failed
+            && let Err(e) = self.trace(&buf[..n])
+        {
+            self.fail(e);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        if !self.failed
+            && let Err(e) = self.log_text().and_then(|_| self.log.flush())
+        {
+            self.fail(e);
+        }
+        Ok(())
+    }
+}
+
+//////////////////
+//  Describing  //
+//////////////////
+
+// Human readable name for a parsed sequence. Sequences without a short name
+// are shown the way they were written, e.g. `CSI ? 1049 h` → `DECSET(1049)`
+// but `CSI > 4 ; 1 m` → `CSI(>4;1m)`.
+pub fn describe(action: &Action) -> String {
+    match action {
+        Action::Print(c) => format!("{:?}", c),
+        Action::Execute(b) => control_name(*b),
+        Action::Csi {
+            params,
+            intermediates,
+            final_byte,
+            ..
+        } => describe_csi(params, intermediates, *final_byte),
+        Action::Esc {
+            intermediates,
+            final_byte,
+            ..
+        } => match (intermediates.as_slice(), final_byte) {
+            ([], b'7') => "DECSC".into(),
+            ([], b'8') => "DECRC".into(),
+            ([], b'c') => "RIS".into(),
+            ([], b'D') => "IND".into(),
+            ([], b'E') => "NEL".into(),
+            ([], b'H') => "HTS".into(),
+            ([], b'M') => "RI".into(),
+            ([], b'=') => "DECKPAM".into(),
+            ([], b'>') => "DECKPNM".into(),
+            ([b'#'], b'8') => "DECALN".into(),
+            (i, f) => format!("ESC({}{})", String::from_utf8_lossy(i), *f as char),
+        },
+        Action::Osc { params, .. } => {
+            let parts: Vec<_> = params.iter().map(|p| String::from_utf8_lossy(p)).collect();
+            match parts.split_first() {
+                Some((code, rest)) => format!("OSC {}({:?})", code, rest.join(";")),
+                None => "OSC".into(),
+            }
+        }
+        Action::DcsHook {
+            params,
+            intermediates,
+            final_byte,
+            ..
+        } => format!("DCS({})", raw(params, intermediates, *final_byte)),
+        Action::DcsPut(b) => format!("{:?}", *b as char),
+        Action::DcsUnhook => "ST".into(),
+    }
+}
+
+fn control_name(b: u8) -> String {
+    match b {
+        0x07 => "BEL".into(),
+        0x08 => "BS".into(),
+        0x09 => "HT".into(),
+        0x0a => "LF".into(),
+        0x0b => "VT".into(),
+        0x0c => "FF".into(),
+        0x0d => "CR".into(),
+        0x0e => "SO".into(),
+        0x0f => "SI".into(),
+        b => format!("C0({:#04x})", b),
+    }
+}
+
+fn raw(params: &Params, intermediates: &[u8], final_byte: u8) -> String {
+    let (private, rest): (Vec<u8>, Vec<u8>) = intermediates
+        .iter()
+        .partition(|b| (0x3c..=0x3f).contains(*b));
+    let params = params
+        .iter()
+        .map(|p| p.iter().map(u16::to_string).collect::<Vec<_>>().join(":"))
+        .collect::<Vec<_>>()
+        .join(";");
+    format!(
+        "{}{}{}{}",
+        String::from_utf8_lossy(&private),
+        params,
+        String::from_utf8_lossy(&rest),
+        final_byte as char
+    )
+}
+
+fn describe_csi(params: &Params, intermediates: &[u8], final_byte: u8) -> String {
+    let n = |i| params.get_or(i, 1);
+    let list = || {
+        params
+            .iter()
+            .map(|p| p.first().copied().unwrap_or(0).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    match (intermediates, final_byte) {
+        ([], b'H' | b'f') => format!("CUP({},{})", n(0), n(1)),
+        ([], b'A') => format!("CUU({})", n(0)),
+        ([], b'B') => format!("CUD({})", n(0)),
+        ([], b'C') => format!("CUF({})", n(0)),
+        ([], b'D') => format!("CUB({})", n(0)),
+        ([], b'E') => format!("CNL({})", n(0)),
+        ([], b'F') => format!("CPL({})", n(0)),
+        ([], b'G') => format!("CHA({})", n(0)),
+        ([], b'd') => format!("VPA({})", n(0)),
+        ([], b'@') => format!("ICH({})", n(0)),
+        ([], b'P') => format!("DCH({})", n(0)),
+        ([], b'X') => format!("ECH({})", n(0)),
+        ([], b'L') => format!("IL({})", n(0)),
+        ([], b'M') => format!("DL({})", n(0)),
+        ([], b'S') => format!("SU({})", n(0)),
+        ([], b'T') => format!("SD({})", n(0)),
+        ([], b'b') => format!("REP({})", n(0)),
+        ([], b'J') => format!("ED({})", params.get(0).unwrap_or(0)),
+        ([], b'K') => format!("EL({})", params.get(0).unwrap_or(0)),
+        ([], b'g') => format!("TBC({})", params.get(0).unwrap_or(0)),
+        ([], b'r') if params.is_empty() => "DECSTBM".into(),
+        ([], b'r') => format!("DECSTBM({},{})", n(0), params.get(1).unwrap_or(0)),
+        ([], b's') => "SCOSC".into(),
+        ([], b'u') => "SCORC".into(),
+        ([], b'n') => format!("DSR({})", list()),
+        ([], b'c') => "DA".into(),
+        ([], b't') => format!("XTWINOPS({})", list()),
+        ([], b'h') => format!("SM({})", list()),
+        ([], b'l') => format!("RM({})", list()),
+        ([], b'm') => format!("SGR({})", sgr(params)),
+        ([b'?'], b'h') => format!("DECSET({})", list()),
+        ([b'?'], b'l') => format!("DECRST({})", list()),
+        ([b' '], b'q') => format!("DECSCUSR({})", params.get(0).unwrap_or(0)),
+        _ => format!("CSI({})", raw(params, intermediates, final_byte)),
+    }
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "Black", "Red", "Green", "Yellow", "Blue", "Magenta", "Cyan", "White",
+];
+
+fn sgr(params: &Params) -> String {
+    let groups = params.groups();
+    if groups.is_empty() {
+        return "reset".into();
+    }
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < groups.len() {
+        let group = &groups[i];
+        let code = group.first().copied().unwrap_or(0);
+        i += 1;
+        let attr = match code {
+            0 => "reset".to_string(),
+            1 => "bold".into(),
+            2 => "dim".into(),
+            3 => "italic".into(),
+            4 => match group.get(1) {
+                Some(style) => format!("underline={}", style),
+                None => "underline".into(),
+            },
+            5 => "blink".into(),
+            7 => "reverse".into(),
+            8 => "hidden".into(),
+            9 => "strike".into(),
+            21 => "double-underline".into(),
+            22 => "-bold/dim".into(),
+            23 => "-italic".into(),
+            24 => "-underline".into(),
+            25 => "-blink".into(),
+            27 => "-reverse".into(),
+            28 => "-hidden".into(),
+            29 => "-strike".into(),
+            30..=37 => format!("fg={}", COLOR_NAMES[code as usize - 30]),
+            40..=47 => format!("bg={}", COLOR_NAMES[code as usize - 40]),
+            90..=97 => format!("fg=Bright{}", COLOR_NAMES[code as usize - 90]),
+            100..=107 => format!("bg=Bright{}", COLOR_NAMES[code as usize - 100]),
+            39 => "fg=default".into(),
+            49 => "bg=default".into(),
+            59 => "ul=default".into(),
+            38 | 48 | 58 => {
+                let target = match code {
+                    38 => "fg",
+                    48 => "bg",
+                    _ => "ul",
+                };
+                // `38:5:n` keeps the color in one group, `38;5;n` spreads it
+                // over the following parameters
+                let args: Vec<u16> = if group.len() > 1 {
+                    group[1..].to_vec()
+                } else {
+                    let len = match groups.get(i).and_then(|g| g.first()) {
+                        Some(5) => 2,
+                        Some(2) => 4,
+                        _ => 1,
+                    };
+                    let args = groups[i..(i + len).min(groups.len())]
+                        .iter()
+                        .map(|g| g.first().copied().unwrap_or(0))
+                        .collect();
+                    i += len;
+                    args
+                };
+                match args.as_slice() {
+                    [5, n] => format!("{}={}", target, n),
+                    // the colon form may carry a color space id before r;g;b
+                    [2, r, g, b] | [2, _, r, g, b, ..] => {
+                        format!("{}=#{:02x}{:02x}{:02x}", target, r, g, b)
+                    }
+                    _ => format!("{}=?", target),
+                }
+            }
+            n => n.to_string(),
+        };
+        out.push(attr);
+    }
+    out.join(", ")
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Tracer;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn traces_sequences() {
+        let log = Capture::default();
+        let mut tracer = Tracer::new(Vec::new(), log.clone());
+        let bytes: &[u8] = b"\x1b[3;4H\x1b[1;31mhi\x1b[38;2;255;0;16m\x1b[48:5:200m\x1b[0m\r\n\
+            \x1b[?1049h\x1b]0;title\x07\x1b[2J\x1b[>4;1m";
+        tracer.write_all(bytes).unwrap();
+        tracer.flush().unwrap();
+        assert_eq!(tracer.into_inner(), bytes);
+
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "CUP(3,4)",
+                "SGR(bold, fg=Red)",
+                "\"hi\"",
+                "SGR(fg=#ff0010)",
+                "SGR(bg=200)",
+                "SGR(reset)",
+                "CR",
+                "LF",
+                "DECSET(1049)",
+                "OSC 0(\"title\")",
+                "ED(2)",
+                "CSI(>4;1m)",
+            ]
+        );
+    }
+
+    struct Full;
+
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::StorageFull.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn log_errors_leave_the_output_alone() {
+        let mut tracer = Tracer::new(Vec::new(), Full);
+        tracer.write_all(b"\x1b[Hhello").unwrap();
+        tracer.flush().unwrap();
+        assert_eq!(
+            tracer.take_error().map(|e| e.kind()),
+            Some(io::ErrorKind::StorageFull)
+        );
+        // no more tracing, and nothing written twice
+        tracer.write_all(b"\x1b[2J").unwrap();
+        assert!(tracer.take_error().is_none());
+        assert_eq!(tracer.into_inner(), b"\x1b[Hhello\x1b[2J");
+    }
+}
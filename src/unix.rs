@@ -16,13 +16,38 @@
 
 #![allow(dead_code, invalid_value)]
 
+use crate::backend::Backend;
+use crate::bell::{BellStyle, VISUAL_BELL_DURATION, VisualBell, ring, visual_bell};
+use crate::color::ColorChoice;
+use crate::cursor;
+use crate::draw::print_at;
+use crate::erase::{Clear, ClearType, Purge};
+use crate::event::Event;
+use crate::input::InputParser;
+use crate::mode::{
+    AltScreenMethod, ApplicationKeypad, ColumnMode, DecMode, DisableMouseCapture,
+    EnterAlternateScreenWith, KeyboardFlags, LeaveAlternateScreen, LeaveAlternateScreenWith,
+    Modes, MouseTracking, NumericKeypad, PopKeyboardFlags, PushKeyboardFlags, ResetMode,
+    RestoreDefaults, SetColumnMode, SetMode,
+};
+use crate::output::{Output, OutputLock, SharedWriter, Target};
+use crate::pointer::{PointerShape, ResetPointerShape, SetPointerShape};
+use crate::reset::{HardReset, SoftReset};
+use crate::style::Style;
+use crate::tab::{ClearAllTabStops, ClearTabStop, SetTabStop, TabBackward, TabForward};
+use crate::{execute, queue};
 use libc::{
-    NCCS, TIOCGWINSZ, c_uchar, c_uint, ioctl, tcflag_t, tcgetattr, tcsetattr, termios, winsize,
+    NCCS, TIOCGWINSZ, c_int, c_uchar, c_uint, ioctl, tcflag_t, tcgetattr, tcsetattr, termios,
+    winsize,
 };
-use std::io::stdin;
-use std::{mem, os::fd::AsRawFd};
-
-pub const STDOUT_BUFFER_SIZE: usize = 2048; // bytes
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Write, stdin};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, Once, OnceLock};
+use std::time::{Duration, Instant};
+use std::{mem, os::fd::AsRawFd, ptr};
 
 pub type Lflag = tcflag_t;
 pub type Iflag = tcflag_t;
@@ -58,8 +83,20 @@ impl TermInfo {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
-#[repr(C)]
+impl Default for TermInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Terminal settings plus the writer escape sequences are sent to.
+//
+// Dropping the `Terminal` returned by `set_raw` or `set_term` puts the
+// terminal back the way the struct describes it: the saved termios settings
+// are reapplied, the alternate screen is left and the cursor shown if they
+// were changed through this value. Use `leak()` to keep the current state
+// instead. Other values only do so after `restore_on_drop()`.
+#[derive(Debug)]
 pub struct Terminal {
     pub term_name: String, // $TERM var
     pub info: TermInfo,
@@ -72,13 +109,80 @@ pub struct Terminal {
     c_ispeed: c_uint,
     c_ospeed: c_uint,
     alt_buffer: bool,
+    alt_method: AltScreenMethod,
     cursor_visable: bool,
+    out: Output,
+    // tcgetattr succeeded, so the termios fields are worth restoring.
+    termios_saved: bool,
+    restore_on_drop: bool,
+    // `read_input` returns `WouldBlock` rather than waiting
+    nonblocking: bool,
+    modes: Modes,
+    bell_style: BellStyle,
+    // a visual bell waiting for `update_bell` to end it
+    bell: Option<VisualBell>,
+    // set once `shared_writer` moved the output behind a mutex
+    shared: Option<SharedWriter>,
+}
+
+// Everything `Terminal` knows about the terminal, in a form that can be
+// saved and loaded, similar to `stty -g`. A wrapper can snapshot the state
+// before running a child and put it back exactly afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TermState {
+    pub term_name: String,
+    pub c_iflags: Iflag,
+    pub c_oflags: Oflag,
+    pub c_cflags: Cflag,
+    pub c_lflags: Lflag,
+    pub c_cc: SCharMap,
+    pub c_line: c_uchar,
+    pub c_ispeed: c_uint,
+    pub c_ospeed: c_uint,
+    pub alt_buffer: bool,
+    pub cursor_visable: bool,
+}
+
+impl TermState {
+    #[cfg(feature = "serde")]
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::to_string(self)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_ron(s: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(s)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::error::EncodeError> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard()).map(|(s, _)| s)
+    }
+
+    pub fn to_termios(&self) -> termios {
+        let mut t: termios = unsafe { mem::zeroed() };
+        t.c_iflag = self.c_iflags;
+        t.c_oflag = self.c_oflags;
+        t.c_cflag = self.c_cflags;
+        t.c_lflag = self.c_lflags;
+        t.c_cc = self.c_cc;
+        t.c_line = self.c_line;
+        t.c_ispeed = self.c_ispeed;
+        t.c_ospeed = self.c_ospeed;
+        t
+    }
 }
 
 impl Default for Terminal {
     fn default() -> Self {
         unsafe {
-            let mut t: termios = mem::MaybeUninit::uninit().assume_init();
+            let mut t: termios = mem::zeroed();
             let res = tcgetattr(stdin().as_raw_fd(), &mut t);
             let name = match std::env::var("TERM") {
                 Ok(t) => t,
@@ -97,7 +201,16 @@ impl Default for Terminal {
                     c_ispeed: t.c_ispeed,
                     c_ospeed: t.c_ospeed,
                     alt_buffer: false,
+                    alt_method: AltScreenMethod::default(),
                     cursor_visable: true,
+                    out: Output::stdout(),
+                    termios_saved: true,
+                    restore_on_drop: false,
+                    nonblocking: false,
+                    modes: Modes::new(),
+                    bell_style: BellStyle::default(),
+                    bell: None,
+                    shared: None,
                 }
             } else {
                 Self {
@@ -112,14 +225,243 @@ impl Default for Terminal {
                     c_ispeed: 0,
                     c_ospeed: 0,
                     alt_buffer: false,
+                    alt_method: AltScreenMethod::default(),
                     cursor_visable: true,
+                    out: Output::stdout(),
+                    termios_saved: false,
+                    restore_on_drop: false,
+                    nonblocking: false,
+                    modes: Modes::new(),
+                    bell_style: BellStyle::default(),
+                    bell: None,
+                    shared: None,
                 }
             }
         }
     }
 }
 
+// A clone writes to the same standard stream or shared writer as the
+// original (other sinks can't be cloned, so it falls back to stdout), shares
+// its mode record and never restores anything on drop.
+impl Clone for Terminal {
+    fn clone(&self) -> Self {
+        let out = match &self.shared {
+            Some(shared) => {
+                let mut out = Output::with_capacity(0, shared.clone());
+                out.set_color_choice(ColorChoice::Always);
+                out
+            }
+            None => self
+                .out
+                .target()
+                .and_then(|target| Output::to(target).ok())
+                .unwrap_or_else(Output::stdout),
+        };
+        Self {
+            term_name: self.term_name.clone(),
+            info: self.info.clone(),
+            c_iflags: self.c_iflags,
+            c_oflags: self.c_oflags,
+            c_cflags: self.c_cflags,
+            c_lflags: self.c_lflags,
+            c_cc: self.c_cc,
+            c_line: self.c_line,
+            c_ispeed: self.c_ispeed,
+            c_ospeed: self.c_ospeed,
+            alt_buffer: self.alt_buffer,
+            alt_method: self.alt_method,
+            cursor_visable: self.cursor_visable,
+            out,
+            termios_saved: self.termios_saved,
+            restore_on_drop: false,
+            nonblocking: self.nonblocking,
+            modes: self.modes.clone(),
+            bell_style: self.bell_style,
+            bell: None,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+// Terminals are equal when they describe the same settings, wherever their
+// output goes.
+impl PartialEq for Terminal {
+    fn eq(&self, other: &Self) -> bool {
+        self.info == other.info
+            && self.alt_method == other.alt_method
+            && self.state() == other.state()
+    }
+}
+
+impl Eq for Terminal {}
+
 impl Terminal {
+    // Current terminal settings, with output going to `sink` instead of stdout.
+    pub fn with_writer<W: Write + Send + 'static>(sink: W) -> Self {
+        let mut t = Self::default();
+        t.set_writer(sink);
+        t
+    }
+
+    pub fn set_writer<W: Write + Send + 'static>(&mut self, sink: W) {
+        self.out = Output::new(sink);
+        self.shared = None;
+    }
+
+    // Current terminal settings, drawing on stderr or the tty so stdout
+    // stays free for data:
+    //
+    //   let mut term = Terminal::with_target(Target::Tty)?;
+    //   queue!(term, MoveToColumn(0), Print(progress))?;
+    //   println!("{}", result);
+    pub fn with_target(target: Target) -> io::Result<Self> {
+        let mut t = Self::default();
+        t.set_target(target)?;
+        Ok(t)
+    }
+
+    pub fn set_target(&mut self, target: Target) -> io::Result<()> {
+        self.out = Output::to(target)?;
+        self.shared = None;
+        Ok(())
+    }
+
+    // A handle other threads can write through without splitting the
+    // terminal's escape sequences (or each other's). The terminal keeps
+    // writing to the same place, unbuffered, with the buffering moved into
+    // the shared output.
+    pub fn shared_writer(&mut self) -> SharedWriter {
+        if let Some(shared) = &self.shared {
+            return shared.clone();
+        }
+        let out = std::mem::replace(&mut self.out, Output::with_capacity(0, io::sink()));
+        let shared = out.into_shared();
+        self.out = Output::with_capacity(0, shared.clone());
+        self.out.set_color_choice(ColorChoice::Always);
+        self.shared = Some(shared.clone());
+        shared
+    }
+
+    pub fn writer(&mut self) -> &mut Output {
+        &mut self.out
+    }
+
+    // Holds back all output, however much, until `end_frame` writes and
+    // flushes it at once. See `Output::begin_frame`.
+    pub fn begin_frame(&mut self, synchronized: bool) -> io::Result<()> {
+        self.out.begin_frame(synchronized)
+    }
+
+    pub fn end_frame(&mut self) -> io::Result<()> {
+        self.out.end_frame()
+    }
+
+    // Asks the terminal where the cursor is, like `query_cursor_position`,
+    // with the request going to this terminal's output (stderr, the tty).
+    // The request is sent even when escapes are stripped or a frame is open.
+    pub fn cursor_position(&mut self) -> io::Result<(u16, u16)> {
+        let send = |request: &[u8]| match &self.shared {
+            Some(shared) => shared.write_request(request),
+            None => self.out.write_request(request),
+        };
+        query_on(send, b"\x1b[6n", QUERY_TIMEOUT, crate::input::cursor_report)
+    }
+
+    // Handle for drawing a frame: holds the stdout (or stderr) lock so other
+    // threads' prints can't land in the middle, and flushes when dropped.
+    //
+    //   let mut frame = term.lock();
+    //   queue!(frame, MoveTo(0, 0), Print(header))?;
+    //   queue!(frame, MoveTo(0, 1), Print(body))?;
+    pub fn lock(&mut self) -> OutputLock<'_> {
+        match &self.shared {
+            Some(shared) => shared.lock(),
+            None => self.out.lock(),
+        }
+    }
+
+    pub fn state(&self) -> TermState {
+        TermState {
+            term_name: self.term_name.clone(),
+            c_iflags: self.c_iflags,
+            c_oflags: self.c_oflags,
+            c_cflags: self.c_cflags,
+            c_lflags: self.c_lflags,
+            c_cc: self.c_cc,
+            c_line: self.c_line,
+            c_ispeed: self.c_ispeed,
+            c_ospeed: self.c_ospeed,
+            alt_buffer: self.alt_buffer,
+            cursor_visable: self.cursor_visable,
+        }
+    }
+
+    // Applies a saved state: termios settings go to the tty and the alternate
+    // screen and cursor visibility are switched to match.
+    pub fn restore(&mut self, state: &TermState) -> io::Result<()> {
+        self.term_name = state.term_name.clone();
+        self.c_iflags = state.c_iflags;
+        self.c_oflags = state.c_oflags;
+        self.c_cflags = state.c_cflags;
+        self.c_lflags = state.c_lflags;
+        self.c_cc = state.c_cc;
+        self.c_line = state.c_line;
+        self.c_ispeed = state.c_ispeed;
+        self.c_ospeed = state.c_ospeed;
+        self.termios_saved = true;
+        if state.alt_buffer {
+            self.enter_alt_screen()?;
+        } else {
+            self.leave_alt_screen()?;
+        }
+        if state.cursor_visable != self.cursor_visable {
+            if state.cursor_visable {
+                queue!(self.out, cursor::Show)?;
+            } else {
+                queue!(self.out, cursor::Hide)?;
+            }
+            self.cursor_visable = state.cursor_visable;
+            self.modes
+                .record(DecMode::CursorVisible, state.cursor_visable);
+        }
+        self.out.flush()?;
+        if unsafe { tcsetattr(stdin().as_raw_fd(), TCSADRAIN, &self.cast_to_termios()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // Makes `read_input` return `WouldBlock` instead of waiting for input.
+    // Stdin's O_NONBLOCK flag is left alone: stdout usually shares its open
+    // file, and would start failing writes with EAGAIN.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    // Raw bytes from stdin. Fails with `WouldBlock` when non-blocking and
+    // nothing is available.
+    pub fn read_input(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_input_from(libc::STDIN_FILENO, buf, self.nonblocking)
+    }
+
+    // Puts the settings held by this value back when it is dropped: the
+    // termios attributes, and the alternate screen, cursor and modes
+    // switched on through it are switched off again. Already the case for
+    // the values `set_raw` and `set_term` return.
+    pub fn restore_on_drop(&mut self) {
+        self.restore_on_drop = true;
+    }
+
+    // Don't restore anything when this value is dropped.
+    pub fn leak(&mut self) {
+        self.restore_on_drop = false;
+    }
+
     pub fn cast_to_termios(&self) -> termios {
         termios {
             c_cc: self.c_cc,
@@ -134,32 +476,393 @@ impl Terminal {
     }
 
     pub fn cursor_visable(&self) -> bool {
-        return self.cursor_visable;
+        self.cursor_visable
     }
 
     pub fn alt_buffer(&self) -> bool {
-        return self.alt_buffer;
+        self.alt_buffer
     }
 
-    pub fn toggle_cursor_visable(&mut self) -> bool {
+    // Shows the cursor unless it already is. Recorded, so a cursor hidden
+    // through `hide_cursor` comes back when the terminal is restored.
+    pub fn show_cursor(&mut self) -> io::Result<()> {
+        if !self.cursor_visable {
+            self.set_mode(DecMode::CursorVisible)?;
+        }
+        Ok(())
+    }
+
+    pub fn hide_cursor(&mut self) -> io::Result<()> {
         if self.cursor_visable {
-            print!("\x1b[?25l");
-        } else {
-            print!("\x1b[?25h");
+            self.reset_mode(DecMode::CursorVisible)?;
         }
-        self.cursor_visable = !self.cursor_visable;
-        return self.cursor_visable;
+        Ok(())
+    }
+
+    pub fn toggle_cursor_visable(&mut self) -> bool {
+        let _ = if self.cursor_visable {
+            self.hide_cursor()
+        } else {
+            self.show_cursor()
+        };
+        self.cursor_visable
     }
 
     pub fn toggle_alt_buffer(&mut self) -> bool {
+        let _ = if self.alt_buffer {
+            self.leave_alt_screen()
+        } else {
+            self.enter_alt_screen()
+        };
+        self.alt_buffer
+    }
+
+    // Switches to a cleared alternate screen, saving the cursor position,
+    // unless already there. Recorded, so restoring the terminal goes back to
+    // the main screen.
+    pub fn enter_alt_screen(&mut self) -> io::Result<()> {
+        if !self.alt_buffer {
+            execute!(self.out, EnterAlternateScreenWith(self.alt_method))?;
+            self.alt_buffer = true;
+            self.modes.record(self.alt_method.mode(), true);
+        }
+        Ok(())
+    }
+
+    // Back to the main screen, with the cursor where it was before entering.
+    pub fn leave_alt_screen(&mut self) -> io::Result<()> {
         if self.alt_buffer {
-            //ESC[?1049l
+            execute!(self.out, LeaveAlternateScreenWith(self.alt_method))?;
+            self.alt_buffer = false;
+            self.modes.record(self.alt_method.mode(), false);
+        }
+        Ok(())
+    }
+
+    // For terminals without 1049. Change it while on the main screen.
+    pub fn set_alt_screen_method(&mut self, method: AltScreenMethod) {
+        self.alt_method = method;
+    }
+
+    pub fn alt_screen_method(&self) -> AltScreenMethod {
+        self.alt_method
+    }
+
+    /////////////
+    //  Modes  //
+    /////////////
+
+    // Sets a DEC private mode and records it, so `restore_modes` (and
+    // dropping the terminal) turns it off again.
+    pub fn set_mode(&mut self, mode: DecMode) -> io::Result<()> {
+        execute!(self.out, SetMode(mode))?;
+        self.track(mode, true);
+        Ok(())
+    }
+
+    pub fn reset_mode(&mut self, mode: DecMode) -> io::Result<()> {
+        execute!(self.out, ResetMode(mode))?;
+        self.track(mode, false);
+        Ok(())
+    }
+
+    fn track(&mut self, mode: DecMode, set: bool) {
+        match mode {
+            DecMode::AltScreen => self.alt_buffer = set,
+            DecMode::CursorVisible => self.cursor_visable = set,
+            _ => {}
+        }
+        self.modes.record(mode, set);
+    }
+
+    // DECCKM: cursor keys send `ESC O A` rather than `ESC [ A`. The event
+    // reader understands both, so this only matters to programs expecting one.
+    pub fn set_application_cursor_keys(&mut self, on: bool) -> io::Result<()> {
+        if on {
+            self.set_mode(DecMode::ApplicationCursorKeys)
+        } else {
+            self.reset_mode(DecMode::ApplicationCursorKeys)
+        }
+    }
+
+    pub fn set_application_keypad(&mut self, on: bool) -> io::Result<()> {
+        if on {
+            execute!(self.out, ApplicationKeypad)?;
+        } else {
+            execute!(self.out, NumericKeypad)?;
+        }
+        self.modes.record_keypad(on);
+        Ok(())
+    }
+
+    // Changes the mouse pointer over the terminal, e.g. to `Pointer` over a
+    // clickable region. `Default` resets it; so does `restore_modes`.
+    pub fn set_pointer_shape(&mut self, shape: PointerShape) -> io::Result<()> {
+        if shape == PointerShape::Default {
+            execute!(self.out, ResetPointerShape)?;
         } else {
-            print!("\x1b[?1049h\x1b[2J");
-            //ESC[?1049h
+            execute!(self.out, SetPointerShape(shape))?;
+        }
+        self.modes
+            .record_pointer_shape(shape != PointerShape::Default);
+        Ok(())
+    }
+
+    pub fn enable_mouse_capture(&mut self, tracking: MouseTracking) -> io::Result<()> {
+        self.set_mode(tracking.mode())?;
+        self.set_mode(DecMode::MouseSgr)
+    }
+
+    pub fn disable_mouse_capture(&mut self) -> io::Result<()> {
+        execute!(self.out, DisableMouseCapture)?;
+        for mode in [
+            DecMode::MouseMotion,
+            DecMode::MouseDrag,
+            DecMode::MouseClicks,
+            DecMode::MouseSgr,
+        ] {
+            self.modes.record(mode, false);
+        }
+        Ok(())
+    }
+
+    pub fn push_keyboard_flags(&mut self, flags: KeyboardFlags) -> io::Result<()> {
+        execute!(self.out, PushKeyboardFlags(flags))?;
+        self.modes.record_keyboard_push();
+        Ok(())
+    }
+
+    pub fn pop_keyboard_flags(&mut self) -> io::Result<()> {
+        execute!(self.out, PopKeyboardFlags)?;
+        self.modes.record_keyboard_pop();
+        Ok(())
+    }
+
+    // Modes changed through this terminal.
+    pub fn modes(&self) -> &Modes {
+        &self.modes
+    }
+
+    // Puts every recorded mode back to its default.
+    pub fn restore_modes(&mut self) -> io::Result<()> {
+        // left first, so the cursor comes back too
+        self.leave_alt_screen()?;
+        if self.modes.is_changed(DecMode::CursorVisible) {
+            self.cursor_visable = true;
+        }
+        self.modes.restore(&mut self.out)?;
+        self.out.flush()
+    }
+
+    // Switches to 80 or 132 columns (DECCOLM) and waits up to `timeout` for
+    // the window to follow. Returns the size afterwards; if it didn't change,
+    // the terminal doesn't support the switch. The screen is cleared either way.
+    pub fn set_column_mode(
+        &mut self,
+        mode: ColumnMode,
+        timeout: Duration,
+    ) -> io::Result<(u32, u32)> {
+        let before = self.size()?;
+        execute!(self.out, SetColumnMode(mode))?;
+        self.modes.record(DecMode::Allow132Columns, true);
+        self.modes
+            .record(DecMode::Columns132, mode == ColumnMode::Columns132);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let size = self.size()?;
+            if size.0 == mode.columns() as u32 || size != before || Instant::now() >= deadline {
+                return Ok(size);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // Rings the bell the way `set_bell_style` chose, audible by default. A
+    // visual bell doesn't wait: the screen stays inverted until `update_bell`
+    // ends it, so an event loop should call that after each wait.
+    pub fn bell(&mut self) -> io::Result<()> {
+        self.end_bell()?;
+        self.bell = ring(&mut self.out, self.bell_style, &self.modes)?;
+        Ok(())
+    }
+
+    pub fn visual_bell(&mut self) -> io::Result<()> {
+        self.end_bell()?;
+        let bell = visual_bell(&mut self.out, &self.modes, VISUAL_BELL_DURATION)?;
+        self.bell = Some(bell);
+        Ok(())
+    }
+
+    // A visual bell that blocks for its duration and ends itself, for
+    // programs without a loop calling `update_bell`.
+    pub fn flash(&mut self) -> io::Result<()> {
+        self.visual_bell()?;
+        std::thread::sleep(VISUAL_BELL_DURATION);
+        self.end_bell()
+    }
+
+    // Ends the visual bell once its time is up. Returns how long the one
+    // still showing has left, to use as the timeout of the next wait.
+    pub fn update_bell(&mut self) -> io::Result<Option<Duration>> {
+        match self.bell {
+            Some(bell) if bell.is_due() => self.end_bell().map(|_| None),
+            bell => Ok(bell.map(|b| b.time_left())),
+        }
+    }
+
+    fn end_bell(&mut self) -> io::Result<()> {
+        match self.bell.take() {
+            Some(bell) => bell.end(&mut self.out),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_bell_style(&mut self, style: BellStyle) {
+        self.bell_style = style;
+    }
+
+    // Multi-line text at (col, row), see `draw::print_at`.
+    pub fn print_at(&mut self, col: u16, row: u16, text: &str) -> io::Result<()> {
+        print_at(self, col, row, text, Style::new())?;
+        self.out.flush()
+    }
+
+    pub fn clear(&mut self, kind: ClearType) -> io::Result<()> {
+        execute!(self.out, Clear(kind))
+    }
+
+    // Empties the screen and the scrollback.
+    pub fn purge(&mut self) -> io::Result<()> {
+        execute!(self.out, Purge)
+    }
+
+    // Installs a panic hook that, before the panic message is printed,
+    // restores the termios settings held by `self` and writes
+    // `RestoreDefaults` (leaving the alternate screen, showing the cursor and
+    // disabling mouse reporting) to the terminal's output target, after
+    // undoing the modes recorded in `modes()`, including ones set after the
+    // hook was installed. Outputs other than stdout and stderr get it on the
+    // tty. The previous hook still runs afterwards. Call it on the
+    // `Terminal` returned by `set_raw`.
+    pub fn install_panic_hook(&self) {
+        let original = self.cast_to_termios();
+        let modes = self.modes.clone();
+        let target = match &self.shared {
+            Some(shared) => shared.target(),
+            None => self.out.target(),
+        }
+        .unwrap_or(Target::Tty);
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            unsafe {
+                tcsetattr(stdin().as_raw_fd(), TCSANOW, &original);
+            }
+            // unbuffered and unfiltered, whatever the color choice
+            let out: Option<Box<dyn Write>> = match target {
+                Target::Stdout => Some(Box::new(io::stdout())),
+                Target::Stderr => Some(Box::new(io::stderr())),
+                Target::Tty => open_tty().ok().map(|(_, tty)| Box::new(tty) as Box<dyn Write>),
+            };
+            if let Some(mut out) = out {
+                let _ = modes.restore(&mut out);
+                let _ = execute!(out, RestoreDefaults);
+            }
+            previous(info);
+        }));
+    }
+
+    pub fn soft_reset(&mut self) -> io::Result<()> {
+        execute!(self.out, SoftReset)
+    }
+
+    // Also clears the screen, see `reset::HardReset`.
+    pub fn hard_reset(&mut self) -> io::Result<()> {
+        self.alt_buffer = false;
+        self.cursor_visable = true;
+        self.modes.clear();
+        execute!(self.out, HardReset)
+    }
+
+    // Gets a messed up terminal back to a usable state: soft reset, main
+    // screen, visible cursor, and cooked (canonical, echoing) input like
+    // `stty sane`.
+    pub fn sane(&mut self) -> io::Result<()> {
+        execute!(self.out, SoftReset, LeaveAlternateScreen, cursor::Show)?;
+        self.alt_buffer = false;
+        self.cursor_visable = true;
+        self.modes.record(DecMode::AltScreen, false);
+        self.modes.record(DecMode::CursorVisible, true);
+        unsafe {
+            let mut t: termios = mem::zeroed();
+            if tcgetattr(stdin().as_raw_fd(), &mut t) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            t.c_lflag |= ICANON | ECHO | ECHOE | ECHOK | ISIG | IEXTEN;
+            t.c_iflag |= ICRNL | IXON;
+            t.c_oflag |= OPOST | ONLCR;
+            if tcsetattr(stdin().as_raw_fd(), TCSANOW, &t) != 0 {
+                return Err(io::Error::last_os_error());
+            }
         }
-        self.alt_buffer = !self.alt_buffer;
-        return self.alt_buffer;
+        Ok(())
+    }
+
+    pub fn set_tab_stop(&mut self) -> io::Result<()> {
+        execute!(self.out, SetTabStop)
+    }
+
+    pub fn clear_tab_stop(&mut self) -> io::Result<()> {
+        execute!(self.out, ClearTabStop)
+    }
+
+    pub fn clear_all_tab_stops(&mut self) -> io::Result<()> {
+        execute!(self.out, ClearAllTabStops)
+    }
+
+    pub fn tab_forward(&mut self, n: u16) -> io::Result<()> {
+        execute!(self.out, TabForward(n))
+    }
+
+    pub fn tab_backward(&mut self, n: u16) -> io::Result<()> {
+        execute!(self.out, TabBackward(n))
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.end_bell();
+        if !self.restore_on_drop {
+            return;
+        }
+        let _ = self.restore_modes();
+        if self.alt_buffer {
+            let _ = queue!(self.out, LeaveAlternateScreenWith(self.alt_method));
+        }
+        if !self.cursor_visable {
+            let _ = queue!(self.out, cursor::Show);
+        }
+        let _ = self.out.flush();
+        if self.termios_saved {
+            unsafe {
+                tcsetattr(stdin().as_raw_fd(), TCSADRAIN, &self.cast_to_termios());
+            }
+        }
+    }
+}
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Backend for Terminal {
+    fn size(&self) -> io::Result<(u32, u32)> {
+        term_size().ok_or_else(|| io::Error::other("unable to query terminal size"))
     }
 }
 
@@ -202,9 +905,11 @@ pub const EXTPROC: Lflag = 0o200000;
 
 // c_oflag bits
 pub const OPOST: Oflag = 0x00000001;
+pub const ONLCR: Oflag = libc::ONLCR;
 
 // c_iflag bits
 pub const IXON: Iflag = libc::IXON;
+pub const ICRNL: Iflag = libc::ICRNL;
 
 // TCFLOW
 pub const TCOOFF: TcFlow = 0;
@@ -222,27 +927,63 @@ pub const TCSANOW: TcSet = 0;
 pub const TCSADRAIN: TcSet = 1;
 pub const TCSAFLUSH: TcSet = 2;
 
-pub fn set_term(t: Terminal) -> Terminal {
-    let original = Terminal::default();
-    unsafe {
-        tcsetattr(stdin().as_raw_fd(), TCSANOW, &t.cast_to_termios());
+static TERMINAL: OnceLock<Mutex<Terminal>> = OnceLock::new();
+
+// Process wide `Terminal`, created on first use from the current settings.
+// Holding the guard serializes termios changes and output between threads.
+// Statics are never dropped, so call `sane()` or restore settings explicitly
+// before exiting.
+pub fn terminal() -> MutexGuard<'static, Terminal> {
+    TERMINAL
+        .get_or_init(|| Mutex::new(Terminal::default()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// The current settings, restored when dropped.
+fn saved_terminal() -> Terminal {
+    let mut t = Terminal::default();
+    t.restore_on_drop = true;
+    t
+}
+
+// Applies the termios settings of `t` to stdin's tty. `action` says when:
+//
+//   TCSANOW    immediately; output still queued for the tty is sent with the
+//              new settings, which can mangle it when e.g. OPOST changes
+//   TCSADRAIN  once all queued output has been sent, nothing is lost
+//   TCSAFLUSH  like TCSADRAIN, and unread input is discarded, for switching
+//              modes without keys typed earlier leaking into the new one
+//
+// Output `t` still has buffered is flushed first, so TCSADRAIN and TCSAFLUSH
+// cover it too. Returns the settings from before, put back when the returned
+// value is dropped.
+#[must_use = "dropping the returned Terminal restores the previous settings"]
+pub fn set_term(mut t: Terminal, action: TcSet) -> io::Result<Terminal> {
+    let original = saved_terminal();
+    t.leak();
+    t.flush()?;
+    if unsafe { tcsetattr(stdin().as_raw_fd(), action, &t.cast_to_termios()) } != 0 {
+        return Err(io::Error::last_os_error());
     }
-    return original;
+    Ok(original)
 }
 
 // If nothing is set the function will set the flag to what it would be in raw mode.
-// Returns the terminal settings before raw mode was enabled
+// Returns the terminal settings before raw mode was enabled, which are put back
+// when the returned value is dropped.
+#[must_use = "dropping the returned Terminal restores the previous settings"]
 pub fn set_raw() -> Terminal {
-    let t: Terminal = Terminal::default();
+    let t = saved_terminal();
     unsafe {
-        let mut termios: libc::termios = mem::MaybeUninit::uninit().assume_init();
+        let mut termios: libc::termios = mem::zeroed();
         tcgetattr(stdin().as_raw_fd(), &mut termios);
         termios.c_lflag &= !(ICANON | ECHO | ISIG);
         termios.c_iflag &= !(IXON);
         termios.c_oflag &= !(OPOST);
         tcsetattr(stdin().as_raw_fd(), TCSANOW, &termios);
     }
-    return t;
+    t
 }
 pub fn set_flags(
     lflags: Option<Lflag>,
@@ -251,7 +992,7 @@ pub fn set_flags(
     cflags: Option<Cflag>,
 ) {
     unsafe {
-        let mut t: libc::termios = mem::MaybeUninit::uninit().assume_init();
+        let mut t: libc::termios = mem::zeroed();
         tcgetattr(stdin().as_raw_fd(), &mut t);
         t.c_iflag = if let Some(flag) = iflags {
             flag
@@ -273,15 +1014,185 @@ pub fn set_flags(
         } else {
             t.c_cflag
         };
-        tcsetattr(stdin().as_raw_fd(), TCSANOW, &mut t);
+        tcsetattr(stdin().as_raw_fd(), TCSANOW, &t);
+    }
+}
+
+// The tty's VINTR character, None if there is no tty or it is disabled.
+pub fn interrupt_char() -> Option<u8> {
+    unsafe {
+        let mut t: termios = mem::zeroed();
+        if tcgetattr(stdin().as_raw_fd(), &mut t) != 0 {
+            return None;
+        }
+        match t.c_cc[VINTR as usize] {
+            0 => None,
+            c => Some(c),
+        }
     }
 }
 
+// Dies of SIGINT the way the process would have if ISIG had been on, so the
+// shell sees the usual status. Restore the terminal first (drop the value
+// `set_raw` returned, or call `sane()`).
+pub fn reraise_interrupt() -> ! {
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+        libc::raise(libc::SIGINT);
+    }
+    // only reached if SIGINT is blocked
+    std::process::exit(130)
+}
+
+// The controlling terminal as (input, output), opened directly so prompts
+// work while stdin or stdout are redirected.
+pub fn open_tty() -> io::Result<(File, File)> {
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let out = tty.try_clone()?;
+    Ok((tty, out))
+}
+
+// Key by key input without echo or signals, restored when dropped (also
+// while unwinding). The reader has to act on the interrupt character.
+pub(crate) struct HiddenInput {
+    fd: c_int,
+    saved: termios,
+}
+
+impl HiddenInput {
+    pub(crate) fn new(tty: &impl AsRawFd) -> io::Result<HiddenInput> {
+        let fd = tty.as_raw_fd();
+        unsafe {
+            let mut saved: termios = mem::zeroed();
+            if tcgetattr(fd, &mut saved) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut t = saved;
+            t.c_lflag &= !(ECHO | ECHONL | ICANON | ISIG);
+            t.c_cc[VMIN as usize] = 1;
+            t.c_cc[VTIME as usize] = 0;
+            if tcsetattr(fd, TCSANOW, &t) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(HiddenInput { fd, saved })
+        }
+    }
+
+    pub(crate) fn interrupt(&self) -> u8 {
+        self.saved.c_cc[VINTR as usize]
+    }
+}
+
+impl Drop for HiddenInput {
+    fn drop(&mut self) {
+        unsafe {
+            tcsetattr(self.fd, TCSANOW, &self.saved);
+        }
+    }
+}
+
+///////////////
+//  Queries  //
+///////////////
+
+// How long queries wait for the terminal to answer. Terminals that don't
+// know a query never answer, so this is also how long they take to fail.
+pub const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Input read while waiting for a reply that wasn't part of it. `EventReader`
+// and `EventLoop` decode it before reading stdin again.
+static UNREAD: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+pub(crate) fn take_unread() -> Vec<u8> {
+    mem::take(&mut *UNREAD.lock().unwrap_or_else(|p| p.into_inner()))
+}
+
+fn unread(bytes: &[u8]) {
+    UNREAD
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .extend_from_slice(bytes);
+}
+
+// Writes `request` to stdout, or to the tty when stdout is redirected, and
+// reads the reply like `query_on`.
+pub(crate) fn query<T>(
+    request: &[u8],
+    timeout: Duration,
+    parse: impl Fn(&[u8]) -> Option<T>,
+) -> io::Result<T> {
+    let send = |out: &mut dyn Write, request: &[u8]| {
+        out.write_all(request)?;
+        out.flush()
+    };
+    if io::stdout().is_terminal() {
+        let out = &mut io::stdout().lock();
+        query_on(|r| send(out, r), request, timeout, parse)
+    } else {
+        let out = &mut open_tty()?.1;
+        query_on(|r| send(out, r), request, timeout, parse)
+    }
+}
+
+// Sends `request` with `send` and reads stdin (the tty if stdin isn't one)
+// until `parse` recognizes the reply or `timeout` passes (`TimedOut`). Echo
+// and line buffering are off while waiting, so this works outside raw mode
+// too.
+pub(crate) fn query_on<T>(
+    send: impl FnOnce(&[u8]) -> io::Result<()>,
+    request: &[u8],
+    timeout: Duration,
+    parse: impl Fn(&[u8]) -> Option<T>,
+) -> io::Result<T> {
+    let tty;
+    let input = if stdin().is_terminal() {
+        libc::STDIN_FILENO
+    } else {
+        tty = File::open("/dev/tty")?;
+        tty.as_raw_fd()
+    };
+    let _hidden = HiddenInput::new(&input)?;
+    send(request)?;
+    let deadline = std::time::Instant::now() + timeout;
+    let mut data = take_unread();
+    let mut buf = [0u8; 256];
+    let result = loop {
+        if let Some((range, value)) = crate::input::find_reply(&data, &parse) {
+            data.drain(range);
+            break Ok(value);
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() || !poll_fd(input, remaining)? {
+            break Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "terminal did not answer the query",
+            ));
+        }
+        match read_fd(input, &mut buf)? {
+            0 => break Err(io::ErrorKind::UnexpectedEof.into()),
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    };
+    unread(&data);
+    result
+}
+
+// Asks the terminal where the cursor is (CPR). Returns zero based
+// (col, row). Keys typed meanwhile are kept for the next `EventReader` read.
+pub fn query_cursor_position() -> io::Result<(u16, u16)> {
+    query_cursor_position_timeout(QUERY_TIMEOUT)
+}
+
+pub fn query_cursor_position_timeout(timeout: Duration) -> io::Result<(u16, u16)> {
+    query(b"\x1b[6n", timeout, crate::input::cursor_report)
+}
+
 pub fn term_size() -> Option<(u32, u32)> {
     unsafe {
         let mut size: winsize = mem::zeroed();
-        if ioctl(std::io::stdout().as_raw_fd(), TIOCGWINSZ, &mut size) == 0 {
-            if size.ws_col > 0 && size.ws_row > 0 {
+        // whichever standard stream is still a terminal
+        for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO, libc::STDIN_FILENO] {
+            if ioctl(fd, TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 && size.ws_row > 0 {
                 return Some((size.ws_col as u32, size.ws_row as u32));
             }
         }
@@ -289,10 +1200,232 @@ pub fn term_size() -> Option<(u32, u32)> {
     None
 }
 
+/////////////////////
+//  Event Reading  //
+/////////////////////
+
+// Reads stdin and decodes it into `Event`s. Enable raw mode first, otherwise
+// input only arrives a line at a time.
+//
+// A lone ESC is delivered as Esc once no further input arrives within the
+// parser's ESC timeout; see `set_esc_timeout`.
+#[derive(Debug, Default)]
+pub struct EventReader {
+    parser: InputParser,
+    events: VecDeque<Event>,
+    nonblocking: bool,
+}
+
+impl EventReader {
+    pub fn new() -> io::Result<EventReader> {
+        Ok(EventReader::default())
+    }
+
+    pub fn set_esc_timeout(&mut self, timeout: Duration) {
+        self.parser.set_esc_timeout(timeout);
+    }
+
+    pub fn normalize_paste_newlines(&mut self, normalize: bool) {
+        self.parser.normalize_paste_newlines(normalize);
+    }
+
+    // With raw mode's ISIG off, Ctrl-C arrives as input. When enabled the
+    // tty's interrupt character is reported as `Event::Interrupt`, so the
+    // app can e.g. ask "press Ctrl-C again to quit"; see `reraise_interrupt`.
+    pub fn interrupt_as_event(&mut self, enable: bool) {
+        self.parser
+            .set_interrupt(if enable { interrupt_char() } else { None });
+    }
+
+    // Makes `read` fail with `WouldBlock` instead of waiting for input.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    // Blocks until at least one event has been decoded, unless set
+    // non-blocking.
+    pub fn read(&mut self) -> io::Result<Event> {
+        match self.next(!self.nonblocking)? {
+            Some(event) => Ok(event),
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    // Returns an event only if one can be decoded from input that is already
+    // available, never waits.
+    pub fn try_read(&mut self) -> io::Result<Option<Event>> {
+        match self.next(false) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            r => r,
+        }
+    }
+
+    fn next(&mut self, wait: bool) -> io::Result<Option<Event>> {
+        let mut buf = [0u8; 1024];
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            let unread = take_unread();
+            if !unread.is_empty() {
+                self.events.extend(self.parser.feed(&unread));
+                continue;
+            }
+            if let Some(timeout) = self.parser.timeout() {
+                let ready = poll_stdin(if wait { timeout } else { Duration::ZERO })?;
+                if !ready {
+                    if !wait && !timeout.is_zero() {
+                        return Ok(None);
+                    }
+                    self.events.extend(self.parser.flush());
+                    continue;
+                }
+            } else if !wait && !poll_stdin(Duration::ZERO)? {
+                return Ok(None);
+            }
+            let n = read_stdin(&mut buf)?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.events.extend(self.parser.feed(&buf[..n]));
+        }
+    }
+}
+
+fn read_input_from(fd: c_int, buf: &mut [u8], nonblocking: bool) -> io::Result<usize> {
+    if nonblocking && !poll_fd(fd, Duration::ZERO)? {
+        return Err(io::ErrorKind::WouldBlock.into());
+    }
+    read_fd(fd, buf)
+}
+
+fn read_stdin(buf: &mut [u8]) -> io::Result<usize> {
+    read_fd(libc::STDIN_FILENO, buf)
+}
+
+pub(crate) fn read_fd(fd: c_int, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+        if n >= 0 {
+            return Ok(n as usize);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+// Waits up to `timeout` for stdin to become readable.
+fn poll_stdin(timeout: Duration) -> io::Result<bool> {
+    poll_fd(libc::STDIN_FILENO, timeout)
+}
+
+// Same for a file, like the tty `open_tty` returns.
+pub(crate) fn wait_readable(file: &File, timeout: Duration) -> io::Result<bool> {
+    poll_fd(file.as_raw_fd(), timeout)
+}
+
+fn poll_fd(fd: c_int, timeout: Duration) -> io::Result<bool> {
+    let mut fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+    loop {
+        match unsafe { libc::poll(&mut fd, 1, millis) } {
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            n => return Ok(n > 0),
+        }
+    }
+}
+
+////////////////
+//  SIGWINCH  //
+////////////////
+
+// The event loop and `PtyChild` both want to hear about resizes, so there is
+// one handler that runs every registered hook (which must be async signal
+// safe) and then whatever handler was installed before it.
+const WINCH_HOOKS: usize = 4;
+static WINCH_HOOK: [AtomicUsize; WINCH_HOOKS] = [const { AtomicUsize::new(0) }; WINCH_HOOKS];
+static WINCH_HANDLER: Once = Once::new();
+static WINCH_INSTALLED: AtomicBool = AtomicBool::new(false);
+static mut PREVIOUS_WINCH: Option<libc::sigaction> = None;
+
+extern "C" fn on_sigwinch(signal: c_int, info: *mut libc::siginfo_t, context: *mut c_void) {
+    for hook in &WINCH_HOOK {
+        let hook = hook.load(Ordering::SeqCst);
+        if hook != 0 {
+            let hook: fn() = unsafe { mem::transmute(hook) };
+            hook();
+        }
+    }
+    let previous = unsafe { *ptr::addr_of!(PREVIOUS_WINCH) };
+    let Some(previous) = previous else {
+        return;
+    };
+    if previous.sa_sigaction == libc::SIG_DFL || previous.sa_sigaction == libc::SIG_IGN {
+        return;
+    }
+    if previous.sa_flags & libc::SA_SIGINFO != 0 {
+        let handler: extern "C" fn(c_int, *mut libc::siginfo_t, *mut c_void) =
+            unsafe { mem::transmute(previous.sa_sigaction) };
+        handler(signal, info, context);
+    } else {
+        let handler: extern "C" fn(c_int) = unsafe { mem::transmute(previous.sa_sigaction) };
+        handler(signal);
+    }
+}
+
+// Runs `hook` on every SIGWINCH from now on, installing the handler first if
+// needed. Registering the same hook twice has no effect.
+pub(crate) fn on_resize_signal(hook: fn()) -> io::Result<()> {
+    let mut result = Ok(());
+    WINCH_HANDLER.call_once(|| unsafe {
+        // the previous handler is known before ours can run
+        let mut previous: libc::sigaction = mem::zeroed();
+        if libc::sigaction(libc::SIGWINCH, ptr::null(), &mut previous) == -1 {
+            result = Err(io::Error::last_os_error());
+            return;
+        }
+        *ptr::addr_of_mut!(PREVIOUS_WINCH) = Some(previous);
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = on_sigwinch as *const () as usize;
+        action.sa_flags = libc::SA_RESTART | libc::SA_SIGINFO;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGWINCH, &action, ptr::null_mut()) == -1 {
+            result = Err(io::Error::last_os_error());
+            return;
+        }
+        WINCH_INSTALLED.store(true, Ordering::SeqCst);
+    });
+    result?;
+    if !WINCH_INSTALLED.load(Ordering::SeqCst) {
+        return Err(io::Error::other("SIGWINCH handler setup failed"));
+    }
+    let hook = hook as usize;
+    for slot in &WINCH_HOOK {
+        match slot.compare_exchange(0, hook, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return Ok(()),
+            Err(current) if current == hook => return Ok(()),
+            Err(_) => {}
+        }
+    }
+    Err(io::Error::other("too many SIGWINCH hooks"))
+}
+
 #[cfg(test)]
 mod test {
 
-    use super::term_size;
+    use super::{term_size, terminal};
+    use std::thread;
 
     #[test]
     fn get_terminal_size() {
@@ -302,4 +1435,120 @@ mod test {
             println!("term size: {},{}", s.0, s.1)
         }
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn state_round_trips() {
+        let mut t = super::Terminal::default();
+        t.leak();
+        let state = t.state();
+        assert_eq!(
+            super::TermState::from_ron(&state.to_ron().unwrap()).unwrap(),
+            state
+        );
+        assert_eq!(
+            super::TermState::from_bytes(&state.to_bytes().unwrap()).unwrap(),
+            state
+        );
+    }
+
+    #[test]
+    fn clones_compare_by_settings() {
+        let t = super::Terminal::with_target(super::Target::Stderr).unwrap();
+        let mut other = t.clone();
+        assert!(other == t);
+        assert_eq!(other.writer().target(), Some(super::Target::Stderr));
+        assert!(!other.restore_on_drop);
+        other.c_lflags ^= libc::ECHO;
+        assert!(other != t);
+    }
+
+    #[test]
+    fn only_guards_restore_on_drop() {
+        let t = super::Terminal::default();
+        assert!(!t.restore_on_drop);
+        let mut saved = super::saved_terminal();
+        assert!(saved.restore_on_drop);
+        saved.leak();
+        assert!(!saved.restore_on_drop);
+    }
+
+    #[test]
+    fn nonblocking_leaves_stdin_flags() {
+        let flags = unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_GETFL) };
+        let mut t = super::Terminal::default();
+        t.set_nonblocking(true);
+        assert!(t.is_nonblocking());
+        let after = unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_GETFL) };
+        assert_eq!(after, flags);
+    }
+
+    #[test]
+    fn nonblocking_read_would_block() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let [read, write] = fds;
+        let mut buf = [0; 8];
+        let err = super::read_input_from(read, &mut buf, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert_eq!(unsafe { libc::write(write, b"ab".as_ptr().cast(), 2) }, 2);
+        assert_eq!(super::read_input_from(read, &mut buf, true).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+        unsafe {
+            libc::close(read);
+            libc::close(write);
+        }
+    }
+
+    #[test]
+    fn flash_ends_itself() {
+        use std::io::{self, Write};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = Capture::default();
+        let mut t = super::Terminal::with_writer(capture.clone());
+        t.flash().unwrap();
+        assert_eq!(capture.0.lock().unwrap().as_slice(), b"\x1b[?5h\x1b[?5l");
+        assert_eq!(t.update_bell().unwrap(), None);
+    }
+
+    #[test]
+    fn resize_signal_runs_hooks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static SEEN: AtomicUsize = AtomicUsize::new(0);
+        fn hook() {
+            SEEN.fetch_add(1, Ordering::SeqCst);
+        }
+        super::on_resize_signal(hook).unwrap();
+        super::on_resize_signal(hook).unwrap();
+        unsafe { libc::raise(libc::SIGWINCH) };
+        assert_eq!(SEEN.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn global_terminal_is_shared() {
+        use crate::bell::BellStyle;
+        let before = terminal().bell_style;
+        thread::spawn(|| terminal().set_bell_style(BellStyle::None))
+            .join()
+            .unwrap();
+        let mut t = terminal();
+        let seen = t.bell_style;
+        t.set_bell_style(before);
+        assert_eq!(seen, BellStyle::None);
+    }
 }
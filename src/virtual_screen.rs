@@ -0,0 +1,1090 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// An in-memory terminal. Feed it the bytes an application writes and inspect
+// the resulting grid, cursor and modes instead of looking at a real terminal.
+
+use crate::cell::Cell;
+use crate::hyperlink::Links;
+use crate::line_size::LineSize;
+use crate::parser::{Action, Params, Parser};
+use crate::style::Style;
+use crate::tab::TabStops;
+use crate::width::{char_width, graphemes};
+use std::collections::{BTreeMap, VecDeque};
+
+// Rows kept in the scrollback unless changed with `set_scrollback_limit`.
+pub const DEFAULT_SCROLLBACK: usize = 1000;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct SavedCursor {
+    col: usize,
+    row: usize,
+    style: Style,
+}
+
+#[derive(Debug, Clone)]
+pub struct VirtualScreen {
+    cols: usize,
+    rows: usize,
+    grid: Vec<Vec<Cell>>,
+    // Main screen contents while the alternate screen is active.
+    main_grid: Option<Vec<Vec<Cell>>>,
+    // ESC # 3..6 of each row, and of the main screen's rows while the
+    // alternate screen is active
+    line_sizes: Vec<LineSize>,
+    main_line_sizes: Vec<LineSize>,
+    col: usize,
+    row: usize,
+    // Set after printing into the last column; the next print wraps first.
+    pending_wrap: bool,
+    style: Style,
+    saved: SavedCursor,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    cursor_visible: bool,
+    auto_wrap: bool,
+    insert_mode: bool,
+    modes: BTreeMap<u16, bool>,
+    title: String,
+    tabs: TabStops,
+    // rows scrolled off the top of the main screen, oldest first
+    scrollback: VecDeque<Vec<Cell>>,
+    scrollback_limit: usize,
+    // OSC 8 targets; cells refer to them by index
+    links: Links,
+    link: Option<u32>,
+    parser: Parser,
+}
+
+impl VirtualScreen {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        Self {
+            cols,
+            rows,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            main_grid: None,
+            line_sizes: vec![LineSize::Single; rows],
+            main_line_sizes: vec![LineSize::Single; rows],
+            col: 0,
+            row: 0,
+            pending_wrap: false,
+            style: Style::new(),
+            saved: SavedCursor::default(),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            cursor_visible: true,
+            auto_wrap: true,
+            insert_mode: false,
+            modes: BTreeMap::new(),
+            title: String::new(),
+            tabs: TabStops::new(cols),
+            scrollback: VecDeque::new(),
+            scrollback_limit: DEFAULT_SCROLLBACK,
+            links: Links::new(),
+            link: None,
+            parser: Parser::new(),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    // (col, row), zero based.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.col, self.row)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn alt_screen(&self) -> bool {
+        self.main_grid.is_some()
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    // State of a DEC private mode (`CSI ? n h/l`) as last set by the stream.
+    pub fn mode(&self, mode: u16) -> bool {
+        match mode {
+            7 => self.auto_wrap,
+            25 => self.cursor_visible,
+            47 | 1047 | 1049 => self.alt_screen(),
+            _ => self.modes.get(&mode).copied().unwrap_or(false),
+        }
+    }
+
+    pub fn tab_stops(&self) -> &TabStops {
+        &self.tabs
+    }
+
+    // Replaces the tab stops, e.g. to match a terminal set up with `tabs -4`.
+    pub fn set_tab_stops(&mut self, tabs: TabStops) {
+        self.tabs = tabs;
+        self.tabs.resize(self.cols);
+    }
+
+    // URL of the OSC 8 link the cell at (col, row) was printed with.
+    pub fn link(&self, col: usize, row: usize) -> Option<&str> {
+        self.cell(col, row)?.link.and_then(|id| self.links.get(id))
+    }
+
+    pub fn line_size(&self, row: usize) -> LineSize {
+        self.line_sizes.get(row).copied().unwrap_or_default()
+    }
+
+    pub fn cell(&self, col: usize, row: usize) -> Option<&Cell> {
+        self.grid.get(row).and_then(|r| r.get(col))
+    }
+
+    pub fn row_text(&self, row: usize) -> String {
+        match self.grid.get(row) {
+            Some(r) => r
+                .iter()
+                .map(|c| c.symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+            None => String::new(),
+        }
+    }
+
+    //////////////////
+    //  Scrollback  //
+    //////////////////
+
+    // Lines a linefeed pushes off the top of the main screen (with no scroll
+    // region set) are kept, up to `limit` of them; 0 turns it off.
+    pub fn set_scrollback_limit(&mut self, limit: usize) {
+        self.scrollback_limit = limit;
+        self.trim_scrollback();
+    }
+
+    pub fn scrollback_limit(&self) -> usize {
+        self.scrollback_limit
+    }
+
+    pub fn scrollback_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    // A scrolled off row, 0 being the oldest.
+    pub fn scrollback_row(&self, index: usize) -> Option<&[Cell]> {
+        self.scrollback.get(index).map(Vec::as_slice)
+    }
+
+    pub fn scrollback_text(&self, index: usize) -> String {
+        self.scrollback_row(index)
+            .map(|r| {
+                r.iter()
+                    .map(|c| c.symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn clear_scrollback(&mut self) {
+        self.scrollback.clear();
+    }
+
+    // The scrollback followed by the screen, as `to_plain_string` would
+    // render it: the whole log an application has written so far.
+    pub fn to_plain_string_with_scrollback(&self) -> String {
+        (0..self.scrollback.len())
+            .map(|i| self.scrollback_text(i))
+            .chain((0..self.rows).map(|r| self.row_text(r)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn trim_scrollback(&mut self) {
+        while self.scrollback.len() > self.scrollback_limit {
+            self.scrollback.pop_front();
+        }
+    }
+
+    /////////////////
+    //  Snapshots  //
+    /////////////////
+
+    // Text of every row with trailing blanks trimmed, one line per row.
+    pub fn to_plain_string(&self) -> String {
+        (0..self.rows)
+            .map(|r| self.row_text(r))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Like `to_plain_string` but with SGR sequences wherever the style
+    // changes, so printing it reproduces the colors of the screen.
+    pub fn to_styled_string(&self) -> String {
+        let mut out = String::new();
+        for (i, row) in self.grid.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let len = row
+                .iter()
+                .rposition(|c| c.symbol != " " || !c.style.is_plain())
+                .map_or(0, |p| p + 1);
+            let mut current = Style::new();
+            for cell in &row[..len] {
+                if cell.style != current {
+                    out.push_str(&cell.style.to_ansi());
+                    current = cell.style;
+                }
+                out.push_str(&cell.symbol);
+            }
+            if !current.is_plain() {
+                out.push_str("\x1b[0m");
+            }
+        }
+        out
+    }
+
+    // Framed grid with a header describing the cursor and a `^` under the
+    // cursor column, meant for snapshot tests:
+    //
+    //   size: 4x2 cursor: (1, 0) visible
+    //   |ab  |
+    //     ^
+    //   |    |
+    pub fn to_debug_string(&self) -> String {
+        let mut out = format!(
+            "size: {}x{} cursor: ({}, {}) {}",
+            self.cols,
+            self.rows,
+            self.col,
+            self.row,
+            if self.cursor_visible {
+                "visible"
+            } else {
+                "hidden"
+            }
+        );
+        for (i, row) in self.grid.iter().enumerate() {
+            out.push_str("\n|");
+            out.extend(row.iter().map(|c| c.symbol()));
+            out.push('|');
+            if i == self.row {
+                out.push('\n');
+                out.push_str(&" ".repeat(self.col + 1));
+                out.push('^');
+            }
+        }
+        out
+    }
+
+    ///////////////
+    //  Diffing  //
+    ///////////////
+
+    // Cells that differ between `self` (before) and `other` (after). Cells
+    // outside the smaller screen compare against blanks.
+    pub fn diff(&self, other: &VirtualScreen) -> ScreenDiff {
+        let blank = Cell::default();
+        let mut cells = Vec::new();
+        for row in 0..self.rows.max(other.rows) {
+            for col in 0..self.cols.max(other.cols) {
+                let before = self.cell(col, row).unwrap_or(&blank);
+                let after = other.cell(col, row).unwrap_or(&blank);
+                if before != after {
+                    cells.push(CellDiff {
+                        col,
+                        row,
+                        before: before.clone(),
+                        after: after.clone(),
+                    });
+                }
+            }
+        }
+        ScreenDiff {
+            before_size: self.size(),
+            after_size: other.size(),
+            before_rows: (0..self.rows).map(|r| self.row_line(r)).collect(),
+            after_rows: (0..other.rows).map(|r| other.row_line(r)).collect(),
+            cells,
+        }
+    }
+
+    fn row_line(&self, row: usize) -> String {
+        self.grid[row].iter().map(|c| c.symbol()).collect()
+    }
+
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        for grid in std::iter::once(&mut self.grid).chain(self.main_grid.as_mut()) {
+            grid.resize(rows, vec![Cell::default(); cols]);
+            for r in grid.iter_mut() {
+                r.resize(cols, Cell::default());
+            }
+        }
+        self.line_sizes.resize(rows, LineSize::Single);
+        self.main_line_sizes.resize(rows, LineSize::Single);
+        self.cols = cols;
+        self.rows = rows;
+        self.tabs.resize(cols);
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.col = self.col.min(cols - 1);
+        self.row = self.row.min(rows - 1);
+        self.pending_wrap = false;
+    }
+
+    pub fn process(&mut self, bytes: &[u8]) {
+        for action in self.parser.feed(bytes) {
+            self.perform(action);
+        }
+    }
+
+    pub fn perform(&mut self, action: Action) {
+        match action {
+            Action::Print(c) => self.print(c),
+            Action::Execute(b) => self.execute(b),
+            Action::Csi {
+                params,
+                intermediates,
+                ignored: false,
+                final_byte,
+            } => self.csi(&params, &intermediates, final_byte),
+            Action::Esc {
+                intermediates,
+                ignored: false,
+                final_byte,
+            } => self.esc(&intermediates, final_byte),
+            Action::Osc { params, .. } => {
+                if let [kind, title, ..] = params.as_slice()
+                    && (kind == b"0" || kind == b"2")
+                {
+                    self.title = String::from_utf8_lossy(title).into_owned();
+                }
+                if let [kind, _, url @ ..] = params.as_slice()
+                    && kind == b"8"
+                {
+                    let url = String::from_utf8_lossy(&url.join(&b';')).into_owned();
+                    self.link = (!url.is_empty()).then(|| self.links.intern(&url));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, c: char) {
+        let width = char_width(c);
+        // Combining marks and friends join the previous grapheme, as does
+        // anything continuing its cluster (after a ZWJ, a flag's second half).
+        let mut col = if self.pending_wrap {
+            self.col
+        } else {
+            self.col.saturating_sub(1)
+        };
+        if self.grid[self.row][col].is_continuation() && col > 0 {
+            col -= 1;
+        }
+        let prev = &self.grid[self.row][col];
+        if width == 0 || (self.col > 0 || self.pending_wrap) && joins(prev.symbol(), c) {
+            let before = prev.width();
+            self.grid[self.row][col].push_char(c);
+            // a cluster that became wide takes the next cell too
+            if before == 1 && self.grid[self.row][col].width() == 2 && !self.pending_wrap {
+                if col + 1 < self.line_cols() {
+                    self.split_wide(self.row, col + 1);
+                    self.grid[self.row][col + 1] =
+                        Cell::continuation(self.style).with_link(self.link);
+                }
+                if self.col + 1 < self.line_cols() {
+                    self.col += 1;
+                } else {
+                    self.col = self.line_cols() - 1;
+                    self.pending_wrap = self.auto_wrap;
+                }
+            }
+            return;
+        }
+        if self.pending_wrap {
+            self.pending_wrap = false;
+            self.col = 0;
+            self.linefeed();
+        }
+        if self.col + width > self.line_cols() {
+            // A wide character that doesn't fit wraps early.
+            if !self.auto_wrap || width > self.line_cols() {
+                return;
+            }
+            self.split_wide(self.row, self.col);
+            self.grid[self.row][self.col] = Cell::blank(self.style);
+            self.col = 0;
+            self.linefeed();
+        }
+        if self.insert_mode {
+            let row = &mut self.grid[self.row];
+            for _ in 0..width {
+                row.insert(self.col, Cell::default());
+            }
+            row.truncate(self.cols);
+        }
+        for i in 0..width {
+            self.split_wide(self.row, self.col + i);
+        }
+        self.grid[self.row][self.col] = Cell::from_char(c, self.style).with_link(self.link);
+        if width == 2 {
+            self.grid[self.row][self.col + 1] = Cell::continuation(self.style).with_link(self.link);
+        }
+        if self.col + width < self.line_cols() {
+            self.col += width;
+        } else {
+            self.col = self.line_cols() - 1;
+            self.pending_wrap = self.auto_wrap;
+        }
+    }
+
+    // Columns the cursor line holds given its line size.
+    fn line_cols(&self) -> usize {
+        self.line_sizes[self.row].columns(self.cols).max(1)
+    }
+
+    // DECDWL / DECDHL: the cursor line loses the cells that no longer fit.
+    fn set_line_size(&mut self, size: LineSize) {
+        self.line_sizes[self.row] = size;
+        let cols = self.line_cols();
+        if cols < self.cols {
+            self.split_wide(self.row, cols);
+        }
+        self.grid[self.row][cols..].fill(Cell::default());
+        self.col = self.col.min(cols - 1);
+        self.pending_wrap = false;
+    }
+
+    // Blanks the other half of a wide character about to be partly
+    // overwritten at (col, row).
+    fn split_wide(&mut self, row: usize, col: usize) {
+        let cells = &mut self.grid[row];
+        if cells[col].is_continuation() && col > 0 {
+            cells[col - 1] = Cell::blank(cells[col - 1].style);
+        } else if cells[col].width() == 2 && col + 1 < cells.len() {
+            cells[col + 1] = Cell::blank(cells[col + 1].style);
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            0x08 => {
+                self.pending_wrap = false;
+                self.col = self.col.saturating_sub(1);
+            }
+            0x09 => self.col = self.tabs.next(self.col),
+            0x0A..=0x0C => {
+                self.pending_wrap = false;
+                self.linefeed();
+            }
+            0x0D => {
+                self.pending_wrap = false;
+                self.col = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn esc(&mut self, intermediates: &[u8], byte: u8) {
+        if intermediates == b"#" {
+            match byte {
+                b'3' => self.set_line_size(LineSize::DoubleHeightTop),
+                b'4' => self.set_line_size(LineSize::DoubleHeightBottom),
+                b'5' => self.set_line_size(LineSize::Single),
+                b'6' => self.set_line_size(LineSize::DoubleWidth),
+                _ => {}
+            }
+            return;
+        }
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.save_cursor(),
+            b'H' => self.tabs.set(self.col),
+            b'8' => self.restore_cursor(),
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.col = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => {
+                let limit = self.scrollback_limit;
+                *self = Self::new(self.cols, self.rows);
+                self.scrollback_limit = limit;
+            }
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, params: &Params, intermediates: &[u8], byte: u8) {
+        if intermediates == b"?" {
+            match byte {
+                b'h' => self.set_private_modes(params, true),
+                b'l' => self.set_private_modes(params, false),
+                _ => {}
+            }
+            return;
+        }
+        if !intermediates.is_empty() {
+            return;
+        }
+        let n = params.get_or(0, 1) as usize;
+        self.pending_wrap = false;
+        match byte {
+            b'A' => self.row = self.row.saturating_sub(n).max(self.top_limit()),
+            b'B' | b'e' => self.row = (self.row + n).min(self.bottom_limit()),
+            b'C' | b'a' => self.col = (self.col + n).min(self.line_cols() - 1),
+            b'D' => self.col = self.col.saturating_sub(n),
+            b'E' => {
+                self.row = (self.row + n).min(self.bottom_limit());
+                self.col = 0;
+            }
+            b'F' => {
+                self.row = self.row.saturating_sub(n).max(self.top_limit());
+                self.col = 0;
+            }
+            b'G' | b'`' => self.col = (n - 1).min(self.line_cols() - 1),
+            b'd' => {
+                self.row = (n - 1).min(self.rows - 1);
+                self.col = self.col.min(self.line_cols() - 1);
+            }
+            b'H' | b'f' => {
+                self.row = (params.get_or(0, 1) as usize - 1).min(self.rows - 1);
+                self.col = (params.get_or(1, 1) as usize - 1).min(self.line_cols() - 1);
+            }
+            b'I' => (0..n).for_each(|_| self.col = self.tabs.next(self.col)),
+            b'Z' => (0..n).for_each(|_| self.col = self.tabs.prev(self.col)),
+            b'g' => match params.get(0).unwrap_or(0) {
+                0 => self.tabs.clear(self.col),
+                3 => self.tabs.clear_all(),
+                _ => {}
+            },
+            b'J' => self.erase_display(params.get(0).unwrap_or(0)),
+            b'K' => self.erase_line(params.get(0).unwrap_or(0)),
+            b'L' => self.insert_lines(n),
+            b'M' => self.delete_lines(n),
+            b'@' => {
+                let (col, cols) = (self.col, self.cols);
+                let row = &mut self.grid[self.row];
+                for _ in 0..n.min(cols - col) {
+                    row.insert(col, Cell::default());
+                }
+                row.truncate(cols);
+            }
+            b'P' => {
+                let (col, cols) = (self.col, self.cols);
+                let row = &mut self.grid[self.row];
+                row.drain(col..(col + n).min(cols));
+                row.resize(cols, Cell::default());
+            }
+            b'X' => {
+                let end = (self.col + n).min(self.cols);
+                self.blank(self.row, self.col, end);
+            }
+            b'S' => self.scroll_up(n),
+            b'T' => self.scroll_down(n),
+            b'm' => self.style.apply_sgr(params),
+            b'r' => {
+                let top = params.get_or(0, 1) as usize - 1;
+                let bottom = (params.get_or(1, self.rows as u16) as usize).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.col = 0;
+                    self.row = 0;
+                }
+            }
+            b'h' if params.get(0) == Some(4) => self.insert_mode = true,
+            b'l' if params.get(0) == Some(4) => self.insert_mode = false,
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn set_private_modes(&mut self, params: &Params, on: bool) {
+        for p in params.iter() {
+            let mode = p[0];
+            match mode {
+                3 if self.mode(40) => {
+                    let rows = self.rows;
+                    self.resize(if on { 132 } else { 80 }, rows);
+                    for r in self.grid.iter_mut() {
+                        r.fill(Cell::default());
+                    }
+                    self.line_sizes.fill(LineSize::Single);
+                    self.col = 0;
+                    self.row = 0;
+                    self.modes.insert(mode, on);
+                }
+                7 => self.auto_wrap = on,
+                25 => self.cursor_visible = on,
+                47 | 1047 | 1049 => {
+                    if mode == 1049 && on {
+                        self.save_cursor();
+                    }
+                    self.set_alt_screen(on);
+                    if mode == 1049 && !on {
+                        self.restore_cursor();
+                    }
+                }
+                _ => {
+                    self.modes.insert(mode, on);
+                }
+            }
+        }
+    }
+
+    fn set_alt_screen(&mut self, on: bool) {
+        if on && self.main_grid.is_none() {
+            let alt = vec![vec![Cell::default(); self.cols]; self.rows];
+            self.main_grid = Some(std::mem::replace(&mut self.grid, alt));
+            self.main_line_sizes =
+                std::mem::replace(&mut self.line_sizes, vec![LineSize::Single; self.rows]);
+        } else if !on && let Some(main) = self.main_grid.take() {
+            self.grid = main;
+            std::mem::swap(&mut self.line_sizes, &mut self.main_line_sizes);
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = SavedCursor {
+            col: self.col,
+            row: self.row,
+            style: self.style,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        self.col = self.saved.col.min(self.cols - 1);
+        self.row = self.saved.row.min(self.rows - 1);
+        self.style = self.saved.style;
+        self.pending_wrap = false;
+    }
+
+    fn top_limit(&self) -> usize {
+        if self.row >= self.scroll_top {
+            self.scroll_top
+        } else {
+            0
+        }
+    }
+
+    fn bottom_limit(&self) -> usize {
+        if self.row <= self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.rows - 1
+        }
+    }
+
+    fn linefeed(&mut self) {
+        if self.row == self.scroll_bottom {
+            if self.scroll_top == 0 && self.main_grid.is_none() && self.scrollback_limit > 0 {
+                self.scrollback.push_back(self.grid[0].clone());
+                self.trim_scrollback();
+            }
+            self.scroll_up(1);
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.row == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.row = self.row.saturating_sub(1);
+        }
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![Cell::blank(Style::new().bg(self.style.bg)); self.cols]
+    }
+
+    fn blank(&mut self, row: usize, start: usize, end: usize) {
+        let cell = Cell::blank(Style::new().bg(self.style.bg));
+        for c in &mut self.grid[row][start..end] {
+            *c = cell.clone();
+        }
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        let n = n.min(self.scroll_bottom - self.scroll_top + 1);
+        for _ in 0..n {
+            self.grid.remove(self.scroll_top);
+            let blank = self.blank_row();
+            self.grid.insert(self.scroll_bottom, blank);
+            self.line_sizes.remove(self.scroll_top);
+            self.line_sizes.insert(self.scroll_bottom, LineSize::Single);
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        let n = n.min(self.scroll_bottom - self.scroll_top + 1);
+        for _ in 0..n {
+            self.grid.remove(self.scroll_bottom);
+            let blank = self.blank_row();
+            self.grid.insert(self.scroll_top, blank);
+            self.line_sizes.remove(self.scroll_bottom);
+            self.line_sizes.insert(self.scroll_top, LineSize::Single);
+        }
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        if self.row < self.scroll_top || self.row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..n.min(self.scroll_bottom - self.row + 1) {
+            self.grid.remove(self.scroll_bottom);
+            let blank = self.blank_row();
+            self.grid.insert(self.row, blank);
+            self.line_sizes.remove(self.scroll_bottom);
+            self.line_sizes.insert(self.row, LineSize::Single);
+        }
+        self.col = 0;
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        if self.row < self.scroll_top || self.row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..n.min(self.scroll_bottom - self.row + 1) {
+            self.grid.remove(self.row);
+            let blank = self.blank_row();
+            self.grid.insert(self.scroll_bottom, blank);
+            self.line_sizes.remove(self.row);
+            self.line_sizes.insert(self.scroll_bottom, LineSize::Single);
+        }
+        self.col = 0;
+    }
+
+    fn erase_display(&mut self, mode: u16) {
+        match mode {
+            0 => {
+                self.blank(self.row, self.col, self.cols);
+                for r in self.row + 1..self.rows {
+                    self.blank(r, 0, self.cols);
+                    self.line_sizes[r] = LineSize::Single;
+                }
+            }
+            1 => {
+                for r in 0..self.row {
+                    self.blank(r, 0, self.cols);
+                    self.line_sizes[r] = LineSize::Single;
+                }
+                self.blank(self.row, 0, self.col + 1);
+            }
+            2 => {
+                for r in 0..self.rows {
+                    self.blank(r, 0, self.cols);
+                }
+                self.line_sizes.fill(LineSize::Single);
+            }
+            // the screen and the scrollback
+            3 => {
+                for r in 0..self.rows {
+                    self.blank(r, 0, self.cols);
+                }
+                self.line_sizes.fill(LineSize::Single);
+                self.scrollback.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, mode: u16) {
+        match mode {
+            0 => self.blank(self.row, self.col, self.cols),
+            1 => self.blank(self.row, 0, self.col + 1),
+            2 => self.blank(self.row, 0, self.cols),
+            _ => {}
+        }
+    }
+}
+
+impl std::io::Write for VirtualScreen {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.process(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellDiff {
+    pub col: usize,
+    pub row: usize,
+    pub before: Cell,
+    pub after: Cell,
+}
+
+// Result of `VirtualScreen::diff`. `Display` prints a report of the changed
+// rows with the differing columns marked, followed by every changed cell:
+//
+//   2 cells differ
+//   row 0:
+//     - |abc  |
+//     + |abd  |
+//          ^
+//   (2, 0): "c" -> "d"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenDiff {
+    pub before_size: (usize, usize),
+    pub after_size: (usize, usize),
+    pub cells: Vec<CellDiff>,
+    before_rows: Vec<String>,
+    after_rows: Vec<String>,
+}
+
+impl ScreenDiff {
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.before_size == self.after_size
+    }
+}
+
+fn style_name(style: &Style) -> String {
+    if style.is_plain() {
+        return "plain".into();
+    }
+    Parser::new()
+        .feed(style.to_ansi().as_bytes())
+        .iter()
+        .map(crate::trace::describe)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl std::fmt::Display for ScreenDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "screens are identical");
+        }
+        if self.before_size != self.after_size {
+            let (bc, br) = self.before_size;
+            let (ac, ar) = self.after_size;
+            writeln!(f, "size: {}x{} -> {}x{}", bc, br, ac, ar)?;
+        }
+        write!(f, "{} cells differ", self.cells.len())?;
+        let mut rows: Vec<usize> = self.cells.iter().map(|c| c.row).collect();
+        rows.dedup();
+        for row in rows {
+            let line = |rows: &[String]| rows.get(row).cloned().unwrap_or_default();
+            write!(f, "\nrow {}:", row)?;
+            write!(f, "\n  - |{}|", line(&self.before_rows))?;
+            write!(f, "\n  + |{}|", line(&self.after_rows))?;
+            let mut marks = String::new();
+            for cell in self.cells.iter().filter(|c| c.row == row) {
+                marks.push_str(&" ".repeat(cell.col - marks.chars().count()));
+                marks.push('^');
+            }
+            write!(f, "\n     {}", marks)?;
+        }
+        for cell in &self.cells {
+            write!(
+                f,
+                "\n({}, {}): {:?} -> {:?}",
+                cell.col, cell.row, cell.before.symbol, cell.after.symbol
+            )?;
+            if cell.before.style != cell.after.style {
+                write!(
+                    f,
+                    " [{} -> {}]",
+                    style_name(&cell.before.style),
+                    style_name(&cell.after.style)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Whether `c` continues the grapheme cluster `symbol`.
+fn joins(symbol: &str, c: char) -> bool {
+    if symbol.is_empty() || symbol == " " {
+        return false;
+    }
+    let mut joined = String::with_capacity(symbol.len() + 4);
+    joined.push_str(symbol);
+    joined.push(c);
+    graphemes(&joined).nth(1).is_none()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::VirtualScreen;
+    use crate::color::{Color, Iso};
+    use crate::line_size::LineSize;
+
+    #[test]
+    fn styled_cell_at_position() {
+        let mut s = VirtualScreen::new(10, 5);
+        s.process(b"\x1b[4;5H\x1b[31mX\x1b[0m");
+        let cell = s.cell(4, 3).unwrap();
+        assert_eq!(cell.symbol(), "X");
+        assert_eq!(
+            cell.style.fg,
+            Color::Iso {
+                color: Iso::Red,
+                bright: false
+            }
+        );
+        assert_eq!(s.cursor(), (5, 3));
+    }
+
+    #[test]
+    fn wraps_and_scrolls() {
+        let mut s = VirtualScreen::new(4, 2);
+        s.process(b"abcdefgh\r\nij");
+        assert_eq!(s.row_text(0), "efgh");
+        assert_eq!(s.row_text(1), "ij");
+    }
+
+    #[test]
+    fn alt_screen_restores_main() {
+        let mut s = VirtualScreen::new(4, 2);
+        s.process(b"main\x1b[?1049h\x1b[Halt\x1b[?1049l");
+        assert_eq!(s.row_text(0), "main");
+        assert!(!s.alt_screen());
+    }
+
+    #[test]
+    fn emoji_sequences_stay_in_one_cell() {
+        let mut s = VirtualScreen::new(8, 2);
+        s.process("\u{1f1fa}\u{1f1f8}\u{1f468}\u{200d}\u{1f469}x".as_bytes());
+        assert_eq!(s.cell(0, 0).unwrap().symbol(), "\u{1f1fa}\u{1f1f8}");
+        assert!(s.cell(1, 0).unwrap().is_continuation());
+        assert_eq!(s.cell(2, 0).unwrap().symbol(), "\u{1f468}\u{200d}\u{1f469}");
+        assert_eq!(s.cell(4, 0).unwrap().symbol(), "x");
+        assert_eq!(s.cursor(), (5, 0));
+    }
+
+    #[test]
+    fn tab_stops() {
+        let mut s = VirtualScreen::new(20, 2);
+        s.process(b"a\tb\x1b[3G\x1bH\r\tc\x1b[2Z\x1b[3gd\r\x1b[I\x1b[Ie");
+        assert_eq!(s.row_text(0), "d c     b          e");
+    }
+
+    #[test]
+    fn double_width_lines() {
+        let mut s = VirtualScreen::new(6, 3);
+        s.process(b"123456\x1b#6\rabcdef");
+        assert_eq!(s.line_size(0), LineSize::DoubleWidth);
+        assert_eq!(s.row_text(0), "abc");
+        assert_eq!(s.row_text(1), "def");
+        s.process(b"\x1b[1;6Hx\x1b#5");
+        assert_eq!(s.row_text(0), "abx");
+        assert_eq!(s.line_size(0), LineSize::Single);
+        // the size moves with its line
+        s.process(b"\x1b[2H\x1b#3\x1b[3H\n");
+        assert_eq!(s.line_size(0), LineSize::DoubleHeightTop);
+        s.process(b"\x1b[2J");
+        assert_eq!(s.line_size(0), LineSize::Single);
+    }
+
+    #[test]
+    fn keeps_scrollback() {
+        let mut s = VirtualScreen::new(4, 2);
+        s.set_scrollback_limit(2);
+        s.process(b"1\r\n2\r\n3\r\n4\r\n5");
+        assert_eq!(s.scrollback_len(), 2);
+        assert_eq!(s.scrollback_text(0), "2");
+        assert_eq!(s.to_plain_string_with_scrollback(), "2\n3\n4\n5");
+        // not from the alternate screen
+        s.process(b"\x1b[?1049h\r\n\r\n\x1b[?1049l");
+        assert_eq!(s.scrollback_text(1), "3");
+        s.process(b"\x1b[3J");
+        assert_eq!(s.scrollback_len(), 0);
+    }
+
+    #[test]
+    fn column_mode_needs_mode_40() {
+        let mut s = VirtualScreen::new(80, 2);
+        s.process(b"text\x1b[?3h");
+        assert_eq!(s.size(), (80, 2));
+        s.process(b"\x1b[?40h\x1b[?3h");
+        assert_eq!(s.size(), (132, 2));
+        assert_eq!(s.row_text(0), "");
+        s.process(b"\x1b[?3l");
+        assert_eq!(s.size(), (80, 2));
+    }
+
+    #[test]
+    fn wide_and_combining_characters() {
+        let mut s = VirtualScreen::new(5, 2);
+        s.process("e\u{301}漢x".as_bytes());
+        assert_eq!(s.cell(0, 0).unwrap().symbol(), "e\u{301}");
+        assert_eq!(s.cell(1, 0).unwrap().symbol(), "漢");
+        assert!(s.cell(2, 0).unwrap().is_continuation());
+        assert_eq!(s.cursor(), (4, 0));
+        s.process("\x1b[1;3Hy".as_bytes());
+        assert_eq!(s.cell(1, 0).unwrap().symbol(), " ");
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut s = VirtualScreen::new(4, 2);
+        s.process(b"a\x1b[1mb\x1b[0m");
+        assert_eq!(s.to_plain_string(), "ab\n");
+        assert_eq!(s.to_styled_string(), "a\x1b[0;1mb\x1b[0m\n");
+        assert_eq!(
+            s.to_debug_string(),
+            "size: 4x2 cursor: (2, 0) visible\n|ab  |\n   ^\n|    |"
+        );
+    }
+
+    #[test]
+    fn diff_reports_changed_cells() {
+        let mut a = VirtualScreen::new(5, 2);
+        a.process(b"abc\r\nxyz");
+        let mut b = a.clone();
+        assert!(a.diff(&b).is_empty());
+
+        b.process(b"\x1b[1;3Hd\x1b[2;1H\x1b[1mx");
+        let diff = a.diff(&b);
+        assert_eq!(diff.cells.len(), 2);
+        assert_eq!((diff.cells[0].col, diff.cells[0].row), (2, 0));
+        assert_eq!(diff.cells[0].after.symbol(), "d");
+        assert_eq!(
+            diff.to_string(),
+            "2 cells differ\n\
+             row 0:\n  - |abc  |\n  + |abd  |\n       ^\n\
+             row 1:\n  - |xyz  |\n  + |xyz  |\n     ^\n\
+             (2, 0): \"c\" -> \"d\"\n\
+             (0, 1): \"x\" -> \"x\" [plain -> SGR(reset, bold)]"
+        );
+    }
+}
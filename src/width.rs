@@ -0,0 +1,270 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Display width of text in terminal cells.
+
+use crate::layout::Align;
+use crate::parser::{Action, Parser};
+use std::sync::atomic::{AtomicU8, Ordering};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// How East Asian ambiguous width characters (Greek and Cyrillic letters, box
+// drawing, `±`, `°`, ...) are counted. Terminals running in a CJK locale often
+// draw them two cells wide; everything measuring text has to agree with the
+// terminal or wrapping and cursor positions drift.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+impl AmbiguousWidth {
+    // Wide for Chinese, Japanese and Korean locales (from `LC_ALL`,
+    // `LC_CTYPE` or `LANG`, the first one set), narrow otherwise.
+    pub fn from_locale() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .filter_map(|v| std::env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .unwrap_or_default();
+        Self::for_locale(&locale)
+    }
+
+    pub fn for_locale(locale: &str) -> Self {
+        if ["ja", "ko", "zh"].iter().any(|l| locale.starts_with(l)) {
+            AmbiguousWidth::Wide
+        } else {
+            AmbiguousWidth::Narrow
+        }
+    }
+
+    // The measurements below with this policy rather than the process wide
+    // one.
+    pub fn char_width(self, c: char) -> usize {
+        match self {
+            AmbiguousWidth::Narrow => c.width(),
+            AmbiguousWidth::Wide => c.width_cjk(),
+        }
+        .unwrap_or(0)
+    }
+
+    pub fn str_width(self, s: &str) -> usize {
+        graphemes(s).map(|g| self.grapheme_width(g)).sum()
+    }
+
+    pub fn grapheme_width(self, g: &str) -> usize {
+        let mut chars = g.chars();
+        let Some(first) = chars.next() else {
+            return 0;
+        };
+        let width = self.char_width(first);
+        let regional = |c: char| ('\u{1f1e6}'..='\u{1f1ff}').contains(&c);
+        if g.contains('\u{fe0f}') || (regional(first) && chars.next().is_some_and(regional)) {
+            return 2;
+        }
+        if width == 0 {
+            // a cluster starting with a zero width character, e.g. a prepended
+            // mark, takes the width of what follows
+            return match self {
+                AmbiguousWidth::Narrow => g.width(),
+                AmbiguousWidth::Wide => g.width_cjk(),
+            }
+            .min(2);
+        }
+        width
+    }
+}
+
+// 0 until set or detected, then 1 + the policy
+static AMBIGUOUS: AtomicU8 = AtomicU8::new(0);
+
+// Process wide, since the width functions below are free functions: it
+// applies to every `Terminal`, `Screen`, `VirtualScreen`, `print_at` and
+// `LineEditor` alike. Until set, the policy comes from
+// `AmbiguousWidth::from_locale`.
+pub fn set_ambiguous_width(width: AmbiguousWidth) {
+    AMBIGUOUS.store(width as u8 + 1, Ordering::Relaxed);
+}
+
+pub fn ambiguous_width() -> AmbiguousWidth {
+    match AMBIGUOUS.load(Ordering::Relaxed) {
+        1 => AmbiguousWidth::Narrow,
+        2 => AmbiguousWidth::Wide,
+        _ => {
+            let width = AmbiguousWidth::from_locale();
+            set_ambiguous_width(width);
+            width
+        }
+    }
+}
+
+// Cells taken by `c`: 0 for combining marks and controls, 2 for wide (CJK,
+// most emoji) characters, otherwise 1. Ambiguous characters follow
+// `ambiguous_width`.
+pub fn char_width(c: char) -> usize {
+    ambiguous_width().char_width(c)
+}
+
+// Cells taken by `s`, counting each grapheme cluster as a unit, so an emoji
+// ZWJ sequence or a flag is 2 and not the sum of its parts.
+pub fn str_width(s: &str) -> usize {
+    ambiguous_width().str_width(s)
+}
+
+// The user-perceived characters of `s`: a base character with its combining
+// marks, an emoji ZWJ sequence, a pair of regional indicators, ...
+pub fn graphemes(s: &str) -> impl DoubleEndedIterator<Item = &str> {
+    s.graphemes(true)
+}
+
+// Cells taken by one grapheme cluster. Terminals size a cluster by its first
+// character, except that an emoji presentation selector or a flag makes it
+// wide. Controls and lone combining marks are 0.
+pub fn grapheme_width(g: &str) -> usize {
+    ambiguous_width().grapheme_width(g)
+}
+
+// The longest prefix of `s` no wider than `width` cells, never splitting a
+// grapheme cluster.
+pub fn truncate(s: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, g) in s.grapheme_indices(true) {
+        used += grapheme_width(g);
+        if used > width {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+/////////////////
+//  Alignment  //
+/////////////////
+
+// Width of what `s` shows, skipping escape sequences: "\x1b[1mok\x1b[0m"
+// is 2 wide.
+pub fn visible_width(s: &str) -> usize {
+    if !s.contains(['\x1b', '\u{9b}', '\u{9d}']) {
+        return str_width(s);
+    }
+    let text: String = Parser::new()
+        .feed(s.as_bytes())
+        .into_iter()
+        .filter_map(|a| match a {
+            Action::Print(c) => Some(c),
+            _ => None,
+        })
+        .collect();
+    str_width(&text)
+}
+
+// `text` padded with spaces to `width` cells. Text already that wide is
+// returned as is, never cut; see `truncate` for that. Escape sequences in
+// `text` take no space, so styled text lines up too.
+pub fn align(text: &str, width: usize, how: Align) -> String {
+    let used = visible_width(text);
+    let free = width.saturating_sub(used);
+    let before = how.offset(used.min(width) as u16, width as u16) as usize;
+    format!(
+        "{}{}{}",
+        " ".repeat(before),
+        text,
+        " ".repeat(free - before)
+    )
+}
+
+pub fn align_left(text: &str, width: usize) -> String {
+    align(text, width, Align::Left)
+}
+
+pub fn align_right(text: &str, width: usize) -> String {
+    align(text, width, Align::Right)
+}
+
+pub fn align_center(text: &str, width: usize) -> String {
+    align(text, width, Align::Center)
+}
+
+// Spreads the words of `text` so it is exactly `width` cells wide, the
+// leftmost gaps getting the extra spaces. A single word, or words that
+// don't fit, are joined with single spaces and left aligned.
+pub fn justify(text: &str, width: usize) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let used: usize = words.iter().map(|w| visible_width(w)).sum();
+    let gaps = words.len().saturating_sub(1);
+    if gaps == 0 || used + gaps > width {
+        return align_left(&words.join(" "), width);
+    }
+    let spaces = width - used;
+    let mut out = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            let n = spaces / gaps + usize::from(i <= spaces % gaps);
+            out.push_str(&" ".repeat(n));
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{
+        AmbiguousWidth, UnicodeWidthChar, align_center, align_left, align_right, graphemes,
+        justify, str_width, truncate, visible_width,
+    };
+
+    #[test]
+    fn locale_policy() {
+        assert_eq!(
+            AmbiguousWidth::for_locale("ja_JP.UTF-8"),
+            AmbiguousWidth::Wide
+        );
+        assert_eq!(
+            AmbiguousWidth::for_locale("en_US.UTF-8"),
+            AmbiguousWidth::Narrow
+        );
+        assert_eq!(AmbiguousWidth::for_locale(""), AmbiguousWidth::Narrow);
+        assert_eq!(('±'.width(), '±'.width_cjk()), (Some(1), Some(2)));
+        assert_eq!(AmbiguousWidth::Narrow.str_width("±1"), 2);
+        assert_eq!(AmbiguousWidth::Wide.str_width("±1"), 3);
+    }
+
+    #[test]
+    fn clusters_are_units() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let text = format!("e\u{301}{family}\u{1f1fa}\u{1f1f8}x");
+        assert_eq!(graphemes(&text).count(), 4);
+        assert_eq!(str_width(&text), 1 + 2 + 2 + 1);
+        assert_eq!(truncate(&text, 4), format!("e\u{301}{family}"));
+        assert_eq!(truncate(&text, 2), "e\u{301}");
+    }
+
+    #[test]
+    fn aligns_by_visible_width() {
+        let styled = "\x1b[1m界a\x1b[0m";
+        assert_eq!(visible_width(styled), 3);
+        assert_eq!(align_right(styled, 5), format!("  {}", styled));
+        assert_eq!(align_center("ab", 5), " ab  ");
+        assert_eq!(align_left("abc", 2), "abc");
+        assert_eq!(justify("a bb  c", 9), "a   bb  c");
+        assert_eq!(justify("one", 5), "one  ");
+        assert_eq!(justify("too many words", 6), "too many words");
+    }
+}
@@ -0,0 +1,156 @@
+/* 
+ * Copyright 2025 Luke Maple
+ * 
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// XTWINOPS (`CSI ... t`) window operations. xterm refuses most of them unless
+// `allowWindowOps` is set, and many terminals only implement the reports, so
+// treat every one of these as a request that may be ignored.
+
+use crate::command::Command;
+use std::io::{self, Write};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Deiconify;
+
+impl Command for Deiconify {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[1t")
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Iconify;
+
+impl Command for Iconify {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x1b[2t")
+    }
+}
+
+// Moves the window's top left corner to (x, y) pixels on the display.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveWindow(pub u16, pub u16);
+
+impl Command for MoveWindow {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[3;{};{}t", self.0, self.1)
+    }
+}
+
+// Resizes the text area to (width, height) pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResizeWindowPixels(pub u16, pub u16);
+
+impl Command for ResizeWindowPixels {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[4;{};{}t", self.1, self.0)
+    }
+}
+
+// Resizes the text area to (cols, rows) cells.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResizeWindowCells(pub u16, pub u16);
+
+impl Command for ResizeWindowCells {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[8;{};{}t", self.1, self.0)
+    }
+}
+
+// The report requests. Each is answered with `CSI answer ; a ; b t`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WindowReport {
+    // (x, y) of the window in pixels, answered with 3
+    Position,
+    // text area (width, height) in pixels, answered with 4
+    TextAreaPixels,
+    // cell (width, height) in pixels, answered with 6
+    CellPixels,
+    // text area (cols, rows), answered with 8
+    TextAreaCells,
+}
+
+impl WindowReport {
+    fn request(&self) -> u16 {
+        match self {
+            WindowReport::Position => 13,
+            WindowReport::TextAreaPixels => 14,
+            WindowReport::CellPixels => 16,
+            WindowReport::TextAreaCells => 18,
+        }
+    }
+
+    fn answer(&self) -> u16 {
+        self.request() - 10
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RequestWindowReport(pub WindowReport);
+
+impl Command for RequestWindowReport {
+    fn write_ansi(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{}t", self.0.request())
+    }
+}
+
+// Asks for a report and waits up to `timeout` for it. Values come back in the
+// order documented on `WindowReport`, i.e. x before y and width before height.
+pub fn query_window(report: WindowReport, timeout: Duration) -> io::Result<(u16, u16)> {
+    let request = RequestWindowReport(report).to_ansi_string();
+    let (a, b) = crate::query(request.as_bytes(), timeout, |seq| {
+        crate::input::window_report(seq, report.answer())
+    })?;
+    Ok(match report {
+        WindowReport::Position => (a, b),
+        // height comes first for sizes
+        _ => (b, a),
+    })
+}
+
+pub fn query_window_position() -> io::Result<(u16, u16)> {
+    query_window(WindowReport::Position, crate::QUERY_TIMEOUT)
+}
+
+// What graphics need to scale images to cells.
+pub fn query_text_area_pixels() -> io::Result<(u16, u16)> {
+    query_window(WindowReport::TextAreaPixels, crate::QUERY_TIMEOUT)
+}
+
+pub fn query_cell_pixels() -> io::Result<(u16, u16)> {
+    query_window(WindowReport::CellPixels, crate::QUERY_TIMEOUT)
+}
+
+pub fn query_text_area_cells() -> io::Result<(u16, u16)> {
+    query_window(WindowReport::TextAreaCells, crate::QUERY_TIMEOUT)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{MoveWindow, RequestWindowReport, ResizeWindowCells, WindowReport};
+    use crate::command::Command;
+
+    #[test]
+    fn sequences() {
+        assert_eq!(MoveWindow(10, 20).to_ansi_string(), "\x1b[3;10;20t");
+        assert_eq!(ResizeWindowCells(80, 24).to_ansi_string(), "\x1b[8;24;80t");
+        assert_eq!(
+            RequestWindowReport(WindowReport::CellPixels).to_ansi_string(),
+            "\x1b[16t"
+        );
+    }
+}
@@ -14,4 +14,887 @@
  * limitations under the License.
  */
 
-// This is where the windows implementation will be when I create it
+use crate::backend::Backend;
+use crate::color::{Background, Color, Foreground};
+use crate::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, Modifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crate::output::{Output, Target};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::windows::io::AsRawHandle;
+use std::time::{Duration, Instant};
+use windows_sys::Win32::Foundation::{HANDLE, INVALID_HANDLE_VALUE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows_sys::Win32::System::Console::{
+    CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, COORD, CTRL_C_EVENT, DOUBLE_CLICK, ENABLE_ECHO_INPUT,
+    ENABLE_EXTENDED_FLAGS, ENABLE_LINE_INPUT, ENABLE_MOUSE_INPUT, ENABLE_PROCESSED_INPUT,
+    ENABLE_QUICK_EDIT_MODE, ENABLE_VIRTUAL_TERMINAL_INPUT, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    ENABLE_WINDOW_INPUT, FOREGROUND_BLUE, FOREGROUND_GREEN, FOREGROUND_INTENSITY, FOREGROUND_RED,
+    FROM_LEFT_1ST_BUTTON_PRESSED, FROM_LEFT_2ND_BUTTON_PRESSED, GenerateConsoleCtrlEvent,
+    GetConsoleMode, GetConsoleScreenBufferInfo, GetStdHandle, INPUT_RECORD, KEY_EVENT,
+    KEY_EVENT_RECORD, LEFT_ALT_PRESSED, LEFT_CTRL_PRESSED, MOUSE_EVENT, MOUSE_EVENT_RECORD,
+    MOUSE_HWHEELED, MOUSE_MOVED, MOUSE_WHEELED, RIGHT_ALT_PRESSED, RIGHT_CTRL_PRESSED,
+    RIGHTMOST_BUTTON_PRESSED, ReadConsoleInputW, SHIFT_PRESSED, STD_HANDLE, STD_INPUT_HANDLE,
+    STD_OUTPUT_HANDLE, SetConsoleCtrlHandler, SetConsoleMode, SetConsoleTextAttribute,
+    WINDOW_BUFFER_SIZE_EVENT,
+};
+use windows_sys::Win32::System::Threading::WaitForSingleObject;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_F24, VK_HOME, VK_INSERT, VK_LEFT,
+    VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_TAB, VK_UP,
+};
+
+fn std_handle(which: STD_HANDLE) -> io::Result<HANDLE> {
+    let handle = unsafe { GetStdHandle(which) };
+    if handle == INVALID_HANDLE_VALUE || handle.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(handle)
+}
+
+fn console_mode(handle: HANDLE) -> io::Result<CONSOLE_MODE> {
+    let mut mode: CONSOLE_MODE = 0;
+    if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(mode)
+}
+
+fn set_console_mode(handle: HANDLE, mode: CONSOLE_MODE) -> io::Result<()> {
+    if unsafe { SetConsoleMode(handle, mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// The console as (input, output) through `CONIN$`/`CONOUT$`, so prompts
+// work while stdin or stdout are redirected.
+pub fn open_tty() -> io::Result<(File, File)> {
+    let input = OpenOptions::new().read(true).write(true).open("CONIN$")?;
+    let output = OpenOptions::new().read(true).write(true).open("CONOUT$")?;
+    Ok((input, output))
+}
+
+// Key by key input without echo or Ctrl-C processing, restored when dropped
+// (also while unwinding). Keys arrive as VT sequences like on unix. The
+// reader has to act on Ctrl-C.
+pub(crate) struct HiddenInput {
+    handle: HANDLE,
+    saved: CONSOLE_MODE,
+}
+
+impl HiddenInput {
+    pub(crate) fn new(tty: &File) -> io::Result<HiddenInput> {
+        let handle = tty.as_raw_handle() as HANDLE;
+        let saved = console_mode(handle)?;
+        set_console_mode(
+            handle,
+            (saved | ENABLE_VIRTUAL_TERMINAL_INPUT)
+                & !(ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT | ENABLE_PROCESSED_INPUT),
+        )?;
+        Ok(HiddenInput { handle, saved })
+    }
+
+    pub(crate) fn interrupt(&self) -> u8 {
+        0x03
+    }
+}
+
+impl Drop for HiddenInput {
+    fn drop(&mut self) {
+        let _ = set_console_mode(self.handle, self.saved);
+    }
+}
+
+// Sends Ctrl-C to the process again, after input handling swallowed it, so
+// it ends the way an unhandled Ctrl-C would. Restore the console first.
+pub fn reraise_interrupt() -> ! {
+    unsafe {
+        SetConsoleCtrlHandler(None, 0);
+        GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0);
+    }
+    // STATUS_CONTROL_C_EXIT, if the event didn't end the process
+    std::process::exit(0xC000013Au32 as i32)
+}
+
+////////////////////////////
+//  Virtual Terminal I/O  //
+////////////////////////////
+
+// Console modes saved by `enable_vt`, put back when dropped.
+#[derive(Debug)]
+pub struct VtMode {
+    input: HANDLE,
+    output: HANDLE,
+    input_mode: CONSOLE_MODE,
+    output_mode: CONSOLE_MODE,
+}
+
+impl Drop for VtMode {
+    fn drop(&mut self) {
+        let _ = set_console_mode(self.output, self.output_mode);
+        let _ = set_console_mode(self.input, self.input_mode);
+    }
+}
+
+// Makes the console interpret escape sequences written to stdout
+// (ENABLE_VIRTUAL_TERMINAL_PROCESSING) and report input as escape sequences
+// (ENABLE_VIRTUAL_TERMINAL_INPUT). Fails on consoles without VT support, e.g.
+// conhost before Windows 10; see the legacy color fallback for those.
+pub fn enable_vt() -> io::Result<VtMode> {
+    let input = std_handle(STD_INPUT_HANDLE)?;
+    let output = std_handle(STD_OUTPUT_HANDLE)?;
+    let saved = VtMode {
+        input,
+        output,
+        input_mode: console_mode(input)?,
+        output_mode: console_mode(output)?,
+    };
+    set_console_mode(
+        output,
+        saved.output_mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    )?;
+    set_console_mode(input, saved.input_mode | ENABLE_VIRTUAL_TERMINAL_INPUT)?;
+    Ok(saved)
+}
+
+///////////////
+//  Console  //
+///////////////
+
+// The console as a `Backend`, what `Terminal` is on Unix. Escape sequences
+// are interpreted for as long as the value lives; the console modes from
+// before are put back when it is dropped, after the output is flushed.
+#[derive(Debug)]
+pub struct Console {
+    out: Output,
+    _vt: VtMode,
+}
+
+impl Console {
+    pub fn new() -> io::Result<Self> {
+        Self::with_target(Target::Stdout)
+    }
+
+    pub fn with_target(target: Target) -> io::Result<Self> {
+        Ok(Self {
+            _vt: enable_vt()?,
+            out: Output::to(target)?,
+        })
+    }
+
+    pub fn writer(&mut self) -> &mut Output {
+        &mut self.out
+    }
+}
+
+impl Write for Console {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Backend for Console {
+    fn size(&self) -> io::Result<(u32, u32)> {
+        term_size().ok_or_else(|| io::Error::other("unable to query console size"))
+    }
+}
+
+/////////////////////////////
+//  Legacy Console Colors  //
+/////////////////////////////
+
+// Colors for consoles that can't interpret escape sequences. The console
+// only has the 16 ISO colors, so extended and rgb values are matched to the
+// closest of those.
+#[derive(Debug)]
+pub struct LegacyConsole {
+    output: HANDLE,
+    default_attrs: u16,
+    attrs: u16,
+}
+
+impl LegacyConsole {
+    pub fn new() -> io::Result<LegacyConsole> {
+        let output = std_handle(STD_OUTPUT_HANDLE)?;
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = unsafe { std::mem::zeroed() };
+        if unsafe { GetConsoleScreenBufferInfo(output, &mut info) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(LegacyConsole {
+            output,
+            default_attrs: info.wAttributes,
+            attrs: info.wAttributes,
+        })
+    }
+
+    pub fn set_foreground(&mut self, fg: Foreground) -> io::Result<()> {
+        if let Some(index) = console_index(&fg.color()) {
+            self.set_attrs((self.attrs & !0x0F) | index)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_background(&mut self, bg: Background) -> io::Result<()> {
+        if let Some(index) = console_index(&bg.color()) {
+            self.set_attrs((self.attrs & !0xF0) | (index << 4))?;
+        }
+        Ok(())
+    }
+
+    // Back to the attributes the console had when this was created.
+    pub fn reset(&mut self) -> io::Result<()> {
+        self.set_attrs(self.default_attrs)
+    }
+
+    fn set_attrs(&mut self, attrs: u16) -> io::Result<()> {
+        if unsafe { SetConsoleTextAttribute(self.output, attrs) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.attrs = attrs;
+        Ok(())
+    }
+}
+
+impl Drop for LegacyConsole {
+    fn drop(&mut self) {
+        let _ = self.reset();
+    }
+}
+
+// Picks escape sequences when the console supports them and falls back to
+// console attributes otherwise. Attribute changes apply to whatever is
+// written after them, so pending output is flushed first.
+#[derive(Debug)]
+pub enum ConsoleColors {
+    Vt(VtMode),
+    Legacy(LegacyConsole),
+}
+
+impl ConsoleColors {
+    pub fn detect() -> io::Result<ConsoleColors> {
+        match enable_vt() {
+            Ok(mode) => Ok(ConsoleColors::Vt(mode)),
+            Err(_) => Ok(ConsoleColors::Legacy(LegacyConsole::new()?)),
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, ConsoleColors::Legacy(_))
+    }
+
+    pub fn set_foreground(&mut self, out: &mut impl Write, fg: Foreground) -> io::Result<()> {
+        match self {
+            ConsoleColors::Vt(_) => out.write_all(fg.to_ansi().as_bytes()),
+            ConsoleColors::Legacy(console) => {
+                out.flush()?;
+                console.set_foreground(fg)
+            }
+        }
+    }
+
+    pub fn set_background(&mut self, out: &mut impl Write, bg: Background) -> io::Result<()> {
+        match self {
+            ConsoleColors::Vt(_) => out.write_all(bg.to_ansi().as_bytes()),
+            ConsoleColors::Legacy(console) => {
+                out.flush()?;
+                console.set_background(bg)
+            }
+        }
+    }
+
+    pub fn reset(&mut self, out: &mut impl Write) -> io::Result<()> {
+        match self {
+            ConsoleColors::Vt(_) => out.write_all(b"\x1b[39;49m"),
+            ConsoleColors::Legacy(console) => {
+                out.flush()?;
+                console.reset()
+            }
+        }
+    }
+}
+
+// The 4 bit foreground attribute for a color, None leaves it unchanged.
+// Console attributes order the channels blue, green, red where ISO uses
+// red, green, blue.
+fn console_index(color: &Color) -> Option<u16> {
+    let ansi = match *color {
+        Color::Iso { color, bright } => color.index() + if bright { 8 } else { 0 },
+        Color::Extended(n) if n < 16 => n,
+        Color::Extended(n) => nearest_ansi(extended_rgb(n)),
+        Color::Rgb { r, g, b } => nearest_ansi((r, g, b)),
+        Color::None | Color::Default => return None,
+    };
+    let mut attrs = 0;
+    if ansi & 1 != 0 {
+        attrs |= FOREGROUND_RED;
+    }
+    if ansi & 2 != 0 {
+        attrs |= FOREGROUND_GREEN;
+    }
+    if ansi & 4 != 0 {
+        attrs |= FOREGROUND_BLUE;
+    }
+    if ansi & 8 != 0 {
+        attrs |= FOREGROUND_INTENSITY;
+    }
+    Some(attrs)
+}
+
+// Rgb value of an xterm 256 color palette entry above 15.
+fn extended_rgb(n: u8) -> (u8, u8, u8) {
+    if n >= 232 {
+        let level = 8 + (n - 232) * 10;
+        return (level, level, level);
+    }
+    let n = n - 16;
+    let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+    (level(n / 36), level((n / 6) % 6), level(n % 6))
+}
+
+// Default conhost palette, in ISO order.
+const CONSOLE_PALETTE: [(u8, u8, u8); 16] = [
+    (12, 12, 12),
+    (197, 15, 31),
+    (19, 161, 14),
+    (193, 156, 0),
+    (0, 55, 218),
+    (136, 23, 152),
+    (58, 150, 221),
+    (204, 204, 204),
+    (118, 118, 118),
+    (231, 72, 86),
+    (22, 198, 12),
+    (249, 241, 165),
+    (59, 120, 255),
+    (180, 0, 158),
+    (97, 214, 214),
+    (242, 242, 242),
+];
+
+fn nearest_ansi((r, g, b): (u8, u8, u8)) -> u8 {
+    let dist = |&(pr, pg, pb): &(u8, u8, u8)| {
+        let dr = r as i32 - pr as i32;
+        let dg = g as i32 - pg as i32;
+        let db = b as i32 - pb as i32;
+        dr * dr + dg * dg + db * db
+    };
+    (0..16)
+        .min_by_key(|&i| dist(&CONSOLE_PALETTE[i]))
+        .unwrap_or(0) as u8
+}
+
+// (cols, rows) of the visible console window. This is the window rectangle,
+// not the screen buffer, which is usually much taller because it includes
+// the scrollback.
+pub fn term_size() -> Option<(u32, u32)> {
+    let handle = std_handle(STD_OUTPUT_HANDLE).ok()?;
+    unsafe {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(handle, &mut info) == 0 {
+            return None;
+        }
+        let window = info.srWindow;
+        let cols = (window.Right - window.Left + 1) as u32;
+        let rows = (window.Bottom - window.Top + 1) as u32;
+        if cols > 0 && rows > 0 {
+            Some((cols, rows))
+        } else {
+            None
+        }
+    }
+}
+
+/////////////////////
+//  Console Input  //
+/////////////////////
+
+// Reads INPUT_RECORDs from the console and turns them into `Event`s. Mouse
+// and window input are switched on (and quick edit, which would swallow
+// clicks, off) for as long as the reader lives.
+//
+// With `enable_vt` active the console reports keys as escape sequences in
+// the UnicodeChar field instead; feed those to `input::InputParser`.
+#[derive(Debug)]
+pub struct EventReader {
+    input: HANDLE,
+    input_mode: CONSOLE_MODE,
+    buttons: u32,
+    surrogate: Option<u16>,
+    size: Option<(u16, u16)>,
+    report_releases: bool,
+}
+
+impl EventReader {
+    pub fn new() -> io::Result<EventReader> {
+        let input = std_handle(STD_INPUT_HANDLE)?;
+        let input_mode = console_mode(input)?;
+        set_console_mode(
+            input,
+            (input_mode | ENABLE_MOUSE_INPUT | ENABLE_WINDOW_INPUT | ENABLE_EXTENDED_FLAGS)
+                & !ENABLE_QUICK_EDIT_MODE,
+        )?;
+        Ok(EventReader {
+            input,
+            input_mode,
+            buttons: 0,
+            surrogate: None,
+            size: term_size().map(|(c, r)| (c as u16, r as u16)),
+            report_releases: false,
+        })
+    }
+
+    // The console always reports key releases; they are passed on as
+    // `KeyEventKind::Release` only when enabled here, matching terminals
+    // that need the kitty flags before they send them.
+    pub fn report_releases(&mut self, report: bool) {
+        self.report_releases = report;
+    }
+
+    // Blocks until a record arrives that maps to an `Event`. Focus and menu
+    // records are skipped.
+    pub fn read(&mut self) -> io::Result<Event> {
+        loop {
+            let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+            let mut read = 0;
+            if unsafe { ReadConsoleInputW(self.input, &mut record, 1, &mut read) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if read == 0 {
+                continue;
+            }
+            if let Some(event) = self.translate(&record) {
+                return Ok(event);
+            }
+        }
+    }
+
+    fn translate(&mut self, record: &INPUT_RECORD) -> Option<Event> {
+        match record.EventType as u32 {
+            KEY_EVENT => self.key(unsafe { &record.Event.KeyEvent }),
+            MOUSE_EVENT => self.mouse(unsafe { &record.Event.MouseEvent }),
+            WINDOW_BUFFER_SIZE_EVENT => self.resize(),
+            _ => None,
+        }
+    }
+
+    fn key(&mut self, key: &KEY_EVENT_RECORD) -> Option<Event> {
+        let kind = if key.bKeyDown != 0 {
+            KeyEventKind::Press
+        } else if self.report_releases {
+            KeyEventKind::Release
+        } else {
+            return None;
+        };
+        let mut modifiers = modifiers(key.dwControlKeyState);
+        let unit = unsafe { key.uChar.UnicodeChar };
+        let code = match key.wVirtualKeyCode {
+            VK_BACK => KeyCode::Backspace,
+            VK_TAB if modifiers.contains(Modifiers::SHIFT) => {
+                modifiers.remove(Modifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            VK_TAB => KeyCode::Tab,
+            VK_RETURN => KeyCode::Enter,
+            VK_ESCAPE => KeyCode::Esc,
+            VK_PRIOR => KeyCode::PageUp,
+            VK_NEXT => KeyCode::PageDown,
+            VK_END => KeyCode::End,
+            VK_HOME => KeyCode::Home,
+            VK_LEFT => KeyCode::Left,
+            VK_UP => KeyCode::Up,
+            VK_RIGHT => KeyCode::Right,
+            VK_DOWN => KeyCode::Down,
+            VK_INSERT => KeyCode::Insert,
+            VK_DELETE => KeyCode::Delete,
+            vk @ VK_F1..=VK_F24 => KeyCode::F((vk - VK_F1 + 1) as u8),
+            // Ctrl+letter arrives as the C0 control; report the letter
+            vk @ 0x41..=0x5A if unit < 0x20 && modifiers.contains(Modifiers::CONTROL) => {
+                KeyCode::Char((vk as u8).to_ascii_lowercase() as char)
+            }
+            _ => KeyCode::Char(self.char(unit)?),
+        };
+        Some(Event::Key(KeyEvent::new(code, modifiers).with_kind(kind)))
+    }
+
+    // Characters outside the BMP come in as two key records, one per
+    // UTF-16 surrogate.
+    fn char(&mut self, unit: u16) -> Option<char> {
+        match unit {
+            0 => None,
+            0xD800..=0xDBFF => {
+                self.surrogate = Some(unit);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.surrogate.take()?;
+                char::decode_utf16([high, unit]).next()?.ok()
+            }
+            _ => {
+                self.surrogate = None;
+                char::from_u32(unit as u32)
+            }
+        }
+    }
+
+    fn mouse(&mut self, mouse: &MOUSE_EVENT_RECORD) -> Option<Event> {
+        let pressed = mouse.dwButtonState;
+        let kind = match mouse.dwEventFlags {
+            MOUSE_WHEELED => {
+                // the high word is the signed wheel delta, positive is away
+                // from the user
+                if (pressed >> 16) as i16 > 0 {
+                    MouseEventKind::ScrollUp
+                } else {
+                    MouseEventKind::ScrollDown
+                }
+            }
+            MOUSE_HWHEELED => {
+                // positive tilts to the right
+                if (pressed >> 16) as i16 > 0 {
+                    MouseEventKind::ScrollRight
+                } else {
+                    MouseEventKind::ScrollLeft
+                }
+            }
+            MOUSE_MOVED => match button(pressed) {
+                Some(b) => MouseEventKind::Drag(b),
+                None => MouseEventKind::Moved,
+            },
+            0 | DOUBLE_CLICK => {
+                let changed = pressed ^ self.buttons;
+                self.buttons = pressed;
+                let b = button(changed)?;
+                if pressed & changed != 0 {
+                    MouseEventKind::Down(b)
+                } else {
+                    MouseEventKind::Up(b)
+                }
+            }
+            _ => return None,
+        };
+        let origin = window_origin().unwrap_or((0, 0));
+        let (column, row) = window_position(mouse.dwMousePosition, origin);
+        Some(Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: modifiers(mouse.dwControlKeyState),
+        }))
+    }
+
+    // The record carries the screen buffer size, which includes scrollback
+    // and changes for reasons other than the window being resized. Report
+    // the window size instead, and only when it actually changed, so this
+    // behaves like SIGWINCH on Unix.
+    fn resize(&mut self) -> Option<Event> {
+        let (cols, rows) = term_size()?;
+        let size = (cols as u16, rows as u16);
+        if self.size == Some(size) {
+            return None;
+        }
+        self.size = Some(size);
+        Some(Event::Resize(size.0, size.1))
+    }
+}
+
+impl Drop for EventReader {
+    fn drop(&mut self) {
+        let _ = set_console_mode(self.input, self.input_mode | ENABLE_EXTENDED_FLAGS);
+    }
+}
+
+fn modifiers(state: u32) -> Modifiers {
+    let mut m = Modifiers::NONE;
+    if state & SHIFT_PRESSED != 0 {
+        m.insert(Modifiers::SHIFT);
+    }
+    if state & (LEFT_ALT_PRESSED | RIGHT_ALT_PRESSED) != 0 {
+        m.insert(Modifiers::ALT);
+    }
+    if state & (LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED) != 0 {
+        m.insert(Modifiers::CONTROL);
+    }
+    m
+}
+
+fn button(state: u32) -> Option<MouseButton> {
+    if state & FROM_LEFT_1ST_BUTTON_PRESSED != 0 {
+        Some(MouseButton::Left)
+    } else if state & RIGHTMOST_BUTTON_PRESSED != 0 {
+        Some(MouseButton::Right)
+    } else if state & FROM_LEFT_2ND_BUTTON_PRESSED != 0 {
+        Some(MouseButton::Middle)
+    } else {
+        None
+    }
+}
+
+// Waits up to `timeout` for input on a console handle, like the one
+// `open_tty` returns.
+pub(crate) fn wait_readable(file: &File, timeout: Duration) -> io::Result<bool> {
+    let millis = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+    match unsafe { WaitForSingleObject(file.as_raw_handle() as HANDLE, millis) } {
+        WAIT_OBJECT_0 => Ok(true),
+        WAIT_TIMEOUT => Ok(false),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// Where the cursor is, zero based (col, row) in the visible window. The
+// console knows it directly, so unlike on unix no input is read.
+pub fn query_cursor_position() -> io::Result<(u16, u16)> {
+    let handle = std_handle(STD_OUTPUT_HANDLE)?;
+    unsafe {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(handle, &mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let col = info.dwCursorPosition.X - info.srWindow.Left;
+        let row = info.dwCursorPosition.Y - info.srWindow.Top;
+        Ok((col.max(0) as u16, row.max(0) as u16))
+    }
+}
+
+// Same as `query_cursor_position`, the console answers immediately.
+pub fn query_cursor_position_timeout(_timeout: Duration) -> io::Result<(u16, u16)> {
+    query_cursor_position()
+}
+
+// How long queries wait for the terminal to answer.
+pub const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Writes `request` to the console and reads its VT input until `parse`
+// recognizes the reply or `timeout` passes (`TimedOut`). Other input read
+// meanwhile is lost, the console hands out records rather than bytes to
+// `EventReader`.
+pub(crate) fn query<T>(
+    request: &[u8],
+    timeout: Duration,
+    parse: impl Fn(&[u8]) -> Option<T>,
+) -> io::Result<T> {
+    let (mut input, mut output) = open_tty()?;
+    let _hidden = HiddenInput::new(&input)?;
+    output.write_all(request)?;
+    output.flush()?;
+    let deadline = Instant::now() + timeout;
+    let mut data = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        if let Some((_, value)) = crate::input::find_reply(&data, &parse) {
+            return Ok(value);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let millis = remaining.as_millis().min(u32::MAX as u128 - 1) as u32;
+        if remaining.is_zero()
+            || unsafe { WaitForSingleObject(input.as_raw_handle() as HANDLE, millis) }
+                != WAIT_OBJECT_0
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "terminal did not answer the query",
+            ));
+        }
+        match input.read(&mut buf)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+// Mouse positions are reported in screen buffer coordinates; this is where
+// the visible window starts within the buffer.
+fn window_origin() -> Option<(i16, i16)> {
+    let handle = std_handle(STD_OUTPUT_HANDLE).ok()?;
+    unsafe {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(handle, &mut info) == 0 {
+            return None;
+        }
+        Some((info.srWindow.Left, info.srWindow.Top))
+    }
+}
+
+// A screen buffer position relative to the window starting at `origin`,
+// clamped to its top left corner.
+fn window_position(pos: COORD, (left, top): (i16, i16)) -> (u16, u16) {
+    (
+        pos.X.saturating_sub(left).max(0) as u16,
+        pos.Y.saturating_sub(top).max(0) as u16,
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{COORD, EventReader, console_index, extended_rgb, modifiers, window_position};
+    use crate::color::{Color, Iso};
+    use crate::event::{
+        Event, KeyCode, KeyEvent, KeyEventKind, Modifiers, MouseButton, MouseEventKind,
+    };
+    use windows_sys::Win32::System::Console::{
+        FOREGROUND_BLUE, FOREGROUND_GREEN, FOREGROUND_INTENSITY, FOREGROUND_RED,
+        FROM_LEFT_1ST_BUTTON_PRESSED, INPUT_RECORD, KEY_EVENT, KEY_EVENT_RECORD,
+        KEY_EVENT_RECORD_0, LEFT_CTRL_PRESSED, MOUSE_EVENT, MOUSE_EVENT_RECORD, MOUSE_WHEELED,
+        RIGHT_ALT_PRESSED, SHIFT_PRESSED,
+    };
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{VK_F5, VK_TAB};
+
+    fn reader() -> EventReader {
+        EventReader {
+            input: std::ptr::null_mut(),
+            input_mode: 0,
+            buttons: 0,
+            surrogate: None,
+            size: None,
+            report_releases: false,
+        }
+    }
+
+    fn key(vk: u16, unit: u16, state: u32, down: bool) -> INPUT_RECORD {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        record.EventType = KEY_EVENT as u16;
+        record.Event.KeyEvent = KEY_EVENT_RECORD {
+            bKeyDown: down as i32,
+            wRepeatCount: 1,
+            wVirtualKeyCode: vk,
+            wVirtualScanCode: 0,
+            uChar: KEY_EVENT_RECORD_0 { UnicodeChar: unit },
+            dwControlKeyState: state,
+        };
+        record
+    }
+
+    fn mouse(buttons: u32, flags: u32) -> INPUT_RECORD {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        record.EventType = MOUSE_EVENT as u16;
+        record.Event.MouseEvent = MOUSE_EVENT_RECORD {
+            dwMousePosition: COORD { X: 3, Y: 4 },
+            dwButtonState: buttons,
+            dwControlKeyState: 0,
+            dwEventFlags: flags,
+        };
+        record
+    }
+
+    #[test]
+    fn modifier_state() {
+        assert_eq!(modifiers(0), Modifiers::NONE);
+        assert_eq!(
+            modifiers(SHIFT_PRESSED | RIGHT_ALT_PRESSED | LEFT_CTRL_PRESSED),
+            Modifiers::SHIFT | Modifiers::ALT | Modifiers::CONTROL
+        );
+    }
+
+    #[test]
+    fn key_records() {
+        let mut reader = reader();
+        let event = |code, modifiers| Some(Event::Key(KeyEvent::new(code, modifiers)));
+        assert_eq!(
+            reader.translate(&key(0x41, 'a' as u16, 0, true)),
+            event(KeyCode::Char('a'), Modifiers::NONE)
+        );
+        assert_eq!(
+            reader.translate(&key(0x41, 0x01, LEFT_CTRL_PRESSED, true)),
+            event(KeyCode::Char('a'), Modifiers::CONTROL)
+        );
+        assert_eq!(
+            reader.translate(&key(VK_TAB, 0x09, SHIFT_PRESSED, true)),
+            event(KeyCode::BackTab, Modifiers::NONE)
+        );
+        assert_eq!(
+            reader.translate(&key(VK_F5, 0, 0, true)),
+            event(KeyCode::F(5), Modifiers::NONE)
+        );
+        // a character outside the BMP, one surrogate per record
+        assert_eq!(reader.translate(&key(0, 0xD83D, 0, true)), None);
+        assert_eq!(
+            reader.translate(&key(0, 0xDE00, 0, true)),
+            event(KeyCode::Char('😀'), Modifiers::NONE)
+        );
+
+        assert_eq!(reader.translate(&key(0x41, 'a' as u16, 0, false)), None);
+        reader.report_releases(true);
+        assert_eq!(
+            reader.translate(&key(0x41, 'a' as u16, 0, false)),
+            Some(Event::Key(
+                KeyEvent::new(KeyCode::Char('a'), Modifiers::NONE).with_kind(KeyEventKind::Release)
+            ))
+        );
+    }
+
+    #[test]
+    fn mouse_records() {
+        let mut reader = reader();
+        let kind = |reader: &mut EventReader, record| match reader.translate(&record) {
+            Some(Event::Mouse(m)) => Some(m.kind),
+            _ => None,
+        };
+        assert_eq!(
+            kind(&mut reader, mouse(FROM_LEFT_1ST_BUTTON_PRESSED, 0)),
+            Some(MouseEventKind::Down(MouseButton::Left))
+        );
+        assert_eq!(
+            kind(&mut reader, mouse(0, 0)),
+            Some(MouseEventKind::Up(MouseButton::Left))
+        );
+        assert_eq!(
+            kind(&mut reader, mouse(120 << 16, MOUSE_WHEELED)),
+            Some(MouseEventKind::ScrollUp)
+        );
+        assert_eq!(
+            kind(
+                &mut reader,
+                mouse((-120i16 as u16 as u32) << 16, MOUSE_WHEELED)
+            ),
+            Some(MouseEventKind::ScrollDown)
+        );
+    }
+
+    #[test]
+    fn buffer_to_window_coordinates() {
+        assert_eq!(window_position(COORD { X: 5, Y: 310 }, (0, 300)), (5, 10));
+        assert_eq!(window_position(COORD { X: 2, Y: 3 }, (4, 300)), (0, 0));
+        assert_eq!(
+            window_position(
+                COORD {
+                    X: i16::MIN,
+                    Y: i16::MAX
+                },
+                (i16::MAX, i16::MIN)
+            ),
+            (0, i16::MAX as u16)
+        );
+    }
+
+    #[test]
+    fn console_attributes() {
+        assert_eq!(console_index(&Color::None), None);
+        assert_eq!(console_index(&Color::Default), None);
+        assert_eq!(
+            console_index(&Color::Iso {
+                color: Iso::Red,
+                bright: false
+            }),
+            Some(FOREGROUND_RED)
+        );
+        assert_eq!(
+            console_index(&Color::Iso {
+                color: Iso::Cyan,
+                bright: true
+            }),
+            Some(FOREGROUND_GREEN | FOREGROUND_BLUE | FOREGROUND_INTENSITY)
+        );
+        assert_eq!(
+            console_index(&Color::Rgb { r: 0, g: 0, b: 255 }),
+            Some(FOREGROUND_BLUE)
+        );
+        assert_eq!(extended_rgb(16), (0, 0, 0));
+        assert_eq!(extended_rgb(231), (255, 255, 255));
+        assert_eq!(extended_rgb(232), (8, 8, 8));
+    }
+}